@@ -0,0 +1,50 @@
+// Times parsing a class with a very large constant pool with and without
+// ParseOptions::parallel_resolution. Any difference depends on the number of threads rayon
+// uses: with a single thread, parallel resolution only adds overhead.
+
+use std::time::{Duration, Instant};
+
+// Builds a class file for java/lang/Object whose constant pool holds the given number of
+// entries, as pairs of a Utf8 name and a ClassInfo referring to it.
+fn synthetic_class(pool_size: u16) -> Vec<u8> {
+    let mut bytes = vec![0xca, 0xfe, 0xba, 0xbe, 0, 0, 0, 52];
+    bytes.extend_from_slice(&(pool_size + 1).to_be_bytes());
+    for i in 0..pool_size / 2 {
+        let name = if i == 0 {
+            "java/lang/Object".to_string()
+        } else {
+            format!("generated/Class{}", i)
+        };
+        bytes.push(1);
+        bytes.extend_from_slice(&(name.len() as u16).to_be_bytes());
+        bytes.extend_from_slice(name.as_bytes());
+        bytes.push(7);
+        bytes.extend_from_slice(&(2 * i + 1).to_be_bytes());
+    }
+    // access flags, this_class, super_class, and no interfaces, fields, methods or attributes
+    bytes.extend_from_slice(&[0, 0x21, 0, 2, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0]);
+    bytes
+}
+
+fn time_parse(bytes: &[u8], opts: &cafebabe::ParseOptions, iterations: u32) -> Duration {
+    let start = Instant::now();
+    for _ in 0..iterations {
+        cafebabe::parse_class_with_options(bytes, opts).unwrap();
+    }
+    start.elapsed() / iterations
+}
+
+fn main() {
+    let bytes = synthetic_class(60000);
+    let iterations = 50;
+    let mut opts = cafebabe::ParseOptions::default();
+    let sequential = time_parse(&bytes, &opts, iterations);
+    opts.parallel_resolution(true);
+    let parallel = time_parse(&bytes, &opts, iterations);
+    println!(
+        "Parsing a class with a 60000 entry constant pool on {} threads:",
+        rayon::current_num_threads()
+    );
+    println!("  sequential resolution: {:?}", sequential);
+    println!("  parallel resolution:   {:?}", parallel);
+}
@@ -1,4 +1,5 @@
 use std::borrow::Cow;
+use std::collections::HashSet;
 use std::ops::Deref;
 
 use crate::bytecode::ByteCode;
@@ -6,16 +7,24 @@ use crate::constant_pool::{
     read_cp_bootstrap_argument, read_cp_classinfo, read_cp_classinfo_opt, read_cp_double,
     read_cp_float, read_cp_integer, read_cp_literalconstant, read_cp_long, read_cp_methodhandle,
     read_cp_moduleinfo, read_cp_nameandtype_opt, read_cp_packageinfo, read_cp_utf8,
-    read_cp_utf8_opt,
+    read_cp_utf8_opt, skip_cp_references,
 };
 use crate::constant_pool::{
     BootstrapArgument, ConstantPoolEntry, LiteralConstant, MethodHandle, NameAndType,
 };
-use crate::descriptors::{is_return_descriptor, parse_field_descriptor, FieldDescriptor};
-use crate::names::is_unqualified_name;
-use crate::{read_u1, read_u2, read_u4, AccessFlags, CafeRc, ParseError, ParseOptions};
+use crate::descriptors::{
+    is_method_descriptor, is_return_descriptor, parse_field_descriptor, FieldDescriptor,
+};
+use crate::names::{is_unqualified_method_name, is_unqualified_name};
+use crate::signatures::{
+    check_field_signature_depth, parse_field_signature_with_depth, ReferenceTypeSignature,
+};
+use crate::{
+    checked_end, read_bytes, read_u1, read_u2, read_u4, AccessFlags, CafeRc, ParseError,
+    ParseOptions,
+};
 
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub struct ExceptionTableEntry<'a> {
     pub start_pc: u16,
     pub end_pc: u16,
@@ -23,17 +32,26 @@ pub struct ExceptionTableEntry<'a> {
     pub catch_type: Option<Cow<'a, str>>,
 }
 
-#[derive(Debug)]
+impl<'a> ExceptionTableEntry<'a> {
+    /// Returns the name of the exception class this handler catches, or "any" if it
+    /// catches all exceptions (as used to implement finally blocks).
+    #[must_use]
+    pub fn catch_type_name(&self) -> &str {
+        self.catch_type.as_deref().unwrap_or("any")
+    }
+}
+
+#[derive(Clone, Debug)]
 pub struct CodeData<'a> {
     pub max_stack: u16,
     pub max_locals: u16,
-    pub code: &'a [u8],
+    pub code: Cow<'a, [u8]>,
     pub bytecode: Option<ByteCode<'a>>,
     pub exception_table: Vec<ExceptionTableEntry<'a>>,
     pub attributes: Vec<AttributeInfo<'a>>,
 }
 
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub enum VerificationType<'a> {
     Top,
     Integer,
@@ -46,7 +64,7 @@ pub enum VerificationType<'a> {
     Object { class_name: Cow<'a, str> },
 }
 
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub enum StackMapEntry<'a> {
     Same {
         offset_delta: u16,
@@ -70,6 +88,16 @@ pub enum StackMapEntry<'a> {
     },
 }
 
+/// A frame of the legacy StackMap attribute used by CLDC (J2ME) class files, which
+/// predates StackMapTable. Unlike StackMapEntry, each frame holds the absolute bytecode
+/// offset and the full set of locals and stack items.
+#[derive(Clone, Debug)]
+pub struct StackMapFrame<'a> {
+    pub offset: u16,
+    pub locals: Vec<VerificationType<'a>>,
+    pub stack: Vec<VerificationType<'a>>,
+}
+
 bitflags! {
     pub struct InnerClassAccessFlags: u16 {
         const PUBLIC = AccessFlags::PUBLIC.bits();
@@ -85,7 +113,7 @@ bitflags! {
     }
 }
 
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub struct InnerClassEntry<'a> {
     pub inner_class_info: Cow<'a, str>,
     pub outer_class_info: Option<Cow<'a, str>>,
@@ -93,13 +121,13 @@ pub struct InnerClassEntry<'a> {
     pub access_flags: InnerClassAccessFlags,
 }
 
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub struct LineNumberEntry {
     pub start_pc: u16,
     pub line_number: u16,
 }
 
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub struct LocalVariableEntry<'a> {
     pub start_pc: u16,
     pub length: u16,
@@ -108,7 +136,7 @@ pub struct LocalVariableEntry<'a> {
     pub index: u16,
 }
 
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub struct LocalVariableTypeEntry<'a> {
     pub start_pc: u16,
     pub length: u16,
@@ -117,7 +145,7 @@ pub struct LocalVariableTypeEntry<'a> {
     pub index: u16,
 }
 
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub enum AnnotationElementValue<'a> {
     ByteConstant(i32),
     CharConstant(i32),
@@ -139,31 +167,31 @@ pub enum AnnotationElementValue<'a> {
     ArrayValue(Vec<AnnotationElementValue<'a>>),
 }
 
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub struct AnnotationElement<'a> {
     pub name: Cow<'a, str>,
     pub value: AnnotationElementValue<'a>,
 }
 
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub struct Annotation<'a> {
     pub type_descriptor: FieldDescriptor<'a>,
     pub elements: Vec<AnnotationElement<'a>>,
 }
 
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub struct ParameterAnnotation<'a> {
     pub annotations: Vec<Annotation<'a>>,
 }
 
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub struct TypeAnnotationLocalVarTargetEntry {
     pub start_pc: u16,
     pub length: u16,
     pub index: u16,
 }
 
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub enum TypeAnnotationTarget {
     TypeParameter {
         index: u8,
@@ -195,7 +223,35 @@ pub enum TypeAnnotationTarget {
     },
 }
 
-#[derive(Debug)]
+/// The kind of type use a type annotation applies to, as identified by its target_type
+/// value (see tables 4.7.20-A and 4.7.20-B of the JVM spec).
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum TypeAnnotationTargetKind {
+    ClassTypeParameter,
+    MethodTypeParameter,
+    Supertype,
+    ClassTypeParameterBound,
+    MethodTypeParameterBound,
+    Field,
+    MethodReturn,
+    MethodReceiver,
+    MethodFormalParameter,
+    Throws,
+    LocalVariable,
+    ResourceVariable,
+    ExceptionParameter,
+    Instanceof,
+    New,
+    ConstructorReference,
+    MethodReference,
+    Cast,
+    ConstructorInvocationTypeArgument,
+    MethodInvocationTypeArgument,
+    ConstructorReferenceTypeArgument,
+    MethodReferenceTypeArgument,
+}
+
+#[derive(Clone, Debug)]
 pub enum TypeAnnotationTargetPathKind {
     DeeperArray,
     DeeperNested,
@@ -203,20 +259,21 @@ pub enum TypeAnnotationTargetPathKind {
     TypeArgument,
 }
 
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub struct TypeAnnotationTargetPathEntry {
     pub path_kind: TypeAnnotationTargetPathKind,
     pub argument_index: u8,
 }
 
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub struct TypeAnnotation<'a> {
+    pub target_kind: TypeAnnotationTargetKind,
     pub target_type: TypeAnnotationTarget,
     pub target_path: Vec<TypeAnnotationTargetPathEntry>,
     pub annotation: Annotation<'a>,
 }
 
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub struct BootstrapMethodEntry<'a> {
     pub method: MethodHandle<'a>,
     pub arguments: Vec<BootstrapArgument<'a>>,
@@ -230,7 +287,7 @@ bitflags! {
     }
 }
 
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub struct MethodParameterEntry<'a> {
     pub name: Option<Cow<'a, str>>,
     pub access_flags: MethodParameterAccessFlags,
@@ -253,7 +310,7 @@ bitflags! {
     }
 }
 
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub struct ModuleRequireEntry<'a> {
     pub name: Cow<'a, str>,
     pub flags: ModuleRequiresFlags,
@@ -267,7 +324,7 @@ bitflags! {
     }
 }
 
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub struct ModuleExportsEntry<'a> {
     pub package_name: Cow<'a, str>,
     pub flags: ModuleExportsFlags,
@@ -281,20 +338,20 @@ bitflags! {
     }
 }
 
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub struct ModuleOpensEntry<'a> {
     pub package_name: Cow<'a, str>,
     pub flags: ModuleOpensFlags,
     pub opens_to: Vec<Cow<'a, str>>,
 }
 
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub struct ModuleProvidesEntry<'a> {
     pub service_interface_name: Cow<'a, str>,
     pub provides_with: Vec<Cow<'a, str>>,
 }
 
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub struct ModuleData<'a> {
     pub name: Cow<'a, str>,
     pub access_flags: ModuleAccessFlags,
@@ -306,18 +363,33 @@ pub struct ModuleData<'a> {
     pub provides: Vec<ModuleProvidesEntry<'a>>,
 }
 
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub struct RecordComponentEntry<'a> {
     pub name: Cow<'a, str>,
     pub descriptor: FieldDescriptor<'a>,
     pub attributes: Vec<AttributeInfo<'a>>,
+    // The ParseOptions::max_nesting_depth the component was parsed with, for parsing its
+    // signature.
+    pub(crate) max_nesting_depth: usize,
+}
+
+impl<'a> RecordComponentEntry<'a> {
+    /// Returns the generic type information of this record component as recorded in its
+    /// Signature attribute, or None if the component does not have one. Fails if the
+    /// signature is malformed, which is only checked during parsing in strict mode.
+    pub fn signature(&self) -> Result<Option<ReferenceTypeSignature<'a>>, ParseError> {
+        signature_attribute(&self.attributes)
+            .map(|s| parse_field_signature_with_depth(s, self.max_nesting_depth))
+            .transpose()
+    }
 }
 
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub enum AttributeData<'a> {
     ConstantValue(LiteralConstant<'a>),
     Code(CodeData<'a>),
     StackMapTable(Vec<StackMapEntry<'a>>),
+    StackMap(Vec<StackMapFrame<'a>>),
     Exceptions(Vec<Cow<'a, str>>),
     InnerClasses(Vec<InnerClassEntry<'a>>),
     EnclosingMethod {
@@ -328,6 +400,9 @@ pub enum AttributeData<'a> {
     Signature(Cow<'a, str>),
     SourceFile(Cow<'a, str>),
     SourceDebugExtension(Cow<'a, str>),
+    /// A SourceDebugExtension attribute whose contents are not valid modified UTF-8. The
+    /// JVM ignores the contents of this attribute, so such class files are still valid.
+    SourceDebugExtensionBytes(Cow<'a, [u8]>),
     LineNumberTable(Vec<LineNumberEntry>),
     LocalVariableTable(Vec<LocalVariableEntry<'a>>),
     LocalVariableTypeTable(Vec<LocalVariableTypeEntry<'a>>),
@@ -348,10 +423,15 @@ pub enum AttributeData<'a> {
     NestMembers(Vec<Cow<'a, str>>),
     PermittedSubclasses(Vec<Cow<'a, str>>),
     Record(Vec<RecordComponentEntry<'a>>),
-    Other(&'a [u8]),
+    /// An attribute that was not decoded, with its raw data provided as-is; the name is
+    /// available in the AttributeInfo. This is used for attributes not defined by the JVM
+    /// spec, such as vendor-specific ones, and for every attribute, including those the
+    /// spec defines, when parsing with
+    /// [`ParseOptions::skip_attribute_bodies`](crate::ParseOptions::skip_attribute_bodies).
+    Other(Cow<'a, [u8]>),
 }
 
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub struct AttributeInfo<'a> {
     pub name: Cow<'a, str>,
     pub data: AttributeData<'a>,
@@ -364,6 +444,115 @@ fn ensure_length(length: usize, expected: usize) -> Result<(), ParseError> {
     Ok(())
 }
 
+fn ensure_pc_range(start_pc: u16, length: u16, code_length: usize) -> Result<(), ParseError> {
+    let start_pc = usize::from(start_pc);
+    if start_pc >= code_length {
+        fail!("Invalid start_pc {}", start_pc);
+    }
+    if usize::from(length) > code_length - start_pc {
+        fail!("Invalid length {} for start_pc {}", length, start_pc);
+    }
+    Ok(())
+}
+
+// Section 4.7.3 requires each exception handler to cover a non-empty range of the
+// code array and to start inside it.
+fn validate_exception_table(
+    code_length: usize,
+    exception_table: &[ExceptionTableEntry],
+) -> Result<(), ParseError> {
+    for (i, entry) in exception_table.iter().enumerate() {
+        if entry.start_pc >= entry.end_pc || usize::from(entry.end_pc) > code_length {
+            fail!(
+                ("Invalid range {} to {}", entry.start_pc, entry.end_pc),
+                ("exception table entry {}", i)
+            );
+        }
+        if usize::from(entry.handler_pc) >= code_length {
+            fail!(
+                ("Invalid handler_pc {}", entry.handler_pc),
+                ("exception table entry {}", i)
+            );
+        }
+    }
+    Ok(())
+}
+
+// Type annotations nested in a Code attribute may target local variables and
+// instructions, whose pc values must lie inside the code array (section 4.7.20.1).
+fn validate_type_annotation_ranges(
+    code_length: usize,
+    annotations: &[TypeAnnotation],
+) -> Result<(), ParseError> {
+    for (j, annotation) in annotations.iter().enumerate() {
+        match &annotation.target_type {
+            TypeAnnotationTarget::LocalVar(entries) => {
+                for (k, entry) in entries.iter().enumerate() {
+                    ensure_pc_range(entry.start_pc, entry.length, code_length).map_err(|e| {
+                        err!(e, "localvar target entry {} of type annotation {}", k, j)
+                    })?;
+                }
+            }
+            TypeAnnotationTarget::Offset { offset }
+            | TypeAnnotationTarget::TypeArgument { offset, .. } => {
+                if usize::from(*offset) >= code_length {
+                    fail!(("Invalid offset {}", offset), ("type annotation {}", j));
+                }
+            }
+            _ => continue,
+        };
+    }
+    Ok(())
+}
+
+// Sections 4.7.12, 4.7.13 and 4.7.14 require the pc values in the debugging tables
+// to refer to locations inside the code array of the enclosing Code attribute.
+fn validate_code_ranges(
+    code_length: usize,
+    attributes: &[AttributeInfo],
+) -> Result<(), ParseError> {
+    for (i, attr) in attributes.iter().enumerate() {
+        match &attr.data {
+            AttributeData::LineNumberTable(entries) => {
+                for (j, entry) in entries.iter().enumerate() {
+                    if usize::from(entry.start_pc) >= code_length {
+                        fail!(
+                            ("Invalid start_pc {}", entry.start_pc),
+                            ("line number {} of LineNumberTable attribute {}", j, i)
+                        );
+                    }
+                }
+            }
+            AttributeData::LocalVariableTable(entries) => {
+                for (j, entry) in entries.iter().enumerate() {
+                    ensure_pc_range(entry.start_pc, entry.length, code_length).map_err(|e| {
+                        err!(e, "variable {} of LocalVariableTable attribute {}", j, i)
+                    })?;
+                }
+            }
+            AttributeData::LocalVariableTypeTable(entries) => {
+                for (j, entry) in entries.iter().enumerate() {
+                    ensure_pc_range(entry.start_pc, entry.length, code_length).map_err(|e| {
+                        err!(
+                            e,
+                            "variable {} of LocalVariableTypeTable attribute {}",
+                            j,
+                            i
+                        )
+                    })?;
+                }
+            }
+            AttributeData::RuntimeVisibleTypeAnnotations(annotations)
+            | AttributeData::RuntimeInvisibleTypeAnnotations(annotations) => {
+                validate_type_annotation_ranges(code_length, annotations)
+                    .map_err(|e| err!(e, "{} attribute {}", attr.name, i))?;
+            }
+            _ => continue,
+        };
+    }
+    Ok(())
+}
+
 fn read_code_data<'a>(
     bytes: &'a [u8],
     ix: &mut usize,
@@ -373,14 +562,7 @@ fn read_code_data<'a>(
     let max_stack = read_u2(bytes, ix)?;
     let max_locals = read_u2(bytes, ix)?;
     let code_length = read_u4(bytes, ix)? as usize;
-    if bytes.len() < *ix + code_length {
-        fail!(
-            "Unexpected end of stream reading code attribute at index {}",
-            *ix
-        );
-    }
-    let code = &bytes[*ix..*ix + code_length];
-    *ix += code_length;
+    let code = read_bytes(bytes, ix, code_length, "code attribute")?;
     let exception_table_count = read_u2(bytes, ix)?;
     let mut exception_table = Vec::with_capacity(exception_table_count.into());
     for i in 0..exception_table_count {
@@ -396,17 +578,20 @@ fn read_code_data<'a>(
             catch_type,
         });
     }
+    validate_exception_table(code_length, &exception_table)?;
     let code_attributes =
         read_attributes(bytes, ix, pool, opts).map_err(|e| err!(e, "code attribute"))?;
+    validate_code_ranges(code_length, &code_attributes)?;
     let bytecode = if opts.parse_bytecode {
-        Some(ByteCode::from(code, pool).map_err(|e| err!(e, "bytecode"))?)
+        // The parsed bytecode isn't written back, so its references are left out
+        Some(skip_cp_references(|| ByteCode::from(code, pool)).map_err(|e| err!(e, "bytecode"))?)
     } else {
         None
     };
     Ok(CodeData {
         max_stack,
         max_locals,
-        code,
+        code: Cow::Borrowed(code),
         bytecode,
         exception_table,
         attributes: code_attributes,
@@ -533,6 +718,40 @@ fn read_stackmaptable_data<'a>(
     Ok(stackmapframes)
 }
 
+fn read_stackmap_data<'a>(
+    bytes: &'a [u8],
+    ix: &mut usize,
+    pool: &[CafeRc<ConstantPoolEntry<'a>>],
+) -> Result<Vec<StackMapFrame<'a>>, ParseError> {
+    let count = read_u2(bytes, ix)?;
+    let mut frames = Vec::with_capacity(count.into());
+    for i in 0..count {
+        let offset = read_u2(bytes, ix)?;
+        let locals_count = read_u2(bytes, ix)?;
+        let mut locals = Vec::with_capacity(locals_count.into());
+        for j in 0..locals_count {
+            locals.push(
+                read_stackmaptable_verification(bytes, ix, pool)
+                    .map_err(|e| err!(e, "local entry {} of stack map frame {}", j, i))?,
+            );
+        }
+        let stack_count = read_u2(bytes, ix)?;
+        let mut stack = Vec::with_capacity(stack_count.into());
+        for j in 0..stack_count {
+            stack.push(
+                read_stackmaptable_verification(bytes, ix, pool)
+                    .map_err(|e| err!(e, "stack entry {} of stack map frame {}", j, i))?,
+            );
+        }
+        frames.push(StackMapFrame {
+            offset,
+            locals,
+            stack,
+        });
+    }
+    Ok(frames)
+}
+
 fn read_exceptions_data<'a>(
     bytes: &'a [u8],
     ix: &mut usize,
@@ -620,6 +839,7 @@ fn read_localvariabletype_data<'a>(
     bytes: &'a [u8],
     ix: &mut usize,
     pool: &[CafeRc<ConstantPoolEntry<'a>>],
+    opts: &ParseOptions,
 ) -> Result<Vec<LocalVariableTypeEntry<'a>>, ParseError> {
     let count = read_u2(bytes, ix)?;
     let mut localvariabletypes = Vec::with_capacity(count.into());
@@ -630,8 +850,16 @@ fn read_localvariabletype_data<'a>(
         if !is_unqualified_name(&name) {
             fail!("Invalid unqualified name for variable {}", i);
         }
-        let signature =
-            read_cp_utf8(bytes, ix, pool).map_err(|e| err!(e, "signature for variable {}", i))?;
+        let signature = read_cp_utf8(bytes, ix, pool)
+            .and_then(|signature| {
+                if opts.strict {
+                    parse_field_signature_with_depth(&signature, opts.max_nesting_depth)?;
+                } else {
+                    check_field_signature_depth(&signature, opts.max_nesting_depth)?;
+                }
+                Ok(signature)
+            })
+            .map_err(|e| err!(e, "signature for variable {}", i))?;
         let index = read_u2(bytes, ix)?;
         localvariabletypes.push(LocalVariableTypeEntry {
             start_pc,
@@ -648,7 +876,13 @@ fn read_annotation_element_value<'a>(
     bytes: &'a [u8],
     ix: &mut usize,
     pool: &[CafeRc<ConstantPoolEntry<'a>>],
+    max_depth: usize,
 ) -> Result<AnnotationElementValue<'a>, ParseError> {
+    // Element values can contain annotations and arrays of further element values, so
+    // a crafted class file could otherwise nest them deeply enough to overflow the stack
+    if max_depth == 0 {
+        fail!("Annotation element values are nested more deeply than the maximum nesting depth");
+    }
     let value = match read_u1(bytes, ix)? as char {
         'B' => AnnotationElementValue::ByteConstant(read_cp_integer(bytes, ix, pool)?),
         'C' => AnnotationElementValue::CharConstant(read_cp_integer(bytes, ix, pool)?),
@@ -676,13 +910,18 @@ fn read_annotation_element_value<'a>(
             }
             AnnotationElementValue::ClassLiteral { class_name }
         }
-        '@' => AnnotationElementValue::AnnotationValue(read_annotation(bytes, ix, pool)?),
+        '@' => AnnotationElementValue::AnnotationValue(read_annotation(
+            bytes,
+            ix,
+            pool,
+            max_depth - 1,
+        )?),
         '[' => {
             let count = read_u2(bytes, ix)?;
             let mut array_values = Vec::with_capacity(count.into());
             for i in 0..count {
                 array_values.push(
-                    read_annotation_element_value(bytes, ix, pool)
+                    read_annotation_element_value(bytes, ix, pool, max_depth - 1)
                         .map_err(|e| err!(e, "array index {}", i))?,
                 );
             }
@@ -697,6 +936,7 @@ fn read_annotation<'a>(
     bytes: &'a [u8],
     ix: &mut usize,
     pool: &[CafeRc<ConstantPoolEntry<'a>>],
+    max_depth: usize,
 ) -> Result<Annotation<'a>, ParseError> {
     let type_descriptor = read_cp_utf8(bytes, ix, pool)
         .and_then(|descriptor| parse_field_descriptor(&descriptor, 0))
@@ -705,7 +945,7 @@ fn read_annotation<'a>(
     let mut elements = Vec::with_capacity(element_count.into());
     for i in 0..element_count {
         let name = read_cp_utf8(bytes, ix, pool).map_err(|e| err!(e, "name of element {}", i))?;
-        let value = read_annotation_element_value(bytes, ix, pool)
+        let value = read_annotation_element_value(bytes, ix, pool, max_depth)
             .map_err(|e| err!(e, "value of element {}", i))?;
         elements.push(AnnotationElement { name, value });
     }
@@ -719,12 +959,14 @@ fn read_annotation_data<'a>(
     bytes: &'a [u8],
     ix: &mut usize,
     pool: &[CafeRc<ConstantPoolEntry<'a>>],
+    max_depth: usize,
 ) -> Result<Vec<Annotation<'a>>, ParseError> {
     let count = read_u2(bytes, ix)?;
     let mut annotations = Vec::with_capacity(count.into());
     for i in 0..count {
-        annotations
-            .push(read_annotation(bytes, ix, pool).map_err(|e| err!(e, "annotation {}", i))?);
+        annotations.push(
+            read_annotation(bytes, ix, pool, max_depth).map_err(|e| err!(e, "annotation {}", i))?,
+        );
     }
     Ok(annotations)
 }
@@ -733,6 +975,7 @@ fn read_parameter_annotation_data<'a>(
     bytes: &'a [u8],
     ix: &mut usize,
     pool: &[CafeRc<ConstantPoolEntry<'a>>],
+    max_depth: usize,
 ) -> Result<Vec<ParameterAnnotation<'a>>, ParseError> {
     let count = read_u1(bytes, ix)?;
     let mut parameters = Vec::with_capacity(count.into());
@@ -741,7 +984,7 @@ fn read_parameter_annotation_data<'a>(
         let mut annotations = Vec::with_capacity(annotation_count.into());
         for j in 0..annotation_count {
             annotations.push(
-                read_annotation(bytes, ix, pool)
+                read_annotation(bytes, ix, pool, max_depth)
                     .map_err(|e| err!(e, "annotation {} of parameter {}", j, i))?,
             );
         }
@@ -754,29 +997,69 @@ fn read_type_annotation_data<'a>(
     bytes: &'a [u8],
     ix: &mut usize,
     pool: &[CafeRc<ConstantPoolEntry<'a>>],
+    max_depth: usize,
 ) -> Result<Vec<TypeAnnotation<'a>>, ParseError> {
     let count = read_u2(bytes, ix)?;
     let mut annotations = Vec::with_capacity(count.into());
     for i in 0..count {
-        let target_type = match read_u1(bytes, ix)? {
-            0x00 | 0x01 => TypeAnnotationTarget::TypeParameter {
-                index: read_u1(bytes, ix)?,
-            },
-            0x10 => TypeAnnotationTarget::Supertype {
+        let target_kind = match read_u1(bytes, ix)? {
+            0x00 => TypeAnnotationTargetKind::ClassTypeParameter,
+            0x01 => TypeAnnotationTargetKind::MethodTypeParameter,
+            0x10 => TypeAnnotationTargetKind::Supertype,
+            0x11 => TypeAnnotationTargetKind::ClassTypeParameterBound,
+            0x12 => TypeAnnotationTargetKind::MethodTypeParameterBound,
+            0x13 => TypeAnnotationTargetKind::Field,
+            0x14 => TypeAnnotationTargetKind::MethodReturn,
+            0x15 => TypeAnnotationTargetKind::MethodReceiver,
+            0x16 => TypeAnnotationTargetKind::MethodFormalParameter,
+            0x17 => TypeAnnotationTargetKind::Throws,
+            0x40 => TypeAnnotationTargetKind::LocalVariable,
+            0x41 => TypeAnnotationTargetKind::ResourceVariable,
+            0x42 => TypeAnnotationTargetKind::ExceptionParameter,
+            0x43 => TypeAnnotationTargetKind::Instanceof,
+            0x44 => TypeAnnotationTargetKind::New,
+            0x45 => TypeAnnotationTargetKind::ConstructorReference,
+            0x46 => TypeAnnotationTargetKind::MethodReference,
+            0x47 => TypeAnnotationTargetKind::Cast,
+            0x48 => TypeAnnotationTargetKind::ConstructorInvocationTypeArgument,
+            0x49 => TypeAnnotationTargetKind::MethodInvocationTypeArgument,
+            0x4A => TypeAnnotationTargetKind::ConstructorReferenceTypeArgument,
+            0x4B => TypeAnnotationTargetKind::MethodReferenceTypeArgument,
+            v => fail!(
+                ("Unrecognized target type {}", v),
+                ("type annotation {}", i)
+            ),
+        };
+        let target_type = match target_kind {
+            TypeAnnotationTargetKind::ClassTypeParameter
+            | TypeAnnotationTargetKind::MethodTypeParameter => {
+                TypeAnnotationTarget::TypeParameter {
+                    index: read_u1(bytes, ix)?,
+                }
+            }
+            TypeAnnotationTargetKind::Supertype => TypeAnnotationTarget::Supertype {
                 index: read_u2(bytes, ix)?,
             },
-            0x11 | 0x12 => TypeAnnotationTarget::TypeParameterBound {
-                type_parameter_index: read_u1(bytes, ix)?,
-                bound_index: read_u1(bytes, ix)?,
-            },
-            0x13..=0x15 => TypeAnnotationTarget::Empty,
-            0x16 => TypeAnnotationTarget::FormalParameter {
-                index: read_u1(bytes, ix)?,
-            },
-            0x17 => TypeAnnotationTarget::Throws {
+            TypeAnnotationTargetKind::ClassTypeParameterBound
+            | TypeAnnotationTargetKind::MethodTypeParameterBound => {
+                TypeAnnotationTarget::TypeParameterBound {
+                    type_parameter_index: read_u1(bytes, ix)?,
+                    bound_index: read_u1(bytes, ix)?,
+                }
+            }
+            TypeAnnotationTargetKind::Field
+            | TypeAnnotationTargetKind::MethodReturn
+            | TypeAnnotationTargetKind::MethodReceiver => TypeAnnotationTarget::Empty,
+            TypeAnnotationTargetKind::MethodFormalParameter => {
+                TypeAnnotationTarget::FormalParameter {
+                    index: read_u1(bytes, ix)?,
+                }
+            }
+            TypeAnnotationTargetKind::Throws => TypeAnnotationTarget::Throws {
                 index: read_u2(bytes, ix)?,
             },
-            0x40 | 0x41 => {
+            TypeAnnotationTargetKind::LocalVariable
+            | TypeAnnotationTargetKind::ResourceVariable => {
                 let localvar_count = read_u2(bytes, ix)?;
                 let mut localvars = Vec::with_capacity(localvar_count.into());
                 for _j in 0..localvar_count {
@@ -791,20 +1074,25 @@ fn read_type_annotation_data<'a>(
                 }
                 TypeAnnotationTarget::LocalVar(localvars)
             }
-            0x42 => TypeAnnotationTarget::Catch {
+            TypeAnnotationTargetKind::ExceptionParameter => TypeAnnotationTarget::Catch {
                 exception_table_index: read_u2(bytes, ix)?,
             },
-            0x43..=0x46 => TypeAnnotationTarget::Offset {
+            TypeAnnotationTargetKind::Instanceof
+            | TypeAnnotationTargetKind::New
+            | TypeAnnotationTargetKind::ConstructorReference
+            | TypeAnnotationTargetKind::MethodReference => TypeAnnotationTarget::Offset {
                 offset: read_u2(bytes, ix)?,
             },
-            0x47..=0x4B => TypeAnnotationTarget::TypeArgument {
-                offset: read_u2(bytes, ix)?,
-                type_argument_index: read_u1(bytes, ix)?,
-            },
-            v => fail!(
-                ("Unrecognized target type {}", v),
-                ("type annotation {}", i)
-            ),
+            TypeAnnotationTargetKind::Cast
+            | TypeAnnotationTargetKind::ConstructorInvocationTypeArgument
+            | TypeAnnotationTargetKind::MethodInvocationTypeArgument
+            | TypeAnnotationTargetKind::ConstructorReferenceTypeArgument
+            | TypeAnnotationTargetKind::MethodReferenceTypeArgument => {
+                TypeAnnotationTarget::TypeArgument {
+                    offset: read_u2(bytes, ix)?,
+                    type_argument_index: read_u1(bytes, ix)?,
+                }
+            }
         };
         let path_count = read_u1(bytes, ix)?;
         let mut target_path = Vec::with_capacity(path_count.into());
@@ -825,9 +1113,10 @@ fn read_type_annotation_data<'a>(
                 argument_index,
             });
         }
-        let annotation =
-            read_annotation(bytes, ix, pool).map_err(|e| err!(e, "type annotation {}", i))?;
+        let annotation = read_annotation(bytes, ix, pool, max_depth)
+            .map_err(|e| err!(e, "type annotation {}", i))?;
         annotations.push(TypeAnnotation {
+            target_kind,
             target_type,
             target_path,
             annotation,
@@ -979,6 +1268,93 @@ fn read_module_data<'a>(
     })
 }
 
+fn ensure_unique<'a, 'b>(
+    names: impl Iterator<Item = &'b Cow<'a, str>>,
+    kind: &str,
+) -> Result<(), ParseError>
+where
+    'a: 'b,
+{
+    let mut seen = HashSet::new();
+    for name in names {
+        if !seen.insert(name) {
+            fail!("Duplicate {} entry {}", kind, name);
+        }
+    }
+    Ok(())
+}
+
+// Checks the constraints on the Module attribute from section 4.7.25 of the JVM spec.
+pub(crate) fn validate_module(module: &ModuleData, major_version: u16) -> Result<(), ParseError> {
+    if module.name == "java.base" {
+        if !module.requires.is_empty() {
+            fail!(
+                "Found {} requires entries for java.base; expected 0",
+                module.requires.len()
+            );
+        }
+    } else {
+        let java_base: Vec<&ModuleRequireEntry> = module
+            .requires
+            .iter()
+            .filter(|entry| entry.name == "java.base")
+            .collect();
+        if java_base.len() != 1 {
+            fail!(
+                "Found {} requires entries for java.base; expected 1",
+                java_base.len()
+            );
+        }
+        if major_version >= 54
+            && java_base[0]
+                .flags
+                .intersects(ModuleRequiresFlags::TRANSITIVE | ModuleRequiresFlags::STATIC_PHASE)
+        {
+            fail!(
+                "Invalid flags {:?} on requires entry for java.base",
+                java_base[0].flags
+            );
+        }
+    }
+    ensure_unique(module.requires.iter().map(|entry| &entry.name), "requires")?;
+    ensure_unique(
+        module.exports.iter().map(|entry| &entry.package_name),
+        "exports",
+    )?;
+    for (i, entry) in module.exports.iter().enumerate() {
+        ensure_unique(entry.exports_to.iter(), "exports_to")
+            .map_err(|e| err!(e, "exports entry {}", i))?;
+    }
+    if module.access_flags.contains(ModuleAccessFlags::OPEN) && !module.opens.is_empty() {
+        fail!(
+            "Found {} opens entries for open module; expected 0",
+            module.opens.len()
+        );
+    }
+    ensure_unique(
+        module.opens.iter().map(|entry| &entry.package_name),
+        "opens",
+    )?;
+    for (i, entry) in module.opens.iter().enumerate() {
+        ensure_unique(entry.opens_to.iter(), "opens_to")
+            .map_err(|e| err!(e, "opens entry {}", i))?;
+    }
+    ensure_unique(module.uses.iter(), "uses")?;
+    ensure_unique(
+        module
+            .provides
+            .iter()
+            .map(|entry| &entry.service_interface_name),
+        "provides",
+    )?;
+    for (i, entry) in module.provides.iter().enumerate() {
+        if entry.provides_with.is_empty() {
+            fail!("Found no provides_with entries for provides entry {}", i);
+        }
+    }
+    Ok(())
+}
+
 fn read_modulepackages_data<'a>(
     bytes: &'a [u8],
     ix: &mut usize,
@@ -1039,33 +1415,151 @@ fn read_record_data<'a>(
             .map_err(|e| err!(e, "descriptor of entry {}", i))?;
         let attributes =
             read_attributes(bytes, ix, pool, opts).map_err(|e| err!(e, "entry {}", i))?;
+        if opts.strict {
+            validate_signature(
+                &attributes,
+                parse_field_signature_with_depth,
+                opts.max_nesting_depth,
+            )
+            .map_err(|e| err!(e, "entry {}", i))?;
+        }
         components.push(RecordComponentEntry {
             name,
             descriptor,
             attributes,
+            max_nesting_depth: opts.max_nesting_depth,
         });
     }
     Ok(components)
 }
 
+// Section 4.7.7 of the JVM spec requires the method_index of an EnclosingMethod attribute
+// to refer to a method. Classes enclosed in an initializer have no method index, so the
+// name can be <init> but not <clinit>.
+fn ensure_enclosing_method(method: &NameAndType) -> Result<(), ParseError> {
+    if !is_unqualified_method_name(&method.name, true, false) {
+        fail!("Invalid enclosing method name {}", method.name);
+    }
+    if !is_method_descriptor(&method.descriptor) {
+        fail!("Invalid enclosing method descriptor {}", method.descriptor);
+    }
+    Ok(())
+}
+
+// The attribute helpers shared by the accessors of ClassFile, FieldInfo and MethodInfo.
+
+pub(crate) fn first_attribute_named<'a, 'b>(
+    attributes: &'b [AttributeInfo<'a>],
+    name: &str,
+) -> Option<&'b AttributeInfo<'a>> {
+    attributes.iter().find(|attr| attr.name == name)
+}
+
+pub(crate) fn all_attributes_named<'a, 'b>(
+    attributes: &'b [AttributeInfo<'a>],
+    name: &'b str,
+) -> impl Iterator<Item = &'b AttributeInfo<'a>> + 'b {
+    attributes.iter().filter(move |attr| attr.name == name)
+}
+
+pub(crate) fn has_deprecated_attribute(attributes: &[AttributeInfo]) -> bool {
+    attributes
+        .iter()
+        .any(|attr| matches!(attr.data, AttributeData::Deprecated))
+}
+
+pub(crate) fn has_synthetic_attribute(attributes: &[AttributeInfo]) -> bool {
+    attributes
+        .iter()
+        .any(|attr| matches!(attr.data, AttributeData::Synthetic))
+}
+
+pub(crate) fn signature_attribute<'a, 'b>(
+    attributes: &'b [AttributeInfo<'a>],
+) -> Option<&'b Cow<'a, str>> {
+    attributes.iter().find_map(|attr| match &attr.data {
+        AttributeData::Signature(signature) => Some(signature),
+        _ => None,
+    })
+}
+
+pub(crate) fn validate_signature<'a, T>(
+    attributes: &[AttributeInfo<'a>],
+    parse: fn(&Cow<'a, str>, usize) -> Result<T, ParseError>,
+    max_depth: usize,
+) -> Result<(), ParseError> {
+    for (i, attr) in attributes.iter().enumerate() {
+        if let AttributeData::Signature(signature) = &attr.data {
+            parse(signature, max_depth).map_err(|e| err!(e, "Signature attribute {}", i))?;
+        }
+    }
+    Ok(())
+}
+
+// Section 4.7.18 and 4.7.19 of the JVM spec say the parameter annotations correspond to the
+// parameters in the method descriptor. However, compilers may omit synthetic and mandated
+// parameters (e.g. the outer instance parameter of an inner class constructor), so only
+// having more annotated parameters than the descriptor declares is treated as an error.
+pub(crate) fn validate_parameter_annotations(
+    attributes: &[AttributeInfo],
+    parameter_count: usize,
+) -> Result<(), ParseError> {
+    for (i, attr) in attributes.iter().enumerate() {
+        let (kind, annotations) = match &attr.data {
+            AttributeData::RuntimeVisibleParameterAnnotations(annotations) => {
+                ("RuntimeVisibleParameterAnnotations", annotations)
+            }
+            AttributeData::RuntimeInvisibleParameterAnnotations(annotations) => {
+                ("RuntimeInvisibleParameterAnnotations", annotations)
+            }
+            _ => continue,
+        };
+        if annotations.len() > parameter_count {
+            fail!(
+                (
+                    "Found annotations for {} parameters; descriptor has {}",
+                    annotations.len(),
+                    parameter_count
+                ),
+                ("{} attribute {}", kind, i)
+            );
+        }
+    }
+    Ok(())
+}
+
 pub(crate) fn read_attributes<'a>(
     bytes: &'a [u8],
     ix: &mut usize,
     pool: &[CafeRc<ConstantPoolEntry<'a>>],
     opts: &ParseOptions,
 ) -> Result<Vec<AttributeInfo<'a>>, ParseError> {
-    let count = read_u2(bytes, ix)?;
+    let count = read_u2(bytes, ix).map_err(|e| err!(e, "attributes count"))?;
     let mut attributes = Vec::with_capacity(count.into());
     for i in 0..count {
+        // Running out of bytes here usually means the attributes count was corrupted, so
+        // report that rather than the failure to read the next name
+        if *ix >= bytes.len() {
+            fail!("Expected {} attributes, found {}", count, i);
+        }
         let name =
             read_cp_utf8(bytes, ix, pool).map_err(|e| err!(e, "name field of attribute {}", i))?;
-        let length = read_u4(bytes, ix)? as usize;
-        let expected_end_ix = *ix + length;
-        if bytes.len() < expected_end_ix {
-            fail!(
-                "Unexpected end of stream reading attributes at index {}",
-                *ix
-            );
+        let length = read_u4(bytes, ix)
+            .map_err(|e| err!(e, "length field of {} attribute {}", name, i))?
+            as usize;
+        let start_ix = *ix;
+        let expected_end_ix = checked_end(bytes, start_ix, length, "attributes")
+            .map_err(|e| err!(e, "{} attribute {}", name, i))?;
+        // Restrict the attribute parsing to the declared length, so that a malformed
+        // attribute fails here rather than consuming the bytes of what follows it
+        let bytes = &bytes[..expected_end_ix];
+        if opts.skip_attribute_bodies {
+            *ix = expected_end_ix;
+            attributes.push(AttributeInfo {
+                name,
+                data: AttributeData::Other(Cow::Borrowed(&bytes[start_ix..])),
+            });
+            continue;
         }
         let data = match name.deref() {
             "ConstantValue" => {
@@ -1085,6 +1579,11 @@ pub(crate) fn read_attributes<'a>(
                     .map_err(|e| err!(e, "StackMapTable attribute {}", i))?;
                 AttributeData::StackMapTable(stackmaptable_data)
             }
+            "StackMap" => {
+                let stackmap_data = read_stackmap_data(bytes, ix, pool)
+                    .map_err(|e| err!(e, "StackMap attribute {}", i))?;
+                AttributeData::StackMap(stackmap_data)
+            }
             "Exceptions" => {
                 let exceptions_data = read_exceptions_data(bytes, ix, pool)
                     .map_err(|e| err!(e, "Exceptions attribute {}", i))?;
@@ -1100,6 +1599,12 @@ pub(crate) fn read_attributes<'a>(
                 let class_name = read_cp_classinfo(bytes, ix, pool)
                     .map_err(|e| err!(e, "class info of EnclosingMethod attribute {}", i))?;
                 let method = read_cp_nameandtype_opt(bytes, ix, pool)
+                    .and_then(|method| {
+                        if let Some(method) = &method {
+                            ensure_enclosing_method(method)?;
+                        }
+                        Ok(method)
+                    })
                     .map_err(|e| err!(e, "method info of EnclosingMethod attribute {}", i))?;
                 AttributeData::EnclosingMethod { class_name, method }
             }
@@ -1109,7 +1614,8 @@ pub(crate) fn read_attributes<'a>(
             }
             "Signature" => {
                 ensure_length(length, 2).map_err(|e| err!(e, "Signature attribute {}", i))?;
-                // TODO: validate signature
+                // The signature grammar depends on what the attribute is attached to,
+                // so it is validated by the owner via validate_signature in strict mode.
                 AttributeData::Signature(
                     read_cp_utf8(bytes, ix, pool)
                         .map_err(|e| err!(e, "signature field of Signature attribute {}", i))?,
@@ -1123,15 +1629,14 @@ pub(crate) fn read_attributes<'a>(
                 )
             }
             "SourceDebugExtension" => {
-                let modified_utf8_data = &bytes[*ix..*ix + length];
-                *ix += length;
-                let debug_str = cesu8::from_java_cesu8(modified_utf8_data).map_err(|e| {
-                    err!(
-                        ("{}", e),
-                        ("modified utf8 data of SourceDebugExtension attribute {}", i)
-                    )
-                })?;
-                AttributeData::SourceDebugExtension(debug_str)
+                let modified_utf8_data = read_bytes(bytes, ix, length, "SourceDebugExtension")
+                    .map_err(|e| err!(e, "SourceDebugExtension attribute {}", i))?;
+                match cesu8::from_java_cesu8(modified_utf8_data) {
+                    Ok(debug_str) => AttributeData::SourceDebugExtension(debug_str),
+                    Err(_) => {
+                        AttributeData::SourceDebugExtensionBytes(Cow::Borrowed(modified_utf8_data))
+                    }
+                }
             }
             "LineNumberTable" => {
                 let linenumber_data = read_linenumber_data(bytes, ix)
@@ -1144,7 +1649,7 @@ pub(crate) fn read_attributes<'a>(
                 AttributeData::LocalVariableTable(localvariable_data)
             }
             "LocalVariableTypeTable" => {
-                let localvariabletype_data = read_localvariabletype_data(bytes, ix, pool)
+                let localvariabletype_data = read_localvariabletype_data(bytes, ix, pool, opts)
                     .map_err(|e| err!(e, "LocalVariableTypeTable attribute {}", i))?;
                 AttributeData::LocalVariableTypeTable(localvariabletype_data)
             }
@@ -1153,38 +1658,47 @@ pub(crate) fn read_attributes<'a>(
                 AttributeData::Deprecated
             }
             "RuntimeVisibleAnnotations" => {
-                let annotation_data = read_annotation_data(bytes, ix, pool)
+                let annotation_data = read_annotation_data(bytes, ix, pool, opts.max_nesting_depth)
                     .map_err(|e| err!(e, "RuntimeVisibleAnnotations attribute {}", i))?;
                 AttributeData::RuntimeVisibleAnnotations(annotation_data)
             }
             "RuntimeInvisibleAnnotations" => {
-                let annotation_data = read_annotation_data(bytes, ix, pool)
+                let annotation_data = read_annotation_data(bytes, ix, pool, opts.max_nesting_depth)
                     .map_err(|e| err!(e, "RuntimeInvisibleAnnotations attribute {}", i))?;
                 AttributeData::RuntimeInvisibleAnnotations(annotation_data)
             }
             "RuntimeVisibleParameterAnnotations" => {
-                let annotation_data = read_parameter_annotation_data(bytes, ix, pool)
-                    .map_err(|e| err!(e, "RuntimeVisibleParameterAnnotations attribute {}", i))?;
+                let annotation_data =
+                    read_parameter_annotation_data(bytes, ix, pool, opts.max_nesting_depth)
+                        .map_err(|e| {
+                            err!(e, "RuntimeVisibleParameterAnnotations attribute {}", i)
+                        })?;
                 AttributeData::RuntimeVisibleParameterAnnotations(annotation_data)
             }
             "RuntimeInvisibleParameterAnnotations" => {
-                let annotation_data = read_parameter_annotation_data(bytes, ix, pool)
-                    .map_err(|e| err!(e, "RuntimeInvisibleParameterAnnotations attribute {}", i))?;
+                let annotation_data =
+                    read_parameter_annotation_data(bytes, ix, pool, opts.max_nesting_depth)
+                        .map_err(|e| {
+                            err!(e, "RuntimeInvisibleParameterAnnotations attribute {}", i)
+                        })?;
                 AttributeData::RuntimeInvisibleParameterAnnotations(annotation_data)
             }
             "RuntimeVisibleTypeAnnotations" => {
-                let annotation_data = read_type_annotation_data(bytes, ix, pool)
-                    .map_err(|e| err!(e, "RuntimeVisibleTypeAnnotations attribute {}", i))?;
+                let annotation_data =
+                    read_type_annotation_data(bytes, ix, pool, opts.max_nesting_depth)
+                        .map_err(|e| err!(e, "RuntimeVisibleTypeAnnotations attribute {}", i))?;
                 AttributeData::RuntimeVisibleTypeAnnotations(annotation_data)
             }
             "RuntimeInvisibleTypeAnnotations" => {
-                let annotation_data = read_type_annotation_data(bytes, ix, pool)
-                    .map_err(|e| err!(e, "RuntimeInvisibleTypeAnnotations attribute {}", i))?;
+                let annotation_data =
+                    read_type_annotation_data(bytes, ix, pool, opts.max_nesting_depth)
+                        .map_err(|e| err!(e, "RuntimeInvisibleTypeAnnotations attribute {}", i))?;
                 AttributeData::RuntimeInvisibleTypeAnnotations(annotation_data)
             }
             "AnnotationDefault" => {
-                let element_value = read_annotation_element_value(bytes, ix, pool)
-                    .map_err(|e| err!(e, "AnnotationDefault attribute {}", i))?;
+                let element_value =
+                    read_annotation_element_value(bytes, ix, pool, opts.max_nesting_depth)
+                        .map_err(|e| err!(e, "AnnotationDefault attribute {}", i))?;
                 AttributeData::AnnotationDefault(element_value)
             }
             "BootstrapMethods" => {
@@ -1234,15 +1748,542 @@ pub(crate) fn read_attributes<'a>(
                     .map_err(|e| err!(e, "Record attribute {}", i))?;
                 AttributeData::Record(record_data)
             }
-            _ => {
-                *ix += length;
-                AttributeData::Other(&bytes[*ix - length..*ix])
-            }
+            _ => AttributeData::Other(Cow::Borrowed(
+                read_bytes(bytes, ix, length, "attribute")
+                    .map_err(|e| err!(e, "{} attribute {}", name, i))?,
+            )),
         };
         if expected_end_ix != *ix {
-            fail!("Length mismatch when reading attribute {}", i);
+            fail!(
+                "Length mismatch when reading {} attribute {} at index {}; declared length {} but consumed {}",
+                name,
+                i,
+                start_ix,
+                length,
+                *ix - start_ix
+            );
         }
         attributes.push(AttributeInfo { name, data });
     }
     Ok(attributes)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::signatures::parse_field_signature;
+
+    fn line_numbers(start_pcs: &[u16]) -> AttributeInfo<'static> {
+        AttributeInfo {
+            name: Cow::Borrowed("LineNumberTable"),
+            data: AttributeData::LineNumberTable(
+                start_pcs
+                    .iter()
+                    .map(|start_pc| LineNumberEntry {
+                        start_pc: *start_pc,
+                        line_number: 1,
+                    })
+                    .collect(),
+            ),
+        }
+    }
+
+    fn local_variable(start_pc: u16, length: u16) -> AttributeInfo<'static> {
+        AttributeInfo {
+            name: Cow::Borrowed("LocalVariableTable"),
+            data: AttributeData::LocalVariableTable(vec![LocalVariableEntry {
+                start_pc,
+                length,
+                name: Cow::Borrowed("x"),
+                descriptor: parse_field_descriptor(&Cow::Borrowed("I"), 0).unwrap(),
+                index: 0,
+            }]),
+        }
+    }
+
+    #[test]
+    fn test_line_number_ranges() {
+        assert!(validate_code_ranges(5, &[line_numbers(&[0, 2, 4])]).is_ok());
+        assert_eq!(
+            validate_code_ranges(5, &[line_numbers(&[0, 5])])
+                .unwrap_err()
+                .to_string(),
+            "Invalid start_pc 5 for line number 1 of LineNumberTable attribute 0"
+        );
+    }
+
+    #[test]
+    fn test_local_variable_ranges() {
+        assert!(validate_code_ranges(5, &[local_variable(0, 5)]).is_ok());
+        assert!(validate_code_ranges(5, &[local_variable(4, 1)]).is_ok());
+        assert!(validate_code_ranges(5, &[local_variable(4, 2)]).is_err());
+        assert!(validate_code_ranges(5, &[local_variable(5, 0)]).is_err());
+        assert!(validate_code_ranges(0, &[local_variable(0, 0)]).is_err());
+    }
+
+    fn exception_handler(
+        start_pc: u16,
+        end_pc: u16,
+        handler_pc: u16,
+    ) -> ExceptionTableEntry<'static> {
+        ExceptionTableEntry {
+            start_pc,
+            end_pc,
+            handler_pc,
+            catch_type: None,
+        }
+    }
+
+    #[test]
+    fn test_exception_table() {
+        assert!(validate_exception_table(5, &[exception_handler(0, 5, 4)]).is_ok());
+        assert_eq!(
+            validate_exception_table(5, &[exception_handler(0, 6, 4)])
+                .unwrap_err()
+                .to_string(),
+            "Invalid range 0 to 6 for exception table entry 0"
+        );
+        assert!(validate_exception_table(5, &[exception_handler(2, 2, 4)]).is_err());
+        assert_eq!(
+            validate_exception_table(5, &[exception_handler(0, 2, 4), exception_handler(0, 2, 5)])
+                .unwrap_err()
+                .to_string(),
+            "Invalid handler_pc 5 for exception table entry 1"
+        );
+        let mut entry = exception_handler(0, 2, 4);
+        assert_eq!(entry.catch_type_name(), "any");
+        entry.catch_type = Some(Cow::Borrowed("java/io/IOException"));
+        assert_eq!(entry.catch_type_name(), "java/io/IOException");
+    }
+
+    fn type_annotation(target_type: TypeAnnotationTarget) -> AttributeInfo<'static> {
+        AttributeInfo {
+            name: Cow::Borrowed("RuntimeInvisibleTypeAnnotations"),
+            data: AttributeData::RuntimeInvisibleTypeAnnotations(vec![TypeAnnotation {
+                target_kind: TypeAnnotationTargetKind::LocalVariable,
+                target_type,
+                target_path: Vec::new(),
+                annotation: Annotation {
+                    type_descriptor: parse_field_descriptor(&Cow::Borrowed("LNonNull;"), 0)
+                        .unwrap(),
+                    elements: Vec::new(),
+                },
+            }]),
+        }
+    }
+
+    #[test]
+    fn test_type_annotation_ranges() {
+        let localvar = |start_pc, length| {
+            type_annotation(TypeAnnotationTarget::LocalVar(vec![
+                TypeAnnotationLocalVarTargetEntry {
+                    start_pc,
+                    length,
+                    index: 1,
+                },
+            ]))
+        };
+        assert!(validate_code_ranges(5, &[localvar(0, 5)]).is_ok());
+        assert_eq!(
+            validate_code_ranges(5, &[localvar(3, 3)])
+                .unwrap_err()
+                .to_string(),
+            "Invalid length 3 for start_pc 3 for localvar target entry 0 of type annotation 0 of RuntimeInvisibleTypeAnnotations attribute 0"
+        );
+        let offset = |offset| type_annotation(TypeAnnotationTarget::Offset { offset });
+        assert!(validate_code_ranges(5, &[offset(4)]).is_ok());
+        assert_eq!(
+            validate_code_ranges(5, &[offset(5)])
+                .unwrap_err()
+                .to_string(),
+            "Invalid offset 5 for type annotation 0 of RuntimeInvisibleTypeAnnotations attribute 0"
+        );
+        assert!(validate_code_ranges(0, &[type_annotation(TypeAnnotationTarget::Empty)]).is_ok());
+    }
+
+    #[test]
+    fn test_record_component_signature() {
+        let mut component = RecordComponentEntry {
+            name: Cow::Borrowed("values"),
+            descriptor: parse_field_descriptor(&Cow::Borrowed("Ljava/util/List;"), 0).unwrap(),
+            attributes: Vec::new(),
+            max_nesting_depth: crate::DEFAULT_MAX_NESTING_DEPTH,
+        };
+        assert_eq!(component.signature(), Ok(None));
+        component.attributes.push(AttributeInfo {
+            name: Cow::Borrowed("Signature"),
+            data: AttributeData::Signature(Cow::Borrowed("Ljava/util/List<TT;>;")),
+        });
+        assert_eq!(
+            component.signature(),
+            Ok(Some(
+                parse_field_signature(&Cow::Borrowed("Ljava/util/List<TT;>;")).unwrap()
+            ))
+        );
+        component.attributes[0].data = AttributeData::Signature(Cow::Borrowed("Ljava/util/List<"));
+        assert!(component.signature().is_err());
+        // The signature is parsed with the nesting depth the component was parsed with
+        let depth = crate::DEFAULT_MAX_NESTING_DEPTH;
+        component.attributes[0].data = AttributeData::Signature(Cow::Owned(format!(
+            "{}Ljava/lang/Object;{}",
+            "Ljava/util/List<".repeat(depth),
+            ">;".repeat(depth)
+        )));
+        assert!(component.signature().is_err());
+        component.max_nesting_depth = depth + 1;
+        assert!(component.signature().is_ok());
+    }
+
+    #[test]
+    fn test_enclosing_method() {
+        let method = |name, descriptor| NameAndType {
+            name: Cow::Borrowed(name),
+            descriptor: Cow::Borrowed(descriptor),
+        };
+        assert!(ensure_enclosing_method(&method("run", "()V")).is_ok());
+        assert!(ensure_enclosing_method(&method("<init>", "(I)V")).is_ok());
+        assert!(ensure_enclosing_method(&method("<clinit>", "()V")).is_err());
+        assert!(ensure_enclosing_method(&method("run", "I")).is_err());
+    }
+
+    #[test]
+    fn test_parameter_annotations() {
+        let attributes = vec![AttributeInfo {
+            name: Cow::Borrowed("RuntimeVisibleParameterAnnotations"),
+            data: AttributeData::RuntimeVisibleParameterAnnotations(vec![
+                ParameterAnnotation {
+                    annotations: Vec::new(),
+                },
+                ParameterAnnotation {
+                    annotations: Vec::new(),
+                },
+            ]),
+        }];
+        assert!(validate_parameter_annotations(&attributes, 2).is_ok());
+        assert!(validate_parameter_annotations(&attributes, 3).is_ok());
+        assert_eq!(
+            validate_parameter_annotations(&attributes, 1)
+                .unwrap_err()
+                .to_string(),
+            "Found annotations for 2 parameters; descriptor has 1 for RuntimeVisibleParameterAnnotations attribute 0"
+        );
+    }
+
+    fn module(name: &'static str, requires: &[&'static str]) -> ModuleData<'static> {
+        ModuleData {
+            name: Cow::Borrowed(name),
+            access_flags: ModuleAccessFlags::empty(),
+            version: None,
+            requires: requires
+                .iter()
+                .map(|name| ModuleRequireEntry {
+                    name: Cow::Borrowed(*name),
+                    flags: ModuleRequiresFlags::MANDATED,
+                    version: None,
+                })
+                .collect(),
+            exports: Vec::new(),
+            opens: Vec::new(),
+            uses: Vec::new(),
+            provides: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_module_requires() {
+        assert!(validate_module(&module("java.base", &[]), 61).is_ok());
+        assert!(validate_module(&module("java.base", &["java.logging"]), 61).is_err());
+        assert!(validate_module(&module("some.module", &["java.base"]), 61).is_ok());
+        assert!(validate_module(&module("some.module", &[]), 61).is_err());
+        assert_eq!(
+            validate_module(
+                &module("some.module", &["java.base", "java.sql", "java.sql"]),
+                61
+            )
+            .unwrap_err()
+            .to_string(),
+            "Duplicate requires entry java.sql"
+        );
+
+        let mut transitive = module("some.module", &["java.base"]);
+        transitive.requires[0].flags = ModuleRequiresFlags::TRANSITIVE;
+        assert!(validate_module(&transitive, 53).is_ok());
+        assert!(validate_module(&transitive, 54).is_err());
+    }
+
+    #[test]
+    fn test_module_opens_and_provides() {
+        let mut open = module("some.module", &["java.base"]);
+        open.access_flags = ModuleAccessFlags::OPEN;
+        open.opens.push(ModuleOpensEntry {
+            package_name: Cow::Borrowed("some/package"),
+            flags: ModuleOpensFlags::empty(),
+            opens_to: Vec::new(),
+        });
+        assert!(validate_module(&open, 61).is_err());
+        open.access_flags = ModuleAccessFlags::empty();
+        assert!(validate_module(&open, 61).is_ok());
+
+        open.provides.push(ModuleProvidesEntry {
+            service_interface_name: Cow::Borrowed("some/Service"),
+            provides_with: Vec::new(),
+        });
+        assert!(validate_module(&open, 61).is_err());
+    }
+
+    #[test]
+    fn test_unknown_attributes() {
+        let pool = vec![
+            CafeRc::new(ConstantPoolEntry::Zero),
+            CafeRc::new(ConstantPoolEntry::Utf8(Cow::Borrowed("CustomAttribute"))),
+            CafeRc::new(ConstantPoolEntry::Utf8(Cow::Borrowed("Deprecated"))),
+        ];
+        let bytes = [0, 2, 0, 1, 0, 0, 0, 3, 0xca, 0xfe, 0xba, 0, 2, 0, 0, 0, 0];
+        let mut ix = 0;
+        let attributes = read_attributes(&bytes, &mut ix, &pool, &ParseOptions::default()).unwrap();
+        assert_eq!(ix, bytes.len());
+        assert_eq!(attributes.len(), 2);
+        assert_eq!(attributes[0].name, "CustomAttribute");
+        assert!(matches!(
+            &attributes[0].data,
+            AttributeData::Other(data) if data.as_ref() == [0xca, 0xfe, 0xba]
+        ));
+        assert!(matches!(attributes[1].data, AttributeData::Deprecated));
+    }
+
+    #[test]
+    fn test_annotation_nesting_depth() {
+        let pool = vec![
+            CafeRc::new(ConstantPoolEntry::Zero),
+            CafeRc::new(ConstantPoolEntry::Utf8(Cow::Borrowed("AnnotationDefault"))),
+        ];
+        // An AnnotationDefault attribute holding an array nested in an array, and so on
+        let nested = |depth: usize| {
+            let mut bytes = vec![0, 1, 0, 1];
+            bytes.extend_from_slice(&(3 * depth as u32).to_be_bytes());
+            for _ in 1..depth {
+                bytes.extend_from_slice(&[b'[', 0, 1]);
+            }
+            bytes.extend_from_slice(&[b'[', 0, 0]);
+            bytes
+        };
+        let mut opts = ParseOptions::default();
+        opts.max_nesting_depth(4);
+        let bytes = nested(4);
+        assert!(read_attributes(&bytes, &mut 0, &pool, &opts).is_ok());
+        let bytes = nested(5);
+        assert_eq!(
+            read_attributes(&bytes, &mut 0, &pool, &opts)
+                .unwrap_err()
+                .to_string(),
+            "Annotation element values are nested more deeply than the maximum nesting depth for array index 0 of array index 0 of array index 0 of array index 0 of AnnotationDefault attribute 0"
+        );
+        let bytes = nested(crate::DEFAULT_MAX_NESTING_DEPTH);
+        assert!(read_attributes(&bytes, &mut 0, &pool, &ParseOptions::default()).is_ok());
+    }
+
+    #[test]
+    fn test_local_variable_type_signature() {
+        let read = |signature: &'static str, opts: &ParseOptions| {
+            let pool = vec![
+                CafeRc::new(ConstantPoolEntry::Zero),
+                CafeRc::new(ConstantPoolEntry::Utf8(Cow::Borrowed(
+                    "LocalVariableTypeTable",
+                ))),
+                CafeRc::new(ConstantPoolEntry::Utf8(Cow::Borrowed("x"))),
+                CafeRc::new(ConstantPoolEntry::Utf8(Cow::Borrowed(signature))),
+            ];
+            let bytes = [0, 1, 0, 1, 0, 0, 0, 12, 0, 1, 0, 0, 0, 0, 0, 2, 0, 3, 0, 0];
+            read_attributes(&bytes, &mut 0, &pool, opts).map(|_| ())
+        };
+        let lenient = ParseOptions::default();
+        let mut strict = ParseOptions::default();
+        strict.strict(true);
+        assert!(read("La/B<La/C;>;", &lenient).is_ok());
+        assert!(read("La/B<La/C;>;", &strict).is_ok());
+        assert!(read("La/B<", &lenient).is_ok());
+        assert_eq!(
+            read("La/B<", &strict).unwrap_err().to_string(),
+            "Unexpected end of signature for signature for variable 0 of LocalVariableTypeTable attribute 0"
+        );
+
+        let mut shallow = ParseOptions::default();
+        shallow.max_nesting_depth(2);
+        assert!(read("La/B<La/C;>;", &shallow).is_ok());
+        assert_eq!(
+            read("La/B<La/B<La/C;>;>;", &shallow).unwrap_err().to_string(),
+            "Signature is nested more deeply than the maximum nesting depth 2 at index 10 for signature for variable 0 of LocalVariableTypeTable attribute 0"
+        );
+    }
+
+    #[test]
+    fn test_attributes_count_mismatch() {
+        let pool = vec![
+            CafeRc::new(ConstantPoolEntry::Zero),
+            CafeRc::new(ConstantPoolEntry::Utf8(Cow::Borrowed("Deprecated"))),
+        ];
+        let opts = ParseOptions::default();
+        let bytes = [0, 2, 0, 1, 0, 0, 0, 0];
+        assert_eq!(
+            read_attributes(&bytes, &mut 0, &pool, &opts)
+                .unwrap_err()
+                .to_string(),
+            "Expected 2 attributes, found 1"
+        );
+        let bytes = [0, 2, 0, 1, 0, 0, 0, 0, 0];
+        assert_eq!(
+            read_attributes(&bytes, &mut 0, &pool, &opts)
+                .unwrap_err()
+                .to_string(),
+            "Unexpected end of stream reading u2 at index 8 for name field of attribute 1"
+        );
+        let bytes = [0, 1, 0, 1, 0, 0, 0, 0];
+        assert_eq!(
+            read_attributes(&bytes, &mut 0, &pool, &opts).unwrap().len(),
+            1
+        );
+    }
+
+    #[test]
+    fn test_stack_map() {
+        let pool = vec![
+            CafeRc::new(ConstantPoolEntry::Zero),
+            CafeRc::new(ConstantPoolEntry::Utf8(Cow::Borrowed("StackMap"))),
+        ];
+        // One frame at offset 5 with locals [int, long] and stack [uninitialized(3)]
+        let bytes = [
+            0, 1, 0, 1, 0, 0, 0, 13, 0, 1, 0, 5, 0, 2, 1, 4, 0, 1, 8, 0, 3,
+        ];
+        let mut ix = 0;
+        let attributes = read_attributes(&bytes, &mut ix, &pool, &ParseOptions::default()).unwrap();
+        assert_eq!(ix, bytes.len());
+        let frames = match &attributes[0].data {
+            AttributeData::StackMap(frames) => frames,
+            data => panic!("Unexpected attribute data {:?}", data),
+        };
+        assert_eq!(frames.len(), 1);
+        assert_eq!(frames[0].offset, 5);
+        assert!(matches!(
+            frames[0].locals[..],
+            [VerificationType::Integer, VerificationType::Long]
+        ));
+        assert!(matches!(
+            frames[0].stack[..],
+            [VerificationType::Uninitialized { code_offset: 3 }]
+        ));
+    }
+
+    #[test]
+    fn test_source_debug_extension() {
+        let pool = vec![
+            CafeRc::new(ConstantPoolEntry::Zero),
+            CafeRc::new(ConstantPoolEntry::Utf8(Cow::Borrowed(
+                "SourceDebugExtension",
+            ))),
+        ];
+        let bytes = [
+            0, 2, 0, 1, 0, 0, 0, 2, b'o', b'k', 0, 1, 0, 0, 0, 2, b'x', 0xff,
+        ];
+        let mut ix = 0;
+        let attributes = read_attributes(&bytes, &mut ix, &pool, &ParseOptions::default()).unwrap();
+        assert_eq!(ix, bytes.len());
+        assert!(matches!(
+            &attributes[0].data,
+            AttributeData::SourceDebugExtension(data) if data == "ok"
+        ));
+        assert!(matches!(
+            &attributes[1].data,
+            AttributeData::SourceDebugExtensionBytes(data) if data.as_ref() == [b'x', 0xff]
+        ));
+    }
+
+    #[test]
+    fn test_attribute_length_mismatch() {
+        let pool = vec![
+            CafeRc::new(ConstantPoolEntry::Zero),
+            CafeRc::new(ConstantPoolEntry::Utf8(Cow::Borrowed("SourceFile"))),
+        ];
+        // A SourceFile attribute with a declared length of 3 instead of 2
+        let bytes = [0, 1, 0, 1, 0, 0, 0, 3, 0, 1, 0];
+        let mut ix = 0;
+        assert_eq!(
+            read_attributes(&bytes, &mut ix, &pool, &ParseOptions::default())
+                .unwrap_err()
+                .to_string(),
+            "Unexpected length 3 for SourceFile attribute 0"
+        );
+        // An Exceptions attribute whose declared length cuts off its last entry
+        let pool = vec![
+            CafeRc::new(ConstantPoolEntry::Zero),
+            CafeRc::new(ConstantPoolEntry::Utf8(Cow::Borrowed("Exceptions"))),
+        ];
+        let bytes = [0, 1, 0, 1, 0, 0, 0, 2, 0, 1, 0, 1];
+        let mut ix = 0;
+        assert_eq!(
+            read_attributes(&bytes, &mut ix, &pool, &ParseOptions::default())
+                .unwrap_err()
+                .to_string(),
+            "Unexpected end of stream reading u2 at index 10 for exception 0 of Exceptions attribute 0"
+        );
+        // An Exceptions attribute whose declared length has trailing bytes
+        let bytes = [0, 1, 0, 1, 0, 0, 0, 4, 0, 0, 0, 1];
+        let mut ix = 0;
+        assert_eq!(
+            read_attributes(&bytes, &mut ix, &pool, &ParseOptions::default())
+                .unwrap_err()
+                .to_string(),
+            "Length mismatch when reading Exceptions attribute 0 at index 8; declared length 4 but consumed 2"
+        );
+    }
+
+    #[test]
+    fn test_skip_attribute_bodies() {
+        let pool = vec![
+            CafeRc::new(ConstantPoolEntry::Zero),
+            CafeRc::new(ConstantPoolEntry::Utf8(Cow::Borrowed("SourceFile"))),
+        ];
+        // The SourceFile attribute refers to an invalid constant pool index, but that
+        // isn't detected since the body is not decoded
+        let bytes = [0, 1, 0, 1, 0, 0, 0, 2, 0, 7];
+        let mut opts = ParseOptions::default();
+        let mut ix = 0;
+        assert!(read_attributes(&bytes, &mut ix, &pool, &opts).is_err());
+        opts.skip_attribute_bodies(true);
+        let mut ix = 0;
+        let attributes = read_attributes(&bytes, &mut ix, &pool, &opts).unwrap();
+        assert_eq!(ix, bytes.len());
+        assert_eq!(attributes[0].name, "SourceFile");
+        assert!(
+            matches!(&attributes[0].data, AttributeData::Other(data) if data.as_ref() == [0, 7])
+        );
+    }
+
+    #[test]
+    fn test_type_annotation_target_kind() {
+        let pool = vec![
+            CafeRc::new(ConstantPoolEntry::Zero),
+            CafeRc::new(ConstantPoolEntry::Utf8(Cow::Borrowed("La/Nullable;"))),
+        ];
+        // A return type annotation and a cast annotation, both without a type path
+        let bytes = [0, 2, 0x14, 0, 0, 1, 0, 0, 0x47, 0, 5, 0, 0, 0, 1, 0, 0];
+        let mut ix = 0;
+        let annotations = read_type_annotation_data(&bytes, &mut ix, &pool, 8).unwrap();
+        assert_eq!(ix, bytes.len());
+        assert_eq!(
+            annotations[0].target_kind,
+            TypeAnnotationTargetKind::MethodReturn
+        );
+        assert!(matches!(
+            annotations[0].target_type,
+            TypeAnnotationTarget::Empty
+        ));
+        assert_eq!(annotations[1].target_kind, TypeAnnotationTargetKind::Cast);
+        assert!(matches!(
+            annotations[1].target_type,
+            TypeAnnotationTarget::TypeArgument {
+                offset: 5,
+                type_argument_index: 0
+            }
+        ));
+    }
+}
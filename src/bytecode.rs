@@ -204,7 +204,7 @@ pub enum Opcode<'a> {
     Tableswitch(RangeTable),
 }
 
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub struct ByteCode<'a> {
     /// This contains pairs of (offset, opcode) where offset is the offset of the start
     /// of the opcode in bytes from the beginning of the data section of the Code attribute.
@@ -680,6 +680,84 @@ fn read_opcodes<'a>(
     Ok(opcodes)
 }
 
+// Returns the offset of every constant pool index operand in the given code, along with
+// whether it is a one-byte index (as used by ldc) rather than a two-byte one. This walks the
+// raw instructions without resolving anything, so the indices can be rewritten in place.
+pub(crate) fn constant_pool_operands(code: &[u8]) -> Result<Vec<(usize, bool)>, ParseError> {
+    let mut operands = Vec::new();
+    let mut ix = 0;
+    while ix < code.len() {
+        let opcode = read_u1(code, &mut ix)?;
+        let operand_length = match opcode {
+            0x00..=0x0f | 0x1a..=0x35 | 0x3b..=0x83 | 0x85..=0x98 | 0xac..=0xb1 => 0,
+            0xbe | 0xbf | 0xc2 | 0xc3 | 0xca | 0xfe | 0xff => 0,
+            0x10 | 0x15..=0x19 | 0x36..=0x3a | 0xa9 | 0xbc => 1,
+            0x11 | 0x84 | 0x99..=0xa8 | 0xc6 | 0xc7 => 2,
+            0xc8 | 0xc9 => 4,
+            0x12 => {
+                operands.push((ix, true));
+                1
+            }
+            0x13 | 0x14 | 0xb2..=0xb8 | 0xbb | 0xbd | 0xc0 | 0xc1 => {
+                operands.push((ix, false));
+                2
+            }
+            0xc5 => {
+                operands.push((ix, false));
+                3
+            }
+            0xb9 | 0xba => {
+                operands.push((ix, false));
+                4
+            }
+            0xaa | 0xab => {
+                // Skip past padding to reach 4-byte alignment
+                ix = (ix + 3) & !0x3;
+                let _default = read_u4(code, &mut ix)?;
+                let entry_count = if opcode == 0xaa {
+                    let low = read_u4(code, &mut ix)? as i32;
+                    let high = read_u4(code, &mut ix)? as i32;
+                    i64::from(high) - i64::from(low) + 1
+                } else {
+                    i64::from(read_u4(code, &mut ix)? as i32) * 2
+                };
+                match usize::try_from(entry_count) {
+                    Ok(n) => n * 4,
+                    _ => fail!("Invalid switch table size at index {}", ix - 4),
+                }
+            }
+            0xc4 => match read_u1(code, &mut ix)? {
+                0x84 => 4,
+                0x15..=0x19 | 0x36..=0x3a | 0xa9 => 2,
+                v => fail!(
+                    "Unexpected opcode {} inside wide modifier at index {}",
+                    v,
+                    ix - 1
+                ),
+            },
+            v => fail!("Unexpected opcode {} at index {}", v, ix - 1),
+        };
+        if code.len() - ix < operand_length {
+            fail!(
+                "Unexpected end of code reading operands of opcode {} at index {}",
+                opcode,
+                ix - 1
+            );
+        }
+        ix += operand_length;
+    }
+    Ok(operands)
+}
+
+// Reads a constant pool index operand at an offset returned by constant_pool_operands.
+pub(crate) fn read_operand(code: &[u8], offset: usize, narrow: bool) -> u16 {
+    if narrow {
+        code[offset].into()
+    } else {
+        u16::from_be_bytes([code[offset], code[offset + 1]])
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -715,4 +793,49 @@ mod tests {
         assert_eq!(bytecode.get_opcode_index(4), Some(2));
         assert_eq!(bytecode.get_opcode_index(5), None);
     }
+
+    #[test]
+    fn test_legacy_opcodes() {
+        // jsr_w +9; jsr +4; nop; astore_1; wide iinc 300, -2; ret 1; wide ret 300
+        let code = [
+            0xc9, 0, 0, 0, 9, 0xa8, 0, 4, 0x00, 0x4c, 0xc4, 0x84, 0x01, 0x2c, 0xff, 0xfe, 0xa9, 1,
+            0xc4, 0xa9, 0x01, 0x2c,
+        ];
+        let bytecode = ByteCode::from(&code, &[]).unwrap();
+        let opcodes: Vec<String> = bytecode
+            .opcodes
+            .iter()
+            .map(|(offset, opcode)| format!("{} {:?}", offset, opcode))
+            .collect();
+        assert_eq!(
+            opcodes,
+            vec![
+                "0 Jsr(9)",
+                "5 Jsr(4)",
+                "8 Nop",
+                "9 Astore(1)",
+                "10 Iinc(300, -2)",
+                "16 Ret(1)",
+                "18 Ret(300)",
+            ]
+        );
+        // jsr to the middle of its own operand
+        assert!(ByteCode::from(&[0xa8, 0, 1], &[]).is_err());
+    }
+
+    #[test]
+    fn test_constant_pool_operands() {
+        // ldc #3; wide iload 300; tableswitch (1 padding byte) with one jump; nop; nop;
+        // invokeinterface #260, 1; ldc_w #7
+        let code = [
+            0x12, 3, 0xc4, 0x15, 0x01, 0x2c, 0xaa, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+            0, 0, 0, 0xb9, 0x01, 0x04, 1, 0, 0x13, 0, 7,
+        ];
+        assert_eq!(
+            constant_pool_operands(&code).unwrap(),
+            vec![(1, true), (27, false), (32, false)]
+        );
+        assert!(constant_pool_operands(&[0x13, 0]).is_err());
+        assert!(constant_pool_operands(&[0xcb]).is_err());
+    }
 }
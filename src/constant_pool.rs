@@ -1,6 +1,6 @@
 use std::borrow::Cow;
-#[cfg(not(feature = "threadsafe"))]
 use std::cell::RefCell;
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::ops::Deref;
 #[cfg(feature = "threadsafe")]
 use std::ops::DerefMut;
@@ -10,11 +10,14 @@ use std::sync::Mutex;
 use crate::descriptors::FieldDescriptor;
 use crate::descriptors::{
     is_array_descriptor, is_field_descriptor, is_method_descriptor, parse_array_descriptor,
+    rename_class_name, rename_in_descriptor,
 };
 use crate::names::{
     is_binary_name, is_module_name, is_unqualified_method_name, is_unqualified_name,
 };
-use crate::{read_u1, read_u2, read_u4, read_u8, CafeRc, ParseError};
+use crate::{read_bytes, read_u1, read_u2, read_u4, read_u8, CafeRc, ParseError};
+
+pub use builder::ConstantPoolBuilder;
 
 #[cfg(not(feature = "threadsafe"))]
 type CafeCell<T> = RefCell<T>;
@@ -37,14 +40,27 @@ impl<'a> ConstantPoolRef<'a> {
             ConstantPoolRef::Unresolved(ix) => {
                 let target = *ix as usize;
                 if target == my_index {
-                    fail!("Constant pool entry at index {} could not be resolved due to self-reference", my_index);
+                    return Err(err!(
+                        "Constant pool entry at index {} could not be resolved due to self-reference",
+                        my_index
+                    )
+                    .with_constant_pool_index(*ix));
                 }
                 if target >= pool.len() {
-                    fail!(
+                    return Err(err!(
                         "Constant pool entry at index {} references out-of-bounds index {}",
                         my_index,
                         target
-                    );
+                    )
+                    .with_constant_pool_index(*ix));
+                }
+                if let ConstantPoolEntry::Unused = pool[target].deref() {
+                    return Err(err!(
+                        "Constant pool entry at index {} references {}",
+                        my_index,
+                        unusable_slot(target)
+                    )
+                    .with_constant_pool_index(*ix));
                 }
                 *self = ConstantPoolRef::Resolved(pool[target].clone());
                 Ok(())
@@ -59,6 +75,39 @@ impl<'a> ConstantPoolRef<'a> {
             ConstantPoolRef::Resolved(target) => target,
         }
     }
+
+    fn unresolved_index(&self) -> Option<u16> {
+        match self {
+            ConstantPoolRef::Unresolved(ix) => Some(*ix),
+            ConstantPoolRef::Resolved(_) => None,
+        }
+    }
+
+    // Returns the index of the entry this refers to. The target of a resolved reference is
+    // looked up by its address in `indices`, which maps the address of each entry in the
+    // constant pool to its index.
+    fn index(&self, indices: &HashMap<usize, u16>) -> Result<u16, ParseError> {
+        match self {
+            ConstantPoolRef::Unresolved(ix) => Ok(*ix),
+            ConstantPoolRef::Resolved(target) => indices
+                .get(&(CafeRc::as_ptr(target) as usize))
+                .copied()
+                .ok_or_else(|| {
+                    err!("Constant pool reference to an entry outside the constant pool")
+                }),
+        }
+    }
+}
+
+// Describes a reference to the slot after a Long or Double entry, which can't be used. The
+// usual cause is a class file written by a tool that doesn't give Long and Double entries
+// two slots, which shifts every later index by one, so the message says as much.
+fn unusable_slot(index: usize) -> String {
+    format!(
+        "index {}, the unusable slot after the Long or Double at index {} (long/double slot accounting mismatch; was the class file written without the extra slot for Long and Double entries?)",
+        index,
+        index - 1
+    )
 }
 
 #[cfg(not(feature = "threadsafe"))]
@@ -81,7 +130,14 @@ trait CafeCellDeref<'a> {
         cp_index: usize,
         pool: &[CafeRc<ConstantPoolEntry<'a>>],
     ) -> Result<(), ParseError>;
+    fn resolve_chain(
+        &self,
+        cp_index: usize,
+        pool: &[CafeRc<ConstantPoolEntry<'a>>],
+        depth: usize,
+    ) -> Result<(), ParseError>;
     fn ensure_type(&self, allowed: ConstantPoolEntryTypes) -> Result<(), ParseError>;
+    fn index(&self, indices: &HashMap<usize, u16>) -> Result<u16, ParseError>;
 }
 
 impl<'a> CafeCellDeref<'a> for CafeCell<ConstantPoolRef<'a>> {
@@ -96,9 +152,45 @@ impl<'a> CafeCellDeref<'a> for CafeCell<ConstantPoolRef<'a>> {
         return self.lock().unwrap().deref_mut().resolve(cp_index, pool);
     }
 
+    fn resolve_chain(
+        &self,
+        cp_index: usize,
+        pool: &[CafeRc<ConstantPoolEntry<'a>>],
+        depth: usize,
+    ) -> Result<(), ParseError> {
+        // The borrow must end before following the reference, which may lead back here
+        #[cfg(not(feature = "threadsafe"))]
+        let target = self.borrow().unresolved_index();
+        #[cfg(feature = "threadsafe")]
+        let target = self.lock().unwrap().deref().unresolved_index();
+        let target = match target {
+            Some(target) => usize::from(target),
+            None => return Ok(()),
+        };
+        // Bad targets are left for resolve to report
+        if target != cp_index && target < pool.len() {
+            if depth == 0 {
+                return Err(err!(
+                    "Constant pool entry at index {} is part of a chain of references that is cyclic or too long",
+                    cp_index
+                )
+                .with_constant_pool_index(target as u16));
+            }
+            pool[target].resolve_chain(target, pool, depth - 1)?;
+        }
+        self.resolve(cp_index, pool)
+    }
+
     fn ensure_type(&self, allowed: ConstantPoolEntryTypes) -> Result<(), ParseError> {
         peel!(self).ensure_type(allowed)
     }
+
+    fn index(&self, indices: &HashMap<usize, u16>) -> Result<u16, ParseError> {
+        #[cfg(not(feature = "threadsafe"))]
+        return self.borrow().index(indices);
+        #[cfg(feature = "threadsafe")]
+        return self.lock().unwrap().deref().index(indices);
+    }
 }
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Hash)]
@@ -138,8 +230,28 @@ bitflags! {
 
         const NEW_METHOD_REFS = Self::METHOD_REF.bits() | Self::INTERFACE_METHOD_REF.bits();
         const CONSTANTS = Self::INTEGER.bits() | Self::FLOAT.bits() | Self::LONG.bits() | Self::DOUBLE.bits() | Self::STRING.bits();
-        const BOOTSTRAP_ARGUMENT = Self::CONSTANTS.bits() | Self::CLASS_INFO.bits() | Self::METHOD_HANDLE.bits() | Self::METHOD_TYPE.bits();
         const LOADABLE = Self::CONSTANTS.bits() | Self::CLASS_INFO.bits() | Self::METHOD_HANDLE.bits() | Self::METHOD_TYPE.bits() | Self::DYNAMIC.bits();
+        const BOOTSTRAP_ARGUMENT = Self::LOADABLE.bits();
+    }
+}
+
+impl ConstantPoolEntryTypes {
+    // Names of the individual types in this set, matching ConstantPoolEntry::kind_name.
+    fn kind_names(self) -> String {
+        let mut names = Vec::new();
+        if self.contains(ConstantPoolEntryTypes::ZERO) {
+            names.push("Zero");
+        }
+        names.extend(
+            CONSTANT_KINDS
+                .iter()
+                .filter(|(_, _, _, entry_type, _)| self.contains(*entry_type))
+                .map(|(_, _, name, _, _)| *name),
+        );
+        if self.contains(ConstantPoolEntryTypes::UNUSED) {
+            names.push("Unused");
+        }
+        names.join(" or ")
     }
 }
 
@@ -149,7 +261,7 @@ type BootstrapMethodRef = u16;
 pub(crate) enum ConstantPoolEntry<'a> {
     Zero,
     Utf8(Cow<'a, str>),
-    Utf8Bytes(&'a [u8]),
+    Utf8Bytes(Cow<'a, [u8]>),
     Integer(i32),
     Float(f32),
     Long(i64),
@@ -207,31 +319,50 @@ impl<'a> ConstantPoolEntry<'a> {
         }
     }
 
-    fn get_type(&self) -> ConstantPoolEntryTypes {
+    // Like resolve, but first resolves the entries this one references, following at
+    // most `depth` further references. A reference is only marked as resolved once
+    // everything reachable through it is, so an entry whose references are all resolved
+    // needs no further work.
+    fn resolve_chain(
+        &self,
+        my_index: usize,
+        pool: &[CafeRc<ConstantPoolEntry<'a>>],
+        depth: usize,
+    ) -> Result<(), ParseError> {
         match self {
-            ConstantPoolEntry::Zero => ConstantPoolEntryTypes::ZERO,
-            ConstantPoolEntry::Utf8(_) | ConstantPoolEntry::Utf8Bytes(_) => {
-                ConstantPoolEntryTypes::UTF8
-            }
-            ConstantPoolEntry::Integer(_) => ConstantPoolEntryTypes::INTEGER,
-            ConstantPoolEntry::Float(_) => ConstantPoolEntryTypes::FLOAT,
-            ConstantPoolEntry::Long(_) => ConstantPoolEntryTypes::LONG,
-            ConstantPoolEntry::Double(_) => ConstantPoolEntryTypes::DOUBLE,
-            ConstantPoolEntry::ClassInfo(_) => ConstantPoolEntryTypes::CLASS_INFO,
-            ConstantPoolEntry::String(_) => ConstantPoolEntryTypes::STRING,
-            ConstantPoolEntry::FieldRef(_, _) => ConstantPoolEntryTypes::FIELD_REF,
-            ConstantPoolEntry::MethodRef(_, _) => ConstantPoolEntryTypes::METHOD_REF,
-            ConstantPoolEntry::InterfaceMethodRef(_, _) => {
-                ConstantPoolEntryTypes::INTERFACE_METHOD_REF
+            ConstantPoolEntry::Zero
+            | ConstantPoolEntry::Utf8(_)
+            | ConstantPoolEntry::Utf8Bytes(_)
+            | ConstantPoolEntry::Integer(_)
+            | ConstantPoolEntry::Float(_)
+            | ConstantPoolEntry::Long(_)
+            | ConstantPoolEntry::Double(_)
+            | ConstantPoolEntry::Unused => Ok(()),
+
+            ConstantPoolEntry::ClassInfo(x)
+            | ConstantPoolEntry::String(x)
+            | ConstantPoolEntry::MethodHandle(_, x)
+            | ConstantPoolEntry::MethodType(x)
+            | ConstantPoolEntry::Dynamic(_, x)
+            | ConstantPoolEntry::InvokeDynamic(_, x)
+            | ConstantPoolEntry::ModuleInfo(x)
+            | ConstantPoolEntry::PackageInfo(x) => x.resolve_chain(my_index, pool, depth),
+
+            ConstantPoolEntry::FieldRef(x, y)
+            | ConstantPoolEntry::MethodRef(x, y)
+            | ConstantPoolEntry::InterfaceMethodRef(x, y)
+            | ConstantPoolEntry::NameAndType(x, y) => {
+                x.resolve_chain(my_index, pool, depth)?;
+                y.resolve_chain(my_index, pool, depth)
             }
-            ConstantPoolEntry::NameAndType(_, _) => ConstantPoolEntryTypes::NAME_AND_TYPE,
-            ConstantPoolEntry::MethodHandle(_, _) => ConstantPoolEntryTypes::METHOD_HANDLE,
-            ConstantPoolEntry::MethodType(_) => ConstantPoolEntryTypes::METHOD_TYPE,
-            ConstantPoolEntry::Dynamic(_, _) => ConstantPoolEntryTypes::DYNAMIC,
-            ConstantPoolEntry::InvokeDynamic(_, _) => ConstantPoolEntryTypes::INVOKE_DYNAMIC,
-            ConstantPoolEntry::ModuleInfo(_) => ConstantPoolEntryTypes::MODULE_INFO,
-            ConstantPoolEntry::PackageInfo(_) => ConstantPoolEntryTypes::PACKAGE_INFO,
-            ConstantPoolEntry::Unused => ConstantPoolEntryTypes::UNUSED,
+        }
+    }
+
+    fn get_type(&self) -> ConstantPoolEntryTypes {
+        match (self.kind(), self) {
+            (Some(kind), _) => kind.entry_type(),
+            (None, ConstantPoolEntry::Zero) => ConstantPoolEntryTypes::ZERO,
+            (None, _) => ConstantPoolEntryTypes::UNUSED,
         }
     }
 
@@ -313,11 +444,48 @@ impl<'a> ConstantPoolEntry<'a> {
         }
     }
 
+    // Returns the type of this entry, or None for the placeholder entries that have no tag.
+    fn kind(&self) -> Option<ConstantKind> {
+        Some(match self {
+            ConstantPoolEntry::Zero | ConstantPoolEntry::Unused => return None,
+            ConstantPoolEntry::Utf8(_) | ConstantPoolEntry::Utf8Bytes(_) => ConstantKind::Utf8,
+            ConstantPoolEntry::Integer(_) => ConstantKind::Integer,
+            ConstantPoolEntry::Float(_) => ConstantKind::Float,
+            ConstantPoolEntry::Long(_) => ConstantKind::Long,
+            ConstantPoolEntry::Double(_) => ConstantKind::Double,
+            ConstantPoolEntry::ClassInfo(_) => ConstantKind::ClassInfo,
+            ConstantPoolEntry::String(_) => ConstantKind::String,
+            ConstantPoolEntry::FieldRef(_, _) => ConstantKind::FieldRef,
+            ConstantPoolEntry::MethodRef(_, _) => ConstantKind::MethodRef,
+            ConstantPoolEntry::InterfaceMethodRef(_, _) => ConstantKind::InterfaceMethodRef,
+            ConstantPoolEntry::NameAndType(_, _) => ConstantKind::NameAndType,
+            ConstantPoolEntry::MethodHandle(_, _) => ConstantKind::MethodHandle,
+            ConstantPoolEntry::MethodType(_) => ConstantKind::MethodType,
+            ConstantPoolEntry::Dynamic(_, _) => ConstantKind::Dynamic,
+            ConstantPoolEntry::InvokeDynamic(_, _) => ConstantKind::InvokeDynamic,
+            ConstantPoolEntry::ModuleInfo(_) => ConstantKind::ModuleInfo,
+            ConstantPoolEntry::PackageInfo(_) => ConstantKind::PackageInfo,
+        })
+    }
+
+    /// Returns a human-readable name for the type of this entry, for use in error messages.
+    fn kind_name(&self) -> &'static str {
+        match (self.kind(), self) {
+            (Some(kind), _) => kind.name(),
+            (None, ConstantPoolEntry::Zero) => "Zero",
+            (None, _) => "Unused",
+        }
+    }
+
     fn ensure_type(&self, allowed: ConstantPoolEntryTypes) -> Result<(), ParseError> {
         if allowed.contains(self.get_type()) {
             Ok(())
         } else {
-            fail!("Unexpected constant pool reference type")
+            fail!(
+                "Unexpected constant pool reference type; expected {} but found {}",
+                allowed.kind_names(),
+                self.kind_name()
+            )
         }
     }
 
@@ -378,7 +546,7 @@ impl<'a> ConstantPoolEntry<'a> {
                     fail!("Invalid field descriptor")
                 }
             }
-            _ => panic!("Attempting to get descriptor from non-NameAndType constant pool entry!"),
+            _ => fail!("Unexpected constant pool reference type"),
         }
     }
 
@@ -395,37 +563,98 @@ impl<'a> ConstantPoolEntry<'a> {
         }
     }
 
-    fn utf8(&self) -> Cow<'a, str> {
+    // Additional checks from sections 4.4.2 and 4.4.8 of the JVM spec on the names of
+    // referenced members, applied only when parsing in strict mode.
+    fn validate_strict(&self) -> Result<(), ParseError> {
         match self {
-            ConstantPoolEntry::Utf8(x) => x.clone(),
-            _ => panic!("Attempting to get utf-8 data from non-utf8 constant pool entry!"),
+            ConstantPoolEntry::MethodRef(_, y) => {
+                let name_and_type = peel!(y).name_and_type()?;
+                if name_and_type.name.starts_with('<') {
+                    if name_and_type.name != "<init>" {
+                        fail!("Invalid method name {}", name_and_type.name);
+                    }
+                    if !name_and_type.descriptor.ends_with(")V") {
+                        fail!(
+                            "Non-void method descriptor {} for <init>",
+                            name_and_type.descriptor
+                        );
+                    }
+                }
+                Ok(())
+            }
+            ConstantPoolEntry::InterfaceMethodRef(_, y) => {
+                let name = peel!(y).name_and_type()?.name;
+                if name.starts_with('<') {
+                    fail!("Invalid interface method name {}", name);
+                }
+                Ok(())
+            }
+            ConstantPoolEntry::MethodHandle(x, y) => {
+                let name = match peel!(y).deref() {
+                    ConstantPoolEntry::FieldRef(_, m)
+                    | ConstantPoolEntry::MethodRef(_, m)
+                    | ConstantPoolEntry::InterfaceMethodRef(_, m) => peel!(m).name_and_type()?.name,
+                    _ => fail!("Unexpected constant pool reference type"),
+                };
+                match x {
+                    ReferenceKind::InvokeVirtual
+                    | ReferenceKind::InvokeStatic
+                    | ReferenceKind::InvokeSpecial
+                    | ReferenceKind::InvokeInterface
+                        if name == "<init>" || name == "<clinit>" =>
+                    {
+                        fail!(
+                            "Invalid method name {} for method handle of kind {:?}",
+                            name,
+                            x
+                        )
+                    }
+                    ReferenceKind::NewInvokeSpecial if name != "<init>" => {
+                        fail!(
+                            "Invalid method name {} for method handle of kind {:?}",
+                            name,
+                            x
+                        )
+                    }
+                    _ => Ok(()),
+                }
+            }
+            _ => Ok(()),
         }
     }
 
-    fn string_literal(&self) -> LiteralConstant<'a> {
+    fn utf8(&self) -> Result<Cow<'a, str>, ParseError> {
         match self {
-            ConstantPoolEntry::Utf8(x) => LiteralConstant::String(x.clone()),
-            ConstantPoolEntry::Utf8Bytes(x) => LiteralConstant::StringBytes(x),
-            _ => panic!("Attempting to get utf-8 data from non-utf8 constant pool entry!"),
+            ConstantPoolEntry::Utf8(x) => Ok(x.clone()),
+            ConstantPoolEntry::Utf8Bytes(_) => {
+                fail!("Attempting to get utf-8 data from non-utf8 constant pool entry!")
+            }
+            _ => fail!("Unexpected constant pool reference type"),
         }
     }
 
-    fn classinfo(&self) -> Cow<'a, str> {
+    fn string_literal(&self) -> Result<LiteralConstant<'a>, ParseError> {
+        match self {
+            ConstantPoolEntry::Utf8(x) => Ok(LiteralConstant::String(x.clone())),
+            ConstantPoolEntry::Utf8Bytes(x) => Ok(LiteralConstant::StringBytes(x.clone())),
+            _ => fail!("Unexpected constant pool reference type"),
+        }
+    }
+
+    fn classinfo(&self) -> Result<Cow<'a, str>, ParseError> {
         match self {
             ConstantPoolEntry::ClassInfo(x) => peel!(x).utf8(),
-            _ => panic!("Attempting to get classinfo data from non-classinfo constant pool entry!"),
+            _ => fail!("Unexpected constant pool reference type"),
         }
     }
 
-    fn name_and_type(&self) -> NameAndType<'a> {
+    fn name_and_type(&self) -> Result<NameAndType<'a>, ParseError> {
         match self {
-            ConstantPoolEntry::NameAndType(x, y) => NameAndType {
-                name: peel!(x).utf8(),
-                descriptor: peel!(y).utf8(),
-            },
-            _ => panic!(
-                "Attempting to get name and type data from non-name-and-type constant pool entry!"
-            ),
+            ConstantPoolEntry::NameAndType(x, y) => Ok(NameAndType {
+                name: peel!(x).utf8()?,
+                descriptor: peel!(y).utf8()?,
+            }),
+            _ => fail!("Unexpected constant pool reference type"),
         }
     }
 }
@@ -444,14 +673,7 @@ fn read_constant_utf8<'a>(
     ix: &mut usize,
 ) -> Result<ConstantPoolEntry<'a>, ParseError> {
     let length = read_u2(bytes, ix)? as usize;
-    if bytes.len() < *ix + length {
-        fail!(
-            "Unexpected end of stream reading CONSTANT_Utf8 at index {}",
-            *ix
-        );
-    }
-    let modified_utf8_data = &bytes[*ix..*ix + length];
-    *ix += length;
+    let modified_utf8_data = read_bytes(bytes, ix, length, "CONSTANT_Utf8")?;
     // If a Java file contains a literal string such as:
     //   String watchThis = "\uDAB9\uBAF5";
     // then that gets encoded into the constant pool as a CONSTANT_Utf8 entry with bytes:
@@ -463,7 +685,9 @@ fn read_constant_utf8<'a>(
     // as Rust strings. Only literal Java strings need to be able to expose the raw bytes.
     match cesu8::from_java_cesu8(modified_utf8_data) {
         Ok(rust_str) => Ok(ConstantPoolEntry::Utf8(rust_str)),
-        _ => Ok(ConstantPoolEntry::Utf8Bytes(modified_utf8_data)),
+        _ => Ok(ConstantPoolEntry::Utf8Bytes(Cow::Borrowed(
+            modified_utf8_data,
+        ))),
     }
 }
 
@@ -633,11 +857,416 @@ fn read_constant_package<'a>(
 
 fn resolve_constant_pool(constant_pool: &[CafeRc<ConstantPoolEntry>]) -> Result<(), ParseError> {
     for (i, cp_entry) in constant_pool.iter().enumerate() {
-        cp_entry.resolve(i, constant_pool)?;
+        cp_entry
+            .resolve(i, constant_pool)
+            .map_err(|e| e.with_constant_pool_index(i as u16))?;
     }
     Ok(())
 }
 
+// The longest chain of references in a valid constant pool, from a MethodHandle through
+// a MethodRef and its ClassInfo to a Utf8 entry.
+const MAX_REFERENCE_CHAIN: usize = 3;
+
+// Resolves the entry at the given index along with everything it refers to, for constant
+// pools read with ParseOptions::defer_resolution. This is called before an entry is
+// looked at, and does nothing for entries that are already resolved (which is all of them
+// in a constant pool that was not deferred). Since the pool was not validated, a chain of
+// references longer than any valid one is reported as an error, which also stops cycles.
+pub(crate) fn resolve_entry(
+    constant_pool: &[CafeRc<ConstantPoolEntry>],
+    index: usize,
+) -> Result<(), ParseError> {
+    constant_pool[index]
+        .resolve_chain(index, constant_pool, MAX_REFERENCE_CHAIN)
+        .map_err(|e| e.with_constant_pool_index(index as u16))
+}
+
+// Resolving an entry only clones the pointers to the entries it references, without
+// looking inside them, so the entries can be resolved in any order. The error reported
+// is the one for the lowest index, the same as the sequential version.
+#[cfg(feature = "parallel")]
+fn resolve_constant_pool_parallel(
+    constant_pool: &[CafeRc<ConstantPoolEntry>],
+) -> Result<(), ParseError> {
+    use rayon::prelude::*;
+
+    let error = constant_pool
+        .par_iter()
+        .enumerate()
+        .find_map_first(|(i, cp_entry)| {
+            cp_entry
+                .resolve(i, constant_pool)
+                .err()
+                .map(|e| e.with_constant_pool_index(i as u16))
+        });
+    match error {
+        Some(e) => Err(e),
+        None => Ok(()),
+    }
+}
+
+// Validating an entry only reads the entries it refers to, so once the whole pool is
+// resolved the entries can be validated in any order. As with resolution, the error
+// reported is the one for the lowest index.
+#[cfg(feature = "parallel")]
+fn validate_constant_pool_parallel(
+    constant_pool: &[CafeRc<ConstantPoolEntry>],
+    major_version: u16,
+) -> Result<(), ParseError> {
+    use rayon::prelude::*;
+
+    let error = constant_pool
+        .par_iter()
+        .enumerate()
+        .find_map_first(|(i, cp_entry)| {
+            cp_entry
+                .validate(major_version)
+                .err()
+                .map(|e| err!(e, "constant pool entry {}", i).with_constant_pool_index(i as u16))
+        });
+    match error {
+        Some(e) => Err(e),
+        None => Ok(()),
+    }
+}
+
+/// An entry in a [`ResolvedConstantPool`]. References to other entries are given as their
+/// indices in the constant pool.
+#[derive(Clone, Debug, PartialEq)]
+pub enum ResolvedEntry<'a> {
+    /// Index 0, and the index after each Long and Double entry, which can't be used.
+    Unusable,
+    Utf8(Cow<'a, str>),
+    /// A Utf8 entry whose contents are not valid modified utf-8.
+    Utf8Bytes(Cow<'a, [u8]>),
+    Integer(i32),
+    Float(f32),
+    Long(i64),
+    Double(f64),
+    ClassInfo {
+        name: u16,
+    },
+    String {
+        value: u16,
+    },
+    FieldRef {
+        class: u16,
+        name_and_type: u16,
+    },
+    MethodRef {
+        class: u16,
+        name_and_type: u16,
+    },
+    InterfaceMethodRef {
+        class: u16,
+        name_and_type: u16,
+    },
+    NameAndType {
+        name: u16,
+        descriptor: u16,
+    },
+    MethodHandle {
+        kind: ReferenceKind,
+        reference: u16,
+    },
+    MethodType {
+        descriptor: u16,
+    },
+    Dynamic {
+        bootstrap_method_attr_index: u16,
+        name_and_type: u16,
+    },
+    InvokeDynamic {
+        bootstrap_method_attr_index: u16,
+        name_and_type: u16,
+    },
+    ModuleInfo {
+        name: u16,
+    },
+    PackageInfo {
+        name: u16,
+    },
+}
+
+impl<'a> ResolvedEntry<'a> {
+    /// Returns the type of this entry, or None if it is unusable.
+    #[must_use]
+    pub fn kind(&self) -> Option<ConstantKind> {
+        Some(match self {
+            ResolvedEntry::Unusable => return None,
+            ResolvedEntry::Utf8(_) | ResolvedEntry::Utf8Bytes(_) => ConstantKind::Utf8,
+            ResolvedEntry::Integer(_) => ConstantKind::Integer,
+            ResolvedEntry::Float(_) => ConstantKind::Float,
+            ResolvedEntry::Long(_) => ConstantKind::Long,
+            ResolvedEntry::Double(_) => ConstantKind::Double,
+            ResolvedEntry::ClassInfo { .. } => ConstantKind::ClassInfo,
+            ResolvedEntry::String { .. } => ConstantKind::String,
+            ResolvedEntry::FieldRef { .. } => ConstantKind::FieldRef,
+            ResolvedEntry::MethodRef { .. } => ConstantKind::MethodRef,
+            ResolvedEntry::InterfaceMethodRef { .. } => ConstantKind::InterfaceMethodRef,
+            ResolvedEntry::NameAndType { .. } => ConstantKind::NameAndType,
+            ResolvedEntry::MethodHandle { .. } => ConstantKind::MethodHandle,
+            ResolvedEntry::MethodType { .. } => ConstantKind::MethodType,
+            ResolvedEntry::Dynamic { .. } => ConstantKind::Dynamic,
+            ResolvedEntry::InvokeDynamic { .. } => ConstantKind::InvokeDynamic,
+            ResolvedEntry::ModuleInfo { .. } => ConstantKind::ModuleInfo,
+            ResolvedEntry::PackageInfo { .. } => ConstantKind::PackageInfo,
+        })
+    }
+}
+
+/// An immutable copy of a class's constant pool, as returned by
+/// [`ClassFile::constant_pool_snapshot`](crate::ClassFile::constant_pool_snapshot). The
+/// entries are laid out exactly as in the class file, with references between them given
+/// as plain indices, so it can be walked and held onto without any of the interior
+/// mutability of the parsed class.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ResolvedConstantPool<'a> {
+    entries: Vec<ResolvedEntry<'a>>,
+}
+
+impl<'a> ResolvedConstantPool<'a> {
+    /// Returns all the entries, indexed by their constant pool index.
+    #[must_use]
+    pub fn entries(&self) -> &[ResolvedEntry<'a>] {
+        &self.entries
+    }
+
+    /// Returns the entry at the given index, or None if the index is out of bounds.
+    #[must_use]
+    pub fn get(&self, index: u16) -> Option<&ResolvedEntry<'a>> {
+        self.entries.get(usize::from(index))
+    }
+
+    /// Returns the string at the given index, or None if that is not a Utf8 entry.
+    #[must_use]
+    pub fn utf8(&self, index: u16) -> Option<&str> {
+        match self.get(index) {
+            Some(ResolvedEntry::Utf8(s)) => Some(s),
+            _ => None,
+        }
+    }
+}
+
+// Converts the constant pool into a snapshot with references between entries given as
+// indices. This is the one place that maps resolved references back to indices; copying,
+// renaming and rebuilding the pool all start from the snapshot.
+pub(crate) fn snapshot_constant_pool<'a>(
+    constant_pool: &[CafeRc<ConstantPoolEntry<'a>>],
+) -> Result<ResolvedConstantPool<'a>, ParseError> {
+    let indices: HashMap<usize, u16> = constant_pool
+        .iter()
+        .enumerate()
+        .map(|(i, cp_entry)| (CafeRc::as_ptr(cp_entry) as usize, i as u16))
+        .collect();
+    let index = |x: &CafeCell<ConstantPoolRef<'a>>| x.index(&indices);
+    let entries = constant_pool
+        .iter()
+        .enumerate()
+        .map(|(i, cp_entry)| {
+            Ok(match cp_entry.deref() {
+                ConstantPoolEntry::Zero | ConstantPoolEntry::Unused => ResolvedEntry::Unusable,
+                ConstantPoolEntry::Utf8(x) => ResolvedEntry::Utf8(x.clone()),
+                ConstantPoolEntry::Utf8Bytes(x) => ResolvedEntry::Utf8Bytes(x.clone()),
+                ConstantPoolEntry::Integer(v) => ResolvedEntry::Integer(*v),
+                ConstantPoolEntry::Float(v) => ResolvedEntry::Float(*v),
+                ConstantPoolEntry::Long(v) => ResolvedEntry::Long(*v),
+                ConstantPoolEntry::Double(v) => ResolvedEntry::Double(*v),
+                ConstantPoolEntry::ClassInfo(x) => ResolvedEntry::ClassInfo { name: index(x)? },
+                ConstantPoolEntry::String(x) => ResolvedEntry::String { value: index(x)? },
+                ConstantPoolEntry::FieldRef(x, y) => ResolvedEntry::FieldRef {
+                    class: index(x)?,
+                    name_and_type: index(y)?,
+                },
+                ConstantPoolEntry::MethodRef(x, y) => ResolvedEntry::MethodRef {
+                    class: index(x)?,
+                    name_and_type: index(y)?,
+                },
+                ConstantPoolEntry::InterfaceMethodRef(x, y) => ResolvedEntry::InterfaceMethodRef {
+                    class: index(x)?,
+                    name_and_type: index(y)?,
+                },
+                ConstantPoolEntry::NameAndType(x, y) => ResolvedEntry::NameAndType {
+                    name: index(x)?,
+                    descriptor: index(y)?,
+                },
+                ConstantPoolEntry::MethodHandle(x, y) => ResolvedEntry::MethodHandle {
+                    kind: *x,
+                    reference: index(y)?,
+                },
+                ConstantPoolEntry::MethodType(x) => ResolvedEntry::MethodType {
+                    descriptor: index(x)?,
+                },
+                ConstantPoolEntry::Dynamic(x, y) => ResolvedEntry::Dynamic {
+                    bootstrap_method_attr_index: *x,
+                    name_and_type: index(y)?,
+                },
+                ConstantPoolEntry::InvokeDynamic(x, y) => ResolvedEntry::InvokeDynamic {
+                    bootstrap_method_attr_index: *x,
+                    name_and_type: index(y)?,
+                },
+                ConstantPoolEntry::ModuleInfo(x) => ResolvedEntry::ModuleInfo { name: index(x)? },
+                ConstantPoolEntry::PackageInfo(x) => ResolvedEntry::PackageInfo { name: index(x)? },
+            })
+            .map_err(|e: ParseError| e.with_constant_pool_index(i as u16))
+        })
+        .collect::<Result<_, _>>()?;
+    Ok(ResolvedConstantPool { entries })
+}
+
+// Copies the constant pool, passing the contents of each Utf8 entry (along with its
+// index) through the given function. References between entries are taken from a
+// snapshot and then resolved against the new pool, so the copied entries share structure
+// the same way the originals do.
+fn copy_constant_pool<'a, 'b>(
+    constant_pool: &[CafeRc<ConstantPoolEntry<'a>>],
+    mut copy_utf8: impl FnMut(usize, &Cow<'a, str>) -> Cow<'b, str>,
+    mut copy_bytes: impl FnMut(&Cow<'a, [u8]>) -> Cow<'b, [u8]>,
+) -> Result<Vec<CafeRc<ConstantPoolEntry<'b>>>, ParseError> {
+    let snapshot = snapshot_constant_pool(constant_pool)?;
+    let unresolved = |x: &u16| CafeCell::new(ConstantPoolRef::Unresolved(*x));
+    let copied_pool: Vec<CafeRc<ConstantPoolEntry<'b>>> = snapshot
+        .entries
+        .iter()
+        .enumerate()
+        .map(|(i, entry)| {
+            CafeRc::new(match entry {
+                ResolvedEntry::Unusable if i == 0 => ConstantPoolEntry::Zero,
+                ResolvedEntry::Unusable => ConstantPoolEntry::Unused,
+                ResolvedEntry::Utf8(x) => ConstantPoolEntry::Utf8(copy_utf8(i, x)),
+                ResolvedEntry::Utf8Bytes(x) => ConstantPoolEntry::Utf8Bytes(copy_bytes(x)),
+                ResolvedEntry::Integer(v) => ConstantPoolEntry::Integer(*v),
+                ResolvedEntry::Float(v) => ConstantPoolEntry::Float(*v),
+                ResolvedEntry::Long(v) => ConstantPoolEntry::Long(*v),
+                ResolvedEntry::Double(v) => ConstantPoolEntry::Double(*v),
+                ResolvedEntry::ClassInfo { name } => ConstantPoolEntry::ClassInfo(unresolved(name)),
+                ResolvedEntry::String { value } => ConstantPoolEntry::String(unresolved(value)),
+                ResolvedEntry::FieldRef {
+                    class,
+                    name_and_type,
+                } => ConstantPoolEntry::FieldRef(unresolved(class), unresolved(name_and_type)),
+                ResolvedEntry::MethodRef {
+                    class,
+                    name_and_type,
+                } => ConstantPoolEntry::MethodRef(unresolved(class), unresolved(name_and_type)),
+                ResolvedEntry::InterfaceMethodRef {
+                    class,
+                    name_and_type,
+                } => ConstantPoolEntry::InterfaceMethodRef(
+                    unresolved(class),
+                    unresolved(name_and_type),
+                ),
+                ResolvedEntry::NameAndType { name, descriptor } => {
+                    ConstantPoolEntry::NameAndType(unresolved(name), unresolved(descriptor))
+                }
+                ResolvedEntry::MethodHandle { kind, reference } => {
+                    ConstantPoolEntry::MethodHandle(*kind, unresolved(reference))
+                }
+                ResolvedEntry::MethodType { descriptor } => {
+                    ConstantPoolEntry::MethodType(unresolved(descriptor))
+                }
+                ResolvedEntry::Dynamic {
+                    bootstrap_method_attr_index,
+                    name_and_type,
+                } => ConstantPoolEntry::Dynamic(
+                    *bootstrap_method_attr_index,
+                    unresolved(name_and_type),
+                ),
+                ResolvedEntry::InvokeDynamic {
+                    bootstrap_method_attr_index,
+                    name_and_type,
+                } => ConstantPoolEntry::InvokeDynamic(
+                    *bootstrap_method_attr_index,
+                    unresolved(name_and_type),
+                ),
+                ResolvedEntry::ModuleInfo { name } => {
+                    ConstantPoolEntry::ModuleInfo(unresolved(name))
+                }
+                ResolvedEntry::PackageInfo { name } => {
+                    ConstantPoolEntry::PackageInfo(unresolved(name))
+                }
+            })
+        })
+        .collect();
+    resolve_constant_pool(&copied_pool)?;
+    Ok(copied_pool)
+}
+
+// Counts the entries of each type in the constant pool, keyed by the type names used in
+// error messages. The placeholder entries at index 0 and after longs and doubles are not
+// counted.
+pub(crate) fn count_constant_pool_entries(
+    constant_pool: &[CafeRc<ConstantPoolEntry>],
+) -> BTreeMap<&'static str, usize> {
+    let mut counts = BTreeMap::new();
+    for cp_entry in constant_pool {
+        if !matches!(
+            cp_entry.deref(),
+            ConstantPoolEntry::Zero | ConstantPoolEntry::Unused
+        ) {
+            *counts.entry(cp_entry.kind_name()).or_insert(0) += 1;
+        }
+    }
+    counts
+}
+
+// Deep-copies the constant pool so that it no longer borrows from the class file bytes.
+pub(crate) fn constant_pool_into_static(
+    constant_pool: &[CafeRc<ConstantPoolEntry>],
+) -> Result<Vec<CafeRc<ConstantPoolEntry<'static>>>, ParseError> {
+    copy_constant_pool(
+        constant_pool,
+        |_, x| Cow::Owned(x.to_string()),
+        |x| Cow::Owned(x.to_vec()),
+    )
+}
+
+// Copies the constant pool with references to the class named `from` changed to refer to
+// `to` instead, and returns the copy along with the number of Utf8 entries that changed.
+// Utf8 entries used as the name of a ClassInfo are renamed if they match exactly. Other
+// entries, apart from the contents of String constants, are renamed where they mention the
+// class in descriptor or signature form.
+pub(crate) fn rename_class_in_constant_pool<'a>(
+    constant_pool: &[CafeRc<ConstantPoolEntry<'a>>],
+    from: &str,
+    to: &str,
+) -> Result<(Vec<CafeRc<ConstantPoolEntry<'a>>>, usize), ParseError> {
+    let mut class_names = HashSet::new();
+    let mut strings = HashSet::new();
+    for entry in snapshot_constant_pool(constant_pool)?.entries {
+        match entry {
+            ResolvedEntry::ClassInfo { name } => {
+                class_names.insert(usize::from(name));
+            }
+            ResolvedEntry::String { value } => {
+                strings.insert(usize::from(value));
+            }
+            _ => (),
+        }
+    }
+    let mut renamed = 0;
+    let renamed_pool = copy_constant_pool(
+        constant_pool,
+        |i, x| {
+            let mut value = x.clone();
+            let changed = if class_names.contains(&i) {
+                rename_class_name(&mut value, from, to)
+            } else if strings.contains(&i) {
+                false
+            } else {
+                rename_in_descriptor(&mut value, from, to)
+            };
+            if changed {
+                renamed += 1;
+            }
+            value
+        },
+        |x| x.clone(),
+    )?;
+    Ok((renamed_pool, renamed))
+}
+
 fn validate_constant_pool(
     constant_pool: &[CafeRc<ConstantPoolEntry>],
     major_version: u16,
@@ -645,7 +1274,19 @@ fn validate_constant_pool(
     for (i, cp_entry) in constant_pool.iter().enumerate() {
         cp_entry
             .validate(major_version)
-            .map_err(|e| err!(e, "constant pool entry {}", i))?;
+            .map_err(|e| err!(e, "constant pool entry {}", i).with_constant_pool_index(i as u16))?;
+    }
+    Ok(())
+}
+
+// Applies the checks that are only done when parsing with ParseOptions::strict.
+pub(crate) fn validate_constant_pool_strict(
+    constant_pool: &[CafeRc<ConstantPoolEntry>],
+) -> Result<(), ParseError> {
+    for (i, cp_entry) in constant_pool.iter().enumerate() {
+        cp_entry
+            .validate_strict()
+            .map_err(|e| err!(e, "constant pool entry {}", i).with_constant_pool_index(i as u16))?;
     }
     Ok(())
 }
@@ -654,6 +1295,57 @@ pub(crate) fn read_constant_pool<'a>(
     bytes: &'a [u8],
     ix: &mut usize,
     major_version: u16,
+) -> Result<Vec<CafeRc<ConstantPoolEntry<'a>>>, ParseError> {
+    read_constant_pool_with(
+        bytes,
+        ix,
+        major_version,
+        Some(|constant_pool, major_version| {
+            resolve_constant_pool(constant_pool)?;
+            validate_constant_pool(constant_pool, major_version)
+        }),
+    )
+}
+
+// Like read_constant_pool, but leaves the references between entries unresolved and
+// skips validating the entries, which depends on those references. Entries are instead
+// resolved by resolve_entry as they are used.
+pub(crate) fn read_constant_pool_deferred<'a>(
+    bytes: &'a [u8],
+    ix: &mut usize,
+    major_version: u16,
+) -> Result<Vec<CafeRc<ConstantPoolEntry<'a>>>, ParseError> {
+    read_constant_pool_with(bytes, ix, major_version, None)
+}
+
+// Like read_constant_pool, but resolves the references between entries and then validates
+// the entries in parallel.
+#[cfg(feature = "parallel")]
+pub(crate) fn read_constant_pool_parallel<'a>(
+    bytes: &'a [u8],
+    ix: &mut usize,
+    major_version: u16,
+) -> Result<Vec<CafeRc<ConstantPoolEntry<'a>>>, ParseError> {
+    read_constant_pool_with(
+        bytes,
+        ix,
+        major_version,
+        Some(|constant_pool, major_version| {
+            resolve_constant_pool_parallel(constant_pool)?;
+            validate_constant_pool_parallel(constant_pool, major_version)
+        }),
+    )
+}
+
+// Resolves the references between the entries of a constant pool and validates the entries
+// for the given class file major version.
+type ResolveFn<'a> = fn(&[CafeRc<ConstantPoolEntry<'a>>], u16) -> Result<(), ParseError>;
+
+fn read_constant_pool_with<'a>(
+    bytes: &'a [u8],
+    ix: &mut usize,
+    major_version: u16,
+    resolve: Option<ResolveFn<'a>>,
 ) -> Result<Vec<CafeRc<ConstantPoolEntry<'a>>>, ParseError> {
     let count = read_u2(bytes, ix)?;
     let mut constant_pool = Vec::with_capacity(count.into());
@@ -661,44 +1353,86 @@ pub(crate) fn read_constant_pool<'a>(
     let mut cp_ix = 1;
     while cp_ix < count {
         let constant_type = read_u1(bytes, ix)?;
-        constant_pool.push(CafeRc::new(match constant_type {
-            1 => read_constant_utf8(bytes, ix)?,
-            3 => read_constant_integer(bytes, ix)?,
-            4 => read_constant_float(bytes, ix)?,
-            5 => read_constant_long(bytes, ix)?,
-            6 => read_constant_double(bytes, ix)?,
-            7 => read_constant_class(bytes, ix)?,
-            8 => read_constant_string(bytes, ix)?,
-            9 => read_constant_fieldref(bytes, ix)?,
-            10 => read_constant_methodref(bytes, ix)?,
-            11 => read_constant_interfacemethodref(bytes, ix)?,
-            12 => read_constant_nameandtype(bytes, ix)?,
-            15 if major_version >= 51 => read_constant_methodhandle(bytes, ix)?,
-            16 if major_version >= 51 => read_constant_methodtype(bytes, ix)?,
-            17 if major_version >= 55 => read_constant_dynamic(bytes, ix)?,
-            18 if major_version >= 51 => read_constant_invokedynamic(bytes, ix)?,
-            19 if major_version >= 53 => read_constant_module(bytes, ix)?,
-            20 if major_version >= 53 => read_constant_package(bytes, ix)?,
-            n => fail!(
-                "Unexpected constant pool entry type {} at index {} for classfile major version {}",
-                n,
+        let kind = match ConstantKind::from_tag(constant_type) {
+            Some(kind) if major_version < kind.min_major_version() => fail!(
+                "Constant pool entry type {} at index {} requires classfile major version {} or later, but found {}",
+                constant_type,
                 *ix - 1,
+                kind.min_major_version(),
                 major_version
             ),
+            Some(kind) => kind,
+            // JVMS 4.4 leaves these tags unassigned, so they are illegal rather than unsupported
+            None if matches!(constant_type, 2 | 13 | 14) => fail!(
+                "Constant pool entry type {} at index {} is not assigned by the JVM spec (4.4) and is never valid",
+                constant_type,
+                *ix - 1
+            ),
+            // Entries carry no length, so there is no way to skip over a type we don't know
+            None => fail!(
+                "Unknown constant pool entry type {} at index {} for classfile major version {}",
+                constant_type,
+                *ix - 1,
+                major_version
+            ),
+        };
+        constant_pool.push(CafeRc::new(match kind {
+            ConstantKind::Utf8 => read_constant_utf8(bytes, ix)?,
+            ConstantKind::Integer => read_constant_integer(bytes, ix)?,
+            ConstantKind::Float => read_constant_float(bytes, ix)?,
+            ConstantKind::Long => read_constant_long(bytes, ix)?,
+            ConstantKind::Double => read_constant_double(bytes, ix)?,
+            ConstantKind::ClassInfo => read_constant_class(bytes, ix)?,
+            ConstantKind::String => read_constant_string(bytes, ix)?,
+            ConstantKind::FieldRef => read_constant_fieldref(bytes, ix)?,
+            ConstantKind::MethodRef => read_constant_methodref(bytes, ix)?,
+            ConstantKind::InterfaceMethodRef => read_constant_interfacemethodref(bytes, ix)?,
+            ConstantKind::NameAndType => read_constant_nameandtype(bytes, ix)?,
+            ConstantKind::MethodHandle => read_constant_methodhandle(bytes, ix)?,
+            ConstantKind::MethodType => read_constant_methodtype(bytes, ix)?,
+            ConstantKind::Dynamic => read_constant_dynamic(bytes, ix)?,
+            ConstantKind::InvokeDynamic => read_constant_invokedynamic(bytes, ix)?,
+            ConstantKind::ModuleInfo => read_constant_module(bytes, ix)?,
+            ConstantKind::PackageInfo => read_constant_package(bytes, ix)?,
         }));
         cp_ix += 1;
-        if constant_type == 5 || constant_type == 6 {
+        if let ConstantKind::Long | ConstantKind::Double = kind {
             // long and double types take up two entries in the constant pool,
             // so eat up another index.
             cp_ix += 1;
             constant_pool.push(CafeRc::new(ConstantPoolEntry::Unused));
         }
     }
-    resolve_constant_pool(&constant_pool)?;
-    validate_constant_pool(&constant_pool, major_version)?;
+    if let Some(resolve) = resolve {
+        resolve(&constant_pool, major_version)?;
+    }
     Ok(constant_pool)
 }
 
+thread_local! {
+    // The constant pool indices read by read_cp_ref_any on this thread while recording,
+    // in the order they were read.
+    static RECORDED_REFERENCES: RefCell<Option<Vec<u16>>> = const { RefCell::new(None) };
+}
+
+// Runs f, returning its result along with the constant pool indices it read through the
+// read_cp_* functions, in the order they appear in the class file.
+pub(crate) fn record_cp_references<T>(f: impl FnOnce() -> T) -> (T, Vec<u16>) {
+    let outer = RECORDED_REFERENCES.with(|r| r.replace(Some(Vec::new())));
+    let result = f();
+    let references = RECORDED_REFERENCES.with(|r| r.replace(outer));
+    (result, references.unwrap_or_default())
+}
+
+// Runs f without recording the constant pool indices it reads, for reads of data that
+// isn't written back, such as the parsed bytecode of a Code attribute.
+pub(crate) fn skip_cp_references<T>(f: impl FnOnce() -> T) -> T {
+    let outer = RECORDED_REFERENCES.with(|r| r.replace(None));
+    let result = f();
+    RECORDED_REFERENCES.with(|r| r.replace(outer));
+    result
+}
+
 fn read_cp_ref_any<'a>(
     bytes: &'a [u8],
     ix: &mut usize,
@@ -706,11 +1440,24 @@ fn read_cp_ref_any<'a>(
 ) -> Result<CafeRc<ConstantPoolEntry<'a>>, ParseError> {
     let cp_index = read_u2(bytes, ix)? as usize;
     if cp_index >= pool.len() {
-        fail!(
+        return Err(err!(
             "Out-of-bounds index {} in constant pool reference",
             cp_index
+        )
+        .with_constant_pool_index(cp_index as u16));
+    }
+    if let ConstantPoolEntry::Unused = pool[cp_index].deref() {
+        return Err(
+            err!("Constant pool reference to {}", unusable_slot(cp_index))
+                .with_constant_pool_index(cp_index as u16),
         );
     }
+    resolve_entry(pool, cp_index)?;
+    RECORDED_REFERENCES.with(|r| {
+        if let Some(references) = r.borrow_mut().as_mut() {
+            references.push(cp_index as u16);
+        }
+    });
     Ok(pool[cp_index].clone())
 }
 
@@ -746,7 +1493,7 @@ pub(crate) fn read_cp_classinfo<'a>(
 ) -> Result<Cow<'a, str>, ParseError> {
     let cp_ref = read_cp_ref_any(bytes, ix, pool)?;
     match cp_ref.deref() {
-        ConstantPoolEntry::ClassInfo(x) => Ok(peel!(x).utf8()),
+        ConstantPoolEntry::ClassInfo(x) => peel!(x).utf8(),
         _ => fail!("Unexpected constant pool reference type"),
     }
 }
@@ -759,7 +1506,7 @@ pub(crate) fn read_cp_classinfo_opt<'a>(
     let cp_ref = read_cp_ref_any(bytes, ix, pool)?;
     match cp_ref.deref() {
         ConstantPoolEntry::Zero => Ok(None),
-        ConstantPoolEntry::ClassInfo(x) => Ok(Some(peel!(x).utf8())),
+        ConstantPoolEntry::ClassInfo(x) => Ok(Some(peel!(x).utf8()?)),
         _ => fail!("Unexpected constant pool reference type"),
     }
 }
@@ -771,7 +1518,7 @@ pub(crate) fn read_cp_moduleinfo<'a>(
 ) -> Result<Cow<'a, str>, ParseError> {
     let cp_ref = read_cp_ref_any(bytes, ix, pool)?;
     match cp_ref.deref() {
-        ConstantPoolEntry::ModuleInfo(x) => Ok(peel!(x).utf8()),
+        ConstantPoolEntry::ModuleInfo(x) => peel!(x).utf8(),
         _ => fail!("Unexpected constant pool reference type"),
     }
 }
@@ -783,7 +1530,7 @@ pub(crate) fn read_cp_packageinfo<'a>(
 ) -> Result<Cow<'a, str>, ParseError> {
     let cp_ref = read_cp_ref_any(bytes, ix, pool)?;
     match cp_ref.deref() {
-        ConstantPoolEntry::PackageInfo(x) => Ok(peel!(x).utf8()),
+        ConstantPoolEntry::PackageInfo(x) => peel!(x).utf8(),
         _ => fail!("Unexpected constant pool reference type"),
     }
 }
@@ -803,8 +1550,8 @@ pub(crate) fn read_cp_nameandtype_opt<'a>(
     match cp_ref.deref() {
         ConstantPoolEntry::Zero => Ok(None),
         ConstantPoolEntry::NameAndType(x, y) => Ok(Some(NameAndType {
-            name: peel!(x).utf8(),
-            descriptor: peel!(y).utf8(),
+            name: peel!(x).utf8()?,
+            descriptor: peel!(y).utf8()?,
         })),
         _ => fail!("Unexpected constant pool reference type"),
     }
@@ -817,7 +1564,20 @@ pub enum LiteralConstant<'a> {
     Long(i64),
     Double(f64),
     String(Cow<'a, str>),
-    StringBytes(&'a [u8]),
+    StringBytes(Cow<'a, [u8]>),
+}
+
+impl<'a> LiteralConstant<'a> {
+    /// Returns the value of a string constant, or None if this is a numeric constant. A
+    /// string that is not valid modified utf-8 is converted lossily.
+    #[must_use]
+    pub fn string_value(&self) -> Option<Cow<'_, str>> {
+        match self {
+            LiteralConstant::String(s) => Some(Cow::Borrowed(s)),
+            LiteralConstant::StringBytes(b) => Some(String::from_utf8_lossy(b)),
+            _ => None,
+        }
+    }
 }
 
 pub(crate) fn read_cp_literalconstant<'a>(
@@ -831,7 +1591,7 @@ pub(crate) fn read_cp_literalconstant<'a>(
         ConstantPoolEntry::Float(v) => Ok(LiteralConstant::Float(*v)),
         ConstantPoolEntry::Long(v) => Ok(LiteralConstant::Long(*v)),
         ConstantPoolEntry::Double(v) => Ok(LiteralConstant::Double(*v)),
-        ConstantPoolEntry::String(v) => Ok(peel!(v).string_literal()),
+        ConstantPoolEntry::String(v) => peel!(v).string_literal(),
         _ => fail!("Unexpected constant pool reference type"),
     }
 }
@@ -904,8 +1664,8 @@ pub(crate) fn read_cp_memberref<'a>(
         ConstantPoolEntry::FieldRef(c, m)
         | ConstantPoolEntry::MethodRef(c, m)
         | ConstantPoolEntry::InterfaceMethodRef(c, m) => Ok(MemberRef {
-            class_name: peel!(c).classinfo(),
-            name_and_type: peel!(m).name_and_type(),
+            class_name: peel!(c).classinfo()?,
+            name_and_type: peel!(m).name_and_type()?,
         }),
         _ => fail!("Unexpected constant pool reference type"),
     }
@@ -926,7 +1686,7 @@ pub(crate) fn read_cp_invokedynamic<'a>(
     match cp_ref.deref() {
         ConstantPoolEntry::InvokeDynamic(x, y) => Ok(InvokeDynamic {
             attr_index: *x,
-            name_and_type: peel!(y).name_and_type(),
+            name_and_type: peel!(y).name_and_type()?,
         }),
         _ => fail!("Unexpected constant pool reference type"),
     }
@@ -957,6 +1717,7 @@ pub(crate) fn get_cp_loadable<'a>(
             cp_index
         );
     }
+    resolve_entry(pool, cp_index)?;
     match pool[cp_index].deref() {
         ConstantPoolEntry::Integer(v) => {
             Ok(Loadable::LiteralConstant(LiteralConstant::Integer(*v)))
@@ -964,27 +1725,38 @@ pub(crate) fn get_cp_loadable<'a>(
         ConstantPoolEntry::Float(v) => Ok(Loadable::LiteralConstant(LiteralConstant::Float(*v))),
         ConstantPoolEntry::Long(v) => Ok(Loadable::LiteralConstant(LiteralConstant::Long(*v))),
         ConstantPoolEntry::Double(v) => Ok(Loadable::LiteralConstant(LiteralConstant::Double(*v))),
-        ConstantPoolEntry::String(v) => Ok(Loadable::LiteralConstant(peel!(v).string_literal())),
-        ConstantPoolEntry::ClassInfo(x) => Ok(Loadable::ClassInfo(peel!(x).utf8())),
+        ConstantPoolEntry::String(v) => Ok(Loadable::LiteralConstant(peel!(v).string_literal()?)),
+        ConstantPoolEntry::ClassInfo(x) => Ok(Loadable::ClassInfo(peel!(x).utf8()?)),
         ConstantPoolEntry::MethodHandle(x, y) => {
             Ok(Loadable::MethodHandle(make_method_handle(x, y)?))
         }
-        ConstantPoolEntry::MethodType(x) => Ok(Loadable::MethodType(peel!(x).utf8())),
+        ConstantPoolEntry::MethodType(x) => Ok(Loadable::MethodType(peel!(x).utf8()?)),
         ConstantPoolEntry::Dynamic(x, y) => Ok(Loadable::Dynamic(Dynamic {
             attr_index: *x,
-            name_and_type: peel!(y).name_and_type(),
+            name_and_type: peel!(y).name_and_type()?,
         })),
         _ => fail!("Unexpected non-loadable constant pool reference found"),
     }
 }
 
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 pub enum MemberKind {
     Field,
     Method,
     InterfaceMethod,
 }
 
+/// A symbolic reference to a field or method of some class, as made by a FieldRef,
+/// MethodRef or InterfaceMethodRef constant pool entry. These can be used as keys when
+/// building call graphs or counting references.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct SymbolicRef<'a> {
+    pub kind: MemberKind,
+    pub class_name: Cow<'a, str>,
+    pub name: Cow<'a, str>,
+    pub descriptor: Cow<'a, str>,
+}
+
 #[derive(Clone, Debug)]
 pub struct MethodHandle<'a> {
     pub kind: ReferenceKind,
@@ -999,19 +1771,19 @@ fn make_method_handle<'a>(
 ) -> Result<MethodHandle<'a>, ParseError> {
     let (class_name, member_kind, member_ref) = match peel!(y).deref() {
         ConstantPoolEntry::FieldRef(c, m) => (
-            peel!(c).classinfo(),
+            peel!(c).classinfo()?,
             MemberKind::Field,
-            peel!(m).name_and_type(),
+            peel!(m).name_and_type()?,
         ),
         ConstantPoolEntry::MethodRef(c, m) => (
-            peel!(c).classinfo(),
+            peel!(c).classinfo()?,
             MemberKind::Method,
-            peel!(m).name_and_type(),
+            peel!(m).name_and_type()?,
         ),
         ConstantPoolEntry::InterfaceMethodRef(c, m) => (
-            peel!(c).classinfo(),
+            peel!(c).classinfo()?,
             MemberKind::InterfaceMethod,
-            peel!(m).name_and_type(),
+            peel!(m).name_and_type()?,
         ),
         _ => fail!("Unexpected constant pool reference type"),
     };
@@ -1035,12 +1807,14 @@ pub(crate) fn read_cp_methodhandle<'a>(
     }
 }
 
-#[derive(Debug)]
+#[derive(Clone, Debug)]
+#[non_exhaustive]
 pub enum BootstrapArgument<'a> {
     LiteralConstant(LiteralConstant<'a>),
     ClassInfo(Cow<'a, str>),
     MethodHandle(MethodHandle<'a>),
     MethodType(Cow<'a, str>),
+    Dynamic(Dynamic<'a>),
 }
 
 pub(crate) fn read_cp_bootstrap_argument<'a>(
@@ -1063,13 +1837,17 @@ pub(crate) fn read_cp_bootstrap_argument<'a>(
             LiteralConstant::Double(*v),
         )),
         ConstantPoolEntry::String(v) => Ok(BootstrapArgument::LiteralConstant(
-            peel!(v).string_literal(),
+            peel!(v).string_literal()?,
         )),
-        ConstantPoolEntry::ClassInfo(x) => Ok(BootstrapArgument::ClassInfo(peel!(x).utf8())),
+        ConstantPoolEntry::ClassInfo(x) => Ok(BootstrapArgument::ClassInfo(peel!(x).utf8()?)),
         ConstantPoolEntry::MethodHandle(x, y) => {
             Ok(BootstrapArgument::MethodHandle(make_method_handle(x, y)?))
         }
-        ConstantPoolEntry::MethodType(x) => Ok(BootstrapArgument::MethodType(peel!(x).utf8())),
+        ConstantPoolEntry::MethodType(x) => Ok(BootstrapArgument::MethodType(peel!(x).utf8()?)),
+        ConstantPoolEntry::Dynamic(x, y) => Ok(BootstrapArgument::Dynamic(Dynamic {
+            attr_index: *x,
+            name_and_type: peel!(y).name_and_type()?,
+        })),
         _ => fail!("Unexpected constant pool reference type"),
     }
 }
@@ -1088,7 +1866,7 @@ pub(crate) fn read_cp_object_array_type<'a>(
     let cp_ref = read_cp_ref_any(bytes, ix, pool)?;
     match cp_ref.deref() {
         ConstantPoolEntry::ClassInfo(x) => {
-            let name = peel!(x).utf8();
+            let name = peel!(x).utf8()?;
             match parse_array_descriptor(&name)? {
                 Some(desc) => Ok(ObjectArrayType::ArrayType(desc)),
                 None => Ok(ObjectArrayType::BinaryName(name)),
@@ -1098,7 +1876,110 @@ pub(crate) fn read_cp_object_array_type<'a>(
     }
 }
 
-#[derive(Debug)]
+/// The type of a constant pool entry, as identified by its tag (see table 4.4-B of the
+/// JVM spec).
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum ConstantKind {
+    Utf8,
+    Integer,
+    Float,
+    Long,
+    Double,
+    ClassInfo,
+    String,
+    FieldRef,
+    MethodRef,
+    InterfaceMethodRef,
+    NameAndType,
+    MethodHandle,
+    MethodType,
+    Dynamic,
+    InvokeDynamic,
+    ModuleInfo,
+    PackageInfo,
+}
+
+// The tag byte, name, entry type and first class file major version that allows it, for
+// each type of constant pool entry.
+type ConstantKindInfo = (ConstantKind, u8, &'static str, ConstantPoolEntryTypes, u16);
+
+#[rustfmt::skip]
+const CONSTANT_KINDS: [ConstantKindInfo; 17] = [
+    (ConstantKind::Utf8, 1, "Utf8", ConstantPoolEntryTypes::UTF8, 45),
+    (ConstantKind::Integer, 3, "Integer", ConstantPoolEntryTypes::INTEGER, 45),
+    (ConstantKind::Float, 4, "Float", ConstantPoolEntryTypes::FLOAT, 45),
+    (ConstantKind::Long, 5, "Long", ConstantPoolEntryTypes::LONG, 45),
+    (ConstantKind::Double, 6, "Double", ConstantPoolEntryTypes::DOUBLE, 45),
+    (ConstantKind::ClassInfo, 7, "ClassInfo", ConstantPoolEntryTypes::CLASS_INFO, 45),
+    (ConstantKind::String, 8, "String", ConstantPoolEntryTypes::STRING, 45),
+    (ConstantKind::FieldRef, 9, "FieldRef", ConstantPoolEntryTypes::FIELD_REF, 45),
+    (ConstantKind::MethodRef, 10, "MethodRef", ConstantPoolEntryTypes::METHOD_REF, 45),
+    (ConstantKind::InterfaceMethodRef, 11, "InterfaceMethodRef",
+        ConstantPoolEntryTypes::INTERFACE_METHOD_REF, 45),
+    (ConstantKind::NameAndType, 12, "NameAndType", ConstantPoolEntryTypes::NAME_AND_TYPE, 45),
+    (ConstantKind::MethodHandle, 15, "MethodHandle", ConstantPoolEntryTypes::METHOD_HANDLE, 51),
+    (ConstantKind::MethodType, 16, "MethodType", ConstantPoolEntryTypes::METHOD_TYPE, 51),
+    (ConstantKind::Dynamic, 17, "Dynamic", ConstantPoolEntryTypes::DYNAMIC, 55),
+    (ConstantKind::InvokeDynamic, 18, "InvokeDynamic", ConstantPoolEntryTypes::INVOKE_DYNAMIC, 51),
+    (ConstantKind::ModuleInfo, 19, "ModuleInfo", ConstantPoolEntryTypes::MODULE_INFO, 53),
+    (ConstantKind::PackageInfo, 20, "PackageInfo", ConstantPoolEntryTypes::PACKAGE_INFO, 53),
+];
+
+impl ConstantKind {
+    // Returns the row of CONSTANT_KINDS that describes this type.
+    fn info(self) -> &'static ConstantKindInfo {
+        let row = match self {
+            ConstantKind::Utf8 => 0,
+            ConstantKind::Integer => 1,
+            ConstantKind::Float => 2,
+            ConstantKind::Long => 3,
+            ConstantKind::Double => 4,
+            ConstantKind::ClassInfo => 5,
+            ConstantKind::String => 6,
+            ConstantKind::FieldRef => 7,
+            ConstantKind::MethodRef => 8,
+            ConstantKind::InterfaceMethodRef => 9,
+            ConstantKind::NameAndType => 10,
+            ConstantKind::MethodHandle => 11,
+            ConstantKind::MethodType => 12,
+            ConstantKind::Dynamic => 13,
+            ConstantKind::InvokeDynamic => 14,
+            ConstantKind::ModuleInfo => 15,
+            ConstantKind::PackageInfo => 16,
+        };
+        &CONSTANT_KINDS[row]
+    }
+
+    // Returns the type of entry identified by the given tag byte, if any.
+    fn from_tag(tag: u8) -> Option<ConstantKind> {
+        CONSTANT_KINDS
+            .iter()
+            .find(|(_, kind_tag, _, _, _)| *kind_tag == tag)
+            .map(|(kind, _, _, _, _)| *kind)
+    }
+
+    /// Returns the tag byte that identifies entries of this type in the class file.
+    #[must_use]
+    pub fn tag(self) -> u8 {
+        self.info().1
+    }
+
+    // Returns a human-readable name for this type, for use in error messages.
+    pub(crate) fn name(self) -> &'static str {
+        self.info().2
+    }
+
+    fn entry_type(self) -> ConstantPoolEntryTypes {
+        self.info().3
+    }
+
+    // Returns the first class file major version in which entries of this type may appear.
+    fn min_major_version(self) -> u16 {
+        self.info().4
+    }
+}
+
+#[derive(Clone, Debug)]
 pub enum ConstantPoolItem<'a> {
     LiteralConstant(LiteralConstant<'a>),
     ClassInfo(Cow<'a, str>),
@@ -1114,88 +1995,349 @@ pub enum ConstantPoolItem<'a> {
     PackageInfo(Cow<'a, str>),
 }
 
-pub struct ConstantPoolIter<'a> {
-    constant_pool: &'a [CafeRc<ConstantPoolEntry<'a>>],
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord)]
+enum SortKeyPart<'k> {
+    Number(i64),
+    Bits(u64),
+    Str(&'k str),
+    Bytes(&'k [u8]),
+}
+
+impl<'a> ConstantPoolItem<'a> {
+    /// Returns the type of the constant pool entry this item was read from.
+    #[must_use]
+    pub fn kind(&self) -> ConstantKind {
+        match self {
+            ConstantPoolItem::LiteralConstant(LiteralConstant::Integer(_)) => ConstantKind::Integer,
+            ConstantPoolItem::LiteralConstant(LiteralConstant::Float(_)) => ConstantKind::Float,
+            ConstantPoolItem::LiteralConstant(LiteralConstant::Long(_)) => ConstantKind::Long,
+            ConstantPoolItem::LiteralConstant(LiteralConstant::Double(_)) => ConstantKind::Double,
+            ConstantPoolItem::LiteralConstant(LiteralConstant::String(_))
+            | ConstantPoolItem::LiteralConstant(LiteralConstant::StringBytes(_)) => {
+                ConstantKind::String
+            }
+            ConstantPoolItem::ClassInfo(_) => ConstantKind::ClassInfo,
+            ConstantPoolItem::FieldRef(_) => ConstantKind::FieldRef,
+            ConstantPoolItem::MethodRef(_) => ConstantKind::MethodRef,
+            ConstantPoolItem::InterfaceMethodRef(_) => ConstantKind::InterfaceMethodRef,
+            ConstantPoolItem::NameAndType(_) => ConstantKind::NameAndType,
+            ConstantPoolItem::MethodHandle(_) => ConstantKind::MethodHandle,
+            ConstantPoolItem::MethodType(_) => ConstantKind::MethodType,
+            ConstantPoolItem::Dynamic(_) => ConstantKind::Dynamic,
+            ConstantPoolItem::InvokeDynamic(_) => ConstantKind::InvokeDynamic,
+            ConstantPoolItem::ModuleInfo(_) => ConstantKind::ModuleInfo,
+            ConstantPoolItem::PackageInfo(_) => ConstantKind::PackageInfo,
+        }
+    }
+
+    /// Returns the value of a String constant pool entry, or None for any other kind of
+    /// entry. See [`LiteralConstant::string_value`].
+    #[must_use]
+    pub fn string_value(&self) -> Option<Cow<'_, str>> {
+        match self {
+            ConstantPoolItem::LiteralConstant(literal) => literal.string_value(),
+            _ => None,
+        }
+    }
+
+    /// Returns the index into the BootstrapMethods attribute of an InvokeDynamic entry, or
+    /// None for any other kind of entry. See [`ClassFile::bootstrap_method`](crate::ClassFile::bootstrap_method).
+    #[must_use]
+    pub fn invoke_dynamic_bootstrap_index(&self) -> Option<u16> {
+        match self {
+            ConstantPoolItem::InvokeDynamic(indy) => Some(indy.attr_index),
+            _ => None,
+        }
+    }
+
+    /// Returns the name and descriptor of the call site of an InvokeDynamic entry, or None
+    /// for any other kind of entry.
+    #[must_use]
+    pub fn invoke_dynamic_name_and_type(&self) -> Option<(&Cow<'a, str>, &Cow<'a, str>)> {
+        match self {
+            ConstantPoolItem::InvokeDynamic(indy) => {
+                Some((&indy.name_and_type.name, &indy.name_and_type.descriptor))
+            }
+            _ => None,
+        }
+    }
+
+    /// Returns a key for ordering constant pool items by their resolved contents, rather
+    /// than by where they happen to be in the constant pool. Two items have equal keys
+    /// exactly when they have the same kind and contents, so sorting the items of two
+    /// constant pools by this key lines them up even if the pools were laid out in a
+    /// different order. Floating point constants are compared by their bit patterns.
+    #[must_use]
+    pub fn canonical_sort_key(&self) -> impl Ord + '_ {
+        fn name_and_type<'k>(nat: &'k NameAndType) -> [SortKeyPart<'k>; 2] {
+            [
+                SortKeyPart::Str(&nat.name),
+                SortKeyPart::Str(&nat.descriptor),
+            ]
+        }
+        fn member_ref<'k>(member: &'k MemberRef) -> Vec<SortKeyPart<'k>> {
+            let [name, descriptor] = name_and_type(&member.name_and_type);
+            vec![SortKeyPart::Str(&member.class_name), name, descriptor]
+        }
+        let (rank, parts): (u8, Vec<SortKeyPart>) = match self {
+            ConstantPoolItem::LiteralConstant(literal) => match literal {
+                LiteralConstant::Integer(v) => (0, vec![SortKeyPart::Number(i64::from(*v))]),
+                LiteralConstant::Float(v) => (1, vec![SortKeyPart::Bits(u64::from(v.to_bits()))]),
+                LiteralConstant::Long(v) => (2, vec![SortKeyPart::Number(*v)]),
+                LiteralConstant::Double(v) => (3, vec![SortKeyPart::Bits(v.to_bits())]),
+                LiteralConstant::String(v) => (4, vec![SortKeyPart::Str(v)]),
+                LiteralConstant::StringBytes(v) => (4, vec![SortKeyPart::Bytes(v)]),
+            },
+            ConstantPoolItem::ClassInfo(name) => (5, vec![SortKeyPart::Str(name)]),
+            ConstantPoolItem::FieldRef(member) => (6, member_ref(member)),
+            ConstantPoolItem::MethodRef(member) => (7, member_ref(member)),
+            ConstantPoolItem::InterfaceMethodRef(member) => (8, member_ref(member)),
+            ConstantPoolItem::NameAndType(nat) => (9, name_and_type(nat).into()),
+            ConstantPoolItem::MethodHandle(handle) => {
+                let [name, descriptor] = name_and_type(&handle.member_ref);
+                let parts = vec![
+                    SortKeyPart::Number(handle.kind as i64),
+                    SortKeyPart::Number(handle.member_kind as i64),
+                    SortKeyPart::Str(&handle.class_name),
+                    name,
+                    descriptor,
+                ];
+                (10, parts)
+            }
+            ConstantPoolItem::MethodType(descriptor) => (11, vec![SortKeyPart::Str(descriptor)]),
+            ConstantPoolItem::Dynamic(dynamic) => {
+                let [name, descriptor] = name_and_type(&dynamic.name_and_type);
+                let attr_index = SortKeyPart::Number(i64::from(dynamic.attr_index));
+                (12, vec![attr_index, name, descriptor])
+            }
+            ConstantPoolItem::InvokeDynamic(indy) => {
+                let [name, descriptor] = name_and_type(&indy.name_and_type);
+                let attr_index = SortKeyPart::Number(i64::from(indy.attr_index));
+                (13, vec![attr_index, name, descriptor])
+            }
+            ConstantPoolItem::ModuleInfo(name) => (14, vec![SortKeyPart::Str(name)]),
+            ConstantPoolItem::PackageInfo(name) => (15, vec![SortKeyPart::Str(name)]),
+        };
+        (rank, parts)
+    }
+}
+
+/// Iterates over the entries of a constant pool borrowed for the lifetime `'p`, yielding
+/// items that live as long as the class file data `'a`.
+pub struct ConstantPoolIter<'p, 'a> {
+    constant_pool: &'p [CafeRc<ConstantPoolEntry<'a>>],
     index: usize,
 }
 
-impl<'a> ConstantPoolIter<'a> {
-    pub(crate) fn new(constant_pool: &'a [CafeRc<ConstantPoolEntry<'a>>]) -> Self {
+impl<'p, 'a> ConstantPoolIter<'p, 'a> {
+    pub(crate) fn new(constant_pool: &'p [CafeRc<ConstantPoolEntry<'a>>]) -> Self {
         ConstantPoolIter {
             constant_pool,
             index: 0,
         }
     }
+
+    fn entries_of_type<T>(
+        self,
+        types: ConstantPoolEntryTypes,
+        map: fn(&ConstantPoolEntry<'a>) -> Option<T>,
+    ) -> ConstantPoolEntries<'p, 'a, T> {
+        ConstantPoolEntries {
+            constant_pool: self.constant_pool,
+            index: self.index,
+            types,
+            map,
+        }
+    }
+
+    /// Consumes the rest of this iterator and yields the Utf8 entries of the constant pool
+    /// along with their indices. Unlike the iterator itself, this includes the names and
+    /// descriptors that are otherwise only visible through the entries referencing them.
+    /// Entries that are not valid modified UTF-8 (which are only permitted as string
+    /// constants when parsing leniently) are skipped.
+    pub fn utf8_entries(self) -> ConstantPoolEntries<'p, 'a, Cow<'a, str>> {
+        self.entries_of_type(ConstantPoolEntryTypes::UTF8, |cp_entry| match cp_entry {
+            ConstantPoolEntry::Utf8(x) => Some(x.clone()),
+            _ => None,
+        })
+    }
+
+    /// Consumes the rest of this iterator and yields the names of the classes referenced
+    /// by ClassInfo entries, along with the indices of those entries.
+    pub fn class_entries(self) -> ConstantPoolEntries<'p, 'a, Cow<'a, str>> {
+        self.entries_of_type(ConstantPoolEntryTypes::CLASS_INFO, |cp_entry| {
+            cp_entry.classinfo().ok()
+        })
+    }
+
+    /// Consumes the rest of this iterator and yields the string constants, along with the
+    /// indices of their String entries.
+    pub fn string_entries(self) -> ConstantPoolEntries<'p, 'a, LiteralConstant<'a>> {
+        self.entries_of_type(ConstantPoolEntryTypes::STRING, |cp_entry| match cp_entry {
+            ConstantPoolEntry::String(x) => peel!(x).string_literal().ok(),
+            _ => None,
+        })
+    }
+
+    /// Consumes the rest of this iterator and yields the NameAndType entries with both their
+    /// name and descriptor resolved, along with their indices.
+    pub fn name_and_type_entries(self) -> ConstantPoolEntries<'p, 'a, NameAndType<'a>> {
+        self.entries_of_type(ConstantPoolEntryTypes::NAME_AND_TYPE, |cp_entry| {
+            cp_entry.name_and_type().ok()
+        })
+    }
+
+    /// Consumes the rest of this iterator and yields the FieldRef entries, along with
+    /// their indices.
+    pub fn field_refs(self) -> ConstantPoolEntries<'p, 'a, MemberRef<'a>> {
+        self.entries_of_type(
+            ConstantPoolEntryTypes::FIELD_REF,
+            |cp_entry| match cp_entry {
+                ConstantPoolEntry::FieldRef(c, m) => Some(MemberRef {
+                    class_name: peel!(c).classinfo().ok()?,
+                    name_and_type: peel!(m).name_and_type().ok()?,
+                }),
+                _ => None,
+            },
+        )
+    }
+
+    /// Consumes the rest of this iterator and yields both the MethodRef and the
+    /// InterfaceMethodRef entries, along with their indices.
+    pub fn method_refs(self) -> ConstantPoolEntries<'p, 'a, MemberRef<'a>> {
+        self.entries_of_type(
+            ConstantPoolEntryTypes::NEW_METHOD_REFS,
+            |cp_entry| match cp_entry {
+                ConstantPoolEntry::MethodRef(c, m)
+                | ConstantPoolEntry::InterfaceMethodRef(c, m) => Some(MemberRef {
+                    class_name: peel!(c).classinfo().ok()?,
+                    name_and_type: peel!(m).name_and_type().ok()?,
+                }),
+                _ => None,
+            },
+        )
+    }
+
+    /// Consumes the rest of this iterator and yields both the InvokeDynamic and the
+    /// Dynamic entries, along with their indices.
+    pub fn dynamic_entries(self) -> ConstantPoolEntries<'p, 'a, ConstantPoolItem<'a>> {
+        self.entries_of_type(
+            ConstantPoolEntryTypes::INVOKE_DYNAMIC | ConstantPoolEntryTypes::DYNAMIC,
+            |cp_entry| constant_pool_item(cp_entry).ok().flatten(),
+        )
+    }
 }
 
-impl<'a> Iterator for ConstantPoolIter<'a> {
+// Converts a constant pool entry into the item yielded by ConstantPoolIter, or None for
+// the entry types that the iterator skips.
+pub(crate) fn constant_pool_item<'a>(
+    cp_entry: &ConstantPoolEntry<'a>,
+) -> Result<Option<ConstantPoolItem<'a>>, ParseError> {
+    let item = match cp_entry {
+        ConstantPoolEntry::Zero
+        | ConstantPoolEntry::Utf8(_)
+        | ConstantPoolEntry::Utf8Bytes(_)
+        | ConstantPoolEntry::Unused => return Ok(None),
+        ConstantPoolEntry::Integer(v) => {
+            ConstantPoolItem::LiteralConstant(LiteralConstant::Integer(*v))
+        }
+        ConstantPoolEntry::Float(v) => {
+            ConstantPoolItem::LiteralConstant(LiteralConstant::Float(*v))
+        }
+        ConstantPoolEntry::Long(v) => ConstantPoolItem::LiteralConstant(LiteralConstant::Long(*v)),
+        ConstantPoolEntry::Double(v) => {
+            ConstantPoolItem::LiteralConstant(LiteralConstant::Double(*v))
+        }
+        ConstantPoolEntry::ClassInfo(x) => ConstantPoolItem::ClassInfo(peel!(x).utf8()?),
+        ConstantPoolEntry::String(x) => {
+            ConstantPoolItem::LiteralConstant(peel!(x).string_literal()?)
+        }
+        ConstantPoolEntry::FieldRef(c, m) => ConstantPoolItem::FieldRef(MemberRef {
+            class_name: peel!(c).classinfo()?,
+            name_and_type: peel!(m).name_and_type()?,
+        }),
+        ConstantPoolEntry::MethodRef(c, m) => ConstantPoolItem::MethodRef(MemberRef {
+            class_name: peel!(c).classinfo()?,
+            name_and_type: peel!(m).name_and_type()?,
+        }),
+        ConstantPoolEntry::InterfaceMethodRef(c, m) => {
+            ConstantPoolItem::InterfaceMethodRef(MemberRef {
+                class_name: peel!(c).classinfo()?,
+                name_and_type: peel!(m).name_and_type()?,
+            })
+        }
+        ConstantPoolEntry::NameAndType(x, y) => ConstantPoolItem::NameAndType(NameAndType {
+            name: peel!(x).utf8()?,
+            descriptor: peel!(y).utf8()?,
+        }),
+        ConstantPoolEntry::MethodHandle(x, y) => {
+            ConstantPoolItem::MethodHandle(make_method_handle(x, y)?)
+        }
+        ConstantPoolEntry::MethodType(x) => ConstantPoolItem::MethodType(peel!(x).utf8()?),
+        ConstantPoolEntry::Dynamic(x, y) => ConstantPoolItem::Dynamic(Dynamic {
+            attr_index: *x,
+            name_and_type: peel!(y).name_and_type()?,
+        }),
+        ConstantPoolEntry::InvokeDynamic(x, y) => ConstantPoolItem::InvokeDynamic(InvokeDynamic {
+            attr_index: *x,
+            name_and_type: peel!(y).name_and_type()?,
+        }),
+        ConstantPoolEntry::ModuleInfo(x) => ConstantPoolItem::ModuleInfo(peel!(x).utf8()?),
+        ConstantPoolEntry::PackageInfo(x) => ConstantPoolItem::PackageInfo(peel!(x).utf8()?),
+    };
+    Ok(Some(item))
+}
+
+impl<'p, 'a> Iterator for ConstantPoolIter<'p, 'a> {
     type Item = ConstantPoolItem<'a>;
 
     fn next(&mut self) -> Option<Self::Item> {
         while self.index + 1 < self.constant_pool.len() {
             self.index += 1;
-            let item = match self.constant_pool[self.index].deref() {
-                ConstantPoolEntry::Zero => panic!("This iterator should never see a Zero item"),
-                ConstantPoolEntry::Utf8(_) | ConstantPoolEntry::Utf8Bytes(_) => continue,
-                ConstantPoolEntry::Integer(v) => {
-                    ConstantPoolItem::LiteralConstant(LiteralConstant::Integer(*v))
-                }
-                ConstantPoolEntry::Float(v) => {
-                    ConstantPoolItem::LiteralConstant(LiteralConstant::Float(*v))
-                }
-                ConstantPoolEntry::Long(v) => {
-                    ConstantPoolItem::LiteralConstant(LiteralConstant::Long(*v))
-                }
-                ConstantPoolEntry::Double(v) => {
-                    ConstantPoolItem::LiteralConstant(LiteralConstant::Double(*v))
-                }
-                ConstantPoolEntry::ClassInfo(x) => ConstantPoolItem::ClassInfo(peel!(x).utf8()),
-                ConstantPoolEntry::String(x) => {
-                    ConstantPoolItem::LiteralConstant(peel!(x).string_literal())
-                }
-                ConstantPoolEntry::FieldRef(c, m) => ConstantPoolItem::FieldRef(MemberRef {
-                    class_name: peel!(c).classinfo(),
-                    name_and_type: peel!(m).name_and_type(),
-                }),
-                ConstantPoolEntry::MethodRef(c, m) => ConstantPoolItem::MethodRef(MemberRef {
-                    class_name: peel!(c).classinfo(),
-                    name_and_type: peel!(m).name_and_type(),
-                }),
-                ConstantPoolEntry::InterfaceMethodRef(c, m) => {
-                    ConstantPoolItem::InterfaceMethodRef(MemberRef {
-                        class_name: peel!(c).classinfo(),
-                        name_and_type: peel!(m).name_and_type(),
-                    })
-                }
-                ConstantPoolEntry::NameAndType(x, y) => {
-                    ConstantPoolItem::NameAndType(NameAndType {
-                        name: peel!(x).utf8(),
-                        descriptor: peel!(y).utf8(),
-                    })
-                }
-                ConstantPoolEntry::MethodHandle(x, y) => {
-                    ConstantPoolItem::MethodHandle(make_method_handle(x, y).unwrap())
-                }
-                ConstantPoolEntry::MethodType(x) => ConstantPoolItem::MethodType(peel!(x).utf8()),
-                ConstantPoolEntry::Dynamic(x, y) => ConstantPoolItem::Dynamic(Dynamic {
-                    attr_index: *x,
-                    name_and_type: peel!(y).name_and_type(),
-                }),
-                ConstantPoolEntry::InvokeDynamic(x, y) => {
-                    ConstantPoolItem::InvokeDynamic(InvokeDynamic {
-                        attr_index: *x,
-                        name_and_type: peel!(y).name_and_type(),
-                    })
-                }
-                ConstantPoolEntry::ModuleInfo(x) => ConstantPoolItem::ModuleInfo(peel!(x).utf8()),
-                ConstantPoolEntry::PackageInfo(x) => ConstantPoolItem::PackageInfo(peel!(x).utf8()),
-                ConstantPoolEntry::Unused => continue,
-            };
-            return Some(item);
+            // Validation of the constant pool guarantees that converting the entry succeeds,
+            // unless resolution was deferred, in which case entries that fail are skipped
+            if resolve_entry(self.constant_pool, self.index).is_err() {
+                continue;
+            }
+            if let Ok(Some(item)) = constant_pool_item(&self.constant_pool[self.index]) {
+                return Some(item);
+            }
+        }
+        None
+    }
+}
+
+/// Iterates over the entries of a single kind in a constant pool, yielding each along
+/// with its constant pool index. This is returned by the filtering methods on
+/// ConstantPoolIter.
+pub struct ConstantPoolEntries<'p, 'a, T> {
+    constant_pool: &'p [CafeRc<ConstantPoolEntry<'a>>],
+    index: usize,
+    types: ConstantPoolEntryTypes,
+    map: fn(&ConstantPoolEntry<'a>) -> Option<T>,
+}
+
+impl<'p, 'a, T> Iterator for ConstantPoolEntries<'p, 'a, T> {
+    type Item = (u16, T);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while self.index + 1 < self.constant_pool.len() {
+            self.index += 1;
+            let cp_entry = &self.constant_pool[self.index];
+            if !self.types.contains(cp_entry.get_type())
+                || resolve_entry(self.constant_pool, self.index).is_err()
+            {
+                continue;
+            }
+            if let Some(item) = (self.map)(cp_entry) {
+                return Some((self.index as u16, item));
+            }
         }
         None
     }
 }
 
+mod builder;
+
 #[cfg(test)]
 mod tests;
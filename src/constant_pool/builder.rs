@@ -0,0 +1,788 @@
+use std::borrow::Cow;
+use std::collections::HashMap;
+
+use crate::constant_pool::{
+    resolve_constant_pool, snapshot_constant_pool, validate_constant_pool, CafeCell, ConstantKind,
+    ConstantPoolEntry, ConstantPoolRef, MemberKind, MethodHandle, ReferenceKind, ResolvedEntry,
+    MAX_REFERENCE_CHAIN,
+};
+use crate::{CafeRc, ParseError};
+
+// The largest constant_pool_count the class file format can represent. Since index 0 is
+// never used, this allows for at most 65534 usable indices.
+const MAX_CONSTANT_POOL_COUNT: usize = 65535;
+
+// The largest number of bytes a CONSTANT_Utf8 entry can hold.
+const MAX_UTF8_LENGTH: usize = 65535;
+
+// Entries are keyed by their contents with references already converted to indices, so
+// that interning the same constant twice finds the existing entry.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+enum BuilderEntry<'a> {
+    Utf8(Cow<'a, [u8]>),
+    Integer(i32),
+    Float(u32),
+    Long(i64),
+    Double(u64),
+    ClassInfo(u16),
+    String(u16),
+    FieldRef(u16, u16),
+    MethodRef(u16, u16),
+    InterfaceMethodRef(u16, u16),
+    NameAndType(u16, u16),
+    MethodHandle(ReferenceKind, u16),
+    MethodType(u16),
+    Dynamic(u16, u16),
+    InvokeDynamic(u16, u16),
+    ModuleInfo(u16),
+    PackageInfo(u16),
+}
+
+impl<'a> BuilderEntry<'a> {
+    fn slots(&self) -> usize {
+        match self {
+            BuilderEntry::Long(_) | BuilderEntry::Double(_) => 2,
+            _ => 1,
+        }
+    }
+
+    fn references(&self) -> Vec<u16> {
+        match self {
+            BuilderEntry::Utf8(_)
+            | BuilderEntry::Integer(_)
+            | BuilderEntry::Float(_)
+            | BuilderEntry::Long(_)
+            | BuilderEntry::Double(_) => vec![],
+            BuilderEntry::ClassInfo(x)
+            | BuilderEntry::String(x)
+            | BuilderEntry::MethodHandle(_, x)
+            | BuilderEntry::MethodType(x)
+            | BuilderEntry::Dynamic(_, x)
+            | BuilderEntry::InvokeDynamic(_, x)
+            | BuilderEntry::ModuleInfo(x)
+            | BuilderEntry::PackageInfo(x) => vec![*x],
+            BuilderEntry::FieldRef(x, y)
+            | BuilderEntry::MethodRef(x, y)
+            | BuilderEntry::InterfaceMethodRef(x, y)
+            | BuilderEntry::NameAndType(x, y) => vec![*x, *y],
+        }
+    }
+
+    // How deeply the entry nests: an entry only refers to entries with a lower depth.
+    fn depth(&self) -> usize {
+        match self {
+            BuilderEntry::Utf8(_)
+            | BuilderEntry::Integer(_)
+            | BuilderEntry::Float(_)
+            | BuilderEntry::Long(_)
+            | BuilderEntry::Double(_) => 0,
+            BuilderEntry::ClassInfo(_)
+            | BuilderEntry::String(_)
+            | BuilderEntry::NameAndType(_, _)
+            | BuilderEntry::MethodType(_)
+            | BuilderEntry::ModuleInfo(_)
+            | BuilderEntry::PackageInfo(_) => 1,
+            BuilderEntry::FieldRef(_, _)
+            | BuilderEntry::MethodRef(_, _)
+            | BuilderEntry::InterfaceMethodRef(_, _)
+            | BuilderEntry::Dynamic(_, _)
+            | BuilderEntry::InvokeDynamic(_, _) => 2,
+            BuilderEntry::MethodHandle(_, _) => 3,
+        }
+    }
+
+    fn remap(&self, remap_index: impl Fn(u16) -> u16) -> Self {
+        let remap = |x: &u16| remap_index(*x);
+        match self {
+            BuilderEntry::Utf8(_)
+            | BuilderEntry::Integer(_)
+            | BuilderEntry::Float(_)
+            | BuilderEntry::Long(_)
+            | BuilderEntry::Double(_) => self.clone(),
+            BuilderEntry::ClassInfo(x) => BuilderEntry::ClassInfo(remap(x)),
+            BuilderEntry::String(x) => BuilderEntry::String(remap(x)),
+            BuilderEntry::FieldRef(x, y) => BuilderEntry::FieldRef(remap(x), remap(y)),
+            BuilderEntry::MethodRef(x, y) => BuilderEntry::MethodRef(remap(x), remap(y)),
+            BuilderEntry::InterfaceMethodRef(x, y) => {
+                BuilderEntry::InterfaceMethodRef(remap(x), remap(y))
+            }
+            BuilderEntry::NameAndType(x, y) => BuilderEntry::NameAndType(remap(x), remap(y)),
+            BuilderEntry::MethodHandle(kind, x) => BuilderEntry::MethodHandle(*kind, remap(x)),
+            BuilderEntry::MethodType(x) => BuilderEntry::MethodType(remap(x)),
+            BuilderEntry::Dynamic(x, y) => BuilderEntry::Dynamic(*x, remap(y)),
+            BuilderEntry::InvokeDynamic(x, y) => BuilderEntry::InvokeDynamic(*x, remap(y)),
+            BuilderEntry::ModuleInfo(x) => BuilderEntry::ModuleInfo(remap(x)),
+            BuilderEntry::PackageInfo(x) => BuilderEntry::PackageInfo(remap(x)),
+        }
+    }
+
+    fn kind(&self) -> ConstantKind {
+        match self {
+            BuilderEntry::Utf8(_) => ConstantKind::Utf8,
+            BuilderEntry::Integer(_) => ConstantKind::Integer,
+            BuilderEntry::Float(_) => ConstantKind::Float,
+            BuilderEntry::Long(_) => ConstantKind::Long,
+            BuilderEntry::Double(_) => ConstantKind::Double,
+            BuilderEntry::ClassInfo(_) => ConstantKind::ClassInfo,
+            BuilderEntry::String(_) => ConstantKind::String,
+            BuilderEntry::FieldRef(_, _) => ConstantKind::FieldRef,
+            BuilderEntry::MethodRef(_, _) => ConstantKind::MethodRef,
+            BuilderEntry::InterfaceMethodRef(_, _) => ConstantKind::InterfaceMethodRef,
+            BuilderEntry::NameAndType(_, _) => ConstantKind::NameAndType,
+            BuilderEntry::MethodHandle(_, _) => ConstantKind::MethodHandle,
+            BuilderEntry::MethodType(_) => ConstantKind::MethodType,
+            BuilderEntry::Dynamic(_, _) => ConstantKind::Dynamic,
+            BuilderEntry::InvokeDynamic(_, _) => ConstantKind::InvokeDynamic,
+            BuilderEntry::ModuleInfo(_) => ConstantKind::ModuleInfo,
+            BuilderEntry::PackageInfo(_) => ConstantKind::PackageInfo,
+        }
+    }
+
+    fn write(&self, out: &mut Vec<u8>) {
+        out.push(self.kind().tag());
+        match self {
+            BuilderEntry::Utf8(data) => {
+                out.extend_from_slice(&(data.len() as u16).to_be_bytes());
+                out.extend_from_slice(data);
+            }
+            BuilderEntry::Integer(v) => out.extend_from_slice(&v.to_be_bytes()),
+            BuilderEntry::Float(bits) => out.extend_from_slice(&bits.to_be_bytes()),
+            BuilderEntry::Long(v) => out.extend_from_slice(&v.to_be_bytes()),
+            BuilderEntry::Double(bits) => out.extend_from_slice(&bits.to_be_bytes()),
+            BuilderEntry::MethodHandle(kind, x) => {
+                out.push(reference_kind_tag(*kind));
+                out.extend_from_slice(&x.to_be_bytes());
+            }
+            BuilderEntry::ClassInfo(x)
+            | BuilderEntry::String(x)
+            | BuilderEntry::MethodType(x)
+            | BuilderEntry::ModuleInfo(x)
+            | BuilderEntry::PackageInfo(x) => out.extend_from_slice(&x.to_be_bytes()),
+            BuilderEntry::FieldRef(x, y)
+            | BuilderEntry::MethodRef(x, y)
+            | BuilderEntry::InterfaceMethodRef(x, y)
+            | BuilderEntry::NameAndType(x, y)
+            | BuilderEntry::Dynamic(x, y)
+            | BuilderEntry::InvokeDynamic(x, y) => {
+                out.extend_from_slice(&x.to_be_bytes());
+                out.extend_from_slice(&y.to_be_bytes());
+            }
+        }
+    }
+}
+
+fn encode_utf8(value: Cow<str>) -> Cow<[u8]> {
+    match value {
+        Cow::Borrowed(s) => cesu8::to_java_cesu8(s),
+        Cow::Owned(s) => Cow::Owned(cesu8::to_java_cesu8(&s).into_owned()),
+    }
+}
+
+fn decode_utf8<'a>(data: &Cow<'a, [u8]>) -> ConstantPoolEntry<'a> {
+    match data {
+        Cow::Borrowed(bytes) => match cesu8::from_java_cesu8(bytes) {
+            Ok(value) => ConstantPoolEntry::Utf8(value),
+            Err(_) => ConstantPoolEntry::Utf8Bytes(Cow::Borrowed(bytes)),
+        },
+        Cow::Owned(bytes) => match cesu8::from_java_cesu8(bytes) {
+            Ok(value) => ConstantPoolEntry::Utf8(Cow::Owned(value.into_owned())),
+            Err(_) => ConstantPoolEntry::Utf8Bytes(Cow::Owned(bytes.clone())),
+        },
+    }
+}
+
+fn reference_kind_name(kind: ReferenceKind) -> &'static str {
+    match kind {
+        ReferenceKind::GetField => "REF_getField",
+        ReferenceKind::GetStatic => "REF_getStatic",
+        ReferenceKind::PutField => "REF_putField",
+        ReferenceKind::PutStatic => "REF_putStatic",
+        ReferenceKind::InvokeVirtual => "REF_invokeVirtual",
+        ReferenceKind::InvokeStatic => "REF_invokeStatic",
+        ReferenceKind::InvokeSpecial => "REF_invokeSpecial",
+        ReferenceKind::NewInvokeSpecial => "REF_newInvokeSpecial",
+        ReferenceKind::InvokeInterface => "REF_invokeInterface",
+    }
+}
+
+fn reference_kind_tag(kind: ReferenceKind) -> u8 {
+    match kind {
+        ReferenceKind::GetField => 1,
+        ReferenceKind::GetStatic => 2,
+        ReferenceKind::PutField => 3,
+        ReferenceKind::PutStatic => 4,
+        ReferenceKind::InvokeVirtual => 5,
+        ReferenceKind::InvokeStatic => 6,
+        ReferenceKind::InvokeSpecial => 7,
+        ReferenceKind::NewInvokeSpecial => 8,
+        ReferenceKind::InvokeInterface => 9,
+    }
+}
+
+/// Builds up a constant pool for writing into a class file. Each method interns a
+/// constant, along with any constants it references, and returns its constant pool index.
+/// Interning a constant that is already present returns the existing index rather than
+/// adding a duplicate entry.
+///
+/// The class file format uses two-byte constant pool indices, so a constant pool holds at
+/// most 65534 usable indices (with long and double constants taking up two each). Adding
+/// a constant beyond that fails with an error rather than producing an index that would
+/// be silently truncated when written.
+#[derive(Clone, Debug)]
+pub struct ConstantPoolBuilder<'a> {
+    entries: Vec<BuilderEntry<'a>>,
+    indices: HashMap<BuilderEntry<'a>, u16>,
+    count: usize,
+    referenced: Option<HashMap<u16, usize>>,
+    // The index of the first copy of each entry that is equal to an earlier one, keyed by
+    // the index of the later copy.
+    duplicates: HashMap<u16, u16>,
+}
+
+impl<'a> Default for ConstantPoolBuilder<'a> {
+    fn default() -> Self {
+        ConstantPoolBuilder::new()
+    }
+}
+
+impl<'a> ConstantPoolBuilder<'a> {
+    /// Creates a builder for an empty constant pool.
+    #[must_use]
+    pub fn new() -> Self {
+        ConstantPoolBuilder {
+            entries: Vec::new(),
+            indices: HashMap::new(),
+            count: 1,
+            referenced: None,
+            duplicates: HashMap::new(),
+        }
+    }
+
+    /// Returns the constant_pool_count of the constant pool built so far, which is one
+    /// more than the largest index in use.
+    #[must_use]
+    pub fn count(&self) -> u16 {
+        self.count as u16
+    }
+
+    fn intern(&mut self, entry: BuilderEntry<'a>) -> Result<u16, ParseError> {
+        let index = match self.indices.get(&entry) {
+            Some(index) => *index,
+            None => {
+                let new_count = self.count + entry.slots();
+                if new_count > MAX_CONSTANT_POOL_COUNT {
+                    fail!(
+                        "Constant pool overflow: {} entries exceed {}",
+                        new_count,
+                        MAX_CONSTANT_POOL_COUNT
+                    );
+                }
+                let index = self.count as u16;
+                self.count = new_count;
+                self.indices.insert(entry.clone(), index);
+                self.entries.push(entry);
+                index
+            }
+        };
+        if let Some(referenced) = &mut self.referenced {
+            *referenced.entry(index).or_insert(0) += 1;
+        }
+        Ok(index)
+    }
+
+    /// Interns a CONSTANT_Utf8 entry holding the given string, encoded as modified UTF-8.
+    pub fn utf8(&mut self, value: impl Into<Cow<'a, str>>) -> Result<u16, ParseError> {
+        self.utf8_bytes(encode_utf8(value.into()))
+    }
+
+    /// Interns a CONSTANT_Utf8 entry holding the given bytes, which should already be
+    /// encoded as modified UTF-8. This allows writing strings that are not valid UTF-8,
+    /// such as the LiteralConstant::StringBytes that the parser produces for them.
+    pub fn utf8_bytes(&mut self, data: impl Into<Cow<'a, [u8]>>) -> Result<u16, ParseError> {
+        let data = data.into();
+        if data.len() > MAX_UTF8_LENGTH {
+            fail!(
+                "Modified UTF-8 data of length {} exceeds {} bytes for a CONSTANT_Utf8",
+                data.len(),
+                MAX_UTF8_LENGTH
+            );
+        }
+        self.intern(BuilderEntry::Utf8(data))
+    }
+
+    pub fn integer(&mut self, value: i32) -> Result<u16, ParseError> {
+        self.intern(BuilderEntry::Integer(value))
+    }
+
+    pub fn float(&mut self, value: f32) -> Result<u16, ParseError> {
+        self.intern(BuilderEntry::Float(value.to_bits()))
+    }
+
+    pub fn long(&mut self, value: i64) -> Result<u16, ParseError> {
+        self.intern(BuilderEntry::Long(value))
+    }
+
+    pub fn double(&mut self, value: f64) -> Result<u16, ParseError> {
+        self.intern(BuilderEntry::Double(value.to_bits()))
+    }
+
+    /// Interns a CONSTANT_Class entry for the class with the given binary name (or array
+    /// descriptor).
+    pub fn class(&mut self, name: impl Into<Cow<'a, str>>) -> Result<u16, ParseError> {
+        let name = self.utf8(name)?;
+        self.intern(BuilderEntry::ClassInfo(name))
+    }
+
+    /// Interns a CONSTANT_String entry for the given string literal.
+    pub fn string(&mut self, value: impl Into<Cow<'a, str>>) -> Result<u16, ParseError> {
+        let value = self.utf8(value)?;
+        self.intern(BuilderEntry::String(value))
+    }
+
+    /// Interns a CONSTANT_String entry for a string literal given as modified UTF-8 bytes.
+    pub fn string_bytes(&mut self, data: impl Into<Cow<'a, [u8]>>) -> Result<u16, ParseError> {
+        let value = self.utf8_bytes(data)?;
+        self.intern(BuilderEntry::String(value))
+    }
+
+    pub fn name_and_type(
+        &mut self,
+        name: impl Into<Cow<'a, str>>,
+        descriptor: impl Into<Cow<'a, str>>,
+    ) -> Result<u16, ParseError> {
+        let name = self.utf8(name)?;
+        let descriptor = self.utf8(descriptor)?;
+        self.intern(BuilderEntry::NameAndType(name, descriptor))
+    }
+
+    pub fn field_ref(
+        &mut self,
+        class_name: impl Into<Cow<'a, str>>,
+        name: impl Into<Cow<'a, str>>,
+        descriptor: impl Into<Cow<'a, str>>,
+    ) -> Result<u16, ParseError> {
+        let class = self.class(class_name)?;
+        let name_and_type = self.name_and_type(name, descriptor)?;
+        self.intern(BuilderEntry::FieldRef(class, name_and_type))
+    }
+
+    pub fn method_ref(
+        &mut self,
+        class_name: impl Into<Cow<'a, str>>,
+        name: impl Into<Cow<'a, str>>,
+        descriptor: impl Into<Cow<'a, str>>,
+    ) -> Result<u16, ParseError> {
+        let class = self.class(class_name)?;
+        let name_and_type = self.name_and_type(name, descriptor)?;
+        self.intern(BuilderEntry::MethodRef(class, name_and_type))
+    }
+
+    pub fn interface_method_ref(
+        &mut self,
+        class_name: impl Into<Cow<'a, str>>,
+        name: impl Into<Cow<'a, str>>,
+        descriptor: impl Into<Cow<'a, str>>,
+    ) -> Result<u16, ParseError> {
+        let class = self.class(class_name)?;
+        let name_and_type = self.name_and_type(name, descriptor)?;
+        self.intern(BuilderEntry::InterfaceMethodRef(class, name_and_type))
+    }
+
+    /// Interns a CONSTANT_MethodHandle entry along with the member reference it refers to.
+    pub fn method_handle(&mut self, handle: &MethodHandle<'a>) -> Result<u16, ParseError> {
+        let class_name = handle.class_name.clone();
+        let name = handle.member_ref.name.clone();
+        let descriptor = handle.member_ref.descriptor.clone();
+        let member = match handle.member_kind {
+            MemberKind::Field => self.field_ref(class_name, name, descriptor)?,
+            MemberKind::Method => self.method_ref(class_name, name, descriptor)?,
+            MemberKind::InterfaceMethod => {
+                self.interface_method_ref(class_name, name, descriptor)?
+            }
+        };
+        self.intern(BuilderEntry::MethodHandle(handle.kind, member))
+    }
+
+    pub fn method_type(&mut self, descriptor: impl Into<Cow<'a, str>>) -> Result<u16, ParseError> {
+        let descriptor = self.utf8(descriptor)?;
+        self.intern(BuilderEntry::MethodType(descriptor))
+    }
+
+    /// Interns a CONSTANT_Dynamic entry. The bootstrap method index refers to the
+    /// BootstrapMethods attribute rather than to the constant pool.
+    pub fn dynamic(
+        &mut self,
+        bootstrap_method: u16,
+        name: impl Into<Cow<'a, str>>,
+        descriptor: impl Into<Cow<'a, str>>,
+    ) -> Result<u16, ParseError> {
+        let name_and_type = self.name_and_type(name, descriptor)?;
+        self.intern(BuilderEntry::Dynamic(bootstrap_method, name_and_type))
+    }
+
+    /// Interns a CONSTANT_InvokeDynamic entry. The bootstrap method index refers to the
+    /// BootstrapMethods attribute rather than to the constant pool.
+    pub fn invoke_dynamic(
+        &mut self,
+        bootstrap_method: u16,
+        name: impl Into<Cow<'a, str>>,
+        descriptor: impl Into<Cow<'a, str>>,
+    ) -> Result<u16, ParseError> {
+        let name_and_type = self.name_and_type(name, descriptor)?;
+        self.intern(BuilderEntry::InvokeDynamic(bootstrap_method, name_and_type))
+    }
+
+    pub fn module(&mut self, name: impl Into<Cow<'a, str>>) -> Result<u16, ParseError> {
+        let name = self.utf8(name)?;
+        self.intern(BuilderEntry::ModuleInfo(name))
+    }
+
+    pub fn package(&mut self, name: impl Into<Cow<'a, str>>) -> Result<u16, ParseError> {
+        let name = self.utf8(name)?;
+        self.intern(BuilderEntry::PackageInfo(name))
+    }
+
+    /// Appends the constant pool built so far to `out` in class file format, starting
+    /// with the constant_pool_count.
+    pub fn write(&self, out: &mut Vec<u8>) {
+        out.extend_from_slice(&self.count().to_be_bytes());
+        for entry in &self.entries {
+            entry.write(out);
+        }
+    }
+
+    /// Returns the constant pool built so far in class file format, starting with the
+    /// constant_pool_count.
+    #[must_use]
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut out = Vec::new();
+        self.write(&mut out);
+        out
+    }
+
+    // Creates a builder holding the entries of a parsed constant pool at their original
+    // indices, so that data which refers to the pool by index (such as the code of a Code
+    // attribute) stays valid. Where the pool has duplicate entries, interning finds the
+    // first of them, and duplicates records the index of the first copy of each other one.
+    pub(crate) fn from_constant_pool(
+        constant_pool: &[CafeRc<ConstantPoolEntry<'a>>],
+    ) -> Result<Self, ParseError> {
+        let mut builder = ConstantPoolBuilder::new();
+        for entry in snapshot_constant_pool(constant_pool)?.entries {
+            let entry = match entry {
+                ResolvedEntry::Unusable => continue,
+                ResolvedEntry::Utf8(x) => BuilderEntry::Utf8(encode_utf8(x)),
+                ResolvedEntry::Utf8Bytes(x) => BuilderEntry::Utf8(x),
+                ResolvedEntry::Integer(v) => BuilderEntry::Integer(v),
+                ResolvedEntry::Float(v) => BuilderEntry::Float(v.to_bits()),
+                ResolvedEntry::Long(v) => BuilderEntry::Long(v),
+                ResolvedEntry::Double(v) => BuilderEntry::Double(v.to_bits()),
+                ResolvedEntry::ClassInfo { name } => BuilderEntry::ClassInfo(name),
+                ResolvedEntry::String { value } => BuilderEntry::String(value),
+                ResolvedEntry::FieldRef {
+                    class,
+                    name_and_type,
+                } => BuilderEntry::FieldRef(class, name_and_type),
+                ResolvedEntry::MethodRef {
+                    class,
+                    name_and_type,
+                } => BuilderEntry::MethodRef(class, name_and_type),
+                ResolvedEntry::InterfaceMethodRef {
+                    class,
+                    name_and_type,
+                } => BuilderEntry::InterfaceMethodRef(class, name_and_type),
+                ResolvedEntry::NameAndType { name, descriptor } => {
+                    BuilderEntry::NameAndType(name, descriptor)
+                }
+                ResolvedEntry::MethodHandle { kind, reference } => {
+                    BuilderEntry::MethodHandle(kind, reference)
+                }
+                ResolvedEntry::MethodType { descriptor } => BuilderEntry::MethodType(descriptor),
+                ResolvedEntry::Dynamic {
+                    bootstrap_method_attr_index,
+                    name_and_type,
+                } => BuilderEntry::Dynamic(bootstrap_method_attr_index, name_and_type),
+                ResolvedEntry::InvokeDynamic {
+                    bootstrap_method_attr_index,
+                    name_and_type,
+                } => BuilderEntry::InvokeDynamic(bootstrap_method_attr_index, name_and_type),
+                ResolvedEntry::ModuleInfo { name } => BuilderEntry::ModuleInfo(name),
+                ResolvedEntry::PackageInfo { name } => BuilderEntry::PackageInfo(name),
+            };
+            builder.push_entry(entry);
+        }
+        builder.index_entries();
+        Ok(builder)
+    }
+
+    // Adds an entry without looking for an existing equal one, so that every entry keeps
+    // its own index. index_entries must be called once all entries have been added.
+    fn push_entry(&mut self, entry: BuilderEntry<'a>) {
+        self.count += entry.slots();
+        self.entries.push(entry);
+    }
+
+    // Keys the entries by their contents so that interning finds the first of any equal
+    // entries. Entries that only differ in which copy of a duplicate entry they refer to are
+    // equal too, so references are keyed as the first copy, which means visiting the
+    // entries in order of their depth.
+    fn index_entries(&mut self) {
+        let mut first_copies = HashMap::new();
+        self.indices.clear();
+        for depth in 0..4 {
+            let mut entry_index = 1;
+            for entry in &self.entries {
+                let index = entry_index as u16;
+                entry_index += entry.slots();
+                if entry.depth() == depth {
+                    let key = entry.remap(|x| first_copies.get(&x).copied().unwrap_or(x));
+                    first_copies.insert(index, *self.indices.entry(key).or_insert(index));
+                }
+            }
+        }
+        self.duplicates = first_copies
+            .into_iter()
+            .filter(|(index, first)| index != first)
+            .collect();
+    }
+
+    // Returns the index of the first copy of each entry that is equal to an earlier one,
+    // keyed by the index of the later copy.
+    pub(crate) fn duplicates(&self) -> &HashMap<u16, u16> {
+        &self.duplicates
+    }
+
+    // Appends the entries interned since this builder was created by from_constant_pool
+    // to the given constant pool, which is left unchanged if the new entries are not valid
+    // for a class file of the given major version.
+    pub(crate) fn extend_constant_pool(
+        &self,
+        constant_pool: &mut Vec<CafeRc<ConstantPoolEntry<'a>>>,
+        major_version: u16,
+    ) -> Result<(), ParseError> {
+        let unresolved = |x: &u16| CafeCell::new(ConstantPoolRef::Unresolved(*x));
+        let mut extended_pool = constant_pool.clone();
+        let mut entry_index = 1;
+        for entry in &self.entries {
+            if entry_index >= constant_pool.len() {
+                extended_pool.push(CafeRc::new(match entry {
+                    BuilderEntry::Utf8(x) => decode_utf8(x),
+                    BuilderEntry::Integer(v) => ConstantPoolEntry::Integer(*v),
+                    BuilderEntry::Float(bits) => ConstantPoolEntry::Float(f32::from_bits(*bits)),
+                    BuilderEntry::Long(v) => ConstantPoolEntry::Long(*v),
+                    BuilderEntry::Double(bits) => ConstantPoolEntry::Double(f64::from_bits(*bits)),
+                    BuilderEntry::ClassInfo(x) => ConstantPoolEntry::ClassInfo(unresolved(x)),
+                    BuilderEntry::String(x) => ConstantPoolEntry::String(unresolved(x)),
+                    BuilderEntry::FieldRef(x, y) => {
+                        ConstantPoolEntry::FieldRef(unresolved(x), unresolved(y))
+                    }
+                    BuilderEntry::MethodRef(x, y) => {
+                        ConstantPoolEntry::MethodRef(unresolved(x), unresolved(y))
+                    }
+                    BuilderEntry::InterfaceMethodRef(x, y) => {
+                        ConstantPoolEntry::InterfaceMethodRef(unresolved(x), unresolved(y))
+                    }
+                    BuilderEntry::NameAndType(x, y) => {
+                        ConstantPoolEntry::NameAndType(unresolved(x), unresolved(y))
+                    }
+                    BuilderEntry::MethodHandle(kind, x) => {
+                        ConstantPoolEntry::MethodHandle(*kind, unresolved(x))
+                    }
+                    BuilderEntry::MethodType(x) => ConstantPoolEntry::MethodType(unresolved(x)),
+                    BuilderEntry::Dynamic(x, y) => ConstantPoolEntry::Dynamic(*x, unresolved(y)),
+                    BuilderEntry::InvokeDynamic(x, y) => {
+                        ConstantPoolEntry::InvokeDynamic(*x, unresolved(y))
+                    }
+                    BuilderEntry::ModuleInfo(x) => ConstantPoolEntry::ModuleInfo(unresolved(x)),
+                    BuilderEntry::PackageInfo(x) => ConstantPoolEntry::PackageInfo(unresolved(x)),
+                }));
+                if entry.slots() == 2 {
+                    extended_pool.push(CafeRc::new(ConstantPoolEntry::Unused));
+                }
+            }
+            entry_index += entry.slots();
+        }
+        resolve_constant_pool(&extended_pool)?;
+        validate_constant_pool(&extended_pool, major_version)?;
+        *constant_pool = extended_pool;
+        Ok(())
+    }
+
+    // Starts recording the index of every constant interned from now on, so that compact
+    // can tell which entries are in use.
+    pub(crate) fn record_references(&mut self) {
+        self.referenced = Some(HashMap::new());
+    }
+
+    pub(crate) fn records_references(&self) -> bool {
+        self.referenced.is_some()
+    }
+
+    // Returns the number of references to each entry, counting those from the constants
+    // interned since record_references was called, those between entries and the given
+    // extra references. Interning a constant also interns the constants it refers to, so
+    // those nested interns are subtracted to count each reference only once.
+    pub(crate) fn reference_counts(&self, extra_references: &[u16]) -> HashMap<u16, usize> {
+        let mut positions = HashMap::new();
+        let mut entry_index = 1;
+        for (position, entry) in self.entries.iter().enumerate() {
+            positions.insert(entry_index as u16, position);
+            entry_index += entry.slots();
+        }
+        let mut counts = self.referenced.clone().unwrap_or_default();
+        for entry in &self.entries {
+            for index in entry.references() {
+                *counts.entry(index).or_insert(0) += 1;
+            }
+        }
+        for index in extra_references {
+            *counts.entry(*index).or_insert(0) += 1;
+        }
+        for (index, interned) in self.referenced.iter().flatten() {
+            for reference in self.entries[positions[index]].references() {
+                *counts.get_mut(&reference).unwrap() -= interned;
+            }
+        }
+        counts
+    }
+
+    // Returns a builder holding only the entries that were interned since record_references
+    // was called or whose indices are given, along with the entries they refer to. The kept
+    // entries stay in the same relative order, so no entry moves to a higher index. Also
+    // returns the new index of each kept entry, keyed by its old index.
+    pub(crate) fn compact(
+        &self,
+        extra_references: &[u16],
+    ) -> Result<(Self, HashMap<u16, u16>), ParseError> {
+        let mut positions = HashMap::new();
+        let mut old_indices = Vec::with_capacity(self.entries.len());
+        let mut entry_index = 1;
+        for (position, entry) in self.entries.iter().enumerate() {
+            positions.insert(entry_index as u16, position);
+            old_indices.push(entry_index as u16);
+            entry_index += entry.slots();
+        }
+        let mut pending: Vec<u16> = self.referenced.iter().flatten().map(|(x, _)| *x).collect();
+        pending.extend_from_slice(extra_references);
+        let mut kept = vec![false; self.entries.len()];
+        while let Some(index) = pending.pop() {
+            let position = match positions.get(&index) {
+                Some(position) => *position,
+                None => fail!("Reference to invalid constant pool index {}", index),
+            };
+            if !kept[position] {
+                kept[position] = true;
+                pending.extend(self.entries[position].references());
+            }
+        }
+        let mut new_indices = HashMap::new();
+        let mut new_count = 1;
+        for (position, entry) in self.entries.iter().enumerate() {
+            if kept[position] {
+                new_indices.insert(old_indices[position], new_count as u16);
+                new_count += entry.slots();
+            }
+        }
+        let mut compacted = ConstantPoolBuilder::new();
+        for (position, entry) in self.entries.iter().enumerate() {
+            if kept[position] {
+                compacted.push_entry(entry.remap(|x| new_indices[&x]));
+            }
+        }
+        compacted.index_entries();
+        Ok((compacted, new_indices))
+    }
+
+    // Describes each entry the way javap does, as its index, its kind, its contents (with
+    // references given as indices) and the resolved contents of anything it refers to.
+    pub(crate) fn describe(&self) -> Vec<(u16, &'static str, String, String)> {
+        let mut positions = HashMap::new();
+        let mut entry_index = 1;
+        for (position, entry) in self.entries.iter().enumerate() {
+            positions.insert(entry_index as u16, position);
+            entry_index += entry.slots();
+        }
+        let mut described = Vec::with_capacity(self.entries.len());
+        let mut entry_index = 1;
+        for (position, entry) in self.entries.iter().enumerate() {
+            let resolved = self.resolved(position, &positions, MAX_REFERENCE_CHAIN);
+            let (kind, contents, comment) = match entry {
+                BuilderEntry::Utf8(_) => ("Utf8", resolved, String::new()),
+                BuilderEntry::Integer(_) => ("Integer", resolved, String::new()),
+                BuilderEntry::Float(_) => ("Float", resolved, String::new()),
+                BuilderEntry::Long(_) => ("Long", resolved, String::new()),
+                BuilderEntry::Double(_) => ("Double", resolved, String::new()),
+                BuilderEntry::ClassInfo(x) => ("Class", format!("#{}", x), resolved),
+                BuilderEntry::String(x) => ("String", format!("#{}", x), resolved),
+                BuilderEntry::FieldRef(x, y) => ("Fieldref", format!("#{}.#{}", x, y), resolved),
+                BuilderEntry::MethodRef(x, y) => ("Methodref", format!("#{}.#{}", x, y), resolved),
+                BuilderEntry::InterfaceMethodRef(x, y) => {
+                    ("InterfaceMethodref", format!("#{}.#{}", x, y), resolved)
+                }
+                BuilderEntry::NameAndType(x, y) => {
+                    ("NameAndType", format!("#{}:#{}", x, y), resolved)
+                }
+                BuilderEntry::MethodHandle(kind, x) => (
+                    "MethodHandle",
+                    format!("{}:#{}", reference_kind_tag(*kind), x),
+                    resolved,
+                ),
+                BuilderEntry::MethodType(x) => ("MethodType", format!("#{}", x), resolved),
+                BuilderEntry::Dynamic(x, y) => ("Dynamic", format!("#{}:#{}", x, y), resolved),
+                BuilderEntry::InvokeDynamic(x, y) => {
+                    ("InvokeDynamic", format!("#{}:#{}", x, y), resolved)
+                }
+                BuilderEntry::ModuleInfo(x) => ("Module", format!("#{}", x), resolved),
+                BuilderEntry::PackageInfo(x) => ("Package", format!("#{}", x), resolved),
+            };
+            described.push((entry_index as u16, kind, contents, comment));
+            entry_index += entry.slots();
+        }
+        described
+    }
+
+    // Renders the entry at the given position with everything it refers to, following at
+    // most `depth` further references. Since the pool may not have been validated (e.g. if
+    // resolution was deferred), a reference that is out of bounds, or that is part of a
+    // chain longer than any valid one, such as a cycle, is rendered as <invalid>.
+    fn resolved(&self, position: usize, positions: &HashMap<u16, usize>, depth: usize) -> String {
+        let resolve = |index: &u16| match (positions.get(index), depth.checked_sub(1)) {
+            (Some(position), Some(depth)) => self.resolved(*position, positions, depth),
+            _ => String::from("<invalid>"),
+        };
+        match &self.entries[position] {
+            BuilderEntry::Utf8(data) => match cesu8::from_java_cesu8(data) {
+                Ok(value) => value.escape_debug().to_string(),
+                Err(_) => String::from_utf8_lossy(data).escape_debug().to_string(),
+            },
+            BuilderEntry::Integer(v) => v.to_string(),
+            BuilderEntry::Float(bits) => format!("{:?}f", f32::from_bits(*bits)),
+            BuilderEntry::Long(v) => format!("{}l", v),
+            BuilderEntry::Double(bits) => format!("{:?}d", f64::from_bits(*bits)),
+            BuilderEntry::ClassInfo(x)
+            | BuilderEntry::String(x)
+            | BuilderEntry::MethodType(x)
+            | BuilderEntry::ModuleInfo(x)
+            | BuilderEntry::PackageInfo(x) => resolve(x),
+            BuilderEntry::FieldRef(x, y)
+            | BuilderEntry::MethodRef(x, y)
+            | BuilderEntry::InterfaceMethodRef(x, y) => {
+                format!("{}.{}", resolve(x), resolve(y))
+            }
+            BuilderEntry::NameAndType(x, y) => {
+                let name = resolve(x);
+                if name.starts_with('<') {
+                    format!("\"{}\":{}", name, resolve(y))
+                } else {
+                    format!("{}:{}", name, resolve(y))
+                }
+            }
+            BuilderEntry::MethodHandle(kind, x) => {
+                format!("{} {}", reference_kind_name(*kind), resolve(x))
+            }
+            BuilderEntry::Dynamic(x, y) | BuilderEntry::InvokeDynamic(x, y) => {
+                format!("#{}:{}", x, resolve(y))
+            }
+        }
+    }
+}
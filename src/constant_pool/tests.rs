@@ -42,7 +42,7 @@ fn wrap(entry: ConstantPoolEntry) -> CafeCell<ConstantPoolRef> {
 fn test_validate_trivial() {
     assert_validate_passes!(Zero);
     assert_validate_passes!(Utf8(Cow::from("some UTF-8")));
-    assert_validate_passes!(Utf8Bytes(&[]));
+    assert_validate_passes!(Utf8Bytes(Cow::Borrowed(&[])));
     assert_validate_passes!(Integer(1));
     assert_validate_passes!(Float(2.0));
     assert_validate_passes!(Long(3));
@@ -60,23 +60,23 @@ fn test_validate_class_info() {
         "Invalid classinfo name"
     );
     assert_validate_fails!(
-        ClassInfo(wrap(Utf8Bytes(&[]))),
+        ClassInfo(wrap(Utf8Bytes(Cow::Borrowed(&[])))),
         "Attempting to get utf-8 data from non-utf8 constant pool entry!"
     );
     assert_validate_fails!(
         ClassInfo(wrap(Zero)),
-        "Unexpected constant pool reference type"
+        "Unexpected constant pool reference type; expected Utf8 but found Zero"
     );
 }
 
 #[test]
 fn test_validate_string() {
     assert_validate_passes!(String(wrap(Utf8(Cow::from("some UTF-8")))));
-    assert_validate_passes!(String(wrap(Utf8Bytes(&[]))));
+    assert_validate_passes!(String(wrap(Utf8Bytes(Cow::Borrowed(&[])))));
 
     assert_validate_fails!(
         String(wrap(Zero)),
-        "Unexpected constant pool reference type"
+        "Unexpected constant pool reference type; expected Utf8 but found Zero"
     );
 }
 
@@ -98,14 +98,14 @@ fn test_validate_field_ref() {
                 wrap(Utf8(Cow::from("I"))),
             )),
         ),
-        "Unexpected constant pool reference type"
+        "Unexpected constant pool reference type; expected ClassInfo but found Zero"
     );
     assert_validate_fails!(
         FieldRef(
             wrap(ClassInfo(wrap(Utf8(Cow::from("some/package/Class"))))),
             wrap(Zero),
         ),
-        "Unexpected constant pool reference type"
+        "Unexpected constant pool reference type; expected NameAndType but found Zero"
     );
     assert_validate_fails!(
         FieldRef(
@@ -122,7 +122,7 @@ fn test_validate_field_ref() {
             wrap(ClassInfo(wrap(Utf8(Cow::from("some/package/Class"))))),
             wrap(NameAndType(
                 wrap(Utf8(Cow::from("someField"))),
-                wrap(Utf8Bytes(&[])),
+                wrap(Utf8Bytes(Cow::Borrowed(&[]))),
             )),
         ),
         "Attempting to get utf-8 data from non-utf8 constant pool entry!"
@@ -154,14 +154,14 @@ fn test_validate_method_ref() {
                 wrap(Utf8(Cow::from("()V"))),
             )),
         ),
-        "Unexpected constant pool reference type"
+        "Unexpected constant pool reference type; expected ClassInfo but found Zero"
     );
     assert_validate_fails!(
         MethodRef(
             wrap(ClassInfo(wrap(Utf8(Cow::from("some/package/Class"))))),
             wrap(Zero),
         ),
-        "Unexpected constant pool reference type"
+        "Unexpected constant pool reference type; expected NameAndType but found Zero"
     );
     assert_validate_fails!(
         MethodRef(
@@ -178,7 +178,7 @@ fn test_validate_method_ref() {
             wrap(ClassInfo(wrap(Utf8(Cow::from("some/package/Class"))))),
             wrap(NameAndType(
                 wrap(Utf8(Cow::from("someMethod"))),
-                wrap(Utf8Bytes(&[])),
+                wrap(Utf8Bytes(Cow::Borrowed(&[]))),
             )),
         ),
         "Attempting to get utf-8 data from non-utf8 constant pool entry!"
@@ -210,14 +210,14 @@ fn test_validate_interface_method_ref() {
                 wrap(Utf8(Cow::from("()V"))),
             )),
         ),
-        "Unexpected constant pool reference type"
+        "Unexpected constant pool reference type; expected ClassInfo but found Zero"
     );
     assert_validate_fails!(
         InterfaceMethodRef(
             wrap(ClassInfo(wrap(Utf8(Cow::from("some/package/Class"))))),
             wrap(Zero),
         ),
-        "Unexpected constant pool reference type"
+        "Unexpected constant pool reference type; expected NameAndType but found Zero"
     );
     assert_validate_fails!(
         InterfaceMethodRef(
@@ -234,7 +234,7 @@ fn test_validate_interface_method_ref() {
             wrap(ClassInfo(wrap(Utf8(Cow::from("some/package/Class"))))),
             wrap(NameAndType(
                 wrap(Utf8(Cow::from("someMethod"))),
-                wrap(Utf8Bytes(&[])),
+                wrap(Utf8Bytes(Cow::Borrowed(&[]))),
             )),
         ),
         "Attempting to get utf-8 data from non-utf8 constant pool entry!"
@@ -256,7 +256,7 @@ fn test_validate_name_and_type() {
     ));
     assert_validate_passes!(NameAndType(
         wrap(Utf8(Cow::from("someUnqualifiedName"))),
-        wrap(Utf8Bytes(&[])),
+        wrap(Utf8Bytes(Cow::Borrowed(&[]))),
     ));
 
     assert_validate_fails!(
@@ -268,15 +268,18 @@ fn test_validate_name_and_type() {
     );
     assert_validate_fails!(
         NameAndType(wrap(Zero), wrap(Utf8(Cow::from("anything goes"))),),
-        "Unexpected constant pool reference type"
+        "Unexpected constant pool reference type; expected Utf8 but found Zero"
     );
     assert_validate_fails!(
-        NameAndType(wrap(Utf8Bytes(&[])), wrap(Utf8(Cow::from("anything goes"))),),
+        NameAndType(
+            wrap(Utf8Bytes(Cow::Borrowed(&[]))),
+            wrap(Utf8(Cow::from("anything goes"))),
+        ),
         "Attempting to get utf-8 data from non-utf8 constant pool entry!"
     );
     assert_validate_fails!(
         NameAndType(wrap(Utf8(Cow::from("someUnqualifiedName"))), wrap(Zero)),
-        "Unexpected constant pool reference type"
+        "Unexpected constant pool reference type; expected Utf8 but found Zero"
     );
 }
 
@@ -298,7 +301,7 @@ fn test_validate_method_handle() {
 
         assert_validate_fails!(
             MethodHandle(kind, wrap(Zero)),
-            "Unexpected constant pool reference type"
+            "Unexpected constant pool reference type; expected FieldRef but found Zero"
         );
     }
 
@@ -316,7 +319,7 @@ fn test_validate_method_handle() {
 
         assert_validate_fails!(
             MethodHandle(kind, wrap(Zero)),
-            "Unexpected constant pool reference type"
+            "Unexpected constant pool reference type; expected MethodRef but found Zero"
         );
     }
 
@@ -347,14 +350,26 @@ fn test_validate_method_handle() {
             if version >= 52 {
                 assert_validate_passes!(version, entry);
             } else {
-                assert_validate_fails!(version, entry, "Unexpected constant pool reference type");
+                assert_validate_fails!(version, entry, "Unexpected constant pool reference type; expected MethodRef but found InterfaceMethodRef");
             }
         }
 
-        assert_validate_fails!(
-            MethodHandle(kind, wrap(Zero)),
-            "Unexpected constant pool reference type"
-        );
+        for version in VERSIONS {
+            let entry = MethodHandle(kind, wrap(Zero));
+            if version >= 52 {
+                assert_validate_fails!(
+                    version,
+                    entry,
+                    "Unexpected constant pool reference type; expected MethodRef or InterfaceMethodRef but found Zero"
+                );
+            } else {
+                assert_validate_fails!(
+                    version,
+                    entry,
+                    "Unexpected constant pool reference type; expected MethodRef but found Zero"
+                );
+            }
+        }
     }
 
     for kind in [InvokeInterface] {
@@ -371,7 +386,7 @@ fn test_validate_method_handle() {
 
         assert_validate_fails!(
             MethodHandle(kind, wrap(Zero)),
-            "Unexpected constant pool reference type"
+            "Unexpected constant pool reference type; expected InterfaceMethodRef but found Zero"
         );
     }
 }
@@ -385,12 +400,12 @@ fn test_validate_method_type() {
         "Invalid method descriptor"
     );
     assert_validate_fails!(
-        MethodType(wrap(Utf8Bytes(&[]))),
+        MethodType(wrap(Utf8Bytes(Cow::Borrowed(&[])))),
         "Attempting to get utf-8 data from non-utf8 constant pool entry!"
     );
     assert_validate_fails!(
         MethodType(wrap(Zero)),
-        "Unexpected constant pool reference type"
+        "Unexpected constant pool reference type; expected Utf8 but found Zero"
     );
 }
 
@@ -413,14 +428,14 @@ fn test_validate_dynamic() {
     assert_validate_passes!(Dynamic(
         0,
         wrap(NameAndType(
-            wrap(Utf8Bytes(&[])),
+            wrap(Utf8Bytes(Cow::Borrowed(&[]))),
             wrap(Utf8(Cow::from("I"))),
         )),
     ));
 
     assert_validate_fails!(
         Dynamic(0, wrap(Zero)),
-        "Unexpected constant pool reference type"
+        "Unexpected constant pool reference type; expected NameAndType but found Zero"
     );
     assert_validate_fails!(
         Dynamic(
@@ -437,7 +452,7 @@ fn test_validate_dynamic() {
             0,
             wrap(NameAndType(
                 wrap(Utf8(Cow::from("someField"))),
-                wrap(Utf8Bytes(&[]))
+                wrap(Utf8Bytes(Cow::Borrowed(&[])))
             )),
         ),
         "Attempting to get utf-8 data from non-utf8 constant pool entry!"
@@ -463,14 +478,14 @@ fn test_validate_invoke_dynamic() {
     assert_validate_passes!(InvokeDynamic(
         0,
         wrap(NameAndType(
-            wrap(Utf8Bytes(&[])),
+            wrap(Utf8Bytes(Cow::Borrowed(&[]))),
             wrap(Utf8(Cow::from("()V"))),
         )),
     ));
 
     assert_validate_fails!(
         InvokeDynamic(0, wrap(Zero)),
-        "Unexpected constant pool reference type"
+        "Unexpected constant pool reference type; expected NameAndType but found Zero"
     );
     assert_validate_fails!(
         InvokeDynamic(
@@ -487,7 +502,7 @@ fn test_validate_invoke_dynamic() {
             0,
             wrap(NameAndType(
                 wrap(Utf8(Cow::from("someMethod"))),
-                wrap(Utf8Bytes(&[]))
+                wrap(Utf8Bytes(Cow::Borrowed(&[])))
             )),
         ),
         "Attempting to get utf-8 data from non-utf8 constant pool entry!"
@@ -503,12 +518,12 @@ fn test_validate_module_info() {
         "Invalid module name"
     );
     assert_validate_fails!(
-        ModuleInfo(wrap(Utf8Bytes(&[]))),
+        ModuleInfo(wrap(Utf8Bytes(Cow::Borrowed(&[])))),
         "Attempting to get utf-8 data from non-utf8 constant pool entry!"
     );
     assert_validate_fails!(
         ModuleInfo(wrap(Zero)),
-        "Unexpected constant pool reference type"
+        "Unexpected constant pool reference type; expected Utf8 but found Zero"
     );
 }
 
@@ -521,11 +536,477 @@ fn test_validate_invoke_package_info() {
         "Invalid binary name"
     );
     assert_validate_fails!(
-        PackageInfo(wrap(Utf8Bytes(&[]))),
+        PackageInfo(wrap(Utf8Bytes(Cow::Borrowed(&[])))),
         "Attempting to get utf-8 data from non-utf8 constant pool entry!"
     );
     assert_validate_fails!(
         PackageInfo(wrap(Zero)),
-        "Unexpected constant pool reference type"
+        "Unexpected constant pool reference type; expected Utf8 but found Zero"
+    );
+}
+
+#[test]
+fn test_read_bootstrap_argument() {
+    let pool = vec![
+        CafeRc::new(Zero),
+        CafeRc::new(Dynamic(
+            2,
+            wrap(NameAndType(
+                wrap(Utf8(Cow::from("someConstant"))),
+                wrap(Utf8(Cow::from("I"))),
+            )),
+        )),
+        CafeRc::new(Utf8(Cow::from("someString"))),
+    ];
+    let mut ix = 0;
+    match read_cp_bootstrap_argument(&[0, 1], &mut ix, &pool) {
+        Ok(BootstrapArgument::Dynamic(dynamic)) => {
+            assert_eq!(dynamic.attr_index, 2);
+            assert_eq!(dynamic.name_and_type.name, "someConstant");
+        }
+        other => panic!("Unexpected bootstrap argument {:?}", other),
+    }
+    let mut ix = 0;
+    assert_eq!(
+        read_cp_bootstrap_argument(&[0, 2], &mut ix, &pool).unwrap_err(),
+        ParseError::new("Unexpected constant pool reference type".to_string())
+    );
+}
+
+#[test]
+fn test_resolve_constant_pool_path() {
+    let unresolved = |ix| CafeCell::new(ConstantPoolRef::Unresolved(ix));
+    let pool = vec![
+        CafeRc::new(Zero),
+        CafeRc::new(Utf8(Cow::from("java/lang/Object"))),
+        CafeRc::new(ClassInfo(unresolved(99))),
+    ];
+    let error = resolve_constant_pool(&pool).unwrap_err();
+    assert_eq!(
+        error.to_string(),
+        "Constant pool entry at index 2 references out-of-bounds index 99"
+    );
+    assert_eq!(error.constant_pool_path(), [2, 99]);
+
+    let pool = vec![CafeRc::new(Zero), CafeRc::new(String(unresolved(1)))];
+    let error = resolve_constant_pool(&pool).unwrap_err();
+    assert_eq!(error.constant_pool_path(), [1, 1]);
+
+    let pool = vec![
+        CafeRc::new(Zero),
+        CafeRc::new(Integer(1)),
+        CafeRc::new(ClassInfo(unresolved(1))),
+    ];
+    resolve_constant_pool(&pool).unwrap();
+    let error = validate_constant_pool(&pool, 65).unwrap_err();
+    assert_eq!(error.constant_pool_path(), [2]);
+
+    let mut ix = 0;
+    let error = read_cp_utf8(&[0, 7], &mut ix, &pool).unwrap_err();
+    assert_eq!(error.constant_pool_path(), [7]);
+}
+
+#[test]
+fn test_long_without_extra_slot() {
+    // A pool written as if the Long at index 1 took a single slot, so the ClassInfo at
+    // index 3 refers to the Utf8 it meant to be at index 2
+    #[rustfmt::skip]
+    let bytes = [
+        0, 5,
+        5, 0, 0, 0, 0, 0, 0, 0, 7,
+        1, 0, 3, b'a', b'/', b'B',
+        7, 0, 2,
+        8, 0, 2,
+    ];
+    let error = read_constant_pool(&bytes, &mut 0, 65).unwrap_err();
+    assert_eq!(
+        error.to_string(),
+        "Constant pool entry at index 4 references index 2, the unusable slot after the Long or Double at index 1 (long/double slot accounting mismatch; was the class file written without the extra slot for Long and Double entries?)"
+    );
+    assert_eq!(error.constant_pool_path(), [4, 2]);
+
+    let pool = vec![
+        CafeRc::new(Zero),
+        CafeRc::new(Double(1.0)),
+        CafeRc::new(Unused),
+    ];
+    let error = read_cp_utf8(&[0, 2], &mut 0, &pool).unwrap_err();
+    assert!(error
+        .to_string()
+        .starts_with("Constant pool reference to index 2, the unusable slot"));
+}
+
+#[test]
+fn test_resolve_entry() {
+    let unresolved = |ix| CafeCell::new(ConstantPoolRef::Unresolved(ix));
+    let pool = vec![
+        CafeRc::new(Zero),
+        CafeRc::new(Utf8(Cow::from("java/lang/Object"))),
+        CafeRc::new(ClassInfo(unresolved(1))),
+        CafeRc::new(Utf8(Cow::from("hashCode"))),
+        CafeRc::new(Utf8(Cow::from("()I"))),
+        CafeRc::new(NameAndType(unresolved(3), unresolved(4))),
+        CafeRc::new(MethodRef(unresolved(2), unresolved(5))),
+        CafeRc::new(MethodHandle(ReferenceKind::InvokeVirtual, unresolved(6))),
+        CafeRc::new(ClassInfo(unresolved(99))),
+        CafeRc::new(ClassInfo(unresolved(10))),
+        CafeRc::new(ClassInfo(unresolved(9))),
+    ];
+    // Resolving the longest valid chain resolves everything along it
+    resolve_entry(&pool, 7).unwrap();
+    match constant_pool_item(&pool[7]).unwrap().unwrap() {
+        ConstantPoolItem::MethodHandle(handle) => {
+            assert_eq!(handle.class_name, "java/lang/Object");
+            assert_eq!(handle.member_ref.name, "hashCode");
+            assert_eq!(handle.member_ref.descriptor, "()I");
+        }
+        item => panic!("Unexpected item {:?}", item),
+    }
+    resolve_entry(&pool, 7).unwrap();
+
+    assert_eq!(
+        resolve_entry(&pool, 8).unwrap_err().to_string(),
+        "Constant pool entry at index 8 references out-of-bounds index 99"
+    );
+    assert_eq!(
+        resolve_entry(&pool, 9).unwrap_err().to_string(),
+        "Constant pool entry at index 10 is part of a chain of references that is cyclic or too long"
+    );
+}
+
+#[cfg(feature = "parallel")]
+#[test]
+fn test_resolve_constant_pool_parallel() {
+    let unresolved = |ix| CafeCell::new(ConstantPoolRef::Unresolved(ix));
+    let pool: Vec<_> = (0..1000)
+        .map(|i| match i {
+            0 => CafeRc::new(Zero),
+            1 => CafeRc::new(Utf8(Cow::from("java/lang/Object"))),
+            _ => CafeRc::new(ClassInfo(unresolved(1))),
+        })
+        .collect();
+    resolve_constant_pool_parallel(&pool).unwrap();
+    assert_eq!(pool[999].classinfo().unwrap(), "java/lang/Object");
+
+    // With several bad entries, the first one is reported, as in the sequential version
+    let mut pool = pool;
+    pool[500] = CafeRc::new(ClassInfo(unresolved(2000)));
+    pool[700] = CafeRc::new(ClassInfo(unresolved(700)));
+    assert_eq!(
+        resolve_constant_pool_parallel(&pool).unwrap_err(),
+        resolve_constant_pool(&pool).unwrap_err()
+    );
+    assert_eq!(
+        resolve_constant_pool_parallel(&pool)
+            .unwrap_err()
+            .constant_pool_path(),
+        [500, 2000]
+    );
+}
+
+#[cfg(feature = "parallel")]
+#[test]
+fn test_validate_constant_pool_parallel() {
+    let resolved = |entry: &CafeRc<ConstantPoolEntry<'static>>| {
+        CafeCell::new(ConstantPoolRef::Resolved(entry.clone()))
+    };
+    let object = CafeRc::new(Utf8(Cow::from("java/lang/Object")));
+    let empty = CafeRc::new(Utf8(Cow::from("")));
+    let mut pool: Vec<_> = (0..1000)
+        .map(|i| match i {
+            0 => CafeRc::new(Zero),
+            _ => CafeRc::new(ClassInfo(resolved(&object))),
+        })
+        .collect();
+    validate_constant_pool_parallel(&pool, 52).unwrap();
+
+    // With several bad entries, the first one is reported, as in the sequential version
+    pool[500] = CafeRc::new(ClassInfo(resolved(&empty)));
+    pool[700] = CafeRc::new(ClassInfo(resolved(&empty)));
+    let error = validate_constant_pool_parallel(&pool, 52).unwrap_err();
+    assert_eq!(error, validate_constant_pool(&pool, 52).unwrap_err());
+    assert_eq!(error.constant_pool_path(), [500]);
+}
+
+#[test]
+fn test_non_utf8_descriptor() {
+    // A NameAndType whose descriptor is not valid utf-8 passes validation if no member
+    // reference uses it, but it cannot be read as a NameAndType
+    let pool = vec![
+        CafeRc::new(Zero),
+        CafeRc::new(NameAndType(
+            wrap(Utf8(Cow::from("someName"))),
+            wrap(Utf8Bytes(Cow::Borrowed(&[0xc0]))),
+        )),
+        CafeRc::new(Integer(1)),
+    ];
+    validate_constant_pool(&pool, 65).unwrap();
+    assert_eq!(
+        read_cp_nameandtype_opt(&[0, 1], &mut 0, &pool).unwrap_err(),
+        ParseError::new(
+            "Attempting to get utf-8 data from non-utf8 constant pool entry!".to_string()
+        )
+    );
+    let items: Vec<ConstantPoolItem> = ConstantPoolIter::new(&pool).collect();
+    assert!(matches!(
+        items[..],
+        [ConstantPoolItem::LiteralConstant(LiteralConstant::Integer(
+            1
+        ))]
+    ));
+}
+
+#[test]
+fn test_read_unknown_constant_type() {
+    // count = 2, followed by a single entry of type 21
+    let bytes = [0, 2, 21, 0, 0];
+    assert_eq!(
+        read_constant_pool(&bytes, &mut 0, 65).unwrap_err(),
+        ParseError::new(
+            "Unknown constant pool entry type 21 at index 2 for classfile major version 65"
+                .to_string()
+        )
+    );
+    for tag in [2, 13, 14] {
+        let bytes = [0, 2, tag, 0, 0];
+        assert_eq!(
+            read_constant_pool(&bytes, &mut 0, 65).unwrap_err(),
+            ParseError::new(format!("Constant pool entry type {} at index 2 is not assigned by the JVM spec (4.4) and is never valid", tag))
+        );
+    }
+    // CONSTANT_Dynamic is known, but not valid in a Java 10 class file
+    let bytes = [0, 2, 17, 0, 0, 0, 0];
+    assert_eq!(
+        read_constant_pool(&bytes, &mut 0, 54).unwrap_err(),
+        ParseError::new("Constant pool entry type 17 at index 2 requires classfile major version 55 or later, but found 54".to_string())
     );
 }
+
+#[test]
+fn test_read_truncated_utf8() {
+    // count = 2, followed by a Utf8 entry declaring 10 bytes of which only 3 are present
+    let bytes = [0, 2, 1, 0, 10, b'a', b'b', b'c'];
+    let err = read_constant_pool(&bytes, &mut 0, 65).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "Unexpected end of stream reading CONSTANT_Utf8 at index 5; needed 10 bytes but only 3 are available"
+    );
+    assert_eq!(
+        err.unexpected_eof(),
+        Some(crate::UnexpectedEof {
+            needed: 10,
+            available: 3
+        })
+    );
+    let bytes = [0, 2, 1, 0];
+    assert_eq!(
+        read_constant_pool(&bytes, &mut 0, 65)
+            .unwrap_err()
+            .unexpected_eof(),
+        None
+    );
+}
+
+fn member_name_and_type(
+    name: &'static str,
+    descriptor: &'static str,
+) -> CafeCell<ConstantPoolRef<'static>> {
+    wrap(NameAndType(
+        wrap(Utf8(Cow::from(name))),
+        wrap(Utf8(Cow::from(descriptor))),
+    ))
+}
+
+fn class_info() -> CafeCell<ConstantPoolRef<'static>> {
+    wrap(ClassInfo(wrap(Utf8(Cow::from("some/package/Class")))))
+}
+
+#[test]
+fn test_validate_strict() {
+    assert_eq!(
+        MethodRef(class_info(), member_name_and_type("<init>", "()V")).validate_strict(),
+        Ok(())
+    );
+    assert_eq!(
+        MethodRef(class_info(), member_name_and_type("<clinit>", "()V")).validate_strict(),
+        Err(ParseError::new("Invalid method name <clinit>".to_string()))
+    );
+    assert_eq!(
+        MethodRef(class_info(), member_name_and_type("<init>", "()I")).validate_strict(),
+        Err(ParseError::new(
+            "Non-void method descriptor ()I for <init>".to_string()
+        ))
+    );
+    assert_eq!(
+        InterfaceMethodRef(class_info(), member_name_and_type("<init>", "()V")).validate_strict(),
+        Err(ParseError::new(
+            "Invalid interface method name <init>".to_string()
+        ))
+    );
+    assert_eq!(
+        MethodHandle(
+            ReferenceKind::NewInvokeSpecial,
+            wrap(MethodRef(
+                class_info(),
+                member_name_and_type("<init>", "()V")
+            ))
+        )
+        .validate_strict(),
+        Ok(())
+    );
+    assert_eq!(
+        MethodHandle(
+            ReferenceKind::InvokeVirtual,
+            wrap(MethodRef(
+                class_info(),
+                member_name_and_type("<init>", "()V")
+            ))
+        )
+        .validate_strict(),
+        Err(ParseError::new(
+            "Invalid method name <init> for method handle of kind InvokeVirtual".to_string()
+        ))
+    );
+    assert_eq!(
+        MethodHandle(
+            ReferenceKind::NewInvokeSpecial,
+            wrap(MethodRef(class_info(), member_name_and_type("run", "()V")))
+        )
+        .validate_strict(),
+        Err(ParseError::new(
+            "Invalid method name run for method handle of kind NewInvokeSpecial".to_string()
+        ))
+    );
+}
+
+#[test]
+fn test_canonical_sort_key() {
+    #[rustfmt::skip]
+    let first = [
+        0, 7,
+        7, 0, 2,
+        1, 0, 3, b'a', b'/', b'B',
+        10, 0, 1, 0, 4,
+        12, 0, 5, 0, 6,
+        1, 0, 1, b'm',
+        1, 0, 3, b'(', b')', b'V',
+    ];
+    #[rustfmt::skip]
+    let second = [
+        0, 7,
+        1, 0, 3, b'(', b')', b'V',
+        1, 0, 1, b'm',
+        12, 0, 2, 0, 1,
+        1, 0, 3, b'a', b'/', b'B',
+        10, 0, 6, 0, 3,
+        7, 0, 4,
+    ];
+    let first = read_constant_pool(&first, &mut 0, 61).unwrap();
+    let second = read_constant_pool(&second, &mut 0, 61).unwrap();
+    let mut first: Vec<_> = ConstantPoolIter::new(&first).collect();
+    let mut second: Vec<_> = ConstantPoolIter::new(&second).collect();
+    assert!(first[0].canonical_sort_key() != second[0].canonical_sort_key());
+
+    first.sort_by(|a, b| a.canonical_sort_key().cmp(&b.canonical_sort_key()));
+    second.sort_by(|a, b| a.canonical_sort_key().cmp(&b.canonical_sort_key()));
+    assert_eq!(first.len(), 3);
+    for (a, b) in first.iter().zip(&second) {
+        assert!(a.canonical_sort_key() == b.canonical_sort_key());
+    }
+    assert!(matches!(first[0], ConstantPoolItem::ClassInfo(_)));
+
+    let zero = ConstantPoolItem::LiteralConstant(LiteralConstant::Float(0.0));
+    let negative_zero = ConstantPoolItem::LiteralConstant(LiteralConstant::Float(-0.0));
+    assert!(zero.canonical_sort_key() != negative_zero.canonical_sort_key());
+}
+
+#[test]
+fn test_string_value() {
+    #[rustfmt::skip]
+    let bytes = [
+        0, 5,
+        8, 0, 2,
+        1, 0, 6, b's', b'e', b'c', b'r', b'e', b't',
+        8, 0, 4,
+        1, 0, 2, b'x', 0xff,
+    ];
+    let pool = read_constant_pool(&bytes, &mut 0, 61).unwrap();
+    let values: Vec<_> = ConstantPoolIter::new(&pool)
+        .filter_map(|item| item.string_value().map(|s| s.into_owned()))
+        .collect();
+    assert_eq!(values, ["secret", "x\u{fffd}"]);
+    assert!(LiteralConstant::Integer(1).string_value().is_none());
+    assert!(ConstantPoolItem::ClassInfo(Cow::Borrowed("a/B"))
+        .string_value()
+        .is_none());
+}
+
+#[test]
+fn test_constant_pool_builder() {
+    let mut builder = ConstantPoolBuilder::new();
+    let method = builder
+        .method_ref("java/lang/Object", "<init>", "()V")
+        .unwrap();
+    assert_eq!(method, 6);
+    assert_eq!(builder.class("java/lang/Object").unwrap(), 2);
+    assert_eq!(builder.long(7).unwrap(), 7);
+    assert_eq!(builder.string("\u{0}text").unwrap(), 10);
+    assert_eq!(builder.count(), 11);
+
+    let bytes = builder.to_bytes();
+    let pool = read_constant_pool(&bytes, &mut 0, 61).unwrap();
+    let items: Vec<_> = ConstantPoolIter::new(&pool).collect();
+    assert!(matches!(&items[0], ConstantPoolItem::ClassInfo(name) if name == "java/lang/Object"));
+    assert!(
+        matches!(&items[2], ConstantPoolItem::MethodRef(m) if m.name_and_type.name == "<init>")
+    );
+    assert!(matches!(
+        &items[3],
+        ConstantPoolItem::LiteralConstant(LiteralConstant::Long(7))
+    ));
+    assert!(matches!(
+        &items[4],
+        ConstantPoolItem::LiteralConstant(LiteralConstant::String(s)) if s == "\u{0}text"
+    ));
+}
+
+#[test]
+fn test_constant_pool_builder_overflow() {
+    let mut builder = ConstantPoolBuilder::new();
+    for i in 0..65533 {
+        builder.integer(i).unwrap();
+    }
+    assert_eq!(
+        builder.long(0).unwrap_err(),
+        ParseError::new("Constant pool overflow: 65536 entries exceed 65535".to_string())
+    );
+    assert_eq!(builder.integer(65533).unwrap(), 65534);
+    assert_eq!(builder.integer(0).unwrap(), 1);
+    assert_eq!(
+        builder.integer(-1).unwrap_err(),
+        ParseError::new("Constant pool overflow: 65536 entries exceed 65535".to_string())
+    );
+    assert_eq!(builder.to_bytes()[..2], [0xff, 0xff]);
+}
+
+#[test]
+fn test_constant_kinds() {
+    for (kind, tag, name, entry_type, _) in CONSTANT_KINDS.iter() {
+        assert_eq!(ConstantKind::from_tag(*tag), Some(*kind));
+        assert_eq!(kind.tag(), *tag);
+        assert_eq!(kind.name(), *name);
+        assert_eq!(kind.entry_type(), *entry_type);
+    }
+    assert_eq!(ConstantKind::from_tag(2), None);
+    assert_eq!(
+        ConstantPoolEntryTypes::LOADABLE.kind_names(),
+        "Integer or Float or Long or Double or ClassInfo or String or MethodHandle or MethodType or Dynamic"
+    );
+    assert_eq!(
+        (ConstantPoolEntryTypes::ZERO | ConstantPoolEntryTypes::UTF8).kind_names(),
+        "Zero or Utf8"
+    );
+    assert_eq!(Utf8Bytes(Cow::Borrowed(b"\xff")).kind_name(), "Utf8");
+    assert_eq!(Zero.kind_name(), "Zero");
+    assert_eq!(Unused.kind_name(), "Unused");
+}
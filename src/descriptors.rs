@@ -1,4 +1,5 @@
 use std::borrow::Cow;
+use std::fmt;
 
 use crate::ParseError;
 
@@ -50,6 +51,19 @@ impl<'a> ClassName<'a> {
     }
 }
 
+/// Formats the class name in its internal binary form, e.g. java/lang/Object.
+impl<'a> fmt::Display for ClassName<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, segment) in self.segments.iter().enumerate() {
+            if i > 0 {
+                f.write_str("/")?;
+            }
+            f.write_str(&segment.name)?;
+        }
+        Ok(())
+    }
+}
+
 // Returns the classname descriptor at the start of the given data, and ignores anything following.
 // Returns an error if there was no such classname.
 fn parse_class_descriptor<'a>(
@@ -69,7 +83,7 @@ fn parse_class_descriptor<'a>(
                 segments.push(segment);
                 continue;
             }
-            _ => panic!("Got unexpected return value from parse_unqualified_segment"),
+            (_, c) => fail!("Unexpected character {} in class name", c),
         }
     }
 }
@@ -96,6 +110,23 @@ impl<'a> FieldType<'a> {
     }
 }
 
+/// Formats the field type as it appears in a descriptor, e.g. I or Ljava/lang/Object;.
+impl<'a> fmt::Display for FieldType<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FieldType::Byte => f.write_str("B"),
+            FieldType::Char => f.write_str("C"),
+            FieldType::Double => f.write_str("D"),
+            FieldType::Float => f.write_str("F"),
+            FieldType::Integer => f.write_str("I"),
+            FieldType::Long => f.write_str("J"),
+            FieldType::Short => f.write_str("S"),
+            FieldType::Boolean => f.write_str("Z"),
+            FieldType::Object(class_name) => write!(f, "L{};", class_name),
+        }
+    }
+}
+
 #[derive(Clone, Debug, Hash, PartialEq, Eq)]
 pub struct FieldDescriptor<'a> {
     pub dimensions: u8,
@@ -108,6 +139,16 @@ impl<'a> FieldDescriptor<'a> {
     }
 }
 
+/// Formats the field descriptor in the form used in class files, e.g. [[I.
+impl<'a> fmt::Display for FieldDescriptor<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for _ in 0..self.dimensions {
+            f.write_str("[")?;
+        }
+        self.field_type.fmt(f)
+    }
+}
+
 // Parse the field descriptor at the start of the given data, and ignores anything
 // following. Returns an error if the data don't start with a field descriptor.
 pub(crate) fn parse_field_descriptor<'a>(
@@ -158,6 +199,16 @@ impl<'a> ReturnDescriptor<'a> {
     }
 }
 
+/// Formats the return descriptor in the form used in class files, e.g. V or I.
+impl<'a> fmt::Display for ReturnDescriptor<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Return(d) => d.fmt(f),
+            Self::Void => f.write_str("V"),
+        }
+    }
+}
+
 fn parse_return_descriptor<'a>(
     data: &Cow<'a, str>,
     index: usize,
@@ -188,6 +239,18 @@ impl<'a> MethodDescriptor<'a> {
     }
 }
 
+/// Formats the method descriptor in the form used in class files, e.g. (ILjava/lang/String;)V.
+impl<'a> fmt::Display for MethodDescriptor<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("(")?;
+        for parameter in &self.parameters {
+            parameter.fmt(f)?;
+        }
+        f.write_str(")")?;
+        self.return_type.fmt(f)
+    }
+}
+
 pub(crate) fn parse_method_descriptor<'a>(
     data: &Cow<'a, str>,
     mut index: usize,
@@ -214,6 +277,7 @@ pub(crate) fn parse_method_descriptor<'a>(
     })
 }
 
+#[allow(clippy::len_zero)]
 pub(crate) fn parse_array_descriptor<'a>(
     data: &Cow<'a, str>,
 ) -> Result<Option<FieldDescriptor<'a>>, ParseError> {
@@ -252,6 +316,54 @@ pub(crate) fn is_return_descriptor(name: &str) -> bool {
     }
 }
 
+// Replaces references to the class named `from` with `to` in a descriptor or signature,
+// where they appear in the form Lfrom; (or Lfrom< and Lfrom. in signatures). Returns true
+// if anything was replaced.
+pub(crate) fn rename_in_descriptor(data: &mut Cow<str>, from: &str, to: &str) -> bool {
+    let pattern = format!("L{}", from);
+    let mut renamed = String::with_capacity(data.len());
+    let mut copied_to = 0;
+    let mut search_from = 0;
+    while let Some(found) = data[search_from..].find(&pattern) {
+        let start = search_from + found;
+        let end = start + pattern.len();
+        search_from = start + 1;
+        // The match must cover the whole class name, not just part of a longer one
+        let at_type_start = start == 0 || "([);<>*+-:^".contains(&data[start - 1..start]);
+        let at_name_end = matches!(
+            data[end..].chars().next(),
+            Some(';') | Some('<') | Some('.')
+        );
+        if at_type_start && at_name_end {
+            renamed.push_str(&data[copied_to..start]);
+            renamed.push('L');
+            renamed.push_str(to);
+            copied_to = end;
+            search_from = end;
+        }
+    }
+    if copied_to == 0 {
+        return false;
+    }
+    renamed.push_str(&data[copied_to..]);
+    *data = Cow::Owned(renamed);
+    true
+}
+
+// Replaces the class name `from` with `to` in a string that holds a class name, which
+// per section 4.4.1 of the JVM spec may also be an array descriptor. Returns true if the
+// name was replaced.
+pub(crate) fn rename_class_name(name: &mut Cow<str>, from: &str, to: &str) -> bool {
+    if name == from {
+        *name = Cow::Owned(to.to_string());
+        true
+    } else if name.starts_with('[') {
+        rename_in_descriptor(name, from, to)
+    } else {
+        false
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -272,6 +384,18 @@ mod tests {
         assert!(!is_field_descriptor("Istuff"));
     }
 
+    #[test]
+    fn test_field_descriptor_display() {
+        for descriptor in ["I", "Z", "[[J", "Ljava/lang/Object;", "[La;"] {
+            assert_eq!(
+                parse_field_descriptor(&Cow::Borrowed(descriptor), 0)
+                    .unwrap()
+                    .to_string(),
+                descriptor
+            );
+        }
+    }
+
     #[test]
     fn test_method_descriptors() {
         assert!(is_method_descriptor("()V"));
@@ -290,6 +414,18 @@ mod tests {
         assert!(!is_method_descriptor("()ILjava/lang/Object;"));
     }
 
+    #[test]
+    fn test_method_descriptor_display() {
+        for descriptor in ["()V", "(IJ)Z", "([Ljava/lang/String;)V", "(La/B;[[D)La/C;"] {
+            assert_eq!(
+                parse_method_descriptor(&Cow::Borrowed(descriptor), 0)
+                    .unwrap()
+                    .to_string(),
+                descriptor
+            );
+        }
+    }
+
     #[test]
     fn test_void_void() {
         let chars = Cow::from("()V");
@@ -613,4 +749,36 @@ mod tests {
         assert!(parse_method_descriptor(&chars_ok, 0).is_ok());
         assert!(parse_method_descriptor(&chars_bad, 0).is_err());
     }
+
+    #[test]
+    fn test_rename_in_descriptor() {
+        let mut data = Cow::Borrowed("(Lcom/old/Name;[Lcom/old/Name;)V");
+        assert!(rename_in_descriptor(
+            &mut data,
+            "com/old/Name",
+            "com/new/Other"
+        ));
+        assert_eq!(data, "(Lcom/new/Other;[Lcom/new/Other;)V");
+
+        let mut data = Cow::Borrowed("Lcom/old/NameX;");
+        assert!(!rename_in_descriptor(
+            &mut data,
+            "com/old/Name",
+            "com/new/Other"
+        ));
+        assert_eq!(data, "Lcom/old/NameX;");
+
+        let mut data = Cow::Borrowed("Ljava/util/List<Lcom/old/Name<TT;>.Inner;>;");
+        assert!(rename_in_descriptor(&mut data, "com/old/Name", "x/N"));
+        assert_eq!(data, "Ljava/util/List<Lx/N<TT;>.Inner;>;");
+
+        let mut name = Cow::Borrowed("com/old/Name");
+        assert!(rename_class_name(&mut name, "com/old/Name", "x/N"));
+        assert_eq!(name, "x/N");
+        let mut name = Cow::Borrowed("[[Lcom/old/Name;");
+        assert!(rename_class_name(&mut name, "com/old/Name", "x/N"));
+        assert_eq!(name, "[[Lx/N;");
+        let mut name = Cow::Borrowed("com/old/Name$Inner");
+        assert!(!rename_class_name(&mut name, "com/old/Name", "x/N"));
+    }
 }
@@ -0,0 +1,145 @@
+//! Comparison of two parsed class files, as returned by
+//! [`ClassFile::diff`](crate::ClassFile::diff).
+
+use std::collections::HashMap;
+
+use crate::{ClassAccessFlags, ClassFile, FieldAccessFlags, MethodAccessFlags};
+
+/// A field or method identified by its name and descriptor.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct MemberKey {
+    pub name: String,
+    pub descriptor: String,
+}
+
+/// A field or method present in both classes whose access flags differ.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MemberChange<F> {
+    pub member: MemberKey,
+    pub old_access_flags: F,
+    pub new_access_flags: F,
+}
+
+/// The differences between two versions of a class. Members are matched by name and
+/// descriptor and classes are compared by name, so the diff is unaffected by the order
+/// of their constant pools. Added and changed members are listed in the order they
+/// appear in the new class, and removed members in the order they appear in the old one.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ClassDiff {
+    /// The old and new access flags of the class, if they differ.
+    pub access_flags: Option<(ClassAccessFlags, ClassAccessFlags)>,
+    /// The old and new superclass names, if they differ.
+    pub super_class: Option<(Option<String>, Option<String>)>,
+    pub added_interfaces: Vec<String>,
+    pub removed_interfaces: Vec<String>,
+    pub added_fields: Vec<MemberKey>,
+    pub removed_fields: Vec<MemberKey>,
+    pub changed_fields: Vec<MemberChange<FieldAccessFlags>>,
+    pub added_methods: Vec<MemberKey>,
+    pub removed_methods: Vec<MemberKey>,
+    pub changed_methods: Vec<MemberChange<MethodAccessFlags>>,
+}
+
+impl ClassDiff {
+    /// Returns true if no differences were found.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        *self == ClassDiff::default()
+    }
+}
+
+// Compares two lists of members, each given as its key and access flags.
+fn diff_members<F: Copy + PartialEq>(
+    old: Vec<(MemberKey, F)>,
+    new: Vec<(MemberKey, F)>,
+) -> (Vec<MemberKey>, Vec<MemberKey>, Vec<MemberChange<F>>) {
+    let old_flags: HashMap<&MemberKey, F> = old.iter().map(|(key, flags)| (key, *flags)).collect();
+    let new_flags: HashMap<&MemberKey, F> = new.iter().map(|(key, flags)| (key, *flags)).collect();
+    let mut added = Vec::new();
+    let mut changed = Vec::new();
+    for (key, flags) in &new {
+        match old_flags.get(key) {
+            None => added.push(key.clone()),
+            Some(old) if old != flags => changed.push(MemberChange {
+                member: key.clone(),
+                old_access_flags: *old,
+                new_access_flags: *flags,
+            }),
+            Some(_) => (),
+        }
+    }
+    let removed = old
+        .iter()
+        .filter(|(key, _)| !new_flags.contains_key(key))
+        .map(|(key, _)| key.clone())
+        .collect();
+    (added, removed, changed)
+}
+
+impl<'a> ClassFile<'a> {
+    /// Compares this class with another version of it, reporting the fields, methods
+    /// and interfaces that were added or removed, and changes to access flags and the
+    /// superclass. This class is treated as the old version and `other` as the new one.
+    #[must_use]
+    pub fn diff(&self, other: &ClassFile) -> ClassDiff {
+        let mut diff = ClassDiff::default();
+        if self.access_flags != other.access_flags {
+            diff.access_flags = Some((self.access_flags, other.access_flags));
+        }
+        if self.super_class != other.super_class {
+            diff.super_class = Some((
+                self.super_class.as_ref().map(|name| name.to_string()),
+                other.super_class.as_ref().map(|name| name.to_string()),
+            ));
+        }
+        diff.added_interfaces = other
+            .interfaces
+            .iter()
+            .filter(|name| !self.interfaces.contains(name))
+            .map(|name| name.to_string())
+            .collect();
+        diff.removed_interfaces = self
+            .interfaces
+            .iter()
+            .filter(|name| !other.interfaces.contains(name))
+            .map(|name| name.to_string())
+            .collect();
+
+        let fields = |class: &ClassFile| {
+            class
+                .fields
+                .iter()
+                .map(|field| {
+                    let key = MemberKey {
+                        name: field.name.to_string(),
+                        descriptor: field.descriptor.to_string(),
+                    };
+                    (key, field.access_flags)
+                })
+                .collect()
+        };
+        let (added, removed, changed) = diff_members(fields(self), fields(other));
+        diff.added_fields = added;
+        diff.removed_fields = removed;
+        diff.changed_fields = changed;
+
+        let methods = |class: &ClassFile| {
+            class
+                .methods
+                .iter()
+                .map(|method| {
+                    let key = MemberKey {
+                        name: method.name.to_string(),
+                        descriptor: method.descriptor.to_string(),
+                    };
+                    (key, method.access_flags)
+                })
+                .collect()
+        };
+        let (added, removed, changed) = diff_members(methods(self), methods(other));
+        diff.added_methods = added;
+        diff.removed_methods = removed;
+        diff.changed_methods = changed;
+        diff
+    }
+}
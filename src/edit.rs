@@ -0,0 +1,113 @@
+// Modification of a parsed class file, such as adding methods for simple instrumentation.
+
+use std::borrow::Cow;
+
+use crate::attributes::{AttributeData, AttributeInfo, CodeData};
+use crate::constant_pool::ConstantPoolBuilder;
+use crate::descriptors::{parse_method_descriptor, ReturnDescriptor};
+use crate::names::is_unqualified_method_name;
+use crate::{
+    validate_method_access_flags, ClassAccessFlags, ClassFile, FieldInfo, MethodAccessFlags,
+    MethodInfo, ParseError,
+};
+
+// The largest methods_count the class file format can represent.
+const MAX_METHODS_COUNT: usize = 65535;
+
+impl<'a> ClassFile<'a> {
+    /// Adds constants to the constant pool of this class. The given function is passed a
+    /// builder holding the existing constant pool, and any constants it interns are
+    /// appended to the pool once it returns successfully. Existing constants keep their
+    /// indices, so the indices the builder returns can be used in bytecode for this class
+    /// (e.g. the code passed to [`ClassFile::add_method`]).
+    pub fn intern_constants<T>(
+        &mut self,
+        f: impl FnOnce(&mut ConstantPoolBuilder<'a>) -> Result<T, ParseError>,
+    ) -> Result<T, ParseError> {
+        let mut builder = ConstantPoolBuilder::from_constant_pool(&self.constant_pool)?;
+        let result = f(&mut builder)?;
+        builder.extend_constant_pool(&mut self.constant_pool, self.major_version)?;
+        Ok(result)
+    }
+
+    /// Appends a method with the given code to this class, interning its name, descriptor
+    /// and the Code attribute name into the constant pool. Any constants referenced by the
+    /// code itself must already be in the pool; use [`ClassFile::intern_constants`] to add
+    /// them and obtain their indices before building the code. The code is written as-is
+    /// by [`ClassFile::to_bytes`], so the caller is responsible for its max_stack,
+    /// max_locals and any StackMapTable it needs to pass verification.
+    pub fn add_method(
+        &mut self,
+        access_flags: MethodAccessFlags,
+        name: impl Into<Cow<'a, str>>,
+        descriptor: impl Into<Cow<'a, str>>,
+        code: CodeData<'a>,
+    ) -> Result<&MethodInfo<'a>, ParseError> {
+        let name = name.into();
+        let descriptor = descriptor.into();
+        let in_interface = self.access_flags.contains(ClassAccessFlags::INTERFACE);
+        if !is_unqualified_method_name(&name, !in_interface, true) {
+            fail!("Invalid unqualified name {} for added method", name);
+        }
+        validate_method_access_flags(access_flags, &name, in_interface, self.major_version)
+            .map_err(|e| err!(e, "added method {}", name))?;
+        if access_flags.intersects(MethodAccessFlags::ABSTRACT | MethodAccessFlags::NATIVE) {
+            fail!(
+                "Invalid access flags {:?} for added method {}; abstract and native methods cannot have code",
+                access_flags,
+                name
+            );
+        }
+        let parsed_descriptor = parse_method_descriptor(&descriptor, 0)
+            .map_err(|e| err!(e, "descriptor of added method {}", name))?;
+        if (name == "<init>" || name == "<clinit>")
+            && parsed_descriptor.return_type != ReturnDescriptor::Void
+        {
+            fail!("Non-void method descriptor for added method {}", name);
+        }
+        if self.find_method(&name, &descriptor).is_some() {
+            fail!(
+                "Added method {}{} is duplicate of an existing method",
+                name,
+                descriptor
+            );
+        }
+        if self.methods.len() >= MAX_METHODS_COUNT {
+            fail!(
+                "Too many methods to add {}; the maximum is {}",
+                name,
+                MAX_METHODS_COUNT
+            );
+        }
+        self.intern_constants(|pool| {
+            pool.utf8(name.clone())?;
+            pool.utf8(descriptor.clone())?;
+            pool.utf8("Code")
+        })?;
+        self.methods.push(MethodInfo {
+            access_flags,
+            name,
+            descriptor: parsed_descriptor,
+            attributes: vec![AttributeInfo {
+                name: Cow::Borrowed("Code"),
+                data: AttributeData::Code(code),
+            }],
+            max_nesting_depth: self.max_nesting_depth,
+        });
+        Ok(&self.methods[self.methods.len() - 1])
+    }
+
+    /// Removes the fields for which the given function returns false. Constant pool entries
+    /// that were only used by the removed fields are left in the pool; they are harmless,
+    /// but still take up space when the class is written out.
+    pub fn retain_fields(&mut self, f: impl FnMut(&FieldInfo<'a>) -> bool) {
+        self.fields.retain(f);
+    }
+
+    /// Removes the methods for which the given function returns false. As with
+    /// [`ClassFile::retain_fields`], constant pool entries that were only used by the
+    /// removed methods are left in the pool.
+    pub fn retain_methods(&mut self, f: impl FnMut(&MethodInfo<'a>) -> bool) {
+        self.methods.retain(f);
+    }
+}
@@ -28,6 +28,9 @@ macro_rules! fail {
     (($msg:literal), ($context:literal)) => {
         return Err(ParseError::with_context(ParseError::new($msg.to_string()), $context.to_string()))
     };
+    (($msg:literal), ($contextfmt:literal, $($contextargs:tt)*)) => {
+        return Err(ParseError::with_context(ParseError::new($msg.to_string()), format!($contextfmt, $($contextargs)*)))
+    };
     ($fmtstr:literal, $($args:tt)*) => {
         return Err(ParseError::new(format!($fmtstr, $($args)*)))
     };
@@ -39,10 +42,27 @@ macro_rules! fail {
     };
 }
 
+/// The details of an error caused by a length field in the input declaring more bytes
+/// than actually remain in it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct UnexpectedEof {
+    /// The number of bytes the length field declared.
+    pub needed: usize,
+    /// The number of bytes that remained in the input.
+    pub available: usize,
+}
+
 #[derive(Debug, PartialEq, Eq)]
 pub struct ParseError {
     msg: String,
     contexts: Vec<String>,
+    constant_pool_path: Vec<u16>,
+    unexpected_eof: Option<UnexpectedEof>,
+    // Whether a value of fixed size (e.g. a u2) ran past the end of the input. Errors with
+    // unexpected_eof set are caused by the end of the input as well.
+    end_of_stream: bool,
+    class_version: Option<(u16, u16)>,
+    magic: Option<u32>,
 }
 
 impl ParseError {
@@ -50,6 +70,11 @@ impl ParseError {
         ParseError {
             msg,
             contexts: Vec::new(),
+            constant_pool_path: Vec::new(),
+            unexpected_eof: None,
+            end_of_stream: false,
+            class_version: None,
+            magic: None,
         }
     }
 
@@ -59,8 +84,80 @@ impl ParseError {
         ParseError {
             msg: base.msg,
             contexts,
+            constant_pool_path: base.constant_pool_path,
+            unexpected_eof: base.unexpected_eof,
+            end_of_stream: base.end_of_stream,
+            class_version: base.class_version,
+            magic: base.magic,
         }
     }
+
+    // Records that the error was hit while following a reference from the constant pool
+    // entry at the given index. As the error propagates outwards, each referring entry is
+    // prepended, so the path reads from the outermost entry to the failing one.
+    pub(crate) fn with_constant_pool_index(mut self, index: u16) -> Self {
+        self.constant_pool_path.insert(0, index);
+        self
+    }
+
+    pub(crate) fn with_unexpected_eof(mut self, needed: usize, available: usize) -> Self {
+        self.unexpected_eof = Some(UnexpectedEof { needed, available });
+        self
+    }
+
+    pub(crate) fn at_end_of_stream(mut self) -> Self {
+        self.end_of_stream = true;
+        self
+    }
+
+    // Returns true if the error was caused by the input ending early, so that it might
+    // parse given more of the input.
+    pub(crate) fn is_end_of_stream(&self) -> bool {
+        self.end_of_stream || self.unexpected_eof.is_some()
+    }
+
+    pub(crate) fn with_class_version(mut self, major_version: u16, minor_version: u16) -> Self {
+        self.class_version = Some((major_version, minor_version));
+        self
+    }
+
+    pub(crate) fn with_magic(mut self, magic: u32) -> Self {
+        self.magic = Some(magic);
+        self
+    }
+
+    /// Returns the chain of constant pool indices that were being followed when this error
+    /// occurred, starting from the referring entry; e.g. `[10, 99]` if entry 10 references
+    /// the out-of-bounds index 99. This is empty if the error did not occur while resolving
+    /// a constant pool reference.
+    #[must_use]
+    pub fn constant_pool_path(&self) -> &[u16] {
+        &self.constant_pool_path
+    }
+
+    /// Returns the declared and remaining byte counts if this error was caused by a length
+    /// field (e.g. of a CONSTANT_Utf8 entry or an attribute) that runs past the end of the
+    /// input, or None for any other error.
+    #[must_use]
+    pub fn unexpected_eof(&self) -> Option<UnexpectedEof> {
+        self.unexpected_eof
+    }
+
+    /// Returns the major and minor version of the class file that failed to parse, or
+    /// None if the error occurred before they could be read (e.g. because the magic
+    /// number was wrong). This helps to tell which compiler produced a broken class file.
+    #[must_use]
+    pub fn class_version(&self) -> Option<(u16, u16)> {
+        self.class_version
+    }
+
+    /// Returns the magic number found at the start of the input if it was not 0xCAFEBABE,
+    /// i.e. if the input is not a class file, or None for any other error. This can help
+    /// to recognize what the input is instead, e.g. 0x504B0304 for a zip archive.
+    #[must_use]
+    pub fn magic(&self) -> Option<u32> {
+        self.magic
+    }
 }
 
 impl fmt::Display for ParseError {
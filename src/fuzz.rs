@@ -0,0 +1,112 @@
+//! Utilities for fuzzing the parser. Parsing arbitrary bytes is expected to fail with a
+//! [`ParseError`](crate::ParseError) most of the time, but it must never panic; these
+//! functions check that property and help reduce an input that violates it.
+
+use std::panic;
+
+use crate::{parse_class, parse_class_with_options, parse_header_only, ClassFile, ParseOptions};
+
+/// Returns true if parsing the given bytes completes without panicking, whether or not
+/// they are a valid class file. The bytes are parsed with the default options as well as
+/// with each of the parse options enabled, and if parsing succeeds, the resulting class
+/// is also walked (e.g. its constant pool iterated, and the class renamed, copied and
+/// written back out) so that panics in the accessors are caught too. This is the body of the fuzz target in
+/// the `fuzz` directory.
+#[must_use]
+pub fn parse_is_safe(bytes: &[u8]) -> bool {
+    let walk = |class: ClassFile| {
+        let _ = class.constantpool_iter().count();
+        let _ = class.referenced_classes();
+        let _ = class.to_bytes();
+        let _ = class.javap_dump();
+        let mut renamed = class.clone();
+        let _ = renamed.rename_class(&class.this_class, "fuzz/Renamed");
+        let _ = class.into_owned();
+    };
+    panic::catch_unwind(|| {
+        let _ = parse_header_only(bytes);
+        if let Ok(class) = parse_class(bytes) {
+            walk(class);
+        }
+        let _ =
+            parse_class_with_options(bytes, ParseOptions::default().skip_attribute_bodies(true));
+        if let Ok(class) =
+            parse_class_with_options(bytes, ParseOptions::default().defer_resolution(true))
+        {
+            walk(class);
+        }
+        let mut opts = ParseOptions::default();
+        opts.parse_bytecode(true)
+            .allow_trailing_bytes(true)
+            .strict(true);
+        if let Ok(class) = parse_class_with_options(bytes, &opts) {
+            walk(class);
+        }
+    })
+    .is_ok()
+}
+
+/// Shrinks an input for which [`parse_is_safe`] returns false, by repeatedly removing
+/// chunks of bytes for as long as the result still panics. Returns None if the input
+/// does not panic in the first place. The result is usually much smaller than the input,
+/// which makes the panic easier to debug, but it is not guaranteed to be minimal.
+#[must_use]
+pub fn shrink_unsafe_input(bytes: &[u8]) -> Option<Vec<u8>> {
+    if parse_is_safe(bytes) {
+        return None;
+    }
+    let mut input = bytes.to_vec();
+    let mut chunk = input.len() / 2;
+    while chunk > 0 {
+        let mut start = 0;
+        while start < input.len() {
+            let end = (start + chunk).min(input.len());
+            let mut candidate = input[..start].to_vec();
+            candidate.extend_from_slice(&input[end..]);
+            if parse_is_safe(&candidate) {
+                start = end;
+            } else {
+                input = candidate;
+            }
+        }
+        chunk /= 2;
+    }
+    Some(input)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_truncated_class_is_safe() {
+        let bytes = std::fs::read("tests/iterator/Object.class").unwrap();
+        for len in 0..=bytes.len() {
+            assert!(parse_is_safe(&bytes[..len]), "length {}", len);
+        }
+        assert_eq!(shrink_unsafe_input(&bytes), None);
+    }
+
+    // A class named A that extends java/lang/Object, with the given entries appended to
+    // its constant pool. These are only rejected when resolution is not deferred.
+    fn class_with_constants(count: u16, constants: &[u8]) -> Vec<u8> {
+        let mut bytes = vec![0xCA, 0xFE, 0xBA, 0xBE, 0, 0, 0, 52];
+        bytes.extend_from_slice(&(5 + count).to_be_bytes());
+        bytes.extend_from_slice(&[1, 0, 1, b'A', 7, 0, 1, 1, 0, 16]);
+        bytes.extend_from_slice(b"java/lang/Object");
+        bytes.extend_from_slice(&[7, 0, 3]);
+        bytes.extend_from_slice(constants);
+        bytes.extend_from_slice(&[0, 0x21, 0, 2, 0, 4, 0, 0, 0, 0, 0, 0, 0, 0]);
+        bytes
+    }
+
+    #[test]
+    fn test_deferred_bad_references_are_safe() {
+        // A ClassInfo referring to the out-of-bounds index 99
+        let out_of_bounds = class_with_constants(1, &[7, 0, 99]);
+        assert!(parse_is_safe(&out_of_bounds));
+        // A ClassInfo and a String referring to each other
+        let cyclic = class_with_constants(2, &[7, 0, 6, 8, 0, 5]);
+        assert!(parse_is_safe(&cyclic));
+    }
+}
@@ -0,0 +1,1053 @@
+// A human-readable dump of a parsed class file in the style of `javap -v`.
+
+use std::collections::HashMap;
+use std::fmt::{self, Write};
+
+use crate::attributes::{
+    Annotation, AnnotationElementValue, AttributeData, AttributeInfo, CodeData, StackMapEntry,
+    VerificationType,
+};
+use crate::bytecode::{ByteCode, JumpOffset, Opcode, PrimitiveArrayType};
+use crate::constant_pool::{BootstrapArgument, ConstantPoolBuilder, LiteralConstant};
+use crate::descriptors::{FieldDescriptor, FieldType, MethodDescriptor, ReturnDescriptor};
+use crate::{ClassFile, FieldInfo, MethodInfo};
+
+const CLASS_FLAGS: &[(u16, &str)] = &[
+    (0x0001, "ACC_PUBLIC"),
+    (0x0010, "ACC_FINAL"),
+    (0x0020, "ACC_SUPER"),
+    (0x0200, "ACC_INTERFACE"),
+    (0x0400, "ACC_ABSTRACT"),
+    (0x1000, "ACC_SYNTHETIC"),
+    (0x2000, "ACC_ANNOTATION"),
+    (0x4000, "ACC_ENUM"),
+    (0x8000, "ACC_MODULE"),
+];
+
+const FIELD_FLAGS: &[(u16, &str)] = &[
+    (0x0001, "ACC_PUBLIC"),
+    (0x0002, "ACC_PRIVATE"),
+    (0x0004, "ACC_PROTECTED"),
+    (0x0008, "ACC_STATIC"),
+    (0x0010, "ACC_FINAL"),
+    (0x0040, "ACC_VOLATILE"),
+    (0x0080, "ACC_TRANSIENT"),
+    (0x1000, "ACC_SYNTHETIC"),
+    (0x4000, "ACC_ENUM"),
+];
+
+const METHOD_FLAGS: &[(u16, &str)] = &[
+    (0x0001, "ACC_PUBLIC"),
+    (0x0002, "ACC_PRIVATE"),
+    (0x0004, "ACC_PROTECTED"),
+    (0x0008, "ACC_STATIC"),
+    (0x0010, "ACC_FINAL"),
+    (0x0020, "ACC_SYNCHRONIZED"),
+    (0x0040, "ACC_BRIDGE"),
+    (0x0080, "ACC_VARARGS"),
+    (0x0100, "ACC_NATIVE"),
+    (0x0400, "ACC_ABSTRACT"),
+    (0x0800, "ACC_STRICT"),
+    (0x1000, "ACC_SYNTHETIC"),
+];
+
+fn flag_names(bits: u16, flags: &[(u16, &str)]) -> String {
+    let names: Vec<&str> = flags
+        .iter()
+        .filter(|(flag, _)| bits & flag != 0)
+        .map(|(_, name)| *name)
+        .collect();
+    format!("(0x{:04x}) {}", bits, names.join(", "))
+}
+
+fn modifiers(bits: u16, keywords: &[(u16, &str)]) -> String {
+    keywords
+        .iter()
+        .filter(|(flag, _)| bits & flag != 0)
+        .map(|(_, keyword)| format!("{} ", keyword))
+        .collect()
+}
+
+const MEMBER_KEYWORDS: &[(u16, &str)] = &[
+    (0x0001, "public"),
+    (0x0002, "private"),
+    (0x0004, "protected"),
+    (0x0008, "static"),
+    (0x0010, "final"),
+];
+
+const FIELD_KEYWORDS: &[(u16, &str)] = &[(0x0040, "volatile"), (0x0080, "transient")];
+
+const METHOD_KEYWORDS: &[(u16, &str)] = &[
+    (0x0020, "synchronized"),
+    (0x0100, "native"),
+    (0x0400, "abstract"),
+    (0x0800, "strictfp"),
+];
+
+fn java_name(name: &str) -> String {
+    name.replace('/', ".")
+}
+
+fn java_type(descriptor: &FieldDescriptor) -> String {
+    let mut name = match &descriptor.field_type {
+        FieldType::Byte => String::from("byte"),
+        FieldType::Char => String::from("char"),
+        FieldType::Double => String::from("double"),
+        FieldType::Float => String::from("float"),
+        FieldType::Integer => String::from("int"),
+        FieldType::Long => String::from("long"),
+        FieldType::Short => String::from("short"),
+        FieldType::Boolean => String::from("boolean"),
+        FieldType::Object(class_name) => java_name(&class_name.to_string()),
+    };
+    for _ in 0..descriptor.dimensions {
+        name.push_str("[]");
+    }
+    name
+}
+
+fn java_return_type(descriptor: &ReturnDescriptor) -> String {
+    match descriptor {
+        ReturnDescriptor::Return(descriptor) => java_type(descriptor),
+        ReturnDescriptor::Void => String::from("void"),
+    }
+}
+
+fn args_size(method: &MethodInfo) -> usize {
+    let receiver = if method.access_flags.bits() & 0x0008 == 0 {
+        1
+    } else {
+        0
+    };
+    receiver
+        + method
+            .descriptor
+            .parameters
+            .iter()
+            .map(|p| match p.field_type {
+                FieldType::Double | FieldType::Long if p.dimensions == 0 => 2,
+                _ => 1,
+            })
+            .sum::<usize>()
+}
+
+fn mnemonic(opcode: u8) -> &'static str {
+    #[rustfmt::skip]
+    const MNEMONICS: [&str; 202] = [
+        "nop", "aconst_null", "iconst_m1", "iconst_0", "iconst_1", "iconst_2", "iconst_3",
+        "iconst_4", "iconst_5", "lconst_0", "lconst_1", "fconst_0", "fconst_1", "fconst_2",
+        "dconst_0", "dconst_1", "bipush", "sipush", "ldc", "ldc_w", "ldc2_w", "iload", "lload",
+        "fload", "dload", "aload", "iload_0", "iload_1", "iload_2", "iload_3", "lload_0",
+        "lload_1", "lload_2", "lload_3", "fload_0", "fload_1", "fload_2", "fload_3", "dload_0",
+        "dload_1", "dload_2", "dload_3", "aload_0", "aload_1", "aload_2", "aload_3", "iaload",
+        "laload", "faload", "daload", "aaload", "baload", "caload", "saload", "istore", "lstore",
+        "fstore", "dstore", "astore", "istore_0", "istore_1", "istore_2", "istore_3", "lstore_0",
+        "lstore_1", "lstore_2", "lstore_3", "fstore_0", "fstore_1", "fstore_2", "fstore_3",
+        "dstore_0", "dstore_1", "dstore_2", "dstore_3", "astore_0", "astore_1", "astore_2",
+        "astore_3", "iastore", "lastore", "fastore", "dastore", "aastore", "bastore", "castore",
+        "sastore", "pop", "pop2", "dup", "dup_x1", "dup_x2", "dup2", "dup2_x1", "dup2_x2", "swap",
+        "iadd", "ladd", "fadd", "dadd", "isub", "lsub", "fsub", "dsub", "imul", "lmul", "fmul",
+        "dmul", "idiv", "ldiv", "fdiv", "ddiv", "irem", "lrem", "frem", "drem", "ineg", "lneg",
+        "fneg", "dneg", "ishl", "lshl", "ishr", "lshr", "iushr", "lushr", "iand", "land", "ior",
+        "lor", "ixor", "lxor", "iinc", "i2l", "i2f", "i2d", "l2i", "l2f", "l2d", "f2i", "f2l",
+        "f2d", "d2i", "d2l", "d2f", "i2b", "i2c", "i2s", "lcmp", "fcmpl", "fcmpg", "dcmpl",
+        "dcmpg", "ifeq", "ifne", "iflt", "ifge", "ifgt", "ifle", "if_icmpeq", "if_icmpne",
+        "if_icmplt", "if_icmpge", "if_icmpgt", "if_icmple", "if_acmpeq", "if_acmpne", "goto",
+        "jsr", "ret", "tableswitch", "lookupswitch", "ireturn", "lreturn", "freturn", "dreturn",
+        "areturn", "return", "getstatic", "putstatic", "getfield", "putfield", "invokevirtual",
+        "invokespecial", "invokestatic", "invokeinterface", "invokedynamic", "new", "newarray",
+        "anewarray", "arraylength", "athrow", "checkcast", "instanceof", "monitorenter",
+        "monitorexit", "wide", "multianewarray", "ifnull", "ifnonnull", "goto_w", "jsr_w",
+    ];
+    match opcode {
+        0xca => "breakpoint",
+        0xfe => "impdep1",
+        0xff => "impdep2",
+        n => MNEMONICS.get(n as usize).copied().unwrap_or("unknown"),
+    }
+}
+
+fn jump_offset(opcode: &Opcode) -> Option<JumpOffset> {
+    match opcode {
+        Opcode::Goto(jump)
+        | Opcode::Jsr(jump)
+        | Opcode::IfAcmpeq(jump)
+        | Opcode::IfAcmpne(jump)
+        | Opcode::IfIcmpeq(jump)
+        | Opcode::IfIcmpge(jump)
+        | Opcode::IfIcmpgt(jump)
+        | Opcode::IfIcmple(jump)
+        | Opcode::IfIcmplt(jump)
+        | Opcode::IfIcmpne(jump)
+        | Opcode::Ifeq(jump)
+        | Opcode::Ifge(jump)
+        | Opcode::Ifgt(jump)
+        | Opcode::Ifle(jump)
+        | Opcode::Iflt(jump)
+        | Opcode::Ifne(jump)
+        | Opcode::Ifnonnull(jump)
+        | Opcode::Ifnull(jump) => Some(*jump),
+        _ => None,
+    }
+}
+
+fn local_variable(opcode: &Opcode) -> Option<u16> {
+    match opcode {
+        Opcode::Aload(index)
+        | Opcode::Astore(index)
+        | Opcode::Dload(index)
+        | Opcode::Dstore(index)
+        | Opcode::Fload(index)
+        | Opcode::Fstore(index)
+        | Opcode::Iload(index)
+        | Opcode::Istore(index)
+        | Opcode::Lload(index)
+        | Opcode::Lstore(index)
+        | Opcode::Ret(index) => Some(*index),
+        _ => None,
+    }
+}
+
+fn primitive_array_type(array_type: PrimitiveArrayType) -> &'static str {
+    match array_type {
+        PrimitiveArrayType::Boolean => "boolean",
+        PrimitiveArrayType::Char => "char",
+        PrimitiveArrayType::Float => "float",
+        PrimitiveArrayType::Double => "double",
+        PrimitiveArrayType::Byte => "byte",
+        PrimitiveArrayType::Short => "short",
+        PrimitiveArrayType::Int => "int",
+        PrimitiveArrayType::Long => "long",
+    }
+}
+
+fn literal(value: &LiteralConstant) -> String {
+    match value {
+        LiteralConstant::Integer(v) => format!("int {}", v),
+        LiteralConstant::Float(v) => format!("float {:?}f", v),
+        LiteralConstant::Long(v) => format!("long {}l", v),
+        LiteralConstant::Double(v) => format!("double {:?}d", v),
+        LiteralConstant::String(v) => format!("String {}", v.escape_debug()),
+        LiteralConstant::StringBytes(v) => {
+            format!("String {}", String::from_utf8_lossy(v).escape_debug())
+        }
+    }
+}
+
+fn verification_type(verification_type: &VerificationType) -> String {
+    match verification_type {
+        VerificationType::Top => String::from("top"),
+        VerificationType::Integer => String::from("int"),
+        VerificationType::Float => String::from("float"),
+        VerificationType::Double => String::from("double"),
+        VerificationType::Long => String::from("long"),
+        VerificationType::Null => String::from("null"),
+        VerificationType::UninitializedThis => String::from("this"),
+        VerificationType::Object { class_name } => format!("class {}", class_name),
+        VerificationType::Uninitialized { code_offset } => {
+            format!("uninitialized {}", code_offset)
+        }
+    }
+}
+
+fn verification_types(types: &[VerificationType]) -> String {
+    let types: Vec<String> = types.iter().map(verification_type).collect();
+    format!("[ {} ]", types.join(", "))
+}
+
+fn element_value(value: &AnnotationElementValue) -> String {
+    match value {
+        AnnotationElementValue::ByteConstant(v)
+        | AnnotationElementValue::CharConstant(v)
+        | AnnotationElementValue::IntConstant(v)
+        | AnnotationElementValue::ShortConstant(v)
+        | AnnotationElementValue::BooleanConstant(v) => v.to_string(),
+        AnnotationElementValue::DoubleConstant(v) => format!("{:?}d", v),
+        AnnotationElementValue::FloatConstant(v) => format!("{:?}f", v),
+        AnnotationElementValue::LongConstant(v) => format!("{}l", v),
+        AnnotationElementValue::StringConstant(v) => format!("\"{}\"", v.escape_debug()),
+        AnnotationElementValue::EnumConstant {
+            type_name,
+            const_name,
+        } => format!("{}.{}", java_type(type_name), const_name),
+        AnnotationElementValue::ClassLiteral { class_name } => format!("class {}", class_name),
+        AnnotationElementValue::AnnotationValue(annotation) => self::annotation(annotation),
+        AnnotationElementValue::ArrayValue(values) => {
+            let values: Vec<String> = values.iter().map(element_value).collect();
+            format!("[{}]", values.join(","))
+        }
+    }
+}
+
+fn annotation(annotation: &Annotation) -> String {
+    let elements: Vec<String> = annotation
+        .elements
+        .iter()
+        .map(|element| format!("{}={}", element.name, element_value(&element.value)))
+        .collect();
+    format!(
+        "{}({})",
+        java_type(&annotation.type_descriptor),
+        elements.join(",")
+    )
+}
+
+struct JavapWriter<'c, 'a> {
+    class: &'c ClassFile<'a>,
+    pool: ConstantPoolBuilder<'a>,
+    descriptions: HashMap<u16, (&'static str, String, String)>,
+}
+
+impl<'c, 'a> JavapWriter<'c, 'a> {
+    // Returns a description of the constant at the given index like the comments javap
+    // adds to instructions, e.g. "Method java/lang/Object.toString:()Ljava/lang/String;".
+    fn constant(&self, index: u16) -> String {
+        match self.descriptions.get(&index) {
+            Some((kind, contents, comment)) => match *kind {
+                "Class" => format!("class {}", comment),
+                "Fieldref" => format!("Field {}", comment),
+                "Methodref" => format!("Method {}", comment),
+                "InterfaceMethodref" => format!("InterfaceMethod {}", comment),
+                "Integer" => format!("int {}", contents),
+                "Float" => format!("float {}", contents),
+                "Long" => format!("long {}", contents),
+                "Double" => format!("double {}", contents),
+                _ => format!("{} {}", kind, comment),
+            },
+            None => String::from("<invalid>"),
+        }
+    }
+
+    fn write_class(&mut self, out: &mut String) -> fmt::Result {
+        let class = self.class;
+        let flags = class.access_flags.bits();
+        let is_interface = flags & 0x0200 != 0;
+        let mut declaration = modifiers(flags & 0x0001, MEMBER_KEYWORDS);
+        if is_interface {
+            declaration.push_str("interface ");
+        } else {
+            declaration.push_str(&modifiers(
+                flags,
+                &[(0x0010, "final"), (0x0400, "abstract")],
+            ));
+            declaration.push_str("class ");
+        }
+        declaration.push_str(&java_name(&class.this_class));
+        if let Some(super_class) = &class.super_class {
+            if !is_interface && super_class != "java/lang/Object" {
+                write!(declaration, " extends {}", java_name(super_class))?;
+            }
+        }
+        if !class.interfaces.is_empty() {
+            let interfaces: Vec<String> = class.interfaces.iter().map(|i| java_name(i)).collect();
+            let keyword = if is_interface {
+                "extends"
+            } else {
+                "implements"
+            };
+            write!(declaration, " {} {}", keyword, interfaces.join(","))?;
+        }
+        writeln!(out, "{}", declaration)?;
+        writeln!(out, "  minor version: {}", class.minor_version)?;
+        writeln!(out, "  major version: {}", class.major_version)?;
+        writeln!(
+            out,
+            "  flags: {}",
+            flag_names(flags | class.undefined_access_flags, CLASS_FLAGS)
+        )?;
+        let this_class = format!(
+            "#{}",
+            self.pool.class(class.this_class.clone()).unwrap_or(0)
+        );
+        writeln!(
+            out,
+            "  this_class: {:<27}// {}",
+            this_class, class.this_class
+        )?;
+        match &class.super_class {
+            Some(super_class) => {
+                let index = format!("#{}", self.pool.class(super_class.clone()).unwrap_or(0));
+                writeln!(out, "  super_class: {:<26}// {}", index, super_class)?;
+            }
+            None => writeln!(out, "  super_class: #0")?,
+        }
+        writeln!(
+            out,
+            "  interfaces: {}, fields: {}, methods: {}, attributes: {}",
+            class.interfaces.len(),
+            class.fields.len(),
+            class.methods.len(),
+            class.attributes.len()
+        )?;
+
+        writeln!(out, "Constant pool:")?;
+        let descriptions = self.pool.describe();
+        let width = descriptions
+            .last()
+            .map_or(0, |(index, ..)| index.to_string().len() + 3);
+        for (index, kind, contents, comment) in &descriptions {
+            let index = format!("#{}", index);
+            if comment.is_empty() {
+                writeln!(
+                    out,
+                    "{:>width$} = {:<18} {}",
+                    index,
+                    kind,
+                    contents,
+                    width = width
+                )?;
+            } else {
+                writeln!(
+                    out,
+                    "{:>width$} = {:<18} {:<14} // {}",
+                    index,
+                    kind,
+                    contents,
+                    comment,
+                    width = width
+                )?;
+            }
+        }
+        self.descriptions = descriptions
+            .into_iter()
+            .map(|(index, kind, contents, comment)| (index, (kind, contents, comment)))
+            .collect();
+
+        writeln!(out, "{{")?;
+        for (i, field) in class.fields.iter().enumerate() {
+            if i > 0 {
+                writeln!(out)?;
+            }
+            self.write_field(out, field)?;
+        }
+        for (i, method) in class.methods.iter().enumerate() {
+            if i > 0 || !class.fields.is_empty() {
+                writeln!(out)?;
+            }
+            self.write_method(out, method)?;
+        }
+        writeln!(out, "}}")?;
+        self.write_attributes(out, &class.attributes, 0)
+    }
+
+    fn write_field(&mut self, out: &mut String, field: &FieldInfo<'a>) -> fmt::Result {
+        let flags = field.access_flags.bits();
+        writeln!(
+            out,
+            "  {}{}{} {};",
+            modifiers(flags, MEMBER_KEYWORDS),
+            modifiers(flags, FIELD_KEYWORDS),
+            java_type(&field.descriptor),
+            field.name
+        )?;
+        writeln!(out, "    descriptor: {}", field.descriptor)?;
+        writeln!(out, "    flags: {}", flag_names(flags, FIELD_FLAGS))?;
+        self.write_attributes(out, &field.attributes, 4)
+    }
+
+    fn write_method(&mut self, out: &mut String, method: &MethodInfo<'a>) -> fmt::Result {
+        let flags = method.access_flags.bits();
+        let descriptor: &MethodDescriptor = &method.descriptor;
+        let parameters: Vec<String> = descriptor.parameters.iter().map(java_type).collect();
+        let mut declaration = String::new();
+        declaration.push_str(&modifiers(flags, MEMBER_KEYWORDS));
+        declaration.push_str(&modifiers(flags, METHOD_KEYWORDS));
+        match method.name.as_ref() {
+            "<clinit>" => declaration.push_str("{}"),
+            "<init>" => write!(
+                declaration,
+                "{}({})",
+                java_name(&self.class.this_class),
+                parameters.join(", ")
+            )?,
+            name => write!(
+                declaration,
+                "{} {}({})",
+                java_return_type(&descriptor.return_type),
+                name,
+                parameters.join(", ")
+            )?,
+        }
+        let exceptions: Vec<String> = method.exceptions().iter().map(|e| java_name(e)).collect();
+        if !exceptions.is_empty() {
+            write!(declaration, " throws {}", exceptions.join(", "))?;
+        }
+        writeln!(out, "  {};", declaration)?;
+        writeln!(out, "    descriptor: {}", descriptor)?;
+        writeln!(out, "    flags: {}", flag_names(flags, METHOD_FLAGS))?;
+        for attribute in &method.attributes {
+            match &attribute.data {
+                AttributeData::Code(code) => self.write_code(out, code, args_size(method))?,
+                _ => self.write_attribute(out, attribute, 4)?,
+            }
+        }
+        Ok(())
+    }
+
+    fn write_code(
+        &mut self,
+        out: &mut String,
+        code: &CodeData<'a>,
+        args_size: usize,
+    ) -> fmt::Result {
+        writeln!(out, "    Code:")?;
+        writeln!(
+            out,
+            "      stack={}, locals={}, args_size={}",
+            code.max_stack, code.max_locals, args_size
+        )?;
+        match &code.bytecode {
+            Some(bytecode) => self.write_bytecode(out, &code.code, bytecode)?,
+            None => writeln!(out, "        <{} bytes of code>", code.code.len())?,
+        }
+        if !code.exception_table.is_empty() {
+            writeln!(out, "      Exception table:")?;
+            writeln!(out, "         from    to  target type")?;
+            for entry in &code.exception_table {
+                let catch_type = match &entry.catch_type {
+                    Some(catch_type) => format!("Class {}", catch_type),
+                    None => String::from("any"),
+                };
+                writeln!(
+                    out,
+                    "         {:>5} {:>5} {:>5}   {}",
+                    entry.start_pc, entry.end_pc, entry.handler_pc, catch_type
+                )?;
+            }
+        }
+        self.write_attributes(out, &code.attributes, 6)
+    }
+
+    fn write_bytecode(
+        &self,
+        out: &mut String,
+        code: &[u8],
+        bytecode: &ByteCode<'a>,
+    ) -> fmt::Result {
+        for (offset, opcode) in &bytecode.opcodes {
+            let raw_opcode = code.get(*offset).copied().unwrap_or(0);
+            let name = mnemonic(raw_opcode);
+            let constant_index = || match raw_opcode {
+                0x12 => code.get(offset + 1).map_or(0, |index| u16::from(*index)),
+                _ => code
+                    .get(offset + 1..offset + 3)
+                    .map_or(0, |index| u16::from_be_bytes([index[0], index[1]])),
+            };
+            let operands = match opcode {
+                Opcode::Ldc(_)
+                | Opcode::LdcW(_)
+                | Opcode::Ldc2W(_)
+                | Opcode::Getfield(_)
+                | Opcode::Getstatic(_)
+                | Opcode::Putfield(_)
+                | Opcode::Putstatic(_)
+                | Opcode::Invokespecial(_)
+                | Opcode::Invokestatic(_)
+                | Opcode::Invokevirtual(_)
+                | Opcode::New(_)
+                | Opcode::Anewarray(_)
+                | Opcode::Checkcast(_)
+                | Opcode::Instanceof(_) => {
+                    let index = constant_index();
+                    format!("{:<20}// {}", format!("#{}", index), self.constant(index))
+                }
+                Opcode::Invokeinterface(_, count) | Opcode::Multianewarray(_, count) => {
+                    let index = constant_index();
+                    format!(
+                        "{:<20}// {}",
+                        format!("#{},  {}", index, count),
+                        self.constant(index)
+                    )
+                }
+                Opcode::Invokedynamic(_) => {
+                    let index = constant_index();
+                    format!(
+                        "{:<20}// {}",
+                        format!("#{},  0", index),
+                        self.constant(index)
+                    )
+                }
+                Opcode::Bipush(value) => value.to_string(),
+                Opcode::Sipush(value) => value.to_string(),
+                Opcode::Iinc(index, value) => format!("{}, {}", index, value),
+                Opcode::Newarray(array_type) => primitive_array_type(*array_type).to_string(),
+                Opcode::Tableswitch(table) => {
+                    let mut operands = format!("{{ // {} to {}\n", table.low, table.high);
+                    for (value, jump) in (table.low..).zip(&table.jumps) {
+                        writeln!(operands, "{:>24}: {}", value, *offset as i64 + *jump as i64)?;
+                    }
+                    writeln!(
+                        operands,
+                        "{:>24}: {}",
+                        "default",
+                        *offset as i64 + table.default as i64
+                    )?;
+                    operands.push_str("            }");
+                    operands
+                }
+                Opcode::Lookupswitch(table) => {
+                    let mut operands = format!("{{ // {}\n", table.match_offsets.len());
+                    for (value, jump) in &table.match_offsets {
+                        writeln!(operands, "{:>24}: {}", value, *offset as i64 + *jump as i64)?;
+                    }
+                    writeln!(
+                        operands,
+                        "{:>24}: {}",
+                        "default",
+                        *offset as i64 + table.default as i64
+                    )?;
+                    operands.push_str("            }");
+                    operands
+                }
+                _ => match (jump_offset(opcode), local_variable(opcode)) {
+                    (Some(jump), _) => (*offset as i64 + jump as i64).to_string(),
+                    // The forms with the index built into the opcode (e.g. aload_0) have no operand
+                    (None, Some(index)) if !name.contains('_') || raw_opcode == 0xc4 => {
+                        index.to_string()
+                    }
+                    _ => String::new(),
+                },
+            };
+            let name = if raw_opcode == 0xc4 {
+                mnemonic(code.get(offset + 1).copied().unwrap_or(0))
+            } else {
+                name
+            };
+            let line = if operands.is_empty() {
+                format!("{:>10}: {}", offset, name)
+            } else if raw_opcode == 0xc4 {
+                format!("{:>10}: wide {:<8} {}", offset, name, operands)
+            } else {
+                format!("{:>10}: {:<13} {}", offset, name, operands)
+            };
+            writeln!(out, "{}", line.trim_end())?;
+        }
+        Ok(())
+    }
+
+    fn write_attributes(
+        &mut self,
+        out: &mut String,
+        attributes: &[AttributeInfo<'a>],
+        indent: usize,
+    ) -> fmt::Result {
+        for attribute in attributes {
+            self.write_attribute(out, attribute, indent)?;
+        }
+        Ok(())
+    }
+
+    fn write_attribute(
+        &mut self,
+        out: &mut String,
+        attribute: &AttributeInfo<'a>,
+        indent: usize,
+    ) -> fmt::Result {
+        let pad = " ".repeat(indent);
+        let name = &attribute.name;
+        match &attribute.data {
+            AttributeData::ConstantValue(value) => {
+                writeln!(out, "{}ConstantValue: {}", pad, literal(value))?
+            }
+            AttributeData::Code(code) => self.write_code(out, code, 0)?,
+            AttributeData::StackMapTable(entries) => {
+                writeln!(
+                    out,
+                    "{}StackMapTable: number_of_entries = {}",
+                    pad,
+                    entries.len()
+                )?;
+                for entry in entries {
+                    self.write_stack_map_entry(out, entry, &pad)?;
+                }
+            }
+            AttributeData::StackMap(frames) => {
+                writeln!(out, "{}StackMap: number_of_entries = {}", pad, frames.len())?;
+                for frame in frames {
+                    writeln!(out, "{}  frame_type = 255 /* full_frame */", pad)?;
+                    writeln!(out, "{}    offset = {}", pad, frame.offset)?;
+                    writeln!(
+                        out,
+                        "{}    locals = {}",
+                        pad,
+                        verification_types(&frame.locals)
+                    )?;
+                    writeln!(
+                        out,
+                        "{}    stack = {}",
+                        pad,
+                        verification_types(&frame.stack)
+                    )?;
+                }
+            }
+            AttributeData::Exceptions(names) => {
+                let names: Vec<String> = names.iter().map(|n| java_name(n)).collect();
+                writeln!(out, "{}Exceptions:", pad)?;
+                writeln!(out, "{}  throws {}", pad, names.join(", "))?;
+            }
+            AttributeData::InnerClasses(entries) => {
+                writeln!(out, "{}InnerClasses:", pad)?;
+                for entry in entries {
+                    let flags = modifiers(entry.access_flags.bits(), MEMBER_KEYWORDS);
+                    let description = match (&entry.inner_name, &entry.outer_class_info) {
+                        (Some(inner_name), Some(outer)) => format!(
+                            "{}=class {} of class {}",
+                            inner_name, entry.inner_class_info, outer
+                        ),
+                        (Some(inner_name), None) => {
+                            format!("{}=class {}", inner_name, entry.inner_class_info)
+                        }
+                        (None, _) => format!("class {}", entry.inner_class_info),
+                    };
+                    writeln!(out, "{}  {}{}", pad, flags, description)?;
+                }
+            }
+            AttributeData::EnclosingMethod { class_name, method } => match method {
+                Some(method) => writeln!(
+                    out,
+                    "{}EnclosingMethod: {}.{}",
+                    pad, class_name, method.name
+                )?,
+                None => writeln!(out, "{}EnclosingMethod: {}", pad, class_name)?,
+            },
+            AttributeData::Synthetic => writeln!(out, "{}Synthetic: true", pad)?,
+            AttributeData::Signature(signature) => {
+                let index = self.pool.utf8(signature.clone()).unwrap_or(0);
+                writeln!(
+                    out,
+                    "{}Signature: {:<26}// {}",
+                    pad,
+                    format!("#{}", index),
+                    signature
+                )?
+            }
+            AttributeData::SourceFile(source_file) => {
+                writeln!(out, "{}SourceFile: \"{}\"", pad, source_file)?
+            }
+            AttributeData::SourceDebugExtension(contents) => {
+                writeln!(out, "{}SourceDebugExtension:", pad)?;
+                for line in contents.lines() {
+                    writeln!(out, "{}  {}", pad, line)?;
+                }
+            }
+            AttributeData::SourceDebugExtensionBytes(bytes) => {
+                writeln!(out, "{}SourceDebugExtension:", pad)?;
+                for line in String::from_utf8_lossy(bytes).lines() {
+                    writeln!(out, "{}  {}", pad, line)?;
+                }
+            }
+            AttributeData::LineNumberTable(entries) => {
+                writeln!(out, "{}LineNumberTable:", pad)?;
+                for entry in entries {
+                    writeln!(
+                        out,
+                        "{}  line {}: {}",
+                        pad, entry.line_number, entry.start_pc
+                    )?;
+                }
+            }
+            AttributeData::LocalVariableTable(entries) => {
+                writeln!(out, "{}LocalVariableTable:", pad)?;
+                writeln!(out, "{}  Start  Length  Slot  Name   Signature", pad)?;
+                for entry in entries {
+                    writeln!(
+                        out,
+                        "{}  {:>5} {:>7} {:>5} {:>5}   {}",
+                        pad,
+                        entry.start_pc,
+                        entry.length,
+                        entry.index,
+                        entry.name,
+                        entry.descriptor
+                    )?;
+                }
+            }
+            AttributeData::LocalVariableTypeTable(entries) => {
+                writeln!(out, "{}LocalVariableTypeTable:", pad)?;
+                writeln!(out, "{}  Start  Length  Slot  Name   Signature", pad)?;
+                for entry in entries {
+                    writeln!(
+                        out,
+                        "{}  {:>5} {:>7} {:>5} {:>5}   {}",
+                        pad, entry.start_pc, entry.length, entry.index, entry.name, entry.signature
+                    )?;
+                }
+            }
+            AttributeData::Deprecated => writeln!(out, "{}Deprecated: true", pad)?,
+            AttributeData::RuntimeVisibleAnnotations(annotations)
+            | AttributeData::RuntimeInvisibleAnnotations(annotations) => {
+                writeln!(out, "{}{}:", pad, name)?;
+                for (i, annotation) in annotations.iter().enumerate() {
+                    writeln!(out, "{}  {}: {}", pad, i, self::annotation(annotation))?;
+                }
+            }
+            AttributeData::RuntimeVisibleParameterAnnotations(parameters)
+            | AttributeData::RuntimeInvisibleParameterAnnotations(parameters) => {
+                writeln!(out, "{}{}:", pad, name)?;
+                for (i, parameter) in parameters.iter().enumerate() {
+                    writeln!(out, "{}  parameter {}:", pad, i)?;
+                    for (j, annotation) in parameter.annotations.iter().enumerate() {
+                        writeln!(out, "{}    {}: {}", pad, j, self::annotation(annotation))?;
+                    }
+                }
+            }
+            AttributeData::RuntimeVisibleTypeAnnotations(annotations)
+            | AttributeData::RuntimeInvisibleTypeAnnotations(annotations) => {
+                writeln!(out, "{}{}:", pad, name)?;
+                for (i, annotation) in annotations.iter().enumerate() {
+                    writeln!(
+                        out,
+                        "{}  {}: {}: {:?}",
+                        pad,
+                        i,
+                        self::annotation(&annotation.annotation),
+                        annotation.target_kind
+                    )?;
+                }
+            }
+            AttributeData::AnnotationDefault(value) => {
+                writeln!(out, "{}AnnotationDefault:", pad)?;
+                writeln!(out, "{}  default_value: {}", pad, element_value(value))?;
+            }
+            AttributeData::BootstrapMethods(entries) => {
+                writeln!(out, "{}BootstrapMethods:", pad)?;
+                for (i, entry) in entries.iter().enumerate() {
+                    let index = self.pool.method_handle(&entry.method).unwrap_or(0);
+                    writeln!(
+                        out,
+                        "{}  {}: #{} {}",
+                        pad,
+                        i,
+                        index,
+                        self.descriptions.get(&index).map_or("", |d| &d.2)
+                    )?;
+                    writeln!(out, "{}    Method arguments:", pad)?;
+                    for argument in &entry.arguments {
+                        let index = self.bootstrap_argument(argument);
+                        let description = match self.descriptions.get(&index) {
+                            Some((_, contents, comment)) if comment.is_empty() => contents,
+                            Some((_, _, comment)) => comment,
+                            None => "<invalid>",
+                        };
+                        writeln!(out, "{}      #{} {}", pad, index, description)?;
+                    }
+                }
+            }
+            AttributeData::MethodParameters(entries) => {
+                writeln!(out, "{}MethodParameters:", pad)?;
+                writeln!(out, "{}  Name                           Flags", pad)?;
+                for entry in entries {
+                    let flags = modifiers(
+                        entry.access_flags.bits(),
+                        &[
+                            (0x0010, "final"),
+                            (0x1000, "synthetic"),
+                            (0x8000, "mandated"),
+                        ],
+                    );
+                    writeln!(
+                        out,
+                        "{}  {:<30} {}",
+                        pad,
+                        entry.name.as_deref().unwrap_or("<no name>"),
+                        flags.trim_end()
+                    )?;
+                }
+            }
+            AttributeData::Module(module) => {
+                writeln!(out, "{}Module:", pad)?;
+                writeln!(
+                    out,
+                    "{}  {} {}",
+                    pad,
+                    module.name,
+                    module.version.as_deref().unwrap_or("")
+                )?;
+                for entry in &module.requires {
+                    writeln!(out, "{}  requires {}", pad, entry.name)?;
+                }
+                for entry in &module.exports {
+                    writeln!(out, "{}  exports {}", pad, entry.package_name)?;
+                }
+                for entry in &module.opens {
+                    writeln!(out, "{}  opens {}", pad, entry.package_name)?;
+                }
+                for name in &module.uses {
+                    writeln!(out, "{}  uses {}", pad, name)?;
+                }
+                for entry in &module.provides {
+                    writeln!(
+                        out,
+                        "{}  provides {} with {}",
+                        pad,
+                        entry.service_interface_name,
+                        entry.provides_with.join(", ")
+                    )?;
+                }
+            }
+            AttributeData::ModulePackages(packages) => {
+                writeln!(out, "{}ModulePackages:", pad)?;
+                for package in packages {
+                    writeln!(out, "{}  {}", pad, package)?;
+                }
+            }
+            AttributeData::ModuleMainClass(main_class) => {
+                writeln!(out, "{}ModuleMainClass: {}", pad, main_class)?
+            }
+            AttributeData::NestHost(host) => writeln!(out, "{}NestHost: class {}", pad, host)?,
+            AttributeData::NestMembers(members) | AttributeData::PermittedSubclasses(members) => {
+                writeln!(out, "{}{}:", pad, name)?;
+                for member in members {
+                    writeln!(out, "{}  {}", pad, member)?;
+                }
+            }
+            AttributeData::Record(components) => {
+                writeln!(out, "{}Record:", pad)?;
+                for component in components {
+                    writeln!(
+                        out,
+                        "{}  {} {};",
+                        pad,
+                        java_type(&component.descriptor),
+                        component.name
+                    )?;
+                    writeln!(out, "{}    descriptor: {}", pad, component.descriptor)?;
+                    self.write_attributes(out, &component.attributes, indent + 4)?;
+                }
+            }
+            AttributeData::Other(bytes) => {
+                writeln!(out, "{}{}: length = 0x{:x}", pad, name, bytes.len())?;
+                for chunk in bytes.chunks(16) {
+                    let hex: Vec<String> = chunk.iter().map(|b| format!("{:02x}", b)).collect();
+                    writeln!(out, "{}   {}", pad, hex.join(" "))?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn write_stack_map_entry(
+        &self,
+        out: &mut String,
+        entry: &StackMapEntry<'a>,
+        pad: &str,
+    ) -> fmt::Result {
+        match entry {
+            StackMapEntry::Same { offset_delta } if *offset_delta < 64 => {
+                writeln!(out, "{}  frame_type = {} /* same */", pad, offset_delta)?
+            }
+            StackMapEntry::Same { offset_delta } => {
+                writeln!(out, "{}  frame_type = 251 /* same_frame_extended */", pad)?;
+                writeln!(out, "{}    offset_delta = {}", pad, offset_delta)?;
+            }
+            StackMapEntry::SameLocals1StackItem {
+                offset_delta,
+                stack,
+            } => {
+                if *offset_delta < 64 {
+                    writeln!(
+                        out,
+                        "{}  frame_type = {} /* same_locals_1_stack_item */",
+                        pad,
+                        64 + offset_delta
+                    )?;
+                } else {
+                    writeln!(
+                        out,
+                        "{}  frame_type = 247 /* same_locals_1_stack_item_frame_extended */",
+                        pad
+                    )?;
+                    writeln!(out, "{}    offset_delta = {}", pad, offset_delta)?;
+                }
+                writeln!(
+                    out,
+                    "{}    stack = {}",
+                    pad,
+                    verification_types(std::slice::from_ref(stack))
+                )?;
+            }
+            StackMapEntry::Chop {
+                offset_delta,
+                chop_count,
+            } => {
+                writeln!(
+                    out,
+                    "{}  frame_type = {} /* chop */",
+                    pad,
+                    251 - i32::from(*chop_count)
+                )?;
+                writeln!(out, "{}    offset_delta = {}", pad, offset_delta)?;
+            }
+            StackMapEntry::Append {
+                offset_delta,
+                locals,
+            } => {
+                writeln!(
+                    out,
+                    "{}  frame_type = {} /* append */",
+                    pad,
+                    251 + locals.len()
+                )?;
+                writeln!(out, "{}    offset_delta = {}", pad, offset_delta)?;
+                writeln!(out, "{}    locals = {}", pad, verification_types(locals))?;
+            }
+            StackMapEntry::FullFrame {
+                offset_delta,
+                locals,
+                stack,
+            } => {
+                writeln!(out, "{}  frame_type = 255 /* full_frame */", pad)?;
+                writeln!(out, "{}    offset_delta = {}", pad, offset_delta)?;
+                writeln!(out, "{}    locals = {}", pad, verification_types(locals))?;
+                writeln!(out, "{}    stack = {}", pad, verification_types(stack))?;
+            }
+        }
+        Ok(())
+    }
+
+    fn bootstrap_argument(&mut self, argument: &BootstrapArgument<'a>) -> u16 {
+        let index = match argument {
+            BootstrapArgument::LiteralConstant(value) => match value {
+                LiteralConstant::Integer(v) => self.pool.integer(*v),
+                LiteralConstant::Float(v) => self.pool.float(*v),
+                LiteralConstant::Long(v) => self.pool.long(*v),
+                LiteralConstant::Double(v) => self.pool.double(*v),
+                LiteralConstant::String(v) => self.pool.string(v.clone()),
+                LiteralConstant::StringBytes(v) => self.pool.string_bytes(v.clone()),
+            },
+            BootstrapArgument::ClassInfo(name) => self.pool.class(name.clone()),
+            BootstrapArgument::MethodHandle(handle) => self.pool.method_handle(handle),
+            BootstrapArgument::MethodType(descriptor) => self.pool.method_type(descriptor.clone()),
+            BootstrapArgument::Dynamic(dynamic) => self.pool.dynamic(
+                dynamic.attr_index,
+                dynamic.name_and_type.name.clone(),
+                dynamic.name_and_type.descriptor.clone(),
+            ),
+        };
+        index.unwrap_or(0)
+    }
+}
+
+impl<'a> ClassFile<'a> {
+    /// Returns a human-readable dump of this class in a format close to that of
+    /// `javap -v`, covering the class flags, the constant pool, the fields and methods
+    /// (including disassembled code) and the attributes. This is intended for debugging;
+    /// the exact format is not stable.
+    #[must_use]
+    pub fn javap_dump(&self) -> String {
+        let pool = match ConstantPoolBuilder::from_constant_pool(&self.constant_pool) {
+            Ok(pool) => pool,
+            Err(e) => return format!("Error: {}\n", e),
+        };
+        let mut writer = JavapWriter {
+            class: self,
+            pool,
+            descriptions: HashMap::new(),
+        };
+        let mut out = String::new();
+        // Writing to a String never fails
+        let _ = writer.write_class(&mut out);
+        out
+    }
+}
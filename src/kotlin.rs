@@ -0,0 +1,100 @@
+use std::borrow::Cow;
+
+use crate::attributes::{AnnotationElementValue, AttributeData};
+use crate::descriptors::{FieldDescriptor, FieldType};
+use crate::ClassFile;
+
+/// The raw fields of the `kotlin.Metadata` annotation that the Kotlin compiler puts on the
+/// classes it generates. The protobuf-encoded contents of `d1` are not interpreted. Fields
+/// that are absent from the annotation (because they have their default value) are None or
+/// empty here.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct KotlinMetadata<'a> {
+    /// The kind of class (`k`), e.g. 1 for a class or 2 for a file facade.
+    pub kind: Option<i32>,
+    /// The version of the metadata format (`mv`).
+    pub metadata_version: Vec<i32>,
+    /// The bytecode interface version (`bv`), which newer compilers no longer write.
+    pub bytecode_version: Vec<i32>,
+    /// The protobuf-encoded metadata (`d1`), split into strings.
+    pub data1: Vec<Cow<'a, str>>,
+    /// The strings referenced by the protobuf-encoded metadata (`d2`).
+    pub data2: Vec<Cow<'a, str>>,
+    /// An extra string whose meaning depends on the kind (`xs`).
+    pub extra_string: Option<Cow<'a, str>>,
+    /// The fully qualified package name, if it differs from the JVM package (`pn`).
+    pub package_name: Option<Cow<'a, str>>,
+    /// Extra flags (`xi`).
+    pub extra_int: Option<i32>,
+}
+
+fn is_kotlin_metadata(descriptor: &FieldDescriptor) -> bool {
+    match &descriptor.field_type {
+        FieldType::Object(class_name) => {
+            descriptor.dimensions == 0 && class_name.to_string() == "kotlin/Metadata"
+        }
+        _ => false,
+    }
+}
+
+fn int_array(value: &AnnotationElementValue) -> Vec<i32> {
+    match value {
+        AnnotationElementValue::ArrayValue(values) => values
+            .iter()
+            .filter_map(|v| match v {
+                AnnotationElementValue::IntConstant(i) => Some(*i),
+                _ => None,
+            })
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+fn string_array<'a>(value: &AnnotationElementValue<'a>) -> Vec<Cow<'a, str>> {
+    match value {
+        AnnotationElementValue::ArrayValue(values) => values
+            .iter()
+            .filter_map(|v| match v {
+                AnnotationElementValue::StringConstant(s) => Some(s.clone()),
+                _ => None,
+            })
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+impl<'a> ClassFile<'a> {
+    /// Returns the contents of the `kotlin.Metadata` annotation on this class, or None if
+    /// it does not have one (i.e. it was not compiled from Kotlin).
+    #[must_use]
+    pub fn kotlin_metadata(&self) -> Option<KotlinMetadata<'a>> {
+        let annotation = self
+            .attributes
+            .iter()
+            .filter_map(|attr| match &attr.data {
+                AttributeData::RuntimeVisibleAnnotations(annotations) => Some(annotations),
+                _ => None,
+            })
+            .flatten()
+            .find(|annotation| is_kotlin_metadata(&annotation.type_descriptor))?;
+        let mut metadata = KotlinMetadata::default();
+        for element in &annotation.elements {
+            match (element.name.as_ref(), &element.value) {
+                ("k", AnnotationElementValue::IntConstant(i)) => metadata.kind = Some(*i),
+                ("mv", value) => metadata.metadata_version = int_array(value),
+                ("bv", value) => metadata.bytecode_version = int_array(value),
+                ("d1", value) => metadata.data1 = string_array(value),
+                ("d2", value) => metadata.data2 = string_array(value),
+                ("xs", AnnotationElementValue::StringConstant(s)) => {
+                    metadata.extra_string = Some(s.clone())
+                }
+                ("pn", AnnotationElementValue::StringConstant(s)) => {
+                    metadata.package_name = Some(s.clone())
+                }
+                ("xi", AnnotationElementValue::IntConstant(i)) => metadata.extra_int = Some(*i),
+                _ => (),
+            }
+        }
+        Some(metadata)
+    }
+}
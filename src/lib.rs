@@ -13,28 +13,62 @@ pub mod attributes;
 pub mod bytecode;
 pub mod constant_pool;
 pub mod descriptors;
+pub mod diff;
+mod edit;
+pub mod fuzz;
+mod javap;
+#[cfg(feature = "kotlin")]
+pub mod kotlin;
 pub mod names;
+mod owned;
+mod rename;
+pub mod signatures;
+pub mod stats;
+mod verify;
+pub mod visitor;
+mod writer;
 
 use std::borrow::Cow;
-use std::collections::HashSet;
+use std::collections::{BTreeSet, HashSet};
+use std::convert::TryFrom;
+use std::io::Read;
 use std::ops::Deref;
+use std::time::Instant;
 
 #[cfg(not(feature = "threadsafe"))]
 use std::rc::Rc;
 #[cfg(feature = "threadsafe")]
 use std::sync::Arc;
 
-use crate::attributes::{read_attributes, AttributeData, AttributeInfo};
+use crate::attributes::{
+    all_attributes_named, first_attribute_named, has_deprecated_attribute, has_synthetic_attribute,
+    read_attributes, signature_attribute, validate_module, validate_parameter_annotations,
+    validate_signature, AnnotationElementValue, AttributeData, AttributeInfo, BootstrapMethodEntry,
+    CodeData, InnerClassAccessFlags, InnerClassEntry, ModuleData, RecordComponentEntry,
+};
+use crate::bytecode::{constant_pool_operands, read_operand};
+#[cfg(feature = "parallel")]
+use crate::constant_pool::read_constant_pool_parallel;
 use crate::constant_pool::{
-    read_constant_pool, read_cp_classinfo, read_cp_classinfo_opt, read_cp_utf8, ConstantPoolEntry,
-    ConstantPoolIter,
+    constant_pool_into_static, constant_pool_item, get_cp_loadable, read_constant_pool,
+    read_constant_pool_deferred, read_cp_classinfo, read_cp_classinfo_opt, read_cp_utf8,
+    record_cp_references, resolve_entry, snapshot_constant_pool, validate_constant_pool_strict,
+    ConstantKind, ConstantPoolEntry, ConstantPoolItem, ConstantPoolIter, Dynamic, InvokeDynamic,
+    LiteralConstant, Loadable, MemberKind, NameAndType, ResolvedConstantPool, SymbolicRef,
 };
 use crate::descriptors::{
-    parse_field_descriptor, parse_method_descriptor, FieldDescriptor, MethodDescriptor,
-    ReturnDescriptor,
+    is_method_descriptor, parse_field_descriptor, parse_method_descriptor, FieldDescriptor,
+    FieldType, MethodDescriptor, ReturnDescriptor,
+};
+pub use crate::error::{ParseError, UnexpectedEof};
+use crate::names::{is_java_identifier, is_unqualified_method_name, is_unqualified_name};
+use crate::owned::IntoStatic;
+use crate::signatures::{
+    parse_class_signature_with_depth, parse_field_signature_with_depth,
+    parse_method_signature_with_depth, referenced_class_names, ClassSignature, MethodSignature,
+    ReferenceTypeSignature,
 };
-pub use crate::error::ParseError;
-use crate::names::{is_unqualified_method_name, is_unqualified_name};
+use crate::stats::ParseStats;
 
 #[cfg(not(feature = "threadsafe"))]
 pub(crate) type CafeRc<T> = Rc<T>;
@@ -42,8 +76,8 @@ pub(crate) type CafeRc<T> = Rc<T>;
 pub(crate) type CafeRc<T> = Arc<T>;
 
 pub(crate) fn read_u1(bytes: &[u8], ix: &mut usize) -> Result<u8, ParseError> {
-    if bytes.len() < *ix + 1 {
-        fail!("Unexpected end of stream reading u1 at index {}", *ix);
+    if bytes.len().saturating_sub(*ix) < 1 {
+        return Err(err!("Unexpected end of stream reading u1 at index {}", *ix).at_end_of_stream());
     }
     let result = bytes[*ix];
     *ix += 1;
@@ -52,8 +86,8 @@ pub(crate) fn read_u1(bytes: &[u8], ix: &mut usize) -> Result<u8, ParseError> {
 
 #[allow(clippy::double_parens, clippy::identity_op)]
 pub(crate) fn read_u2(bytes: &[u8], ix: &mut usize) -> Result<u16, ParseError> {
-    if bytes.len() < *ix + 2 {
-        fail!("Unexpected end of stream reading u2 at index {}", *ix);
+    if bytes.len().saturating_sub(*ix) < 2 {
+        return Err(err!("Unexpected end of stream reading u2 at index {}", *ix).at_end_of_stream());
     }
     let result = ((bytes[*ix + 0] as u16) << 8) | (bytes[*ix + 1] as u16);
     *ix += 2;
@@ -62,8 +96,8 @@ pub(crate) fn read_u2(bytes: &[u8], ix: &mut usize) -> Result<u16, ParseError> {
 
 #[allow(clippy::double_parens, clippy::identity_op)]
 pub(crate) fn read_u4(bytes: &[u8], ix: &mut usize) -> Result<u32, ParseError> {
-    if bytes.len() < *ix + 4 {
-        fail!("Unexpected end of stream reading u4 at index {}", *ix);
+    if bytes.len().saturating_sub(*ix) < 4 {
+        return Err(err!("Unexpected end of stream reading u4 at index {}", *ix).at_end_of_stream());
     }
     let result = ((bytes[*ix + 0] as u32) << 24)
         | ((bytes[*ix + 1] as u32) << 16)
@@ -75,8 +109,8 @@ pub(crate) fn read_u4(bytes: &[u8], ix: &mut usize) -> Result<u32, ParseError> {
 
 #[allow(clippy::double_parens, clippy::identity_op)]
 pub(crate) fn read_u8(bytes: &[u8], ix: &mut usize) -> Result<u64, ParseError> {
-    if bytes.len() < *ix + 8 {
-        fail!("Unexpected end of stream reading u8 at index {}", *ix);
+    if bytes.len().saturating_sub(*ix) < 8 {
+        return Err(err!("Unexpected end of stream reading u8 at index {}", *ix).at_end_of_stream());
     }
     let result = ((bytes[*ix + 0] as u64) << 56)
         | ((bytes[*ix + 1] as u64) << 48)
@@ -90,16 +124,65 @@ pub(crate) fn read_u8(bytes: &[u8], ix: &mut usize) -> Result<u64, ParseError> {
     Ok(result)
 }
 
+// Returns the `length` bytes starting at `ix` and advances past them, for data whose
+// length was read from the input. The returned slice borrows from the input.
+pub(crate) fn read_bytes<'a>(
+    bytes: &'a [u8],
+    ix: &mut usize,
+    length: usize,
+    what: &str,
+) -> Result<&'a [u8], ParseError> {
+    let end = checked_end(bytes, *ix, length, what)?;
+    let data = &bytes[*ix..end];
+    *ix = end;
+    Ok(data)
+}
+
+// Returns the index just past `length` bytes starting at `ix`, for data whose length was
+// read from the input. The addition is checked so that a crafted length can't overflow.
+pub(crate) fn checked_end(
+    bytes: &[u8],
+    ix: usize,
+    length: usize,
+    what: &str,
+) -> Result<usize, ParseError> {
+    match ix.checked_add(length) {
+        Some(end) if end <= bytes.len() => Ok(end),
+        _ => {
+            let available = bytes.len().saturating_sub(ix);
+            Err(err!(
+                "Unexpected end of stream reading {} at index {}; needed {} bytes but only {} are available",
+                what,
+                ix,
+                length,
+                available
+            )
+            .with_unexpected_eof(length, available))
+        }
+    }
+}
+
 fn read_interfaces<'a>(
     bytes: &'a [u8],
     ix: &mut usize,
     pool: &[CafeRc<ConstantPoolEntry<'a>>],
 ) -> Result<Vec<Cow<'a, str>>, ParseError> {
-    let count = read_u2(bytes, ix)?;
+    let count = read_u2(bytes, ix).map_err(|e| err!(e, "interfaces count"))?;
     let mut interfaces = Vec::with_capacity(count.into());
+    let mut unique_interfaces = HashSet::with_capacity(count.into());
     for i in 0..count {
-        interfaces
-            .push(read_cp_classinfo(bytes, ix, pool).map_err(|e| err!(e, "interface {}", i))?);
+        let interface =
+            read_cp_classinfo(bytes, ix, pool).map_err(|e| err!(e, "interface {}", i))?;
+        if interface.starts_with('[') {
+            fail!("Found array type for interface {}", i);
+        }
+        if !unique_interfaces.insert(interface.clone()) {
+            fail!(
+                "Interface {} is duplicate of previously-encountered interface",
+                i
+            );
+        }
+        interfaces.push(interface);
     }
     Ok(interfaces)
 }
@@ -132,6 +215,20 @@ bitflags! {
     }
 }
 
+// Generates convenience methods on an access flags type for checking individual flags.
+macro_rules! flag_helpers {
+    ($flags:ident { $($helper:ident => $flag:ident),* $(,)? }) => {
+        impl $flags {
+            $(
+                #[must_use]
+                pub fn $helper(&self) -> bool {
+                    self.contains($flags::$flag)
+                }
+            )*
+        }
+    };
+}
+
 bitflags! {
     pub struct FieldAccessFlags: u16 {
         const PUBLIC = AccessFlags::PUBLIC.bits();
@@ -146,12 +243,121 @@ bitflags! {
     }
 }
 
-#[derive(Debug)]
+flag_helpers!(FieldAccessFlags {
+    is_public => PUBLIC,
+    is_private => PRIVATE,
+    is_protected => PROTECTED,
+    is_static => STATIC,
+    is_final => FINAL,
+    is_volatile => VOLATILE,
+    is_transient => TRANSIENT,
+    is_synthetic => SYNTHETIC,
+    is_enum => ENUM,
+});
+
+fn count_visibility_flags(flags: u16) -> u32 {
+    (flags & (AccessFlags::PUBLIC | AccessFlags::PRIVATE | AccessFlags::PROTECTED).bits())
+        .count_ones()
+}
+
+// Checks the field access flag rules from section 4.5 of the JVM spec.
+fn validate_field_access_flags(
+    flags: FieldAccessFlags,
+    in_interface: bool,
+) -> Result<(), ParseError> {
+    if count_visibility_flags(flags.bits()) > 1 {
+        fail!("Multiple visibility flags set in access flags {:?}", flags);
+    }
+    if flags.contains(FieldAccessFlags::FINAL | FieldAccessFlags::VOLATILE) {
+        fail!("Both FINAL and VOLATILE set in access flags {:?}", flags);
+    }
+    if in_interface {
+        let required =
+            FieldAccessFlags::PUBLIC | FieldAccessFlags::STATIC | FieldAccessFlags::FINAL;
+        if !flags.contains(required) || !(required | FieldAccessFlags::SYNTHETIC).contains(flags) {
+            fail!("Invalid access flags {:?} for interface field", flags);
+        }
+    }
+    Ok(())
+}
+
+#[derive(Clone, Debug)]
 pub struct FieldInfo<'a> {
     pub access_flags: FieldAccessFlags,
     pub name: Cow<'a, str>,
     pub descriptor: FieldDescriptor<'a>,
     pub attributes: Vec<AttributeInfo<'a>>,
+    // The ParseOptions::max_nesting_depth the field was parsed with, for parsing its signature.
+    max_nesting_depth: usize,
+}
+
+impl<'a> FieldInfo<'a> {
+    /// Returns the generic type of this field as recorded in its Signature attribute,
+    /// or None if the field does not have one. Fails if the signature is malformed, which
+    /// is only checked during parsing in strict mode.
+    pub fn signature(&self) -> Result<Option<ReferenceTypeSignature<'a>>, ParseError> {
+        signature_attribute(&self.attributes)
+            .map(|s| parse_field_signature_with_depth(s, self.max_nesting_depth))
+            .transpose()
+    }
+
+    /// Returns the first attribute of this field with the given name, or None if there
+    /// is no such attribute. Use attributes_named for attributes that may occur more
+    /// than once.
+    #[must_use]
+    pub fn attribute(&self, name: &str) -> Option<&AttributeInfo<'a>> {
+        first_attribute_named(&self.attributes, name)
+    }
+
+    /// Returns all the attributes of this field with the given name.
+    pub fn attributes_named<'b>(
+        &'b self,
+        name: &'b str,
+    ) -> impl Iterator<Item = &'b AttributeInfo<'a>> + 'b {
+        all_attributes_named(&self.attributes, name)
+    }
+
+    /// Returns true if this field has a Deprecated attribute.
+    #[must_use]
+    pub fn is_deprecated(&self) -> bool {
+        has_deprecated_attribute(&self.attributes)
+    }
+
+    /// Returns true if this field has a Synthetic attribute. Note that this is distinct
+    /// from the SYNTHETIC access flag, which newer compilers use instead of the attribute.
+    #[must_use]
+    pub fn has_synthetic_attribute(&self) -> bool {
+        has_synthetic_attribute(&self.attributes)
+    }
+
+    /// Returns true if this field was generated by the compiler, as indicated by either
+    /// the SYNTHETIC access flag or a Synthetic attribute.
+    #[must_use]
+    pub fn is_synthetic(&self) -> bool {
+        self.access_flags.is_synthetic() || self.has_synthetic_attribute()
+    }
+}
+
+// Section 4.7.8 allows a synthetic member to be marked with either the SYNTHETIC flag or
+// a Synthetic attribute. The flag was introduced in version 49, after which compilers
+// use it in place of the attribute, so strict validation rejects a member marked only in
+// the way that doesn't match the class file version.
+fn validate_synthetic(
+    has_flag: bool,
+    attributes: &[AttributeInfo],
+    major_version: u16,
+) -> Result<(), ParseError> {
+    let has_attribute = has_synthetic_attribute(attributes);
+    if major_version >= 49 && has_attribute && !has_flag {
+        fail!("Found Synthetic attribute without SYNTHETIC flag");
+    }
+    if major_version < 49 && has_flag && !has_attribute {
+        fail!(
+            "Found SYNTHETIC flag without Synthetic attribute in version {} class file",
+            major_version
+        );
+    }
+    Ok(())
 }
 
 fn read_fields<'a>(
@@ -159,20 +365,28 @@ fn read_fields<'a>(
     ix: &mut usize,
     pool: &[CafeRc<ConstantPoolEntry<'a>>],
     opts: &ParseOptions,
+    in_interface: bool,
+    major_version: u16,
 ) -> Result<Vec<FieldInfo<'a>>, ParseError> {
-    let count = read_u2(bytes, ix)?;
+    let count = read_u2(bytes, ix).map_err(|e| err!(e, "fields count"))?;
     let mut fields = Vec::with_capacity(count.into());
     let mut unique_ids: HashSet<(Cow<'a, str>, FieldDescriptor<'a>)> = HashSet::new();
     for i in 0..count {
-        let access_flags = FieldAccessFlags::from_bits_truncate(read_u2(bytes, ix)?);
+        let access_flags = FieldAccessFlags::from_bits_truncate(
+            read_u2(bytes, ix).map_err(|e| err!(e, "access flags of class field {}", i))?,
+        );
+        if opts.strict {
+            validate_field_access_flags(access_flags, in_interface)
+                .map_err(|e| err!(e, "class field {}", i))?;
+        }
         let name =
             read_cp_utf8(bytes, ix, pool).map_err(|e| err!(e, "name of class field {}", i))?;
         if !is_unqualified_name(&name) {
             fail!("Invalid unqualified name for class field {}", i);
         }
         let descriptor = read_cp_utf8(bytes, ix, pool)
+            .and_then(|d| parse_field_descriptor(&d, 0))
             .map_err(|e| err!(e, "descriptor of class field {}", i))?;
-        let descriptor = parse_field_descriptor(&descriptor, 0)?;
 
         let unique_id = (name.clone(), descriptor.clone());
         if !unique_ids.insert(unique_id) {
@@ -183,11 +397,22 @@ fn read_fields<'a>(
         }
         let attributes =
             read_attributes(bytes, ix, pool, opts).map_err(|e| err!(e, "class field {}", i))?;
+        if opts.strict {
+            validate_signature(
+                &attributes,
+                parse_field_signature_with_depth,
+                opts.max_nesting_depth,
+            )
+            .map_err(|e| err!(e, "class field {}", i))?;
+            validate_synthetic(access_flags.is_synthetic(), &attributes, major_version)
+                .map_err(|e| err!(e, "class field {}", i))?;
+        }
         fields.push(FieldInfo {
             access_flags,
             name,
             descriptor,
             attributes,
+            max_nesting_depth: opts.max_nesting_depth,
         });
     }
     Ok(fields)
@@ -210,12 +435,202 @@ bitflags! {
     }
 }
 
-#[derive(Debug)]
+flag_helpers!(MethodAccessFlags {
+    is_public => PUBLIC,
+    is_private => PRIVATE,
+    is_protected => PROTECTED,
+    is_static => STATIC,
+    is_final => FINAL,
+    is_synchronized => SYNCHRONIZED,
+    is_bridge => BRIDGE,
+    is_varargs => VARARGS,
+    is_native => NATIVE,
+    is_abstract => ABSTRACT,
+    is_strict => STRICT,
+    is_synthetic => SYNTHETIC,
+});
+
+// Checks the method access flag rules from section 4.6 of the JVM spec.
+fn validate_method_access_flags(
+    flags: MethodAccessFlags,
+    name: &str,
+    in_interface: bool,
+    major_version: u16,
+) -> Result<(), ParseError> {
+    if name == "<clinit>" {
+        // Flags other than STATIC are ignored on class initialization methods
+        if major_version >= 51 && !flags.contains(MethodAccessFlags::STATIC) {
+            fail!(
+                "STATIC not set in access flags {:?} for clinit method",
+                flags
+            );
+        }
+        return Ok(());
+    }
+    if count_visibility_flags(flags.bits()) > 1 {
+        fail!("Multiple visibility flags set in access flags {:?}", flags);
+    }
+    if in_interface {
+        if major_version < 52 {
+            if !flags.contains(MethodAccessFlags::PUBLIC | MethodAccessFlags::ABSTRACT) {
+                fail!("Invalid access flags {:?} for interface method", flags);
+            }
+        } else if flags.intersects(
+            MethodAccessFlags::PROTECTED
+                | MethodAccessFlags::FINAL
+                | MethodAccessFlags::SYNCHRONIZED
+                | MethodAccessFlags::NATIVE,
+        ) || !flags.intersects(MethodAccessFlags::PUBLIC | MethodAccessFlags::PRIVATE)
+        {
+            fail!("Invalid access flags {:?} for interface method", flags);
+        }
+    }
+    if flags.contains(MethodAccessFlags::ABSTRACT) {
+        let mut disallowed = MethodAccessFlags::PRIVATE
+            | MethodAccessFlags::STATIC
+            | MethodAccessFlags::FINAL
+            | MethodAccessFlags::SYNCHRONIZED
+            | MethodAccessFlags::NATIVE;
+        if (46..=60).contains(&major_version) {
+            disallowed |= MethodAccessFlags::STRICT;
+        }
+        if flags.intersects(disallowed) {
+            fail!("Invalid access flags {:?} for abstract method", flags);
+        }
+    }
+    if name == "<init>" {
+        let allowed = MethodAccessFlags::PUBLIC
+            | MethodAccessFlags::PRIVATE
+            | MethodAccessFlags::PROTECTED
+            | MethodAccessFlags::VARARGS
+            | MethodAccessFlags::STRICT
+            | MethodAccessFlags::SYNTHETIC;
+        if !allowed.contains(flags) {
+            fail!("Invalid access flags {:?} for init method", flags);
+        }
+    }
+    Ok(())
+}
+
+#[derive(Clone, Debug)]
 pub struct MethodInfo<'a> {
     pub access_flags: MethodAccessFlags,
     pub name: Cow<'a, str>,
     pub descriptor: MethodDescriptor<'a>,
     pub attributes: Vec<AttributeInfo<'a>>,
+    // The ParseOptions::max_nesting_depth the method was parsed with, for parsing its signature.
+    max_nesting_depth: usize,
+}
+
+impl<'a> MethodInfo<'a> {
+    /// Returns the generic type information of this method as recorded in its Signature
+    /// attribute, or None if the method does not have one. Fails if the signature is
+    /// malformed, which is only checked during parsing in strict mode.
+    pub fn signature(&self) -> Result<Option<MethodSignature<'a>>, ParseError> {
+        signature_attribute(&self.attributes)
+            .map(|s| parse_method_signature_with_depth(s, self.max_nesting_depth))
+            .transpose()
+    }
+
+    /// Returns the first attribute of this method with the given name, or None if there
+    /// is no such attribute. Use attributes_named for attributes that may occur more
+    /// than once.
+    #[must_use]
+    pub fn attribute(&self, name: &str) -> Option<&AttributeInfo<'a>> {
+        first_attribute_named(&self.attributes, name)
+    }
+
+    /// Returns all the attributes of this method with the given name.
+    pub fn attributes_named<'b>(
+        &'b self,
+        name: &'b str,
+    ) -> impl Iterator<Item = &'b AttributeInfo<'a>> + 'b {
+        all_attributes_named(&self.attributes, name)
+    }
+
+    /// Returns true if this method has a Deprecated attribute.
+    #[must_use]
+    pub fn is_deprecated(&self) -> bool {
+        has_deprecated_attribute(&self.attributes)
+    }
+
+    /// Returns true if this method has a Synthetic attribute. Note that this is distinct
+    /// from the SYNTHETIC access flag, which newer compilers use instead of the attribute.
+    #[must_use]
+    pub fn has_synthetic_attribute(&self) -> bool {
+        has_synthetic_attribute(&self.attributes)
+    }
+
+    /// Returns true if this is a bridge method, generated by the compiler to forward calls
+    /// to a method whose signature differs after erasure (e.g. one with a covariant return
+    /// type). Compilers also mark bridge methods as synthetic.
+    #[must_use]
+    pub fn is_bridge(&self) -> bool {
+        self.access_flags.is_bridge()
+    }
+
+    /// Returns true if this method was generated by the compiler, as indicated by either
+    /// the SYNTHETIC access flag or a Synthetic attribute.
+    #[must_use]
+    pub fn is_synthetic(&self) -> bool {
+        self.access_flags.is_synthetic() || self.has_synthetic_attribute()
+    }
+
+    /// Returns the number of parameters in this method's descriptor. Note that long and
+    /// double parameters count as a single parameter here, even though they occupy two
+    /// local variable slots.
+    #[must_use]
+    pub fn argument_count(&self) -> usize {
+        self.descriptor.parameters.len()
+    }
+
+    /// Returns true if this method's return type is void.
+    #[must_use]
+    pub fn returns_void(&self) -> bool {
+        self.descriptor.return_type == ReturnDescriptor::Void
+    }
+
+    /// Returns the contents of this method's Code attribute, or None if the method does
+    /// not have one (e.g. if it is abstract or native).
+    #[must_use]
+    pub fn code(&self) -> Option<&CodeData<'a>> {
+        self.attributes.iter().find_map(|attr| match &attr.data {
+            AttributeData::Code(code) => Some(code),
+            _ => None,
+        })
+    }
+
+    /// Returns the raw bytes of the code array in this method's Code attribute, without
+    /// decoding them into instructions, or None if the method does not have one. For a
+    /// freshly parsed class this borrows directly from the input bytes.
+    #[must_use]
+    pub fn code_bytes(&self) -> Option<&[u8]> {
+        self.code().map(|code| code.code.as_ref())
+    }
+
+    /// Returns the names of the checked exception classes this method is declared to
+    /// throw, as recorded in its Exceptions attribute. This is empty if the method does
+    /// not have an Exceptions attribute.
+    #[must_use]
+    pub fn exceptions(&self) -> &[Cow<'a, str>] {
+        self.attributes
+            .iter()
+            .find_map(|attr| match &attr.data {
+                AttributeData::Exceptions(exceptions) => Some(exceptions.as_slice()),
+                _ => None,
+            })
+            .unwrap_or(&[])
+    }
+
+    /// Returns the default value of this annotation interface element as recorded in its
+    /// AnnotationDefault attribute, or None if the method does not have one.
+    #[must_use]
+    pub fn annotation_default(&self) -> Option<&AnnotationElementValue<'a>> {
+        self.attributes.iter().find_map(|attr| match &attr.data {
+            AttributeData::AnnotationDefault(value) => Some(value),
+            _ => None,
+        })
+    }
 }
 
 fn read_methods<'a>(
@@ -226,17 +641,23 @@ fn read_methods<'a>(
     in_interface: bool,
     major_version: u16,
 ) -> Result<Vec<MethodInfo<'a>>, ParseError> {
-    let count = read_u2(bytes, ix)?;
+    let count = read_u2(bytes, ix).map_err(|e| err!(e, "methods count"))?;
     let mut methods = Vec::with_capacity(count.into());
     let mut unique_ids: HashSet<(Cow<'a, str>, MethodDescriptor<'a>)> = HashSet::new();
     for i in 0..count {
-        let access_flags = MethodAccessFlags::from_bits_truncate(read_u2(bytes, ix)?);
+        let access_flags = MethodAccessFlags::from_bits_truncate(
+            read_u2(bytes, ix).map_err(|e| err!(e, "access flags of class method {}", i))?,
+        );
         let name =
             read_cp_utf8(bytes, ix, pool).map_err(|e| err!(e, "name of class method {}", i))?;
         let allow_init = !in_interface;
         if !is_unqualified_method_name(&name, allow_init, true) {
             fail!("Invalid unqualified name for class method {}", i);
         }
+        if opts.strict {
+            validate_method_access_flags(access_flags, &name, in_interface, major_version)
+                .map_err(|e| err!(e, "class method {}", i))?;
+        }
         let descriptor = read_cp_utf8(bytes, ix, pool)
             .and_then(|d| parse_method_descriptor(&d, 0))
             .map_err(|e| err!(e, "descriptor of class method {}", i))?;
@@ -261,11 +682,32 @@ fn read_methods<'a>(
         }
         let attributes =
             read_attributes(bytes, ix, pool, opts).map_err(|e| err!(e, "class method {}", i))?;
+        if opts.strict {
+            validate_parameter_annotations(&attributes, descriptor.parameters.len())
+                .map_err(|e| err!(e, "class method {}", i))?;
+            validate_signature(
+                &attributes,
+                parse_method_signature_with_depth,
+                opts.max_nesting_depth,
+            )
+            .map_err(|e| err!(e, "class method {}", i))?;
+            validate_synthetic(access_flags.is_synthetic(), &attributes, major_version)
+                .map_err(|e| err!(e, "class method {}", i))?;
+        }
+        validate_at_most_one(&attributes, "Exceptions", |data| {
+            matches!(data, AttributeData::Exceptions(_))
+        })
+        .map_err(|e| err!(e, "class method {}", i))?;
+        validate_at_most_one(&attributes, "AnnotationDefault", |data| {
+            matches!(data, AttributeData::AnnotationDefault(_))
+        })
+        .map_err(|e| err!(e, "class method {}", i))?;
         methods.push(MethodInfo {
             access_flags,
             name,
             descriptor,
             attributes,
+            max_nesting_depth: opts.max_nesting_depth,
         });
     }
     Ok(methods)
@@ -285,6 +727,109 @@ bitflags! {
     }
 }
 
+flag_helpers!(ClassAccessFlags {
+    is_public => PUBLIC,
+    is_final => FINAL,
+    is_super => SUPER,
+    is_interface => INTERFACE,
+    is_abstract => ABSTRACT,
+    is_synthetic => SYNTHETIC,
+    is_annotation => ANNOTATION,
+    is_enum => ENUM,
+    is_module => MODULE,
+});
+
+flag_helpers!(InnerClassAccessFlags {
+    is_public => PUBLIC,
+    is_private => PRIVATE,
+    is_protected => PROTECTED,
+    is_static => STATIC,
+    is_final => FINAL,
+    is_interface => INTERFACE,
+    is_abstract => ABSTRACT,
+    is_synthetic => SYNTHETIC,
+    is_annotation => ANNOTATION,
+    is_enum => ENUM,
+});
+
+// Checks the class access flag rules from section 4.1 of the JVM spec.
+fn validate_class_access_flags(flags: ClassAccessFlags) -> Result<(), ParseError> {
+    if flags.contains(ClassAccessFlags::INTERFACE) {
+        if !flags.contains(ClassAccessFlags::ABSTRACT)
+            || flags.intersects(
+                ClassAccessFlags::FINAL
+                    | ClassAccessFlags::SUPER
+                    | ClassAccessFlags::ENUM
+                    | ClassAccessFlags::MODULE,
+            )
+        {
+            fail!("Invalid access flags {:?} for interface", flags);
+        }
+    } else {
+        if flags.contains(ClassAccessFlags::ANNOTATION) {
+            fail!(
+                "ANNOTATION set without INTERFACE in access flags {:?}",
+                flags
+            );
+        }
+        if flags.contains(ClassAccessFlags::FINAL | ClassAccessFlags::ABSTRACT) {
+            fail!("Both FINAL and ABSTRACT set in access flags {:?}", flags);
+        }
+    }
+    Ok(())
+}
+
+// Section 4.7.6 of the JVM spec requires that from version 51 onwards, an inner class
+// without a simple name (i.e. an anonymous class) must also not have an outer class.
+fn validate_inner_classes(
+    attributes: &[AttributeInfo],
+    major_version: u16,
+) -> Result<(), ParseError> {
+    if major_version < 51 {
+        return Ok(());
+    }
+    for (i, attr) in attributes.iter().enumerate() {
+        if let AttributeData::InnerClasses(classes) = &attr.data {
+            for (j, entry) in classes.iter().enumerate() {
+                if entry.inner_name.is_none() && entry.outer_class_info.is_some() {
+                    fail!(
+                        ("Found outer class info without inner name"),
+                        ("inner class {} of InnerClasses attribute {}", j, i)
+                    );
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+fn validate_at_most_one(
+    attributes: &[AttributeInfo],
+    kind: &str,
+    is_kind: impl Fn(&AttributeData) -> bool,
+) -> Result<(), ParseError> {
+    let count = attributes.iter().filter(|attr| is_kind(&attr.data)).count();
+    if count > 1 {
+        fail!("Found {} {} attributes; expected at most 1", count, kind);
+    }
+    Ok(())
+}
+
+// Section 4.7.28 and 4.7.29 of the JVM spec allow at most one of each nest attribute, and
+// a class may not have both since it cannot be both a nest host and a nest member.
+fn validate_nest_attributes(attributes: &[AttributeInfo]) -> Result<(), ParseError> {
+    let is_host = |data: &AttributeData| matches!(data, AttributeData::NestHost(_));
+    let is_members = |data: &AttributeData| matches!(data, AttributeData::NestMembers(_));
+    validate_at_most_one(attributes, "NestHost", is_host)?;
+    validate_at_most_one(attributes, "NestMembers", is_members)?;
+    if attributes.iter().any(|attr| is_host(&attr.data))
+        && attributes.iter().any(|attr| is_members(&attr.data))
+    {
+        fail!("Found both NestHost and NestMembers attributes; expected at most one of them");
+    }
+    Ok(())
+}
+
 fn validate_bootstrap_methods<'a>(
     pool: &[CafeRc<ConstantPoolEntry<'a>>],
     attributes: &[AttributeInfo<'a>],
@@ -314,7 +859,16 @@ fn validate_bootstrap_methods<'a>(
     Ok(())
 }
 
-#[derive(Debug)]
+/// The highest class file major version whose rules this crate knows about (Java 21).
+/// Class files with a higher major version are still parsed, using the rules of this
+/// version, since later versions have so far only relaxed or added to them; see
+/// [`ClassFile::warnings`].
+pub const MAX_KNOWN_MAJOR_VERSION: u16 = 65;
+
+// The minor version that marks a class file as depending on preview features.
+const PREVIEW_MINOR_VERSION: u16 = 0xFFFF;
+
+#[derive(Clone, Debug)]
 pub struct ClassFile<'a> {
     pub major_version: u16,
     pub minor_version: u16,
@@ -326,117 +880,1283 @@ pub struct ClassFile<'a> {
     pub fields: Vec<FieldInfo<'a>>,
     pub methods: Vec<MethodInfo<'a>>,
     pub attributes: Vec<AttributeInfo<'a>>,
+    byte_length: usize,
+    // Class access flag bits that have no meaning assigned; kept so they can be written back.
+    undefined_access_flags: u16,
+    // The ParseOptions::max_nesting_depth the class was parsed with, for parsing its signature.
+    max_nesting_depth: usize,
+    // The constant pool indices referred to from outside the constant pool and code when the
+    // class was parsed, in order, so that to_bytes can refer to the same copy of a constant
+    // that the constant pool holds more than once.
+    pool_references: Vec<u16>,
 }
 
-impl<'a> ClassFile<'a> {
-    #[must_use]
-    pub fn constantpool_iter(&'a self) -> ConstantPoolIter<'a> {
-        ConstantPoolIter::new(&self.constant_pool)
-    }
-}
+/// A class file that does not borrow from the bytes it was parsed from, as returned by
+/// [`ClassFile::into_owned`]. It can be cloned cheaply and stored independently of the input.
+pub type OwnedClassFile = ClassFile<'static>;
 
-#[derive(Debug)]
-pub struct ParseOptions {
-    parse_bytecode: bool,
+fn add_descriptor_class<'a>(
+    descriptor: &FieldDescriptor<'a>,
+    classes: &mut BTreeSet<Cow<'a, str>>,
+) {
+    if let FieldType::Object(class_name) = &descriptor.field_type {
+        classes.insert(Cow::Owned(class_name.to_string()));
+    }
 }
 
-impl Default for ParseOptions {
-    fn default() -> Self {
-        Self {
-            parse_bytecode: true,
+fn add_annotation_types<'a>(
+    attributes: &[AttributeInfo<'a>],
+    classes: &mut BTreeSet<Cow<'a, str>>,
+) {
+    for attr in attributes {
+        match &attr.data {
+            AttributeData::RuntimeVisibleAnnotations(annotations)
+            | AttributeData::RuntimeInvisibleAnnotations(annotations) => {
+                for annotation in annotations {
+                    add_descriptor_class(&annotation.type_descriptor, classes);
+                }
+            }
+            AttributeData::RuntimeVisibleParameterAnnotations(parameters)
+            | AttributeData::RuntimeInvisibleParameterAnnotations(parameters) => {
+                for annotation in parameters.iter().flat_map(|p| &p.annotations) {
+                    add_descriptor_class(&annotation.type_descriptor, classes);
+                }
+            }
+            AttributeData::RuntimeVisibleTypeAnnotations(annotations)
+            | AttributeData::RuntimeInvisibleTypeAnnotations(annotations) => {
+                for annotation in annotations {
+                    add_descriptor_class(&annotation.annotation.type_descriptor, classes);
+                }
+            }
+            _ => (),
         }
     }
 }
 
-impl ParseOptions {
-    /// Turns on or off parsing of bytecode from the Code attributes of methods. If parsing
-    /// is enabled, the CodeData structure's optional bytecode field will be populated
-    /// (or parsing will fail entirely if bytecode parsing failed). If parsing is disabled,
-    /// the CodeData structure's optional bytecode field will be set to None. Parsing is
-    /// enabled by default, but can be disabled to speed up parsing in cases where the
-    /// parsed bytecode is not needed.
-    pub fn parse_bytecode(&mut self, parse: bool) -> &mut ParseOptions {
-        self.parse_bytecode = parse;
-        self
+/// Parses a class file with the default options, so that `ClassFile::try_from(bytes)?`
+/// can be used in place of [`parse_class`].
+impl<'a> TryFrom<&'a [u8]> for ClassFile<'a> {
+    type Error = ParseError;
+
+    fn try_from(raw_bytes: &'a [u8]) -> Result<Self, Self::Error> {
+        parse_class(raw_bytes)
     }
 }
 
-#[allow(clippy::needless_lifetimes)]
-pub fn parse_class<'a>(raw_bytes: &'a [u8]) -> Result<ClassFile<'a>, ParseError> {
-    parse_class_with_options(raw_bytes, &ParseOptions::default())
-}
+impl<'a> ClassFile<'a> {
+    /// Parses a class file from the given bytes with the default options. The returned
+    /// class borrows its strings from the bytes where possible, so it can't outlive them;
+    /// use [`into_owned`](ClassFile::into_owned) to detach it. This is the same as
+    /// [`parse_class`].
+    pub fn parse(raw_bytes: &'a [u8]) -> Result<Self, ParseError> {
+        parse_class(raw_bytes)
+    }
 
-pub fn parse_class_with_options<'a>(
-    raw_bytes: &'a [u8],
-    opts: &ParseOptions,
-) -> Result<ClassFile<'a>, ParseError> {
-    let mut ix = 0;
-    if read_u4(raw_bytes, &mut ix)? != 0xCAFE_BABE {
-        fail!("Unexpected magic header");
+    /// Parses a class file from the given bytes with the given options. This is the same
+    /// as [`parse_class_with_options`].
+    pub fn parse_with_options(
+        raw_bytes: &'a [u8],
+        opts: &ParseOptions,
+    ) -> Result<Self, ParseError> {
+        parse_class_with_options(raw_bytes, opts)
     }
-    let minor_version = read_u2(raw_bytes, &mut ix)?;
-    let major_version = read_u2(raw_bytes, &mut ix)?;
-    let constant_pool = read_constant_pool(raw_bytes, &mut ix, major_version)?;
 
-    let access_flags = ClassAccessFlags::from_bits_truncate(read_u2(raw_bytes, &mut ix)?);
-    let is_module = access_flags.contains(ClassAccessFlags::MODULE);
-    if is_module {
-        if major_version < 53 {
-            fail!(
-                "Found invalid MODULE class access flag on class file of major version {}",
-                major_version
-            );
+    #[must_use]
+    pub fn constantpool_iter(&self) -> ConstantPoolIter<'_, 'a> {
+        ConstantPoolIter::new(&self.constant_pool)
+    }
+
+    /// Returns an immutable copy of the constant pool in which references between entries
+    /// are plain indices, for analyses that walk the pool by index. Returns an error if a
+    /// reference between entries can't be mapped back to an index in the pool, or if the
+    /// class was parsed with [`ParseOptions::defer_resolution`] and one of its entries
+    /// can't be resolved.
+    pub fn constant_pool_snapshot(&self) -> Result<ResolvedConstantPool<'a>, ParseError> {
+        for index in 0..self.constant_pool.len() {
+            resolve_entry(&self.constant_pool, index)?;
         }
-        if access_flags != ClassAccessFlags::MODULE {
-            fail!(
-                "Found invalid class access flags {:?}; no other flags should be set with MODULE",
-                access_flags
-            );
+        snapshot_constant_pool(&self.constant_pool)
+    }
+
+    /// Returns the constant at the given constant pool index as it would be loaded by an
+    /// `ldc` or `ldc_w` instruction, or None if those instructions can't load that entry
+    /// in a class file of this version. Integer, Float and String constants are always
+    /// loadable, Class constants from version 49, MethodType and MethodHandle constants
+    /// from version 51, and Dynamic constants from version 55. Long and Double constants,
+    /// and Dynamic constants with a `J` or `D` descriptor, are loaded by `ldc2_w` instead,
+    /// so are not returned.
+    #[must_use]
+    pub fn loadable_constant(&self, index: u16) -> Option<Loadable<'a>> {
+        let loadable = get_cp_loadable(index.into(), &self.constant_pool).ok()?;
+        let min_major_version = match &loadable {
+            Loadable::LiteralConstant(LiteralConstant::Long(_))
+            | Loadable::LiteralConstant(LiteralConstant::Double(_)) => return None,
+            Loadable::LiteralConstant(_) => 45,
+            Loadable::ClassInfo(_) => 49,
+            Loadable::MethodHandle(_) | Loadable::MethodType(_) => 51,
+            Loadable::Dynamic(dynamic) => match dynamic.name_and_type.descriptor.as_bytes() {
+                [b'J'] | [b'D'] => return None,
+                _ => 55,
+            },
+        };
+        if self.major_version < min_major_version {
+            return None;
         }
+        Some(loadable)
     }
-    let this_class =
-        read_cp_classinfo(raw_bytes, &mut ix, &constant_pool).map_err(|e| err!(e, "this_class"))?;
-    let super_class = read_cp_classinfo_opt(raw_bytes, &mut ix, &constant_pool)
-        .map_err(|e| err!(e, "super_class"))?;
-    let interfaces = read_interfaces(raw_bytes, &mut ix, &constant_pool)?;
-    let fields = read_fields(raw_bytes, &mut ix, &constant_pool, opts)?;
-    let methods = read_methods(
-        raw_bytes,
-        &mut ix,
-        &constant_pool,
-        opts,
-        access_flags.contains(ClassAccessFlags::INTERFACE),
-        major_version,
-    )?;
-    let attributes =
-        read_attributes(raw_bytes, &mut ix, &constant_pool, opts).map_err(|e| err!(e, "class"))?;
-    // Section 4.8 "Format Checking" says the class file must not have extra bytes at the end
-    if ix != raw_bytes.len() {
-        fail!("Extra bytes found at index {} after reading class file", ix);
+
+    /// Returns the constant pool entry referenced by the operand at the given byte offset
+    /// into the code of the given method, which should be a method of this class. The
+    /// offset must be that of the constant pool index operand itself (i.e. one past the
+    /// opcode of e.g. an `invokevirtual` or `ldc`), not of the instruction. Returns None
+    /// if the method has no code or there is no such operand at that offset, so tools that
+    /// patch invoke targets in place can check what they are about to overwrite.
+    #[must_use]
+    pub fn constant_at_code_offset(
+        &self,
+        method: &MethodInfo<'a>,
+        offset: usize,
+    ) -> Option<ConstantPoolItem<'a>> {
+        let code = method.code_bytes()?;
+        let operands = constant_pool_operands(code).ok()?;
+        let (_, narrow) = operands.iter().find(|(start, _)| *start == offset)?;
+        let index = usize::from(read_operand(code, offset, *narrow));
+        let cp_entry = self.constant_pool.get(index)?;
+        resolve_entry(&self.constant_pool, index).ok()?;
+        constant_pool_item(cp_entry).ok().flatten()
     }
 
-    if is_module {
-        if let Some(super_class) = super_class {
-            fail!(
-                "Found non-empty super_class {}; expected none for module",
-                super_class
-            );
-        }
-        if !interfaces.is_empty() {
-            fail!(
-                "Found {} interfaces; expected 0 for module",
-                interfaces.len()
-            );
-        }
-        if !fields.is_empty() {
-            fail!("Found {} fields; expected 0 for module", fields.len());
-        }
-        if !methods.is_empty() {
-            fail!("Found {} methods; expected 0 for module", methods.len());
+    /// Returns the number of bytes of the input that make up this class file. This is
+    /// the length of the input unless trailing bytes were allowed in the ParseOptions.
+    #[must_use]
+    pub fn byte_length(&self) -> usize {
+        self.byte_length
+    }
+
+    /// Converts this class file into one that owns all of its data, so that it no longer
+    /// borrows from the input bytes. This copies every string in the class file. Returns
+    /// an error if the constant pool can't be copied, which happens when the class was
+    /// parsed with [`ParseOptions::defer_resolution`] and one of its entries can't be
+    /// resolved.
+    pub fn into_owned(self) -> Result<OwnedClassFile, ParseError> {
+        Ok(ClassFile {
+            major_version: self.major_version,
+            minor_version: self.minor_version,
+            constant_pool: constant_pool_into_static(&self.constant_pool)?,
+            access_flags: self.access_flags,
+            this_class: self.this_class.into_static(),
+            super_class: self.super_class.into_static(),
+            interfaces: self.interfaces.into_static(),
+            fields: self.fields.into_static(),
+            methods: self.methods.into_static(),
+            attributes: self.attributes.into_static(),
+            byte_length: self.byte_length,
+            undefined_access_flags: self.undefined_access_flags,
+            max_nesting_depth: self.max_nesting_depth,
+            pool_references: self.pool_references,
+        })
+    }
+
+    /// Returns the method with the given name and descriptor (e.g. "main" and
+    /// "([Ljava/lang/String;)V"), or None if there is no such method.
+    #[must_use]
+    pub fn find_method(&self, name: &str, descriptor: &str) -> Option<&MethodInfo<'a>> {
+        if !is_method_descriptor(descriptor) {
+            return None;
         }
+        let descriptor = parse_method_descriptor(&Cow::Borrowed(descriptor), 0).ok()?;
+        self.methods
+            .iter()
+            .find(|method| method.name == name && method.descriptor == descriptor)
     }
 
-    validate_bootstrap_methods(&constant_pool, &attributes)?;
+    /// Returns all the methods with the given name, i.e. all the overloads of the method.
+    pub fn find_methods_named<'b>(
+        &'b self,
+        name: &'b str,
+    ) -> impl Iterator<Item = &'b MethodInfo<'a>> + 'b {
+        self.methods
+            .iter()
+            .filter(move |method| method.name == name)
+    }
+
+    /// Returns the methods of this class other than bridge methods and other synthetic
+    /// methods generated by the compiler, i.e. those marked with the SYNTHETIC flag or a
+    /// Synthetic attribute.
+    pub fn real_methods(&self) -> impl Iterator<Item = &MethodInfo<'a>> + '_ {
+        self.methods
+            .iter()
+            .filter(|method| !method.is_bridge() && !method.is_synthetic())
+    }
+
+    /// Returns the generic type information of this class as recorded in its Signature
+    /// attribute, or None if the class does not have one. Fails if the signature is
+    /// malformed, which is only checked during parsing in strict mode.
+    pub fn signature(&self) -> Result<Option<ClassSignature<'a>>, ParseError> {
+        signature_attribute(&self.attributes)
+            .map(|s| parse_class_signature_with_depth(s, self.max_nesting_depth))
+            .transpose()
+    }
+
+    /// Returns the first attribute of this class with the given name, or None if there
+    /// is no such attribute. Use attributes_named for attributes that may occur more
+    /// than once.
+    #[must_use]
+    pub fn attribute(&self, name: &str) -> Option<&AttributeInfo<'a>> {
+        first_attribute_named(&self.attributes, name)
+    }
+
+    /// Returns all the attributes of this class with the given name.
+    pub fn attributes_named<'b>(
+        &'b self,
+        name: &'b str,
+    ) -> impl Iterator<Item = &'b AttributeInfo<'a>> + 'b {
+        all_attributes_named(&self.attributes, name)
+    }
+
+    /// Returns true if this class has a Deprecated attribute.
+    #[must_use]
+    pub fn is_deprecated(&self) -> bool {
+        has_deprecated_attribute(&self.attributes)
+    }
+
+    /// Returns true if this class has a Synthetic attribute. Note that this is distinct
+    /// from the SYNTHETIC access flag, which newer compilers use instead of the attribute.
+    #[must_use]
+    pub fn has_synthetic_attribute(&self) -> bool {
+        has_synthetic_attribute(&self.attributes)
+    }
+
+    /// Returns true if this class was generated by the compiler, as indicated by either
+    /// the SYNTHETIC access flag or a Synthetic attribute.
+    #[must_use]
+    pub fn is_synthetic(&self) -> bool {
+        self.access_flags.is_synthetic() || self.has_synthetic_attribute()
+    }
+
+    /// Returns the components of this record class as recorded in its Record attribute,
+    /// or None if this class is not a record.
+    #[must_use]
+    pub fn record_components(&self) -> Option<&[RecordComponentEntry<'a>]> {
+        self.attributes.iter().find_map(|attr| match &attr.data {
+            AttributeData::Record(components) => Some(components.as_slice()),
+            _ => None,
+        })
+    }
+
+    /// Returns true if this class is a record, i.e. if it has a Record attribute.
+    #[must_use]
+    pub fn is_record(&self) -> bool {
+        self.record_components().is_some()
+    }
+
+    /// Returns the names of the classes permitted to directly extend or implement this
+    /// class as recorded in its PermittedSubclasses attribute, or None if this class is
+    /// not sealed. Note that the returned list may be empty, in which case the class is
+    /// sealed but no class is permitted to extend or implement it.
+    #[must_use]
+    pub fn permitted_subclasses(&self) -> Option<&[Cow<'a, str>]> {
+        self.attributes.iter().find_map(|attr| match &attr.data {
+            AttributeData::PermittedSubclasses(classes) => Some(classes.as_slice()),
+            _ => None,
+        })
+    }
+
+    /// Returns true if this class is sealed, i.e. if it has a PermittedSubclasses attribute.
+    #[must_use]
+    pub fn is_sealed(&self) -> bool {
+        self.permitted_subclasses().is_some()
+    }
+
+    /// Returns true if this class is sealed and the class with the given name is listed
+    /// in its PermittedSubclasses attribute. A tool checking a whole classpath can use this
+    /// together with [`ClassFile::extends`] and [`ClassFile::implements`] to verify that
+    /// every direct subclass of a sealed class is permitted.
+    #[must_use]
+    pub fn permits(&self, name: &str) -> bool {
+        matches!(self.permitted_subclasses(), Some(classes) if classes.iter().any(|c| c == name))
+    }
+
+    /// Returns true if the class with the given name is the direct superclass of this
+    /// class. Superclasses further up the hierarchy are not considered.
+    #[must_use]
+    pub fn extends(&self, name: &str) -> bool {
+        matches!(&self.super_class, Some(super_class) if super_class == name)
+    }
+
+    /// Returns true if the interface with the given name is one of the direct
+    /// superinterfaces of this class, i.e. if it is listed in the class's interfaces.
+    /// Interfaces inherited from superclasses or superinterfaces are not considered.
+    #[must_use]
+    pub fn implements(&self, name: &str) -> bool {
+        self.interfaces.iter().any(|interface| interface == name)
+    }
+
+    /// Returns the entries of this class's InnerClasses attribute, which describe the
+    /// nested classes referenced by this class. This is empty if the class does not have
+    /// an InnerClasses attribute.
+    #[must_use]
+    pub fn inner_classes(&self) -> &[InnerClassEntry<'a>] {
+        self.attributes
+            .iter()
+            .find_map(|attr| match &attr.data {
+                AttributeData::InnerClasses(classes) => Some(classes.as_slice()),
+                _ => None,
+            })
+            .unwrap_or(&[])
+    }
+
+    /// Returns the innermost class enclosing this local or anonymous class, along with
+    /// the method enclosing it if there is one, as recorded in the EnclosingMethod
+    /// attribute. Returns None if the class does not have an EnclosingMethod attribute.
+    #[must_use]
+    pub fn enclosing_method(&self) -> Option<(&Cow<'a, str>, Option<&NameAndType<'a>>)> {
+        self.attributes.iter().find_map(|attr| match &attr.data {
+            AttributeData::EnclosingMethod { class_name, method } => {
+                Some((class_name, method.as_ref()))
+            }
+            _ => None,
+        })
+    }
+
+    /// Returns the names of all the classes this class depends on: its superclass and
+    /// interfaces, the classes referenced from its constant pool, the classes named in
+    /// the descriptors of its fields, methods and member references, and the types of its
+    /// annotations. Array types contribute their element class, and the class itself is
+    /// not included.
+    #[must_use]
+    pub fn referenced_classes(&self) -> BTreeSet<Cow<'a, str>> {
+        let mut classes = BTreeSet::new();
+        classes.extend(self.super_class.iter().cloned());
+        classes.extend(self.interfaces.iter().cloned());
+        for constant in self.constantpool_iter() {
+            let descriptor = match constant {
+                ConstantPoolItem::ClassInfo(name) if !name.starts_with('[') => {
+                    classes.insert(name);
+                    continue;
+                }
+                // The name of an array class is its descriptor
+                ConstantPoolItem::ClassInfo(descriptor)
+                | ConstantPoolItem::MethodType(descriptor) => descriptor,
+                ConstantPoolItem::NameAndType(name_and_type) => name_and_type.descriptor,
+                _ => continue,
+            };
+            // Descriptors that no member reference uses aren't validated, so a malformed
+            // one is skipped
+            classes.extend(referenced_class_names(&descriptor).unwrap_or_default());
+        }
+        for field in &self.fields {
+            add_descriptor_class(&field.descriptor, &mut classes);
+            add_annotation_types(&field.attributes, &mut classes);
+        }
+        for method in &self.methods {
+            for parameter in &method.descriptor.parameters {
+                add_descriptor_class(parameter, &mut classes);
+            }
+            if let ReturnDescriptor::Return(return_type) = &method.descriptor.return_type {
+                add_descriptor_class(return_type, &mut classes);
+            }
+            add_annotation_types(&method.attributes, &mut classes);
+        }
+        add_annotation_types(&self.attributes, &mut classes);
+        classes.remove(&self.this_class);
+        classes
+    }
+
+    /// Returns the distinct descriptors and signatures in this class that mention the class
+    /// with the given name, e.g. those that include `Lcom/foo/Bar;` when given
+    /// `com/foo/Bar`. Only whole names match, so `Lcom/foo/BarBaz;` does not. This covers
+    /// the descriptors of this class's fields and methods, of the members and method types
+    /// referenced from its constant pool, and the Signature attributes of the class and its
+    /// fields and methods.
+    #[must_use]
+    pub fn descriptors_referencing(&self, class: &str) -> Vec<Cow<'a, str>> {
+        let mut descriptors = Vec::new();
+        let mut seen = HashSet::new();
+        let mut check = |data: Cow<'a, str>, names: Vec<Cow<str>>| {
+            if names.iter().any(|name| name == class) && seen.insert(data.to_string()) {
+                descriptors.push(data);
+            }
+        };
+        let mut check_descriptor = |data: Cow<'a, str>| {
+            let names = referenced_class_names(&data).unwrap_or_default();
+            check(data.clone(), names);
+        };
+        for field in &self.fields {
+            check_descriptor(Cow::Owned(field.descriptor.to_string()));
+        }
+        for method in &self.methods {
+            check_descriptor(Cow::Owned(method.descriptor.to_string()));
+        }
+        for constant in self.constantpool_iter() {
+            match constant {
+                ConstantPoolItem::NameAndType(name_and_type) => {
+                    check_descriptor(name_and_type.descriptor)
+                }
+                ConstantPoolItem::MethodType(descriptor) => check_descriptor(descriptor),
+                _ => (),
+            }
+        }
+        let signatures = signature_attribute(&self.attributes)
+            .into_iter()
+            .chain(
+                self.fields
+                    .iter()
+                    .filter_map(|f| signature_attribute(&f.attributes)),
+            )
+            .chain(
+                self.methods
+                    .iter()
+                    .filter_map(|m| signature_attribute(&m.attributes)),
+            );
+        for signature in signatures {
+            // A malformed signature is skipped, as it can't be known which classes it names
+            let names = referenced_class_names(signature).unwrap_or_default();
+            check(signature.clone(), names);
+        }
+        descriptors
+    }
+
+    /// Returns the symbolic references this class makes to fields and methods of other
+    /// classes, in constant pool order. A linker can check these against the classes
+    /// available on the classpath. References to members of this class and of array
+    /// types (e.g. `[I.clone()`) are not included.
+    #[must_use]
+    pub fn unresolved_external_refs(&self) -> Vec<SymbolicRef<'a>> {
+        self.constantpool_iter()
+            .filter_map(|constant| {
+                let (kind, member_ref) = match constant {
+                    ConstantPoolItem::FieldRef(r) => (MemberKind::Field, r),
+                    ConstantPoolItem::MethodRef(r) => (MemberKind::Method, r),
+                    ConstantPoolItem::InterfaceMethodRef(r) => (MemberKind::InterfaceMethod, r),
+                    _ => return None,
+                };
+                if member_ref.class_name == self.this_class
+                    || member_ref.class_name.starts_with('[')
+                {
+                    return None;
+                }
+                Some(SymbolicRef {
+                    kind,
+                    class_name: member_ref.class_name,
+                    name: member_ref.name_and_type.name,
+                    descriptor: member_ref.name_and_type.descriptor,
+                })
+            })
+            .collect()
+    }
+
+    /// Returns the text of every String entry in the constant pool, in constant pool
+    /// order. These are the strings that can be loaded with `ldc`, as opposed to the Utf8
+    /// entries that hold names and descriptors. A string that is not valid modified utf-8
+    /// is converted lossily.
+    pub fn string_constants(&self) -> impl Iterator<Item = Cow<'a, str>> + '_ {
+        self.constantpool_iter()
+            .string_entries()
+            .filter_map(|(_, literal)| match literal {
+                LiteralConstant::String(s) => Some(s),
+                LiteralConstant::StringBytes(b) => {
+                    Some(Cow::Owned(String::from_utf8_lossy(&b).into_owned()))
+                }
+                _ => None,
+            })
+    }
+
+    /// Returns true if any class name or NameAndType name in the constant pool contains
+    /// characters that are legal in a class file but not in a Java source identifier,
+    /// such as spaces, dashes or leading digits. This is a cheap heuristic for spotting
+    /// obfuscated classes; classes compiled from other JVM languages may also trip it.
+    /// The special method names `<init>` and `<clinit>` are not counted, and neither are
+    /// the `package-info` and `module-info` classes that hold package and module
+    /// declarations.
+    #[must_use]
+    pub fn has_non_standard_names(&self) -> bool {
+        self.constantpool_iter().any(|constant| match constant {
+            ConstantPoolItem::ClassInfo(name) => {
+                let class = if name.starts_with('[') {
+                    let element = name.trim_start_matches('[');
+                    match element.strip_prefix('L') {
+                        Some(class) => class.trim_end_matches(';'),
+                        None => return false,
+                    }
+                } else {
+                    &name
+                };
+                let (package, simple_name) = match class.rfind('/') {
+                    Some(i) => (Some(&class[..i]), &class[i + 1..]),
+                    None => (None, class),
+                };
+                // javac names these after their source files, which are not identifiers
+                let special = simple_name == "package-info"
+                    || (package.is_none() && simple_name == "module-info");
+                !(special || is_java_identifier(simple_name))
+                    || package
+                        .into_iter()
+                        .flat_map(|package| package.split('/'))
+                        .any(|part| !is_java_identifier(part))
+            }
+            ConstantPoolItem::NameAndType(name_and_type) => {
+                let name = &name_and_type.name;
+                name != "<init>" && name != "<clinit>" && !is_java_identifier(name)
+            }
+            _ => false,
+        })
+    }
+
+    /// Returns warnings about things in this class file that were accepted but may not
+    /// have been fully validated, e.g. a major version newer than
+    /// [`MAX_KNOWN_MAJOR_VERSION`]. Returns an empty vector if there is nothing to report.
+    #[must_use]
+    pub fn warnings(&self) -> Vec<String> {
+        let mut warnings = Vec::new();
+        if self.major_version > MAX_KNOWN_MAJOR_VERSION {
+            warnings.push(format!(
+                "Major version {} is newer than the latest known version {}; parsed using the rules of version {}",
+                self.major_version, MAX_KNOWN_MAJOR_VERSION, MAX_KNOWN_MAJOR_VERSION
+            ));
+        }
+        warnings
+    }
+
+    /// Returns true if this class has a `public static void main(String[])` method, i.e.
+    /// if it can be run as a program by the java launcher.
+    #[must_use]
+    pub fn has_main(&self) -> bool {
+        let flags = MethodAccessFlags::PUBLIC | MethodAccessFlags::STATIC;
+        matches!(
+            self.find_method("main", "([Ljava/lang/String;)V"),
+            Some(method) if method.access_flags.contains(flags)
+        )
+    }
+
+    /// Returns true if this class is a functional interface, i.e. an interface with exactly
+    /// one abstract method, so that lambdas can implement it. Abstract methods that match
+    /// public methods of java/lang/Object don't count, as every implementation inherits
+    /// those. This is a structural check that doesn't depend on the @FunctionalInterface
+    /// annotation, but it only sees the methods declared in this class, so an interface
+    /// whose abstract method is inherited from a superinterface is not recognized.
+    #[must_use]
+    pub fn is_functional_interface(&self) -> bool {
+        const OBJECT_METHODS: [(&str, &str); 3] = [
+            ("equals", "(Ljava/lang/Object;)Z"),
+            ("hashCode", "()I"),
+            ("toString", "()Ljava/lang/String;"),
+        ];
+        if !self.access_flags.contains(ClassAccessFlags::INTERFACE)
+            || self.access_flags.contains(ClassAccessFlags::ANNOTATION)
+        {
+            return false;
+        }
+        let abstract_methods = self.methods.iter().filter(|method| {
+            method.access_flags.contains(MethodAccessFlags::ABSTRACT)
+                && !OBJECT_METHODS.iter().any(|(name, descriptor)| {
+                    method.name == *name && method.descriptor.to_string() == *descriptor
+                })
+        });
+        abstract_methods.count() == 1
+    }
+
+    /// Returns true if this class is an enum class, i.e. if it has the ENUM access flag.
+    #[must_use]
+    pub fn is_enum(&self) -> bool {
+        self.access_flags.contains(ClassAccessFlags::ENUM)
+    }
+
+    /// Returns the names of the constants of this enum class in declaration order. This is
+    /// best-effort: the constants are taken to be the static fields with the ENUM access
+    /// flag whose type is this class. The result is empty for classes that are not enums.
+    #[must_use]
+    pub fn enum_constants(&self) -> Vec<Cow<'a, str>> {
+        if !self.is_enum() {
+            return Vec::new();
+        }
+        self.fields
+            .iter()
+            .filter(|field| {
+                let is_own_type = match &field.descriptor.field_type {
+                    FieldType::Object(class_name) => class_name.to_string() == self.this_class,
+                    _ => false,
+                };
+                field
+                    .access_flags
+                    .contains(FieldAccessFlags::STATIC | FieldAccessFlags::ENUM)
+                    && field.descriptor.dimensions == 0
+                    && is_own_type
+            })
+            .map(|field| field.name.clone())
+            .collect()
+    }
+
+    /// Returns true if this class is an annotation interface rather than an ordinary class
+    /// or interface.
+    #[must_use]
+    pub fn is_annotation(&self) -> bool {
+        self.access_flags.contains(ClassAccessFlags::ANNOTATION)
+    }
+
+    /// Returns true if the ACC_SUPER flag is set on this class. The flag asks for the
+    /// modern semantics of invokespecial, where a call to a superclass method is resolved
+    /// from the direct superclass rather than being bound to the named method. Whether it
+    /// matters depends on the JVM rather than on the class file: JVMs for Java SE 8 and
+    /// later treat every class as if the flag were set, whatever its version, while older
+    /// JVMs honour the flag as written. Note that the same bit (0x0020) is reused as
+    /// ACC_IDENTITY by the value classes preview, where it marks identity classes instead.
+    #[must_use]
+    pub fn has_acc_super(&self) -> bool {
+        self.access_flags.contains(ClassAccessFlags::SUPER)
+    }
+
+    /// Returns true if this class depends on the preview features of its Java SE release,
+    /// as marked by a minor version of 65535 in class files of major version 56 (Java 12)
+    /// onwards. Such a class can only be loaded by a JVM of exactly that release with
+    /// preview features enabled.
+    #[must_use]
+    pub fn is_preview(&self) -> bool {
+        self.major_version >= 56 && self.minor_version == PREVIEW_MINOR_VERSION
+    }
+
+    /// Returns true if this class is a module descriptor (i.e. a module-info class) rather
+    /// than an ordinary class or interface.
+    #[must_use]
+    pub fn is_module(&self) -> bool {
+        self.access_flags.contains(ClassAccessFlags::MODULE)
+    }
+
+    /// Returns the module described by this module-info class as recorded in its Module
+    /// attribute, or None if this class is not a module descriptor.
+    #[must_use]
+    pub fn module(&self) -> Option<&ModuleData<'a>> {
+        self.attributes.iter().find_map(|attr| match &attr.data {
+            AttributeData::Module(module) => Some(module),
+            _ => None,
+        })
+    }
+
+    /// Returns the packages of the module described by this module-info class as recorded
+    /// in its ModulePackages attribute. This is empty if the class does not have a
+    /// ModulePackages attribute.
+    #[must_use]
+    pub fn module_packages(&self) -> &[Cow<'a, str>] {
+        self.attributes
+            .iter()
+            .find_map(|attr| match &attr.data {
+                AttributeData::ModulePackages(packages) => Some(packages.as_slice()),
+                _ => None,
+            })
+            .unwrap_or(&[])
+    }
+
+    /// Returns the name of the main class of the module described by this module-info
+    /// class as recorded in its ModuleMainClass attribute, or None if the class does not
+    /// have one.
+    #[must_use]
+    pub fn module_main_class(&self) -> Option<&Cow<'a, str>> {
+        self.attributes.iter().find_map(|attr| match &attr.data {
+            AttributeData::ModuleMainClass(main_class) => Some(main_class),
+            _ => None,
+        })
+    }
+
+    /// Returns the name of the source file this class was compiled from as recorded in
+    /// its SourceFile attribute, or None if the class does not have one.
+    #[must_use]
+    pub fn source_file(&self) -> Option<&Cow<'a, str>> {
+        self.attributes.iter().find_map(|attr| match &attr.data {
+            AttributeData::SourceFile(source_file) => Some(source_file),
+            _ => None,
+        })
+    }
+
+    /// Returns the contents of this class's SourceDebugExtension attribute, or None if the
+    /// class does not have one. Contents that are not valid modified UTF-8 are decoded
+    /// lossily; the raw bytes are available from the SourceDebugExtensionBytes attribute
+    /// data in that case.
+    #[must_use]
+    pub fn source_debug_extension(&self) -> Option<Cow<'_, str>> {
+        self.attributes.iter().find_map(|attr| match &attr.data {
+            AttributeData::SourceDebugExtension(debug_str) => Some(Cow::Borrowed(&**debug_str)),
+            AttributeData::SourceDebugExtensionBytes(bytes) => Some(String::from_utf8_lossy(bytes)),
+            _ => None,
+        })
+    }
+
+    /// Returns the name of the host class of the nest this class belongs to, as recorded
+    /// in its NestHost attribute, or None if the class does not have one.
+    #[must_use]
+    pub fn nest_host(&self) -> Option<&Cow<'a, str>> {
+        self.attributes.iter().find_map(|attr| match &attr.data {
+            AttributeData::NestHost(host) => Some(host),
+            _ => None,
+        })
+    }
+
+    /// Returns the entry at the given index of this class's BootstrapMethods attribute,
+    /// i.e. the bootstrap method of an InvokeDynamic or Dynamic constant with that
+    /// `attr_index`. Returns None if the class has no such entry.
+    #[must_use]
+    pub fn bootstrap_method(&self, index: u16) -> Option<&BootstrapMethodEntry<'a>> {
+        self.attributes.iter().find_map(|attr| match &attr.data {
+            AttributeData::BootstrapMethods(methods) => methods.get(usize::from(index)),
+            _ => None,
+        })
+    }
+
+    /// Returns the invokedynamic call sites and dynamically-computed constants in this
+    /// class's constant pool, i.e. its InvokeDynamic and Dynamic entries, along with the
+    /// bootstrap methods that link them. These are what lambdas, string concatenation and
+    /// records compile to. If the class was parsed with
+    /// [`ParseOptions::skip_attribute_bodies`], its BootstrapMethods attribute was not
+    /// decoded, so there are no bootstrap methods to link and nothing is returned.
+    pub fn invoke_dynamic_sites(&self) -> impl Iterator<Item = IndySite<'_, 'a>> + '_ {
+        self.constantpool_iter()
+            .dynamic_entries()
+            .filter_map(move |(index, constant)| {
+                let (kind, attr_index, name_and_type) = match constant {
+                    ConstantPoolItem::InvokeDynamic(InvokeDynamic {
+                        attr_index,
+                        name_and_type,
+                    }) => (ConstantKind::InvokeDynamic, attr_index, name_and_type),
+                    ConstantPoolItem::Dynamic(Dynamic {
+                        attr_index,
+                        name_and_type,
+                    }) => (ConstantKind::Dynamic, attr_index, name_and_type),
+                    _ => return None,
+                };
+                // The bootstrap method indices are validated during parsing, so this is
+                // only missing if the BootstrapMethods attribute body was skipped
+                let bootstrap_method = self.bootstrap_method(attr_index)?;
+                Some(IndySite {
+                    index,
+                    kind,
+                    name_and_type,
+                    bootstrap_method,
+                })
+            })
+    }
+
+    /// Returns the names of the classes that are members of the nest hosted by this class,
+    /// as recorded in its NestMembers attribute. This is empty if the class does not
+    /// have a NestMembers attribute.
+    #[must_use]
+    pub fn nest_members(&self) -> &[Cow<'a, str>] {
+        self.attributes
+            .iter()
+            .find_map(|attr| match &attr.data {
+                AttributeData::NestMembers(members) => Some(members.as_slice()),
+                _ => None,
+            })
+            .unwrap_or(&[])
+    }
+}
+
+#[derive(Debug)]
+pub struct ParseOptions {
+    parse_bytecode: bool,
+    skip_attribute_bodies: bool,
+    allow_trailing_bytes: bool,
+    strict: bool,
+    max_nesting_depth: usize,
+    defer_resolution: bool,
+    #[cfg(feature = "parallel")]
+    parallel_resolution: bool,
+}
+
+// The default for ParseOptions::max_nesting_depth, which is also used when parsing
+// signatures outside of parsing a class. This is far deeper than anything a compiler
+// generates, while staying well within a 2MB thread stack even in debug builds.
+pub(crate) const DEFAULT_MAX_NESTING_DEPTH: usize = 128;
+
+impl Default for ParseOptions {
+    fn default() -> Self {
+        Self {
+            parse_bytecode: true,
+            skip_attribute_bodies: false,
+            allow_trailing_bytes: false,
+            strict: false,
+            max_nesting_depth: DEFAULT_MAX_NESTING_DEPTH,
+            defer_resolution: false,
+            #[cfg(feature = "parallel")]
+            parallel_resolution: false,
+        }
+    }
+}
+
+impl ParseOptions {
+    /// Turns on or off parsing of bytecode from the Code attributes of methods. If parsing
+    /// is enabled, the CodeData structure's optional bytecode field will be populated
+    /// (or parsing will fail entirely if bytecode parsing failed). If parsing is disabled,
+    /// the CodeData structure's optional bytecode field will be set to None. Parsing is
+    /// enabled by default, but can be disabled to speed up parsing in cases where the
+    /// parsed bytecode is not needed.
+    pub fn parse_bytecode(&mut self, parse: bool) -> &mut ParseOptions {
+        self.parse_bytecode = parse;
+        self
+    }
+
+    /// Turns on or off skipping of attribute bodies. If skipping is enabled, attributes
+    /// are not decoded and every AttributeInfo will have AttributeData::Other with the raw
+    /// attribute bytes, and any validation that depends on attribute contents is skipped.
+    /// Skipping is disabled by default, but can be enabled to speed up parsing in cases
+    /// where only the class structure and member names and descriptors are needed.
+    pub fn skip_attribute_bodies(&mut self, skip: bool) -> &mut ParseOptions {
+        self.skip_attribute_bodies = skip;
+        self
+    }
+
+    /// Turns on or off allowing extra bytes after the end of the class file. By default
+    /// parsing fails if there are any such bytes, as required by the JVM spec. Allowing
+    /// them is useful when the class file is embedded in a larger structure; the
+    /// ClassFile's byte_length method can then be used to find where the class file ends.
+    pub fn allow_trailing_bytes(&mut self, allow: bool) -> &mut ParseOptions {
+        self.allow_trailing_bytes = allow;
+        self
+    }
+
+    /// Turns on or off strict validation. When enabled, parsing will additionally fail
+    /// on class files that violate constraints which are not needed to parse the file.
+    /// Most of these come from the JVM spec, such as illegal combinations of access flags,
+    /// malformed Signature attributes or misplaced `<init>` names in constant pool member
+    /// references, though not all of them are enforced by the JVM itself. Strict validation
+    /// also rejects annotation interfaces that do not extend java/lang/annotation/Annotation,
+    /// which is a rule of the Java language (JLS 9.6) rather than of the class file format.
+    /// Strict validation is disabled by default.
+    pub fn strict(&mut self, strict: bool) -> &mut ParseOptions {
+        self.strict = strict;
+        self
+    }
+
+    /// Sets how deeply generic signatures and annotation element values may be nested
+    /// before parsing fails, e.g. how many levels of type arguments a signature can have.
+    /// Parsing these is recursive, so the limit guards against class files crafted to
+    /// overflow the stack. The default is 128; lower it when parsing untrusted class
+    /// files on threads with small stacks. The limit also applies when the signature
+    /// accessors of the parsed class and its members parse their Signature attributes.
+    pub fn max_nesting_depth(&mut self, depth: usize) -> &mut ParseOptions {
+        self.max_nesting_depth = depth;
+        self
+    }
+
+    /// Turns on or off deferring the resolution of references between constant pool
+    /// entries. Normally every reference is resolved, and every entry checked to refer to
+    /// entries of the right types, before the rest of the class file is parsed. When
+    /// deferred, an entry and the entries it refers to are only resolved when something
+    /// reads it, so the cost is proportional to the entries actually used. Problems with
+    /// entries that are never read are then not reported, and a badly typed reference is
+    /// only reported by the accessor that reads it. Strict validation needs the whole
+    /// pool, so this has no effect when [`strict`](ParseOptions::strict) is enabled.
+    /// Resolution is not deferred by default.
+    pub fn defer_resolution(&mut self, defer: bool) -> &mut ParseOptions {
+        self.defer_resolution = defer;
+        self
+    }
+
+    /// Turns on or off resolving the references between constant pool entries, and then
+    /// validating the entries, on the rayon thread pool. Whether this is any faster depends
+    /// on the number of threads in the pool and the size of the constant pool; for all but
+    /// very large constant pools (tens of thousands of entries) the overhead outweighs the
+    /// gain, so it is disabled by default. Only available with the `parallel` feature.
+    #[cfg(feature = "parallel")]
+    pub fn parallel_resolution(&mut self, parallel: bool) -> &mut ParseOptions {
+        self.parallel_resolution = parallel;
+        self
+    }
+}
+
+/// Parses a class file. The returned ClassFile borrows names, strings and code from
+/// `raw_bytes` rather than copying them, so its lifetime is tied to the input; this lets
+/// the input be a memory-mapped region, as long as the mapping outlives the ClassFile and
+/// is not modified while it is mapped. Every length read from the input is checked
+/// against the end of `raw_bytes`, so a crafted class file produces an error rather than
+/// a read past the end. Use [`ClassFile::into_owned`] to detach the result from the input.
+#[allow(clippy::needless_lifetimes)]
+pub fn parse_class<'a>(raw_bytes: &'a [u8]) -> Result<ClassFile<'a>, ParseError> {
+    parse_class_with_options(raw_bytes, &ParseOptions::default())
+}
+
+// Checks the this_class and super_class rules from section 4.1 of the JVM spec.
+fn validate_class_hierarchy(
+    access_flags: ClassAccessFlags,
+    this_class: &str,
+    super_class: &Option<Cow<str>>,
+) -> Result<(), ParseError> {
+    if this_class.starts_with('[') {
+        fail!("Found array type {} for this_class", this_class);
+    }
+    if access_flags.contains(ClassAccessFlags::MODULE) && this_class != "module-info" {
+        fail!(
+            "Found this_class {} for module; expected module-info",
+            this_class
+        );
+    }
+    match super_class {
+        None => {
+            if this_class != "java/lang/Object" && !access_flags.contains(ClassAccessFlags::MODULE)
+            {
+                fail!("Found no super_class for class {}", this_class);
+            }
+        }
+        Some(super_class) => {
+            if super_class.starts_with('[') {
+                fail!("Found array type {} for super_class", super_class);
+            }
+            if access_flags.contains(ClassAccessFlags::INTERFACE)
+                && super_class != "java/lang/Object"
+            {
+                fail!(
+                    "Found super_class {} for interface; expected java/lang/Object",
+                    super_class
+                );
+            }
+        }
+    }
+    Ok(())
+}
+
+// Annotation interfaces must extend java.lang.annotation.Annotation, per section 9.6 of
+// the JLS. The ANNOTATION flag requiring INTERFACE is checked with the other access flags.
+fn validate_annotation_interface(
+    access_flags: ClassAccessFlags,
+    interfaces: &[Cow<str>],
+) -> Result<(), ParseError> {
+    if access_flags.contains(ClassAccessFlags::ANNOTATION)
+        && !interfaces
+            .iter()
+            .any(|i| i == "java/lang/annotation/Annotation")
+    {
+        fail!("Found annotation interface without java/lang/annotation/Annotation superinterface");
+    }
+    Ok(())
+}
+
+/// The portion of a class file preceding the fields, methods and attributes.
+#[derive(Clone, Debug)]
+pub struct ClassHeader<'a> {
+    pub major_version: u16,
+    pub minor_version: u16,
+    constant_pool: Vec<CafeRc<ConstantPoolEntry<'a>>>,
+    pub access_flags: ClassAccessFlags,
+    pub this_class: Cow<'a, str>,
+    pub super_class: Option<Cow<'a, str>>,
+    pub interfaces: Vec<Cow<'a, str>>,
+    undefined_access_flags: u16,
+}
+
+/// An InvokeDynamic or Dynamic constant pool entry together with its bootstrap method, as
+/// returned by [`ClassFile::invoke_dynamic_sites`].
+#[derive(Clone, Debug)]
+pub struct IndySite<'b, 'a> {
+    /// The index of the entry in the constant pool.
+    pub index: u16,
+    /// Either [`ConstantKind::InvokeDynamic`] for an invokedynamic call site or
+    /// [`ConstantKind::Dynamic`] for a dynamically-computed constant.
+    pub kind: ConstantKind,
+    pub name_and_type: NameAndType<'a>,
+    /// The bootstrap method, along with its static arguments.
+    pub bootstrap_method: &'b BootstrapMethodEntry<'a>,
+}
+
+impl<'a> ClassHeader<'a> {
+    #[must_use]
+    pub fn constantpool_iter(&self) -> ConstantPoolIter<'_, 'a> {
+        ConstantPoolIter::new(&self.constant_pool)
+    }
+}
+
+fn read_class_header<'a>(
+    raw_bytes: &'a [u8],
+    ix: &mut usize,
+    opts: &ParseOptions,
+) -> Result<ClassHeader<'a>, ParseError> {
+    let magic = read_u4(raw_bytes, ix).map_err(|e| err!(e, "magic number"))?;
+    if magic != 0xCAFE_BABE {
+        return Err(err!(
+            "Not a class file; found magic number {:#010X} instead of 0xCAFEBABE",
+            magic
+        )
+        .with_magic(magic));
+    }
+    let minor_version = read_u2(raw_bytes, ix)?;
+    let major_version = read_u2(raw_bytes, ix)?;
+    // Section 4.1 restricts the minor version of newer class files to 0, or to 65535 for
+    // classes that use preview features
+    if opts.strict
+        && major_version >= 56
+        && minor_version != 0
+        && minor_version != PREVIEW_MINOR_VERSION
+    {
+        fail!(
+            "Invalid minor version {} for major version {}; expected 0 or {}",
+            minor_version,
+            major_version,
+            PREVIEW_MINOR_VERSION
+        );
+    }
+    #[cfg(feature = "parallel")]
+    let constant_pool = if opts.defer_resolution && !opts.strict {
+        read_constant_pool_deferred(raw_bytes, ix, major_version)?
+    } else if opts.parallel_resolution {
+        read_constant_pool_parallel(raw_bytes, ix, major_version)?
+    } else {
+        read_constant_pool(raw_bytes, ix, major_version)?
+    };
+    #[cfg(not(feature = "parallel"))]
+    let constant_pool = if opts.defer_resolution && !opts.strict {
+        read_constant_pool_deferred(raw_bytes, ix, major_version)?
+    } else {
+        read_constant_pool(raw_bytes, ix, major_version)?
+    };
+    if opts.strict {
+        validate_constant_pool_strict(&constant_pool)?;
+    }
+
+    let raw_access_flags = read_u2(raw_bytes, ix)?;
+    let access_flags = ClassAccessFlags::from_bits_truncate(raw_access_flags);
+    let is_module = access_flags.contains(ClassAccessFlags::MODULE);
+    if is_module {
+        if major_version < 53 {
+            fail!(
+                "Found invalid MODULE class access flag on class file of major version {}",
+                major_version
+            );
+        }
+        if access_flags != ClassAccessFlags::MODULE {
+            fail!(
+                "Found invalid class access flags {:?}; no other flags should be set with MODULE",
+                access_flags
+            );
+        }
+    }
+    if opts.strict {
+        validate_class_access_flags(access_flags)?;
+    }
+    let this_class =
+        read_cp_classinfo(raw_bytes, ix, &constant_pool).map_err(|e| err!(e, "this_class"))?;
+    let super_class =
+        read_cp_classinfo_opt(raw_bytes, ix, &constant_pool).map_err(|e| err!(e, "super_class"))?;
+    let interfaces = read_interfaces(raw_bytes, ix, &constant_pool)?;
+    validate_class_hierarchy(access_flags, &this_class, &super_class)?;
+    if opts.strict {
+        validate_annotation_interface(access_flags, &interfaces)?;
+    }
+    Ok(ClassHeader {
+        major_version,
+        minor_version,
+        constant_pool,
+        access_flags,
+        this_class,
+        super_class,
+        interfaces,
+        undefined_access_flags: raw_access_flags & !ClassAccessFlags::all().bits(),
+    })
+}
+
+/// Parses only the header of the class file, i.e. everything up to and including the
+/// interfaces. This is much faster than parsing the whole class file for cases where
+/// only the class name, superclass or interfaces are needed. Note that since the rest
+/// of the class file is not read, this does not detect errors that occur after the header.
+#[allow(clippy::needless_lifetimes)]
+pub fn parse_header_only<'a>(raw_bytes: &'a [u8]) -> Result<ClassHeader<'a>, ParseError> {
+    let mut ix = 0;
+    read_class_header(raw_bytes, &mut ix, &ParseOptions::default())
+        .map_err(|e| add_class_version(e, raw_bytes))
+}
+
+/// Reads a class file from the given reader only as far as is needed to parse its header,
+/// and passes the class name and superclass name to the callback. This is intended for
+/// tools that scan many class files (e.g. all the entries of a jar) to build up a picture
+/// of the class hierarchy without fully parsing each class.
+pub fn scan_class_names<R: Read, F: FnMut(&str, Option<&str>)>(
+    mut reader: R,
+    callback: &mut F,
+) -> Result<(), ParseError> {
+    let mut bytes = Vec::new();
+    let mut limit = 4096;
+    loop {
+        let wanted = limit - bytes.len();
+        let read = reader
+            .by_ref()
+            .take(wanted as u64)
+            .read_to_end(&mut bytes)
+            .map_err(|e| err!("Error reading class file: {}", e))?;
+        match parse_header_only(&bytes) {
+            Ok(header) => {
+                callback(&header.this_class, header.super_class.as_deref());
+                return Ok(());
+            }
+            // Only an error caused by the end of the data read so far can be fixed by
+            // reading more, and then only if the reader isn't exhausted
+            Err(e) if read == wanted && e.is_end_of_stream() => limit *= 2,
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+pub fn parse_class_with_options<'a>(
+    raw_bytes: &'a [u8],
+    opts: &ParseOptions,
+) -> Result<ClassFile<'a>, ParseError> {
+    read_class(raw_bytes, opts, None)
+}
+
+/// Parses the class file like [`parse_class_with_options`], and also returns statistics
+/// about the class and how long parsing took, for profiling.
+pub fn parse_class_with_stats<'a>(
+    raw_bytes: &'a [u8],
+    opts: &ParseOptions,
+) -> Result<(ClassFile<'a>, ParseStats), ParseError> {
+    let start = Instant::now();
+    let mut stats = ParseStats::default();
+    let class = read_class(raw_bytes, opts, Some(&mut stats))?;
+    stats.total_time = start.elapsed();
+    stats.record_class(&class);
+    Ok((class, stats))
+}
+
+/// Parses a class file that starts at the given index of a larger buffer, such as a
+/// custom container format, without copying it out first. Any bytes after the class
+/// file are ignored. Returns the class along with the index just past its end. Note
+/// that indices in error messages are relative to the start of the class file.
+pub fn parse_at<'a>(
+    raw_bytes: &'a [u8],
+    start: usize,
+) -> Result<(ClassFile<'a>, usize), ParseError> {
+    if start > raw_bytes.len() {
+        fail!(
+            "Start index {} is past the end of the {} byte buffer",
+            start,
+            raw_bytes.len()
+        );
+    }
+    let mut opts = ParseOptions::default();
+    opts.allow_trailing_bytes(true);
+    let class = read_class(&raw_bytes[start..], &opts, None)?;
+    let end = start + class.byte_length;
+    Ok((class, end))
+}
+
+// Records the version on an error from parsing a class file that got as far as having
+// one, so that it is available even when later validation fails.
+fn add_class_version(e: ParseError, raw_bytes: &[u8]) -> ParseError {
+    match raw_bytes {
+        [0xCA, 0xFE, 0xBA, 0xBE, minor_hi, minor_lo, major_hi, major_lo, ..] => e
+            .with_class_version(
+                u16::from_be_bytes([*major_hi, *major_lo]),
+                u16::from_be_bytes([*minor_hi, *minor_lo]),
+            ),
+        _ => e,
+    }
+}
+
+fn read_class<'a>(
+    raw_bytes: &'a [u8],
+    opts: &ParseOptions,
+    stats: Option<&mut ParseStats>,
+) -> Result<ClassFile<'a>, ParseError> {
+    let (class, pool_references) =
+        record_cp_references(|| read_class_contents(raw_bytes, opts, stats));
+    let mut class = class.map_err(|e| add_class_version(e, raw_bytes))?;
+    class.pool_references = pool_references;
+    Ok(class)
+}
+
+fn read_class_contents<'a>(
+    raw_bytes: &'a [u8],
+    opts: &ParseOptions,
+    stats: Option<&mut ParseStats>,
+) -> Result<ClassFile<'a>, ParseError> {
+    // Reading the clock is only worth it when the time is reported
+    let start = stats.as_ref().map(|_| Instant::now());
+    let mut ix = 0;
+    let ClassHeader {
+        major_version,
+        minor_version,
+        constant_pool,
+        access_flags,
+        this_class,
+        super_class,
+        interfaces,
+        undefined_access_flags,
+    } = read_class_header(raw_bytes, &mut ix, opts)?;
+    if let (Some(stats), Some(start)) = (stats, start) {
+        stats.header_time = start.elapsed();
+    }
+    let is_module = access_flags.contains(ClassAccessFlags::MODULE);
+    let is_interface = access_flags.contains(ClassAccessFlags::INTERFACE);
+    let fields = read_fields(
+        raw_bytes,
+        &mut ix,
+        &constant_pool,
+        opts,
+        is_interface,
+        major_version,
+    )?;
+    let methods = read_methods(
+        raw_bytes,
+        &mut ix,
+        &constant_pool,
+        opts,
+        is_interface,
+        major_version,
+    )?;
+    let attributes =
+        read_attributes(raw_bytes, &mut ix, &constant_pool, opts).map_err(|e| err!(e, "class"))?;
+    validate_nest_attributes(&attributes).map_err(|e| err!(e, "class"))?;
+    validate_inner_classes(&attributes, major_version).map_err(|e| err!(e, "class"))?;
+    if opts.strict {
+        validate_signature(
+            &attributes,
+            parse_class_signature_with_depth,
+            opts.max_nesting_depth,
+        )
+        .map_err(|e| err!(e, "class"))?;
+        validate_synthetic(access_flags.is_synthetic(), &attributes, major_version)
+            .map_err(|e| err!(e, "class"))?;
+    }
+    // Section 4.8 "Format Checking" says the class file must not have extra bytes at the end
+    if ix != raw_bytes.len() && !opts.allow_trailing_bytes {
+        fail!("Extra bytes found at index {} after reading class file", ix);
+    }
+
+    if is_module {
+        if let Some(super_class) = super_class {
+            fail!(
+                "Found non-empty super_class {}; expected none for module",
+                super_class
+            );
+        }
+        if !interfaces.is_empty() {
+            fail!(
+                "Found {} interfaces; expected 0 for module",
+                interfaces.len()
+            );
+        }
+        if !fields.is_empty() {
+            fail!("Found {} fields; expected 0 for module", fields.len());
+        }
+        if !methods.is_empty() {
+            fail!("Found {} methods; expected 0 for module", methods.len());
+        }
+    }
+    if is_module && !opts.skip_attribute_bodies {
+        let modules: Vec<&ModuleData> = attributes
+            .iter()
+            .filter_map(|attr| match &attr.data {
+                AttributeData::Module(module) => Some(module),
+                _ => None,
+            })
+            .collect();
+        if modules.len() != 1 {
+            fail!(
+                "Found {} Module attributes; expected 1 for module",
+                modules.len()
+            );
+        }
+        validate_module(modules[0], major_version).map_err(|e| err!(e, "Module attribute"))?;
+    }
+
+    if !opts.skip_attribute_bodies {
+        validate_bootstrap_methods(&constant_pool, &attributes)?;
+    }
 
     let class_file = ClassFile {
         major_version,
@@ -449,6 +2169,767 @@ pub fn parse_class_with_options<'a>(
         fields,
         methods,
         attributes,
+        byte_length: ix,
+        undefined_access_flags,
+        max_nesting_depth: opts.max_nesting_depth,
+        pool_references: Vec::new(),
     };
     Ok(class_file)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn nest_host(host: &'static str) -> AttributeInfo<'static> {
+        AttributeInfo {
+            name: Cow::Borrowed("NestHost"),
+            data: AttributeData::NestHost(Cow::Borrowed(host)),
+        }
+    }
+
+    fn nest_members(members: &[&'static str]) -> AttributeInfo<'static> {
+        AttributeInfo {
+            name: Cow::Borrowed("NestMembers"),
+            data: AttributeData::NestMembers(members.iter().map(|m| Cow::Borrowed(*m)).collect()),
+        }
+    }
+
+    fn class_with_attributes(attributes: Vec<AttributeInfo<'static>>) -> ClassFile<'static> {
+        ClassFile {
+            major_version: 61,
+            minor_version: 0,
+            constant_pool: Vec::new(),
+            access_flags: ClassAccessFlags::PUBLIC | ClassAccessFlags::SUPER,
+            this_class: Cow::Borrowed("a/Outer"),
+            super_class: Some(Cow::Borrowed("java/lang/Object")),
+            interfaces: Vec::new(),
+            fields: Vec::new(),
+            methods: Vec::new(),
+            attributes,
+            byte_length: 0,
+            undefined_access_flags: 0,
+            max_nesting_depth: DEFAULT_MAX_NESTING_DEPTH,
+            pool_references: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_bootstrap_method() {
+        use crate::attributes::BootstrapMethodEntry;
+        use crate::constant_pool::{InvokeDynamic, MemberKind, MethodHandle, ReferenceKind};
+
+        let name_and_type = |name, descriptor| NameAndType {
+            name: Cow::Borrowed(name),
+            descriptor: Cow::Borrowed(descriptor),
+        };
+        let entry = BootstrapMethodEntry {
+            method: MethodHandle {
+                kind: ReferenceKind::InvokeStatic,
+                class_name: Cow::Borrowed("java/lang/invoke/LambdaMetafactory"),
+                member_kind: MemberKind::Method,
+                member_ref: name_and_type("metafactory", "()Ljava/lang/invoke/CallSite;"),
+            },
+            arguments: Vec::new(),
+        };
+        let class = class_with_attributes(vec![AttributeInfo {
+            name: Cow::Borrowed("BootstrapMethods"),
+            data: AttributeData::BootstrapMethods(vec![entry]),
+        }]);
+        let indy = ConstantPoolItem::InvokeDynamic(InvokeDynamic {
+            attr_index: 0,
+            name_and_type: name_and_type("run", "()Ljava/lang/Runnable;"),
+        });
+        let index = indy.invoke_dynamic_bootstrap_index().unwrap();
+        assert_eq!(
+            class
+                .bootstrap_method(index)
+                .unwrap()
+                .method
+                .member_ref
+                .name,
+            "metafactory"
+        );
+        assert!(class.bootstrap_method(1).is_none());
+        let (name, descriptor) = indy.invoke_dynamic_name_and_type().unwrap();
+        assert_eq!(name, "run");
+        assert_eq!(descriptor, "()Ljava/lang/Runnable;");
+
+        let class_info = ConstantPoolItem::ClassInfo(Cow::Borrowed("a/B"));
+        assert!(class_info.invoke_dynamic_bootstrap_index().is_none());
+        assert!(class_info.invoke_dynamic_name_and_type().is_none());
+    }
+
+    #[test]
+    fn test_invoke_dynamic_sites() {
+        use crate::attributes::BootstrapMethodEntry;
+        use crate::constant_pool::{MemberKind, MethodHandle, ReferenceKind};
+
+        let entry = BootstrapMethodEntry {
+            method: MethodHandle {
+                kind: ReferenceKind::InvokeStatic,
+                class_name: Cow::Borrowed("java/lang/invoke/LambdaMetafactory"),
+                member_kind: MemberKind::Method,
+                member_ref: NameAndType {
+                    name: Cow::Borrowed("metafactory"),
+                    descriptor: Cow::Borrowed("()Ljava/lang/invoke/CallSite;"),
+                },
+            },
+            arguments: Vec::new(),
+        };
+        let mut class = class_with_attributes(vec![AttributeInfo {
+            name: Cow::Borrowed("BootstrapMethods"),
+            data: AttributeData::BootstrapMethods(vec![entry]),
+        }]);
+        assert_eq!(class.invoke_dynamic_sites().count(), 0);
+        // Utf8 "run", Utf8 "()V", NameAndType run:()V and InvokeDynamic #0:run:()V
+        const POOL: &[u8] = &[
+            0, 5, 1, 0, 3, b'r', b'u', b'n', 1, 0, 3, b'(', b')', b'V', 12, 0, 1, 0, 2, 18, 0, 0,
+            0, 3,
+        ];
+        class.constant_pool = read_constant_pool(POOL, &mut 0, 61).unwrap();
+        let sites: Vec<IndySite> = class.invoke_dynamic_sites().collect();
+        assert_eq!(sites.len(), 1);
+        assert_eq!(sites[0].index, 4);
+        assert_eq!(sites[0].kind, ConstantKind::InvokeDynamic);
+        assert_eq!(sites[0].name_and_type.name, "run");
+        assert_eq!(sites[0].name_and_type.descriptor, "()V");
+        assert_eq!(
+            sites[0].bootstrap_method.method.member_ref.name,
+            "metafactory"
+        );
+        // As left by skip_attribute_bodies
+        class.attributes[0].data = AttributeData::Other(Cow::Borrowed(&[0, 1, 0, 5, 0, 0]));
+        assert_eq!(class.invoke_dynamic_sites().count(), 0);
+    }
+
+    #[test]
+    fn test_loadable_dynamic_constant() {
+        let mut class = class_with_attributes(Vec::new());
+        // Utf8 "x", "J" and "I", NameAndType x:J and x:I, and Dynamic #0 of each
+        const POOL: &[u8] = &[
+            0, 8, 1, 0, 1, b'x', 1, 0, 1, b'J', 1, 0, 1, b'I', 12, 0, 1, 0, 2, 12, 0, 1, 0, 3, 17,
+            0, 0, 0, 4, 17, 0, 0, 0, 5,
+        ];
+        class.constant_pool = read_constant_pool(POOL, &mut 0, 61).unwrap();
+        // A long Dynamic constant is loaded by ldc2_w
+        assert!(class.loadable_constant(6).is_none());
+        assert!(matches!(
+            class.loadable_constant(7),
+            Some(Loadable::Dynamic(dynamic)) if dynamic.name_and_type.descriptor == "I"
+        ));
+        class.major_version = 54;
+        assert!(class.loadable_constant(7).is_none());
+    }
+
+    #[test]
+    fn test_has_non_standard_names() {
+        let class_with_pool = |pool_bytes: &'static [u8]| {
+            let mut class = class_with_attributes(Vec::new());
+            class.constant_pool = read_constant_pool(pool_bytes, &mut 0, 61).unwrap();
+            class
+        };
+        // Class a/B and NameAndType <init>:()V
+        let class = class_with_pool(&[
+            0, 6, 1, 0, 3, b'a', b'/', b'B', 7, 0, 1, 1, 0, 6, b'<', b'i', b'n', b'i', b't', b'>',
+            1, 0, 3, b'(', b')', b'V', 12, 0, 3, 0, 4,
+        ]);
+        assert!(!class.has_non_standard_names());
+        // Class [La/B; and [I
+        let class = class_with_pool(&[
+            0, 5, 1, 0, 6, b'[', b'L', b'a', b'/', b'B', b';', 7, 0, 1, 1, 0, 2, b'[', b'I', 7, 0,
+            3,
+        ]);
+        assert!(!class.has_non_standard_names());
+        // Class a/1B
+        let class = class_with_pool(&[0, 3, 1, 0, 4, b'a', b'/', b'1', b'B', 7, 0, 1]);
+        assert!(class.has_non_standard_names());
+        // NameAndType do-it:()V
+        let class = class_with_pool(&[
+            0, 4, 1, 0, 5, b'd', b'o', b'-', b'i', b't', 1, 0, 3, b'(', b')', b'V', 12, 0, 1, 0, 2,
+        ]);
+        assert!(class.has_non_standard_names());
+        // Class x/y/package-info and module-info
+        let class = class_with_pool(&[
+            0, 5, 1, 0, 16, b'x', b'/', b'y', b'/', b'p', b'a', b'c', b'k', b'a', b'g', b'e', b'-',
+            b'i', b'n', b'f', b'o', 7, 0, 1, 1, 0, 11, b'm', b'o', b'd', b'u', b'l', b'e', b'-',
+            b'i', b'n', b'f', b'o', 7, 0, 3,
+        ]);
+        assert!(!class.has_non_standard_names());
+        // Class x/module-info
+        let class = class_with_pool(&[
+            0, 3, 1, 0, 13, b'x', b'/', b'm', b'o', b'd', b'u', b'l', b'e', b'-', b'i', b'n', b'f',
+            b'o', 7, 0, 1,
+        ]);
+        assert!(class.has_non_standard_names());
+    }
+
+    #[test]
+    fn test_is_functional_interface() {
+        let method = |flags, name, descriptor| MethodInfo {
+            access_flags: flags,
+            name: Cow::Borrowed(name),
+            descriptor: parse_method_descriptor(&Cow::Borrowed(descriptor), 0).unwrap(),
+            attributes: Vec::new(),
+            max_nesting_depth: DEFAULT_MAX_NESTING_DEPTH,
+        };
+        let abstract_flags = MethodAccessFlags::PUBLIC | MethodAccessFlags::ABSTRACT;
+        let mut class = class_with_attributes(Vec::new());
+        class.access_flags = ClassAccessFlags::INTERFACE | ClassAccessFlags::ABSTRACT;
+        class.methods = vec![
+            method(
+                abstract_flags,
+                "compare",
+                "(Ljava/lang/Object;Ljava/lang/Object;)I",
+            ),
+            method(abstract_flags, "equals", "(Ljava/lang/Object;)Z"),
+            method(
+                MethodAccessFlags::PUBLIC,
+                "reversed",
+                "()Ljava/util/Comparator;",
+            ),
+            method(
+                MethodAccessFlags::PUBLIC | MethodAccessFlags::STATIC,
+                "naturalOrder",
+                "()Ljava/util/Comparator;",
+            ),
+        ];
+        assert!(class.is_functional_interface());
+
+        class.methods.push(method(abstract_flags, "equals", "(I)Z"));
+        assert!(!class.is_functional_interface());
+        class.methods.pop();
+        class.methods.remove(0);
+        assert!(!class.is_functional_interface());
+
+        let mut class = class_with_attributes(Vec::new());
+        class.methods = vec![method(abstract_flags, "run", "()V")];
+        class.access_flags = ClassAccessFlags::ABSTRACT;
+        assert!(!class.is_functional_interface());
+    }
+
+    #[test]
+    fn test_has_acc_super() {
+        let mut class = class_with_attributes(Vec::new());
+        class.access_flags = ClassAccessFlags::PUBLIC;
+        assert!(!class.has_acc_super());
+        class.access_flags |= ClassAccessFlags::SUPER;
+        assert!(class.has_acc_super());
+        class.major_version = 51;
+        assert!(class.has_acc_super());
+    }
+
+    #[test]
+    fn test_source_debug_extension() {
+        assert_eq!(
+            class_with_attributes(Vec::new()).source_debug_extension(),
+            None
+        );
+        let class = class_with_attributes(vec![AttributeInfo {
+            name: Cow::Borrowed("SourceDebugExtension"),
+            data: AttributeData::SourceDebugExtension(Cow::Borrowed("SMAP\nOuter.java\n")),
+        }]);
+        assert_eq!(
+            class.source_debug_extension().unwrap(),
+            "SMAP\nOuter.java\n"
+        );
+
+        let class = class_with_attributes(vec![AttributeInfo {
+            name: Cow::Borrowed("SourceDebugExtension"),
+            data: AttributeData::SourceDebugExtensionBytes(Cow::Borrowed(&[b'S', 0xff])),
+        }]);
+        assert_eq!(class.source_debug_extension().unwrap(), "S\u{fffd}");
+    }
+
+    #[test]
+    fn test_source_file() {
+        assert_eq!(class_with_attributes(Vec::new()).source_file(), None);
+        let class = class_with_attributes(vec![AttributeInfo {
+            name: Cow::Borrowed("SourceFile"),
+            data: AttributeData::SourceFile(Cow::Borrowed("Outer.java")),
+        }]);
+        assert_eq!(class.source_file().map(|s| s.as_ref()), Some("Outer.java"));
+    }
+
+    #[test]
+    fn test_is_module() {
+        let mut class = class_with_attributes(Vec::new());
+        assert!(!class.is_module());
+        class.access_flags = ClassAccessFlags::MODULE;
+        assert!(class.is_module());
+    }
+
+    #[test]
+    fn test_module_attributes() {
+        let class = class_with_attributes(Vec::new());
+        assert!(class.module_packages().is_empty());
+        assert!(class.module_main_class().is_none());
+        let class = class_with_attributes(vec![
+            AttributeInfo {
+                name: Cow::Borrowed("ModulePackages"),
+                data: AttributeData::ModulePackages(vec![
+                    Cow::Borrowed("com/example"),
+                    Cow::Borrowed("com/example/impl"),
+                ]),
+            },
+            AttributeInfo {
+                name: Cow::Borrowed("ModuleMainClass"),
+                data: AttributeData::ModuleMainClass(Cow::Borrowed("com/example/Main")),
+            },
+        ]);
+        assert_eq!(class.module_packages(), ["com/example", "com/example/impl"]);
+        assert_eq!(class.module_main_class().unwrap(), "com/example/Main");
+    }
+
+    #[test]
+    fn test_marker_attributes() {
+        let class = class_with_attributes(Vec::new());
+        assert!(!class.is_deprecated());
+        assert!(!class.has_synthetic_attribute());
+        let class = class_with_attributes(vec![
+            AttributeInfo {
+                name: Cow::Borrowed("Deprecated"),
+                data: AttributeData::Deprecated,
+            },
+            AttributeInfo {
+                name: Cow::Borrowed("Synthetic"),
+                data: AttributeData::Synthetic,
+            },
+        ]);
+        assert!(class.is_deprecated());
+        assert!(class.has_synthetic_attribute());
+        assert!(!class.access_flags.is_synthetic());
+    }
+
+    #[test]
+    fn test_attribute_lookup() {
+        let class = class_with_attributes(vec![
+            AttributeInfo {
+                name: Cow::Borrowed("Custom"),
+                data: AttributeData::Other(Cow::Borrowed(&[1])),
+            },
+            AttributeInfo {
+                name: Cow::Borrowed("Deprecated"),
+                data: AttributeData::Deprecated,
+            },
+            AttributeInfo {
+                name: Cow::Borrowed("Custom"),
+                data: AttributeData::Other(Cow::Borrowed(&[2])),
+            },
+        ]);
+        assert!(matches!(
+            class.attribute("Custom").map(|attr| &attr.data),
+            Some(AttributeData::Other(data)) if data.as_ref() == [1]
+        ));
+        assert!(class.attribute("Missing").is_none());
+        assert_eq!(class.attributes_named("Custom").count(), 2);
+        assert_eq!(class.attributes_named("Deprecated").count(), 1);
+    }
+
+    #[test]
+    fn test_permitted_subclasses() {
+        let class = class_with_attributes(Vec::new());
+        assert!(!class.is_sealed());
+        assert_eq!(class.permitted_subclasses(), None);
+        let class = class_with_attributes(vec![AttributeInfo {
+            name: Cow::Borrowed("PermittedSubclasses"),
+            data: AttributeData::PermittedSubclasses(Vec::new()),
+        }]);
+        assert!(class.is_sealed());
+        assert_eq!(class.permitted_subclasses(), Some(&[][..]));
+        assert!(!class.permits("a/Outer$Inner"));
+        let class = class_with_attributes(vec![AttributeInfo {
+            name: Cow::Borrowed("PermittedSubclasses"),
+            data: AttributeData::PermittedSubclasses(vec![Cow::Borrowed("a/Outer$Inner")]),
+        }]);
+        assert!(class.permits("a/Outer$Inner"));
+        assert!(!class.permits("a/Outer$Other"));
+    }
+
+    #[test]
+    fn test_extends_implements() {
+        let mut class = class_with_attributes(Vec::new());
+        assert!(class.extends("java/lang/Object"));
+        assert!(!class.extends("a/Base"));
+        assert!(!class.implements("java/lang/Runnable"));
+        class.interfaces.push(Cow::Borrowed("java/lang/Runnable"));
+        assert!(class.implements("java/lang/Runnable"));
+        assert!(!class.implements("java/lang/Object"));
+        class.super_class = None;
+        assert!(!class.extends("java/lang/Object"));
+    }
+
+    #[test]
+    fn test_inner_classes() {
+        let entry = |outer: Option<&'static str>, name: Option<&'static str>| InnerClassEntry {
+            inner_class_info: Cow::Borrowed("a/Outer$1"),
+            outer_class_info: outer.map(Cow::Borrowed),
+            inner_name: name.map(Cow::Borrowed),
+            access_flags: InnerClassAccessFlags::STATIC,
+        };
+        let attributes = |entries| {
+            vec![AttributeInfo {
+                name: Cow::Borrowed("InnerClasses"),
+                data: AttributeData::InnerClasses(entries),
+            }]
+        };
+        assert!(validate_inner_classes(&attributes(vec![entry(None, None)]), 61).is_ok());
+        assert!(validate_inner_classes(
+            &attributes(vec![entry(Some("a/Outer"), Some("Inner"))]),
+            61
+        )
+        .is_ok());
+        assert!(
+            validate_inner_classes(&attributes(vec![entry(Some("a/Outer"), None)]), 50).is_ok()
+        );
+        assert_eq!(
+            validate_inner_classes(&attributes(vec![entry(Some("a/Outer"), None)]), 51)
+                .unwrap_err()
+                .to_string(),
+            "Found outer class info without inner name for inner class 0 of InnerClasses attribute 0"
+        );
+
+        let class = class_with_attributes(attributes(vec![entry(None, None)]));
+        assert_eq!(class.inner_classes().len(), 1);
+        assert!(class.inner_classes()[0].access_flags.is_static());
+    }
+
+    #[test]
+    fn test_method_exceptions() {
+        let mut method = MethodInfo {
+            access_flags: MethodAccessFlags::PUBLIC,
+            name: Cow::Borrowed("run"),
+            descriptor: parse_method_descriptor(&Cow::Borrowed("()V"), 0).unwrap(),
+            attributes: Vec::new(),
+            max_nesting_depth: DEFAULT_MAX_NESTING_DEPTH,
+        };
+        assert!(method.exceptions().is_empty());
+        method.attributes.push(AttributeInfo {
+            name: Cow::Borrowed("Exceptions"),
+            data: AttributeData::Exceptions(vec![Cow::Borrowed("java/io/IOException")]),
+        });
+        assert_eq!(method.exceptions(), &["java/io/IOException"]);
+    }
+
+    #[test]
+    fn test_annotation_default() {
+        let mut method = MethodInfo {
+            access_flags: MethodAccessFlags::PUBLIC | MethodAccessFlags::ABSTRACT,
+            name: Cow::Borrowed("value"),
+            descriptor: parse_method_descriptor(&Cow::Borrowed("()I"), 0).unwrap(),
+            attributes: Vec::new(),
+            max_nesting_depth: DEFAULT_MAX_NESTING_DEPTH,
+        };
+        assert!(method.annotation_default().is_none());
+        method.attributes.push(AttributeInfo {
+            name: Cow::Borrowed("AnnotationDefault"),
+            data: AttributeData::AnnotationDefault(AnnotationElementValue::IntConstant(3)),
+        });
+        assert!(matches!(
+            method.annotation_default(),
+            Some(AnnotationElementValue::IntConstant(3))
+        ));
+    }
+
+    #[test]
+    fn test_class_hierarchy() {
+        let class = ClassAccessFlags::PUBLIC | ClassAccessFlags::SUPER;
+        let interface = ClassAccessFlags::INTERFACE | ClassAccessFlags::ABSTRACT;
+        let object = Some(Cow::Borrowed("java/lang/Object"));
+        let other = Some(Cow::Borrowed("a/Base"));
+        assert!(validate_class_hierarchy(class, "java/lang/Object", &None).is_ok());
+        assert!(validate_class_hierarchy(ClassAccessFlags::MODULE, "module-info", &None).is_ok());
+        assert_eq!(
+            validate_class_hierarchy(ClassAccessFlags::MODULE, "a/Outer", &None)
+                .unwrap_err()
+                .to_string(),
+            "Found this_class a/Outer for module; expected module-info"
+        );
+        assert!(validate_class_hierarchy(class, "a/Outer", &None).is_err());
+        assert!(validate_class_hierarchy(class, "a/Outer", &other).is_ok());
+        assert!(validate_class_hierarchy(class, "[La/Outer;", &object).is_err());
+        assert!(
+            validate_class_hierarchy(class, "a/Outer", &Some(Cow::Borrowed("[La/Base;"))).is_err()
+        );
+        assert!(validate_class_hierarchy(interface, "a/Iface", &object).is_ok());
+        assert_eq!(
+            validate_class_hierarchy(interface, "a/Iface", &other)
+                .unwrap_err()
+                .to_string(),
+            "Found super_class a/Base for interface; expected java/lang/Object"
+        );
+    }
+
+    #[test]
+    fn test_enum_constants() {
+        let field = |name: &'static str, access_flags, descriptor: &'static str| FieldInfo {
+            access_flags,
+            name: Cow::Borrowed(name),
+            descriptor: parse_field_descriptor(&Cow::Borrowed(descriptor), 0).unwrap(),
+            attributes: Vec::new(),
+            max_nesting_depth: DEFAULT_MAX_NESTING_DEPTH,
+        };
+        let constant = FieldAccessFlags::PUBLIC
+            | FieldAccessFlags::STATIC
+            | FieldAccessFlags::FINAL
+            | FieldAccessFlags::ENUM;
+        let mut class = class_with_attributes(Vec::new());
+        class.fields = vec![
+            field("RED", constant, "La/Outer;"),
+            field("count", FieldAccessFlags::PRIVATE, "I"),
+            field("GREEN", constant, "La/Outer;"),
+            field("$VALUES", FieldAccessFlags::STATIC, "[La/Outer;"),
+            field("OTHER", FieldAccessFlags::STATIC, "La/Outer;"),
+        ];
+        assert!(!class.is_enum());
+        assert!(class.enum_constants().is_empty());
+        class.access_flags |= ClassAccessFlags::ENUM;
+        assert!(class.is_enum());
+        assert_eq!(class.enum_constants(), ["RED", "GREEN"]);
+    }
+
+    #[test]
+    fn test_validate_annotation_interface() {
+        let annotation =
+            ClassAccessFlags::INTERFACE | ClassAccessFlags::ABSTRACT | ClassAccessFlags::ANNOTATION;
+        let interface = ClassAccessFlags::INTERFACE | ClassAccessFlags::ABSTRACT;
+        let supers = [Cow::Borrowed("java/lang/annotation/Annotation")];
+        assert!(validate_annotation_interface(annotation, &supers).is_ok());
+        assert!(validate_annotation_interface(interface, &[]).is_ok());
+        assert_eq!(
+            validate_annotation_interface(annotation, &[Cow::Borrowed("a/Iface")])
+                .unwrap_err()
+                .to_string(),
+            "Found annotation interface without java/lang/annotation/Annotation superinterface"
+        );
+        let mut class = class_with_attributes(Vec::new());
+        assert!(!class.is_annotation());
+        class.access_flags = annotation;
+        assert!(class.is_annotation());
+    }
+
+    #[test]
+    fn test_read_interfaces() {
+        let mut pool_bytes = vec![0, 5, 1, 0, 3];
+        pool_bytes.extend_from_slice(b"a/I");
+        pool_bytes.extend_from_slice(&[7, 0, 1, 1, 0, 6]);
+        pool_bytes.extend_from_slice(b"[La/I;");
+        pool_bytes.extend_from_slice(&[7, 0, 3]);
+        let pool = read_constant_pool(&pool_bytes, &mut 0, 61).unwrap();
+        let mut ix = 0;
+        assert_eq!(
+            read_interfaces(&[0, 1, 0, 2], &mut ix, &pool).unwrap(),
+            vec!["a/I"]
+        );
+        let mut ix = 0;
+        assert!(read_interfaces(&[0, 2, 0, 2, 0, 2], &mut ix, &pool).is_err());
+        let mut ix = 0;
+        assert!(read_interfaces(&[0, 1, 0, 4], &mut ix, &pool).is_err());
+    }
+
+    #[test]
+    fn test_read_past_end() {
+        let bytes = [1, 2, 3];
+        assert!(read_u2(&bytes, &mut 2).is_err());
+        let mut ix = usize::MAX;
+        assert!(read_u4(&bytes, &mut ix).is_err());
+        assert!(read_u8(&bytes, &mut 4).is_err());
+        let mut ix = 1;
+        assert_eq!(read_bytes(&bytes, &mut ix, 2, "data").unwrap(), &[2, 3]);
+        assert_eq!(ix, 3);
+        assert!(read_bytes(&bytes, &mut 1, 3, "data").is_err());
+        assert_eq!(
+            read_bytes(&bytes, &mut 1, usize::MAX, "data")
+                .unwrap_err()
+                .unexpected_eof(),
+            Some(UnexpectedEof {
+                needed: usize::MAX,
+                available: 2
+            })
+        );
+    }
+
+    #[test]
+    fn test_read_fields_context() {
+        let pool_bytes = [0, 4, 1, 0, 1, b'x', 1, 0, 1, b'I', 1, 0, 1, b'Q'];
+        let pool = read_constant_pool(&pool_bytes, &mut 0, 61).unwrap();
+        let opts = ParseOptions::default();
+        let mut ix = 0;
+        assert_eq!(
+            read_fields(
+                &[0, 1, 0, 0, 0, 1, 0, 3, 0, 0],
+                &mut ix,
+                &pool,
+                &opts,
+                false,
+                61
+            )
+            .unwrap_err()
+            .to_string(),
+            "Unexpected field type for descriptor of class field 0"
+        );
+        let mut ix = 0;
+        assert_eq!(
+            read_fields(
+                &[0, 2, 0, 0, 0, 1, 0, 2, 0, 0, 0],
+                &mut ix,
+                &pool,
+                &opts,
+                false,
+                61
+            )
+            .unwrap_err()
+            .to_string(),
+            "Unexpected end of stream reading u2 at index 10 for access flags of class field 1"
+        );
+    }
+
+    #[test]
+    fn test_read_fields_signature() {
+        let mut pool_bytes = vec![0, 5, 1, 0, 1, b'x', 1, 0, 1, b'I', 1, 0, 9];
+        pool_bytes.extend_from_slice(b"Signature");
+        pool_bytes.extend_from_slice(&[1, 0, 2, b'L', b'<']);
+        let pool = read_constant_pool(&pool_bytes, &mut 0, 61).unwrap();
+        let field_bytes = [0, 1, 0, 0, 0, 1, 0, 2, 0, 1, 0, 3, 0, 0, 0, 2, 0, 4];
+        let fields = read_fields(
+            &field_bytes,
+            &mut 0,
+            &pool,
+            &ParseOptions::default(),
+            false,
+            61,
+        )
+        .unwrap();
+        assert!(fields[0].signature().is_err());
+        let mut opts = ParseOptions::default();
+        opts.strict(true);
+        assert!(read_fields(&field_bytes, &mut 0, &pool, &opts, false, 61).is_err());
+    }
+
+    #[test]
+    fn test_synthetic() {
+        let synthetic = [AttributeInfo {
+            name: Cow::Borrowed("Synthetic"),
+            data: AttributeData::Synthetic,
+        }];
+        assert!(validate_synthetic(false, &[], 61).is_ok());
+        assert!(validate_synthetic(true, &[], 61).is_ok());
+        assert!(validate_synthetic(true, &synthetic, 61).is_ok());
+        assert_eq!(
+            validate_synthetic(false, &synthetic, 61)
+                .unwrap_err()
+                .to_string(),
+            "Found Synthetic attribute without SYNTHETIC flag"
+        );
+        assert!(validate_synthetic(false, &synthetic, 48).is_ok());
+        assert!(validate_synthetic(true, &synthetic, 48).is_ok());
+        assert_eq!(
+            validate_synthetic(true, &[], 48).unwrap_err().to_string(),
+            "Found SYNTHETIC flag without Synthetic attribute in version 48 class file"
+        );
+
+        let mut class = class_with_attributes(synthetic.to_vec());
+        assert!(!class.access_flags.is_synthetic() && class.is_synthetic());
+        class.attributes.clear();
+        assert!(!class.is_synthetic());
+        class.access_flags |= ClassAccessFlags::SYNTHETIC;
+        assert!(class.is_synthetic());
+    }
+
+    #[test]
+    fn test_nest_attributes() {
+        assert!(validate_nest_attributes(&[]).is_ok());
+        assert!(validate_nest_attributes(&[nest_host("a/Outer")]).is_ok());
+        assert!(validate_nest_attributes(&[nest_members(&["a/Outer$Inner"])]).is_ok());
+        assert_eq!(
+            validate_nest_attributes(&[nest_host("a/Outer"), nest_members(&["a/Outer$Inner"])])
+                .unwrap_err()
+                .to_string(),
+            "Found both NestHost and NestMembers attributes; expected at most one of them"
+        );
+        assert!(validate_nest_attributes(&[nest_host("a/Outer"), nest_host("a/Other")]).is_err());
+    }
+
+    #[test]
+    fn test_class_access_flags() {
+        let flags = ClassAccessFlags::PUBLIC | ClassAccessFlags::SUPER;
+        assert!(flags.is_public() && flags.is_super() && !flags.is_final());
+        assert!(validate_class_access_flags(flags).is_ok());
+        assert!(validate_class_access_flags(ClassAccessFlags::INTERFACE).is_err());
+        assert!(validate_class_access_flags(
+            ClassAccessFlags::INTERFACE | ClassAccessFlags::ABSTRACT | ClassAccessFlags::ANNOTATION
+        )
+        .is_ok());
+        assert!(validate_class_access_flags(
+            ClassAccessFlags::INTERFACE | ClassAccessFlags::ABSTRACT | ClassAccessFlags::FINAL
+        )
+        .is_err());
+        assert!(validate_class_access_flags(ClassAccessFlags::ANNOTATION).is_err());
+        assert!(
+            validate_class_access_flags(ClassAccessFlags::FINAL | ClassAccessFlags::ABSTRACT)
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_field_access_flags() {
+        let constant =
+            FieldAccessFlags::PUBLIC | FieldAccessFlags::STATIC | FieldAccessFlags::FINAL;
+        assert!(validate_field_access_flags(constant, true).is_ok());
+        assert!(validate_field_access_flags(constant | FieldAccessFlags::SYNTHETIC, true).is_ok());
+        assert!(validate_field_access_flags(FieldAccessFlags::PUBLIC, true).is_err());
+        assert!(validate_field_access_flags(FieldAccessFlags::PRIVATE, false).is_ok());
+        assert!(validate_field_access_flags(
+            FieldAccessFlags::PUBLIC | FieldAccessFlags::PRIVATE,
+            false
+        )
+        .is_err());
+        assert!(validate_field_access_flags(
+            FieldAccessFlags::FINAL | FieldAccessFlags::VOLATILE,
+            false
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_method_access_flags() {
+        let bridge = MethodAccessFlags::PUBLIC | MethodAccessFlags::BRIDGE;
+        assert!(bridge.is_bridge() && !bridge.is_synthetic());
+        assert!(validate_method_access_flags(bridge, "foo", false, 61).is_ok());
+        let abs = MethodAccessFlags::PUBLIC | MethodAccessFlags::ABSTRACT;
+        assert!(validate_method_access_flags(abs, "foo", true, 51).is_ok());
+        assert!(
+            validate_method_access_flags(abs | MethodAccessFlags::FINAL, "foo", false, 61).is_err()
+        );
+        assert!(
+            validate_method_access_flags(abs | MethodAccessFlags::STRICT, "foo", false, 52)
+                .is_err()
+        );
+        assert!(
+            validate_method_access_flags(abs | MethodAccessFlags::STRICT, "foo", false, 61).is_ok()
+        );
+        assert!(validate_method_access_flags(MethodAccessFlags::PUBLIC, "foo", true, 51).is_err());
+        assert!(validate_method_access_flags(MethodAccessFlags::PRIVATE, "foo", true, 52).is_ok());
+        assert!(validate_method_access_flags(MethodAccessFlags::STATIC, "foo", true, 52).is_err());
+        assert!(
+            validate_method_access_flags(MethodAccessFlags::STATIC, "<clinit>", false, 51).is_ok()
+        );
+        assert!(
+            validate_method_access_flags(MethodAccessFlags::empty(), "<clinit>", false, 51)
+                .is_err()
+        );
+        assert!(
+            validate_method_access_flags(MethodAccessFlags::empty(), "<clinit>", false, 50).is_ok()
+        );
+        assert!(
+            validate_method_access_flags(MethodAccessFlags::PUBLIC, "<init>", false, 61).is_ok()
+        );
+        assert!(validate_method_access_flags(
+            MethodAccessFlags::PUBLIC | MethodAccessFlags::STATIC,
+            "<init>",
+            false,
+            61
+        )
+        .is_err());
+    }
+}
@@ -1,3 +1,6 @@
+#[cfg(feature = "unicode")]
+use std::borrow::Cow;
+
 pub(crate) fn is_binary_name(name: &str) -> bool {
     for segment in name.split('/') {
         if !is_unqualified_name(segment) {
@@ -48,3 +51,54 @@ pub(crate) fn is_module_name(name: &str) -> bool {
     }
     true
 }
+
+// Approximates Java's Character.isJavaIdentifierStart/Part, which is what javac accepts
+// in source identifiers. Names that are legal in a class file but fail this check are
+// typically produced by obfuscators or by compilers for other languages.
+pub(crate) fn is_java_identifier(name: &str) -> bool {
+    let mut chars = name.chars();
+    match chars.next() {
+        Some(c) if c.is_alphabetic() || c == '_' || c == '$' => (),
+        _ => return false,
+    }
+    chars.all(|c| c.is_alphanumeric() || c == '_' || c == '$')
+}
+
+/// Unicode normalization of names and strings read from a class file. Only available with
+/// the `unicode` feature.
+#[cfg(feature = "unicode")]
+pub trait Utf8Normalize {
+    /// Returns this text in Unicode Normalization Form C, for comparing names from the
+    /// class file against identifiers from sources that normalize differently. The text
+    /// is borrowed as-is if it is already normalized.
+    fn utf8_normalized(&self) -> Cow<'_, str>;
+}
+
+#[cfg(feature = "unicode")]
+impl Utf8Normalize for str {
+    fn utf8_normalized(&self) -> Cow<'_, str> {
+        use unicode_normalization::{is_nfc, UnicodeNormalization};
+
+        if is_nfc(self) {
+            Cow::Borrowed(self)
+        } else {
+            Cow::Owned(self.nfc().collect())
+        }
+    }
+}
+
+#[cfg(all(test, feature = "unicode"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_utf8_normalized() {
+        let composed = "caf\u{e9}/Menu";
+        let decomposed = "cafe\u{301}/Menu";
+        assert_ne!(composed, decomposed);
+        assert!(matches!(composed.utf8_normalized(), Cow::Borrowed(s) if s == composed));
+        assert_eq!(decomposed.utf8_normalized(), composed);
+        let name: Cow<str> = Cow::Borrowed(decomposed);
+        assert_eq!(name.utf8_normalized(), composed);
+    }
+}
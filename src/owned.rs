@@ -0,0 +1,441 @@
+// Conversions from parsed structures that borrow from the class file bytes into
+// equivalent structures with a 'static lifetime.
+
+use std::borrow::Cow;
+
+use crate::attributes::{
+    Annotation, AnnotationElement, AnnotationElementValue, AttributeData, AttributeInfo,
+    BootstrapMethodEntry, CodeData, ExceptionTableEntry, InnerClassEntry, LocalVariableEntry,
+    LocalVariableTypeEntry, MethodParameterEntry, ModuleData, ModuleExportsEntry, ModuleOpensEntry,
+    ModuleProvidesEntry, ModuleRequireEntry, ParameterAnnotation, RecordComponentEntry,
+    StackMapEntry, StackMapFrame, TypeAnnotation, VerificationType,
+};
+use crate::bytecode::{ByteCode, Opcode};
+use crate::constant_pool::{
+    BootstrapArgument, Dynamic, InvokeDynamic, LiteralConstant, Loadable, MemberRef, MethodHandle,
+    NameAndType, ObjectArrayType,
+};
+use crate::descriptors::{
+    ClassName, FieldDescriptor, FieldType, MethodDescriptor, ReturnDescriptor, UnqualifiedSegment,
+};
+use crate::{FieldInfo, MethodInfo};
+
+pub(crate) trait IntoStatic {
+    type Static;
+
+    fn into_static(self) -> Self::Static;
+}
+
+impl<'a> IntoStatic for Cow<'a, str> {
+    type Static = Cow<'static, str>;
+
+    fn into_static(self) -> Self::Static {
+        Cow::Owned(self.into_owned())
+    }
+}
+
+impl<'a> IntoStatic for Cow<'a, [u8]> {
+    type Static = Cow<'static, [u8]>;
+
+    fn into_static(self) -> Self::Static {
+        Cow::Owned(self.into_owned())
+    }
+}
+
+impl<T: IntoStatic> IntoStatic for Option<T> {
+    type Static = Option<T::Static>;
+
+    fn into_static(self) -> Self::Static {
+        self.map(IntoStatic::into_static)
+    }
+}
+
+impl<T: IntoStatic> IntoStatic for Vec<T> {
+    type Static = Vec<T::Static>;
+
+    fn into_static(self) -> Self::Static {
+        self.into_iter().map(IntoStatic::into_static).collect()
+    }
+}
+
+// Implements IntoStatic for a struct by converting each of the listed fields and
+// moving the remaining ones (which must not borrow anything) across unchanged.
+macro_rules! struct_into_static {
+    ($name:ident { $($field:ident),* } copy { $($copied:ident),* }) => {
+        impl<'a> IntoStatic for $name<'a> {
+            type Static = $name<'static>;
+
+            fn into_static(self) -> Self::Static {
+                $name {
+                    $($field: self.$field.into_static(),)*
+                    $($copied: self.$copied,)*
+                }
+            }
+        }
+    };
+}
+
+struct_into_static!(UnqualifiedSegment { name } copy {});
+struct_into_static!(ClassName { segments } copy {});
+struct_into_static!(FieldDescriptor { field_type } copy { dimensions });
+struct_into_static!(MethodDescriptor { parameters, return_type } copy {});
+
+impl<'a> IntoStatic for FieldType<'a> {
+    type Static = FieldType<'static>;
+
+    fn into_static(self) -> Self::Static {
+        match self {
+            FieldType::Byte => FieldType::Byte,
+            FieldType::Char => FieldType::Char,
+            FieldType::Double => FieldType::Double,
+            FieldType::Float => FieldType::Float,
+            FieldType::Integer => FieldType::Integer,
+            FieldType::Long => FieldType::Long,
+            FieldType::Short => FieldType::Short,
+            FieldType::Boolean => FieldType::Boolean,
+            FieldType::Object(class_name) => FieldType::Object(class_name.into_static()),
+        }
+    }
+}
+
+impl<'a> IntoStatic for ReturnDescriptor<'a> {
+    type Static = ReturnDescriptor<'static>;
+
+    fn into_static(self) -> Self::Static {
+        match self {
+            ReturnDescriptor::Return(d) => ReturnDescriptor::Return(d.into_static()),
+            ReturnDescriptor::Void => ReturnDescriptor::Void,
+        }
+    }
+}
+
+struct_into_static!(NameAndType { name, descriptor } copy {});
+struct_into_static!(MemberRef { class_name, name_and_type } copy {});
+struct_into_static!(InvokeDynamic { name_and_type } copy { attr_index });
+struct_into_static!(Dynamic { name_and_type } copy { attr_index });
+struct_into_static!(MethodHandle { class_name, member_ref } copy { kind, member_kind });
+
+impl<'a> IntoStatic for LiteralConstant<'a> {
+    type Static = LiteralConstant<'static>;
+
+    fn into_static(self) -> Self::Static {
+        match self {
+            LiteralConstant::Integer(v) => LiteralConstant::Integer(v),
+            LiteralConstant::Float(v) => LiteralConstant::Float(v),
+            LiteralConstant::Long(v) => LiteralConstant::Long(v),
+            LiteralConstant::Double(v) => LiteralConstant::Double(v),
+            LiteralConstant::String(s) => LiteralConstant::String(s.into_static()),
+            LiteralConstant::StringBytes(b) => LiteralConstant::StringBytes(b.into_static()),
+        }
+    }
+}
+
+impl<'a> IntoStatic for Loadable<'a> {
+    type Static = Loadable<'static>;
+
+    fn into_static(self) -> Self::Static {
+        match self {
+            Loadable::LiteralConstant(c) => Loadable::LiteralConstant(c.into_static()),
+            Loadable::ClassInfo(s) => Loadable::ClassInfo(s.into_static()),
+            Loadable::MethodHandle(h) => Loadable::MethodHandle(h.into_static()),
+            Loadable::MethodType(s) => Loadable::MethodType(s.into_static()),
+            Loadable::Dynamic(d) => Loadable::Dynamic(d.into_static()),
+        }
+    }
+}
+
+impl<'a> IntoStatic for BootstrapArgument<'a> {
+    type Static = BootstrapArgument<'static>;
+
+    fn into_static(self) -> Self::Static {
+        match self {
+            BootstrapArgument::LiteralConstant(c) => {
+                BootstrapArgument::LiteralConstant(c.into_static())
+            }
+            BootstrapArgument::ClassInfo(s) => BootstrapArgument::ClassInfo(s.into_static()),
+            BootstrapArgument::MethodHandle(h) => BootstrapArgument::MethodHandle(h.into_static()),
+            BootstrapArgument::MethodType(s) => BootstrapArgument::MethodType(s.into_static()),
+            BootstrapArgument::Dynamic(d) => BootstrapArgument::Dynamic(d.into_static()),
+        }
+    }
+}
+
+impl<'a> IntoStatic for ObjectArrayType<'a> {
+    type Static = ObjectArrayType<'static>;
+
+    fn into_static(self) -> Self::Static {
+        match self {
+            ObjectArrayType::ArrayType(d) => ObjectArrayType::ArrayType(d.into_static()),
+            ObjectArrayType::BinaryName(s) => ObjectArrayType::BinaryName(s.into_static()),
+        }
+    }
+}
+
+impl<'a> IntoStatic for Opcode<'a> {
+    type Static = Opcode<'static>;
+
+    fn into_static(self) -> Self::Static {
+        macro_rules! convert {
+            ([$($unit:ident),*], [$($copied:ident),*], { $($arms:tt)* }) => {
+                match self {
+                    $(Opcode::$unit => Opcode::$unit,)*
+                    $(Opcode::$copied(x) => Opcode::$copied(x),)*
+                    $($arms)*
+                }
+            };
+        }
+        convert!(
+            [
+            Aaload, Aastore, AconstNull, Areturn, Arraylength, Athrow, Baload, Bastore,
+            Breakpoint, Caload, Castore, D2f, D2i, D2l, Dadd, Daload, Dastore, Dcmpg,
+            Dcmpl, Dconst0, Dconst1, Ddiv, Dmul, Dneg, Drem, Dreturn, Dsub, Dup, DupX1,
+            DupX2, Dup2, Dup2X1, Dup2X2, F2d, F2i, F2l, Fadd, Faload, Fastore, Fcmpg,
+            Fcmpl, Fconst0, Fconst1, Fconst2, Fdiv, Fmul, Fneg, Frem, Freturn, Fsub,
+            I2b, I2c, I2d, I2f, I2l, I2s, Iadd, Iaload, Iand, Iastore, IconstM1,
+            Iconst0, Iconst1, Iconst2, Iconst3, Iconst4, Iconst5, Idiv, Impdep1,
+            Impdep2, Imul, Ineg, Ior, Irem, Ireturn, Ishl, Ishr, Isub, Iushr, Ixor, L2d,
+            L2f, L2i, Ladd, Laload, Land, Lastore, Lcmp, Lconst0, Lconst1, Ldiv, Lmul,
+            Lneg, Lor, Lrem, Lreturn, Lshl, Lshr, Lsub, Lushr, Lxor, Monitorenter,
+            Monitorexit, Nop, Pop, Pop2, Return, Saload, Sastore, Swap
+            ],
+            [
+            Aload, Astore, Bipush, Dload, Dstore, Fload, Fstore, Goto, IfAcmpeq,
+            IfAcmpne, IfIcmpeq, IfIcmpge, IfIcmpgt, IfIcmple, IfIcmplt, IfIcmpne, Ifeq,
+            Ifge, Ifgt, Ifle, Iflt, Ifne, Ifnonnull, Ifnull, Iload, Istore, Jsr, Lload,
+            Lookupswitch, Lstore, Newarray, Ret, Sipush, Tableswitch
+            ],
+            {
+                Opcode::Anewarray(t) => Opcode::Anewarray(t.into_static()),
+                Opcode::Checkcast(t) => Opcode::Checkcast(t.into_static()),
+                Opcode::Getfield(m) => Opcode::Getfield(m.into_static()),
+                Opcode::Getstatic(m) => Opcode::Getstatic(m.into_static()),
+                Opcode::Iinc(index, value) => Opcode::Iinc(index, value),
+                Opcode::Instanceof(t) => Opcode::Instanceof(t.into_static()),
+                Opcode::Invokedynamic(i) => Opcode::Invokedynamic(i.into_static()),
+                Opcode::Invokeinterface(m, count) => Opcode::Invokeinterface(m.into_static(), count),
+                Opcode::Invokespecial(m) => Opcode::Invokespecial(m.into_static()),
+                Opcode::Invokestatic(m) => Opcode::Invokestatic(m.into_static()),
+                Opcode::Invokevirtual(m) => Opcode::Invokevirtual(m.into_static()),
+                Opcode::Ldc(l) => Opcode::Ldc(l.into_static()),
+                Opcode::LdcW(l) => Opcode::LdcW(l.into_static()),
+                Opcode::Ldc2W(l) => Opcode::Ldc2W(l.into_static()),
+                Opcode::Multianewarray(t, dimensions) => Opcode::Multianewarray(t.into_static(), dimensions),
+                Opcode::New(s) => Opcode::New(s.into_static()),
+                Opcode::Putfield(m) => Opcode::Putfield(m.into_static()),
+                Opcode::Putstatic(m) => Opcode::Putstatic(m.into_static()),
+            }
+        )
+    }
+}
+
+impl<'a> IntoStatic for ByteCode<'a> {
+    type Static = ByteCode<'static>;
+
+    fn into_static(self) -> Self::Static {
+        ByteCode {
+            opcodes: self
+                .opcodes
+                .into_iter()
+                .map(|(offset, opcode)| (offset, opcode.into_static()))
+                .collect(),
+        }
+    }
+}
+
+struct_into_static!(ExceptionTableEntry { catch_type } copy { start_pc, end_pc, handler_pc });
+struct_into_static!(CodeData { code, bytecode, exception_table, attributes } copy { max_stack, max_locals });
+
+impl<'a> IntoStatic for VerificationType<'a> {
+    type Static = VerificationType<'static>;
+
+    fn into_static(self) -> Self::Static {
+        match self {
+            VerificationType::Top => VerificationType::Top,
+            VerificationType::Integer => VerificationType::Integer,
+            VerificationType::Float => VerificationType::Float,
+            VerificationType::Long => VerificationType::Long,
+            VerificationType::Double => VerificationType::Double,
+            VerificationType::Null => VerificationType::Null,
+            VerificationType::UninitializedThis => VerificationType::UninitializedThis,
+            VerificationType::Uninitialized { code_offset } => {
+                VerificationType::Uninitialized { code_offset }
+            }
+            VerificationType::Object { class_name } => VerificationType::Object {
+                class_name: class_name.into_static(),
+            },
+        }
+    }
+}
+
+impl<'a> IntoStatic for StackMapEntry<'a> {
+    type Static = StackMapEntry<'static>;
+
+    fn into_static(self) -> Self::Static {
+        match self {
+            StackMapEntry::Same { offset_delta } => StackMapEntry::Same { offset_delta },
+            StackMapEntry::SameLocals1StackItem {
+                offset_delta,
+                stack,
+            } => StackMapEntry::SameLocals1StackItem {
+                offset_delta,
+                stack: stack.into_static(),
+            },
+            StackMapEntry::Chop {
+                offset_delta,
+                chop_count,
+            } => StackMapEntry::Chop {
+                offset_delta,
+                chop_count,
+            },
+            StackMapEntry::Append {
+                offset_delta,
+                locals,
+            } => StackMapEntry::Append {
+                offset_delta,
+                locals: locals.into_static(),
+            },
+            StackMapEntry::FullFrame {
+                offset_delta,
+                locals,
+                stack,
+            } => StackMapEntry::FullFrame {
+                offset_delta,
+                locals: locals.into_static(),
+                stack: stack.into_static(),
+            },
+        }
+    }
+}
+
+struct_into_static!(StackMapFrame { locals, stack } copy { offset });
+struct_into_static!(InnerClassEntry { inner_class_info, outer_class_info, inner_name } copy { access_flags });
+struct_into_static!(LocalVariableEntry { name, descriptor } copy { start_pc, length, index });
+struct_into_static!(LocalVariableTypeEntry { name, signature } copy { start_pc, length, index });
+
+impl<'a> IntoStatic for AnnotationElementValue<'a> {
+    type Static = AnnotationElementValue<'static>;
+
+    fn into_static(self) -> Self::Static {
+        match self {
+            AnnotationElementValue::ByteConstant(v) => AnnotationElementValue::ByteConstant(v),
+            AnnotationElementValue::CharConstant(v) => AnnotationElementValue::CharConstant(v),
+            AnnotationElementValue::DoubleConstant(v) => AnnotationElementValue::DoubleConstant(v),
+            AnnotationElementValue::FloatConstant(v) => AnnotationElementValue::FloatConstant(v),
+            AnnotationElementValue::IntConstant(v) => AnnotationElementValue::IntConstant(v),
+            AnnotationElementValue::LongConstant(v) => AnnotationElementValue::LongConstant(v),
+            AnnotationElementValue::ShortConstant(v) => AnnotationElementValue::ShortConstant(v),
+            AnnotationElementValue::BooleanConstant(v) => {
+                AnnotationElementValue::BooleanConstant(v)
+            }
+            AnnotationElementValue::StringConstant(s) => {
+                AnnotationElementValue::StringConstant(s.into_static())
+            }
+            AnnotationElementValue::EnumConstant {
+                type_name,
+                const_name,
+            } => AnnotationElementValue::EnumConstant {
+                type_name: type_name.into_static(),
+                const_name: const_name.into_static(),
+            },
+            AnnotationElementValue::ClassLiteral { class_name } => {
+                AnnotationElementValue::ClassLiteral {
+                    class_name: class_name.into_static(),
+                }
+            }
+            AnnotationElementValue::AnnotationValue(a) => {
+                AnnotationElementValue::AnnotationValue(a.into_static())
+            }
+            AnnotationElementValue::ArrayValue(v) => {
+                AnnotationElementValue::ArrayValue(v.into_static())
+            }
+        }
+    }
+}
+
+struct_into_static!(AnnotationElement { name, value } copy {});
+struct_into_static!(Annotation { type_descriptor, elements } copy {});
+struct_into_static!(ParameterAnnotation { annotations } copy {});
+struct_into_static!(TypeAnnotation { annotation } copy { target_kind, target_type, target_path });
+struct_into_static!(BootstrapMethodEntry { method, arguments } copy {});
+struct_into_static!(MethodParameterEntry { name } copy { access_flags });
+struct_into_static!(ModuleRequireEntry { name, version } copy { flags });
+struct_into_static!(ModuleExportsEntry { package_name, exports_to } copy { flags });
+struct_into_static!(ModuleOpensEntry { package_name, opens_to } copy { flags });
+struct_into_static!(ModuleProvidesEntry { service_interface_name, provides_with } copy {});
+struct_into_static!(ModuleData { name, version, requires, exports, opens, uses, provides } copy { access_flags });
+struct_into_static!(RecordComponentEntry { name, descriptor, attributes } copy { max_nesting_depth });
+
+impl<'a> IntoStatic for AttributeData<'a> {
+    type Static = AttributeData<'static>;
+
+    fn into_static(self) -> Self::Static {
+        match self {
+            AttributeData::ConstantValue(c) => AttributeData::ConstantValue(c.into_static()),
+            AttributeData::Code(c) => AttributeData::Code(c.into_static()),
+            AttributeData::StackMapTable(v) => AttributeData::StackMapTable(v.into_static()),
+            AttributeData::StackMap(v) => AttributeData::StackMap(v.into_static()),
+            AttributeData::Exceptions(v) => AttributeData::Exceptions(v.into_static()),
+            AttributeData::InnerClasses(v) => AttributeData::InnerClasses(v.into_static()),
+            AttributeData::EnclosingMethod { class_name, method } => {
+                AttributeData::EnclosingMethod {
+                    class_name: class_name.into_static(),
+                    method: method.into_static(),
+                }
+            }
+            AttributeData::Synthetic => AttributeData::Synthetic,
+            AttributeData::Signature(s) => AttributeData::Signature(s.into_static()),
+            AttributeData::SourceFile(s) => AttributeData::SourceFile(s.into_static()),
+            AttributeData::SourceDebugExtension(s) => {
+                AttributeData::SourceDebugExtension(s.into_static())
+            }
+            AttributeData::SourceDebugExtensionBytes(b) => {
+                AttributeData::SourceDebugExtensionBytes(b.into_static())
+            }
+            AttributeData::LineNumberTable(v) => AttributeData::LineNumberTable(v),
+            AttributeData::LocalVariableTable(v) => {
+                AttributeData::LocalVariableTable(v.into_static())
+            }
+            AttributeData::LocalVariableTypeTable(v) => {
+                AttributeData::LocalVariableTypeTable(v.into_static())
+            }
+            AttributeData::Deprecated => AttributeData::Deprecated,
+            AttributeData::RuntimeVisibleAnnotations(v) => {
+                AttributeData::RuntimeVisibleAnnotations(v.into_static())
+            }
+            AttributeData::RuntimeInvisibleAnnotations(v) => {
+                AttributeData::RuntimeInvisibleAnnotations(v.into_static())
+            }
+            AttributeData::RuntimeVisibleParameterAnnotations(v) => {
+                AttributeData::RuntimeVisibleParameterAnnotations(v.into_static())
+            }
+            AttributeData::RuntimeInvisibleParameterAnnotations(v) => {
+                AttributeData::RuntimeInvisibleParameterAnnotations(v.into_static())
+            }
+            AttributeData::RuntimeVisibleTypeAnnotations(v) => {
+                AttributeData::RuntimeVisibleTypeAnnotations(v.into_static())
+            }
+            AttributeData::RuntimeInvisibleTypeAnnotations(v) => {
+                AttributeData::RuntimeInvisibleTypeAnnotations(v.into_static())
+            }
+            AttributeData::AnnotationDefault(v) => {
+                AttributeData::AnnotationDefault(v.into_static())
+            }
+            AttributeData::BootstrapMethods(v) => AttributeData::BootstrapMethods(v.into_static()),
+            AttributeData::MethodParameters(v) => AttributeData::MethodParameters(v.into_static()),
+            AttributeData::Module(m) => AttributeData::Module(m.into_static()),
+            AttributeData::ModulePackages(v) => AttributeData::ModulePackages(v.into_static()),
+            AttributeData::ModuleMainClass(s) => AttributeData::ModuleMainClass(s.into_static()),
+            AttributeData::NestHost(s) => AttributeData::NestHost(s.into_static()),
+            AttributeData::NestMembers(v) => AttributeData::NestMembers(v.into_static()),
+            AttributeData::PermittedSubclasses(v) => {
+                AttributeData::PermittedSubclasses(v.into_static())
+            }
+            AttributeData::Record(v) => AttributeData::Record(v.into_static()),
+            AttributeData::Other(b) => AttributeData::Other(b.into_static()),
+        }
+    }
+}
+
+struct_into_static!(AttributeInfo { name, data } copy {});
+struct_into_static!(FieldInfo { name, descriptor, attributes } copy { access_flags, max_nesting_depth });
+struct_into_static!(MethodInfo { name, descriptor, attributes } copy { access_flags, max_nesting_depth });
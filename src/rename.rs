@@ -0,0 +1,409 @@
+// Renaming of a class throughout a parsed class file, for relocating (a.k.a. shading)
+// classes from one package into another.
+
+use std::borrow::Cow;
+
+use crate::attributes::{
+    Annotation, AnnotationElement, AnnotationElementValue, AttributeData, AttributeInfo,
+    BootstrapMethodEntry, CodeData, ExceptionTableEntry, InnerClassEntry, LocalVariableEntry,
+    LocalVariableTypeEntry, ModuleData, ModuleProvidesEntry, ParameterAnnotation,
+    RecordComponentEntry, StackMapEntry, StackMapFrame, TypeAnnotation, VerificationType,
+};
+use crate::bytecode::{ByteCode, Opcode};
+use crate::constant_pool::{
+    rename_class_in_constant_pool, BootstrapArgument, Dynamic, InvokeDynamic, Loadable, MemberRef,
+    MethodHandle, NameAndType, ObjectArrayType,
+};
+use crate::descriptors::{
+    rename_class_name, rename_in_descriptor, ClassName, FieldDescriptor, FieldType,
+    MethodDescriptor, ReturnDescriptor, UnqualifiedSegment,
+};
+use crate::{ClassFile, FieldInfo, MethodInfo, ParseError};
+
+trait RenameClass {
+    fn rename_class(&mut self, from: &str, to: &str);
+}
+
+impl<T: RenameClass> RenameClass for Vec<T> {
+    fn rename_class(&mut self, from: &str, to: &str) {
+        for item in self {
+            item.rename_class(from, to);
+        }
+    }
+}
+
+impl<T: RenameClass> RenameClass for Option<T> {
+    fn rename_class(&mut self, from: &str, to: &str) {
+        if let Some(item) = self {
+            item.rename_class(from, to);
+        }
+    }
+}
+
+fn rename_class_names(names: &mut [Cow<str>], from: &str, to: &str) {
+    for name in names {
+        rename_class_name(name, from, to);
+    }
+}
+
+impl<'a> RenameClass for ClassName<'a> {
+    fn rename_class(&mut self, from: &str, to: &str) {
+        if self.to_string() == from {
+            self.segments = to
+                .split('/')
+                .map(|segment| UnqualifiedSegment {
+                    name: Cow::Owned(segment.to_string()),
+                })
+                .collect();
+        }
+    }
+}
+
+impl<'a> RenameClass for FieldType<'a> {
+    fn rename_class(&mut self, from: &str, to: &str) {
+        if let FieldType::Object(class_name) = self {
+            class_name.rename_class(from, to);
+        }
+    }
+}
+
+impl<'a> RenameClass for FieldDescriptor<'a> {
+    fn rename_class(&mut self, from: &str, to: &str) {
+        self.field_type.rename_class(from, to);
+    }
+}
+
+impl<'a> RenameClass for ReturnDescriptor<'a> {
+    fn rename_class(&mut self, from: &str, to: &str) {
+        if let ReturnDescriptor::Return(descriptor) = self {
+            descriptor.rename_class(from, to);
+        }
+    }
+}
+
+impl<'a> RenameClass for MethodDescriptor<'a> {
+    fn rename_class(&mut self, from: &str, to: &str) {
+        self.parameters.rename_class(from, to);
+        self.return_type.rename_class(from, to);
+    }
+}
+
+impl<'a> RenameClass for NameAndType<'a> {
+    fn rename_class(&mut self, from: &str, to: &str) {
+        rename_in_descriptor(&mut self.descriptor, from, to);
+    }
+}
+
+impl<'a> RenameClass for MemberRef<'a> {
+    fn rename_class(&mut self, from: &str, to: &str) {
+        rename_class_name(&mut self.class_name, from, to);
+        self.name_and_type.rename_class(from, to);
+    }
+}
+
+impl<'a> RenameClass for InvokeDynamic<'a> {
+    fn rename_class(&mut self, from: &str, to: &str) {
+        self.name_and_type.rename_class(from, to);
+    }
+}
+
+impl<'a> RenameClass for Dynamic<'a> {
+    fn rename_class(&mut self, from: &str, to: &str) {
+        self.name_and_type.rename_class(from, to);
+    }
+}
+
+impl<'a> RenameClass for MethodHandle<'a> {
+    fn rename_class(&mut self, from: &str, to: &str) {
+        rename_class_name(&mut self.class_name, from, to);
+        self.member_ref.rename_class(from, to);
+    }
+}
+
+impl<'a> RenameClass for Loadable<'a> {
+    fn rename_class(&mut self, from: &str, to: &str) {
+        match self {
+            Loadable::LiteralConstant(_) => (),
+            Loadable::ClassInfo(name) => {
+                rename_class_name(name, from, to);
+            }
+            Loadable::MethodHandle(handle) => handle.rename_class(from, to),
+            Loadable::MethodType(descriptor) => {
+                rename_in_descriptor(descriptor, from, to);
+            }
+            Loadable::Dynamic(dynamic) => dynamic.rename_class(from, to),
+        }
+    }
+}
+
+impl<'a> RenameClass for BootstrapArgument<'a> {
+    fn rename_class(&mut self, from: &str, to: &str) {
+        match self {
+            BootstrapArgument::LiteralConstant(_) => (),
+            BootstrapArgument::ClassInfo(name) => {
+                rename_class_name(name, from, to);
+            }
+            BootstrapArgument::MethodHandle(handle) => handle.rename_class(from, to),
+            BootstrapArgument::MethodType(descriptor) => {
+                rename_in_descriptor(descriptor, from, to);
+            }
+            BootstrapArgument::Dynamic(dynamic) => dynamic.rename_class(from, to),
+        }
+    }
+}
+
+impl<'a> RenameClass for ObjectArrayType<'a> {
+    fn rename_class(&mut self, from: &str, to: &str) {
+        match self {
+            ObjectArrayType::ArrayType(descriptor) => descriptor.rename_class(from, to),
+            ObjectArrayType::BinaryName(name) => {
+                rename_class_name(name, from, to);
+            }
+        }
+    }
+}
+
+impl<'a> RenameClass for ByteCode<'a> {
+    fn rename_class(&mut self, from: &str, to: &str) {
+        for (_, opcode) in &mut self.opcodes {
+            match opcode {
+                Opcode::Anewarray(t)
+                | Opcode::Checkcast(t)
+                | Opcode::Instanceof(t)
+                | Opcode::Multianewarray(t, _) => t.rename_class(from, to),
+                Opcode::Getfield(m)
+                | Opcode::Getstatic(m)
+                | Opcode::Putfield(m)
+                | Opcode::Putstatic(m)
+                | Opcode::Invokeinterface(m, _)
+                | Opcode::Invokespecial(m)
+                | Opcode::Invokestatic(m)
+                | Opcode::Invokevirtual(m) => m.rename_class(from, to),
+                Opcode::Invokedynamic(i) => i.rename_class(from, to),
+                Opcode::Ldc(l) | Opcode::LdcW(l) | Opcode::Ldc2W(l) => l.rename_class(from, to),
+                Opcode::New(name) => {
+                    rename_class_name(name, from, to);
+                }
+                _ => (),
+            }
+        }
+    }
+}
+
+impl<'a> RenameClass for ExceptionTableEntry<'a> {
+    fn rename_class(&mut self, from: &str, to: &str) {
+        if let Some(catch_type) = &mut self.catch_type {
+            rename_class_name(catch_type, from, to);
+        }
+    }
+}
+
+impl<'a> RenameClass for CodeData<'a> {
+    fn rename_class(&mut self, from: &str, to: &str) {
+        self.bytecode.rename_class(from, to);
+        self.exception_table.rename_class(from, to);
+        self.attributes.rename_class(from, to);
+    }
+}
+
+impl<'a> RenameClass for VerificationType<'a> {
+    fn rename_class(&mut self, from: &str, to: &str) {
+        if let VerificationType::Object { class_name } = self {
+            rename_class_name(class_name, from, to);
+        }
+    }
+}
+
+impl<'a> RenameClass for StackMapEntry<'a> {
+    fn rename_class(&mut self, from: &str, to: &str) {
+        match self {
+            StackMapEntry::Same { .. } | StackMapEntry::Chop { .. } => (),
+            StackMapEntry::SameLocals1StackItem { stack, .. } => stack.rename_class(from, to),
+            StackMapEntry::Append { locals, .. } => locals.rename_class(from, to),
+            StackMapEntry::FullFrame { locals, stack, .. } => {
+                locals.rename_class(from, to);
+                stack.rename_class(from, to);
+            }
+        }
+    }
+}
+
+impl<'a> RenameClass for StackMapFrame<'a> {
+    fn rename_class(&mut self, from: &str, to: &str) {
+        self.locals.rename_class(from, to);
+        self.stack.rename_class(from, to);
+    }
+}
+
+impl<'a> RenameClass for InnerClassEntry<'a> {
+    fn rename_class(&mut self, from: &str, to: &str) {
+        rename_class_name(&mut self.inner_class_info, from, to);
+        if let Some(outer_class_info) = &mut self.outer_class_info {
+            rename_class_name(outer_class_info, from, to);
+        }
+    }
+}
+
+impl<'a> RenameClass for LocalVariableEntry<'a> {
+    fn rename_class(&mut self, from: &str, to: &str) {
+        self.descriptor.rename_class(from, to);
+    }
+}
+
+impl<'a> RenameClass for LocalVariableTypeEntry<'a> {
+    fn rename_class(&mut self, from: &str, to: &str) {
+        rename_in_descriptor(&mut self.signature, from, to);
+    }
+}
+
+impl<'a> RenameClass for AnnotationElementValue<'a> {
+    fn rename_class(&mut self, from: &str, to: &str) {
+        match self {
+            AnnotationElementValue::EnumConstant { type_name, .. } => {
+                type_name.rename_class(from, to)
+            }
+            AnnotationElementValue::ClassLiteral { class_name } => {
+                rename_in_descriptor(class_name, from, to);
+            }
+            AnnotationElementValue::AnnotationValue(annotation) => {
+                annotation.rename_class(from, to)
+            }
+            AnnotationElementValue::ArrayValue(values) => values.rename_class(from, to),
+            _ => (),
+        }
+    }
+}
+
+impl<'a> RenameClass for AnnotationElement<'a> {
+    fn rename_class(&mut self, from: &str, to: &str) {
+        self.value.rename_class(from, to);
+    }
+}
+
+impl<'a> RenameClass for Annotation<'a> {
+    fn rename_class(&mut self, from: &str, to: &str) {
+        self.type_descriptor.rename_class(from, to);
+        self.elements.rename_class(from, to);
+    }
+}
+
+impl<'a> RenameClass for ParameterAnnotation<'a> {
+    fn rename_class(&mut self, from: &str, to: &str) {
+        self.annotations.rename_class(from, to);
+    }
+}
+
+impl<'a> RenameClass for TypeAnnotation<'a> {
+    fn rename_class(&mut self, from: &str, to: &str) {
+        self.annotation.rename_class(from, to);
+    }
+}
+
+impl<'a> RenameClass for BootstrapMethodEntry<'a> {
+    fn rename_class(&mut self, from: &str, to: &str) {
+        self.method.rename_class(from, to);
+        self.arguments.rename_class(from, to);
+    }
+}
+
+impl<'a> RenameClass for ModuleProvidesEntry<'a> {
+    fn rename_class(&mut self, from: &str, to: &str) {
+        rename_class_name(&mut self.service_interface_name, from, to);
+        rename_class_names(&mut self.provides_with, from, to);
+    }
+}
+
+impl<'a> RenameClass for ModuleData<'a> {
+    fn rename_class(&mut self, from: &str, to: &str) {
+        rename_class_names(&mut self.uses, from, to);
+        self.provides.rename_class(from, to);
+    }
+}
+
+impl<'a> RenameClass for RecordComponentEntry<'a> {
+    fn rename_class(&mut self, from: &str, to: &str) {
+        self.descriptor.rename_class(from, to);
+        self.attributes.rename_class(from, to);
+    }
+}
+
+impl<'a> RenameClass for AttributeInfo<'a> {
+    fn rename_class(&mut self, from: &str, to: &str) {
+        match &mut self.data {
+            AttributeData::Code(code) => code.rename_class(from, to),
+            AttributeData::StackMapTable(entries) => entries.rename_class(from, to),
+            AttributeData::StackMap(frames) => frames.rename_class(from, to),
+            AttributeData::Exceptions(names)
+            | AttributeData::NestMembers(names)
+            | AttributeData::PermittedSubclasses(names) => rename_class_names(names, from, to),
+            AttributeData::InnerClasses(entries) => entries.rename_class(from, to),
+            AttributeData::EnclosingMethod { class_name, method } => {
+                rename_class_name(class_name, from, to);
+                method.rename_class(from, to);
+            }
+            AttributeData::Signature(signature) => {
+                rename_in_descriptor(signature, from, to);
+            }
+            AttributeData::LocalVariableTable(entries) => entries.rename_class(from, to),
+            AttributeData::LocalVariableTypeTable(entries) => entries.rename_class(from, to),
+            AttributeData::RuntimeVisibleAnnotations(annotations)
+            | AttributeData::RuntimeInvisibleAnnotations(annotations) => {
+                annotations.rename_class(from, to)
+            }
+            AttributeData::RuntimeVisibleParameterAnnotations(annotations)
+            | AttributeData::RuntimeInvisibleParameterAnnotations(annotations) => {
+                annotations.rename_class(from, to)
+            }
+            AttributeData::RuntimeVisibleTypeAnnotations(annotations)
+            | AttributeData::RuntimeInvisibleTypeAnnotations(annotations) => {
+                annotations.rename_class(from, to)
+            }
+            AttributeData::AnnotationDefault(value) => value.rename_class(from, to),
+            AttributeData::BootstrapMethods(entries) => entries.rename_class(from, to),
+            AttributeData::Module(module) => module.rename_class(from, to),
+            AttributeData::ModuleMainClass(name) | AttributeData::NestHost(name) => {
+                rename_class_name(name, from, to);
+            }
+            AttributeData::Record(components) => components.rename_class(from, to),
+            _ => (),
+        }
+    }
+}
+
+impl<'a> RenameClass for FieldInfo<'a> {
+    fn rename_class(&mut self, from: &str, to: &str) {
+        self.descriptor.rename_class(from, to);
+        self.attributes.rename_class(from, to);
+    }
+}
+
+impl<'a> RenameClass for MethodInfo<'a> {
+    fn rename_class(&mut self, from: &str, to: &str) {
+        self.descriptor.rename_class(from, to);
+        self.attributes.rename_class(from, to);
+    }
+}
+
+impl<'a> ClassFile<'a> {
+    /// Renames the class `from` to `to` (both given as binary names, e.g. com/old/Name)
+    /// everywhere it is referenced in this class: in the class name, superclass and
+    /// interfaces, in field, method and other descriptors, in signatures, in bytecode and
+    /// in the constant pool. String constants are not renamed. Returns the number of
+    /// constant pool entries that were rewritten, which is zero if the class is not
+    /// referenced at all. Returns an error, leaving the class unchanged, if the constant
+    /// pool can't be copied, as with [`ClassFile::into_owned`].
+    pub fn rename_class(&mut self, from: &str, to: &str) -> Result<usize, ParseError> {
+        let (constant_pool, renamed) =
+            rename_class_in_constant_pool(&self.constant_pool, from, to)?;
+        self.constant_pool = constant_pool;
+        rename_class_name(&mut self.this_class, from, to);
+        if let Some(super_class) = &mut self.super_class {
+            rename_class_name(super_class, from, to);
+        }
+        rename_class_names(&mut self.interfaces, from, to);
+        self.fields.rename_class(from, to);
+        self.methods.rename_class(from, to);
+        self.attributes.rename_class(from, to);
+        Ok(renamed)
+    }
+}
@@ -0,0 +1,718 @@
+use std::borrow::Cow;
+
+use crate::descriptors::UnqualifiedSegment;
+use crate::{ParseError, DEFAULT_MAX_NESTING_DEPTH};
+
+#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq)]
+pub enum BaseType {
+    Byte,
+    Char,
+    Double,
+    Float,
+    Integer,
+    Long,
+    Short,
+    Boolean,
+}
+
+#[derive(Clone, Debug, Hash, PartialEq, Eq)]
+pub enum JavaTypeSignature<'a> {
+    Base(BaseType),
+    Reference(ReferenceTypeSignature<'a>),
+}
+
+#[derive(Clone, Debug, Hash, PartialEq, Eq)]
+pub enum ReferenceTypeSignature<'a> {
+    Class(ClassTypeSignature<'a>),
+    TypeVariable(Cow<'a, str>),
+    Array(Box<JavaTypeSignature<'a>>),
+}
+
+#[derive(Clone, Debug, Hash, PartialEq, Eq)]
+pub enum TypeArgument<'a> {
+    /// The unbounded wildcard `*`
+    Any,
+    Exact(ReferenceTypeSignature<'a>),
+    /// A wildcard with an upper bound, i.e. `? extends T`
+    Extends(ReferenceTypeSignature<'a>),
+    /// A wildcard with a lower bound, i.e. `? super T`
+    Super(ReferenceTypeSignature<'a>),
+}
+
+#[derive(Clone, Debug, Hash, PartialEq, Eq)]
+pub struct SimpleClassTypeSignature<'a> {
+    pub name: Cow<'a, str>,
+    pub type_arguments: Vec<TypeArgument<'a>>,
+}
+
+#[derive(Clone, Debug, Hash, PartialEq, Eq)]
+pub struct ClassTypeSignature<'a> {
+    pub package: Vec<UnqualifiedSegment<'a>>,
+    pub class: SimpleClassTypeSignature<'a>,
+    /// Inner class names that follow the outer class, separated by '.' in the signature
+    pub suffixes: Vec<SimpleClassTypeSignature<'a>>,
+}
+
+#[derive(Clone, Debug, Hash, PartialEq, Eq)]
+pub struct TypeParameter<'a> {
+    pub name: Cow<'a, str>,
+    pub class_bound: Option<ReferenceTypeSignature<'a>>,
+    pub interface_bounds: Vec<ReferenceTypeSignature<'a>>,
+}
+
+#[derive(Clone, Debug, Hash, PartialEq, Eq)]
+pub struct ClassSignature<'a> {
+    pub type_parameters: Vec<TypeParameter<'a>>,
+    pub superclass: ClassTypeSignature<'a>,
+    pub superinterfaces: Vec<ClassTypeSignature<'a>>,
+}
+
+#[derive(Clone, Debug, Hash, PartialEq, Eq)]
+pub enum ResultSignature<'a> {
+    Return(JavaTypeSignature<'a>),
+    Void,
+}
+
+#[derive(Clone, Debug, Hash, PartialEq, Eq)]
+pub struct MethodSignature<'a> {
+    pub type_parameters: Vec<TypeParameter<'a>>,
+    pub parameters: Vec<JavaTypeSignature<'a>>,
+    pub result: ResultSignature<'a>,
+    /// Each of these is either a class type signature or a type variable signature
+    pub throws: Vec<ReferenceTypeSignature<'a>>,
+}
+
+struct SignatureReader<'a, 'b> {
+    data: &'b Cow<'a, str>,
+    index: usize,
+    depth: usize,
+    max_depth: usize,
+    // Whether reading failed because the signature exceeded max_depth.
+    too_deep: bool,
+}
+
+impl<'a, 'b> SignatureReader<'a, 'b> {
+    fn new(data: &'b Cow<'a, str>, max_depth: usize) -> Self {
+        SignatureReader {
+            data,
+            index: 0,
+            depth: 0,
+            max_depth,
+            too_deep: false,
+        }
+    }
+
+    fn peek(&self) -> Option<u8> {
+        self.data.as_bytes().get(self.index).copied()
+    }
+
+    fn next(&mut self) -> Result<u8, ParseError> {
+        match self.peek() {
+            Some(c) => {
+                self.index += 1;
+                Ok(c)
+            }
+            None => fail!("Unexpected end of signature"),
+        }
+    }
+
+    fn expect(&mut self, expected: u8) -> Result<(), ParseError> {
+        let c = self.next()?;
+        if c != expected {
+            fail!(
+                "Expected '{}' but found '{}' at index {} of signature",
+                expected as char,
+                c as char,
+                self.index - 1
+            );
+        }
+        Ok(())
+    }
+
+    fn slice(&self, start: usize, end: usize) -> Cow<'a, str> {
+        match self.data {
+            Cow::Borrowed(borrowed_str) => Cow::Borrowed(&borrowed_str[start..end]),
+            Cow::Owned(ref owned_str) => Cow::Owned(owned_str[start..end].to_string()),
+        }
+    }
+
+    fn ensure_finished(&self) -> Result<(), ParseError> {
+        if self.index != self.data.len() {
+            fail!(
+                "Unexpected trailing data at index {} of signature",
+                self.index
+            );
+        }
+        Ok(())
+    }
+
+    fn read_identifier(&mut self) -> Result<Cow<'a, str>, ParseError> {
+        let start = self.index;
+        while let Some(c) = self.peek() {
+            if matches!(c, b'.' | b';' | b'[' | b'/' | b'<' | b'>' | b':') {
+                break;
+            }
+            self.index += 1;
+        }
+        if start == self.index {
+            fail!("Expected identifier at index {} of signature", start);
+        }
+        // Identifiers only end at ASCII characters, so the slice is on a char boundary
+        Ok(self.slice(start, self.index))
+    }
+
+    fn read_java_type(&mut self) -> Result<JavaTypeSignature<'a>, ParseError> {
+        let base_type = match self.peek() {
+            Some(b'B') => BaseType::Byte,
+            Some(b'C') => BaseType::Char,
+            Some(b'D') => BaseType::Double,
+            Some(b'F') => BaseType::Float,
+            Some(b'I') => BaseType::Integer,
+            Some(b'J') => BaseType::Long,
+            Some(b'S') => BaseType::Short,
+            Some(b'Z') => BaseType::Boolean,
+            _ => return Ok(JavaTypeSignature::Reference(self.read_reference_type()?)),
+        };
+        self.index += 1;
+        Ok(JavaTypeSignature::Base(base_type))
+    }
+
+    // All the recursion in signatures (through type arguments and bounds) goes through
+    // reference types, so limiting their depth bounds it.
+    fn read_reference_type(&mut self) -> Result<ReferenceTypeSignature<'a>, ParseError> {
+        if self.depth == self.max_depth {
+            self.too_deep = true;
+            fail!(
+                "Signature is nested more deeply than the maximum nesting depth {} at index {}",
+                self.max_depth,
+                self.index
+            );
+        }
+        self.depth += 1;
+        let result = self.read_unnested_reference_type();
+        self.depth -= 1;
+        result
+    }
+
+    fn read_unnested_reference_type(&mut self) -> Result<ReferenceTypeSignature<'a>, ParseError> {
+        match self.peek() {
+            Some(b'L') => Ok(ReferenceTypeSignature::Class(self.read_class_type()?)),
+            Some(b'T') => {
+                self.index += 1;
+                let name = self.read_identifier()?;
+                self.expect(b';')?;
+                Ok(ReferenceTypeSignature::TypeVariable(name))
+            }
+            Some(b'[') => {
+                // The dimensions are read in a loop rather than recursively, and limited
+                // in the same way as in descriptors
+                let start = self.index;
+                while self.peek() == Some(b'[') {
+                    self.index += 1;
+                }
+                let dimensions = self.index - start;
+                if dimensions > 255 {
+                    fail!(
+                        "Array type with {} dimensions at index {} of signature; at most 255 are allowed",
+                        dimensions,
+                        start
+                    );
+                }
+                let mut signature = ReferenceTypeSignature::Array(Box::new(self.read_java_type()?));
+                for _ in 1..dimensions {
+                    signature = ReferenceTypeSignature::Array(Box::new(
+                        JavaTypeSignature::Reference(signature),
+                    ));
+                }
+                Ok(signature)
+            }
+            Some(c) => fail!(
+                "Unexpected character '{}' at index {} of signature",
+                c as char,
+                self.index
+            ),
+            None => fail!("Unexpected end of signature"),
+        }
+    }
+
+    fn read_simple_class_type(
+        &mut self,
+        name: Cow<'a, str>,
+    ) -> Result<SimpleClassTypeSignature<'a>, ParseError> {
+        let mut type_arguments = Vec::new();
+        if self.peek() == Some(b'<') {
+            self.index += 1;
+            loop {
+                let type_argument = match self.peek() {
+                    Some(b'*') => {
+                        self.index += 1;
+                        TypeArgument::Any
+                    }
+                    Some(b'+') => {
+                        self.index += 1;
+                        TypeArgument::Extends(self.read_reference_type()?)
+                    }
+                    Some(b'-') => {
+                        self.index += 1;
+                        TypeArgument::Super(self.read_reference_type()?)
+                    }
+                    _ => TypeArgument::Exact(self.read_reference_type()?),
+                };
+                type_arguments.push(type_argument);
+                if self.peek() == Some(b'>') {
+                    self.index += 1;
+                    break;
+                }
+            }
+        }
+        Ok(SimpleClassTypeSignature {
+            name,
+            type_arguments,
+        })
+    }
+
+    fn read_class_type(&mut self) -> Result<ClassTypeSignature<'a>, ParseError> {
+        self.expect(b'L')?;
+        let mut package = Vec::new();
+        let mut name = self.read_identifier()?;
+        while self.peek() == Some(b'/') {
+            self.index += 1;
+            package.push(UnqualifiedSegment { name });
+            name = self.read_identifier()?;
+        }
+        let class = self.read_simple_class_type(name)?;
+        let mut suffixes = Vec::new();
+        while self.peek() == Some(b'.') {
+            self.index += 1;
+            let name = self.read_identifier()?;
+            suffixes.push(self.read_simple_class_type(name)?);
+        }
+        self.expect(b';')?;
+        Ok(ClassTypeSignature {
+            package,
+            class,
+            suffixes,
+        })
+    }
+
+    fn read_type_parameters(&mut self) -> Result<Vec<TypeParameter<'a>>, ParseError> {
+        let mut type_parameters = Vec::new();
+        if self.peek() != Some(b'<') {
+            return Ok(type_parameters);
+        }
+        self.index += 1;
+        loop {
+            let name = self.read_identifier()?;
+            self.expect(b':')?;
+            let class_bound = match self.peek() {
+                Some(b':') => None,
+                _ => Some(self.read_reference_type()?),
+            };
+            let mut interface_bounds = Vec::new();
+            while self.peek() == Some(b':') {
+                self.index += 1;
+                interface_bounds.push(self.read_reference_type()?);
+            }
+            type_parameters.push(TypeParameter {
+                name,
+                class_bound,
+                interface_bounds,
+            });
+            if self.peek() == Some(b'>') {
+                self.index += 1;
+                break;
+            }
+        }
+        Ok(type_parameters)
+    }
+}
+
+/// Parses the contents of a Signature attribute attached to a class, as described
+/// in section 4.7.9.1 of the JVM spec.
+pub fn parse_class_signature<'a>(data: &Cow<'a, str>) -> Result<ClassSignature<'a>, ParseError> {
+    parse_class_signature_with_depth(data, DEFAULT_MAX_NESTING_DEPTH)
+}
+
+pub(crate) fn parse_class_signature_with_depth<'a>(
+    data: &Cow<'a, str>,
+    max_depth: usize,
+) -> Result<ClassSignature<'a>, ParseError> {
+    let mut reader = SignatureReader::new(data, max_depth);
+    let type_parameters = reader.read_type_parameters()?;
+    let superclass = reader.read_class_type()?;
+    let mut superinterfaces = Vec::new();
+    while reader.peek().is_some() {
+        superinterfaces.push(reader.read_class_type()?);
+    }
+    Ok(ClassSignature {
+        type_parameters,
+        superclass,
+        superinterfaces,
+    })
+}
+
+/// Parses the contents of a Signature attribute attached to a method, as described
+/// in section 4.7.9.1 of the JVM spec.
+pub fn parse_method_signature<'a>(data: &Cow<'a, str>) -> Result<MethodSignature<'a>, ParseError> {
+    parse_method_signature_with_depth(data, DEFAULT_MAX_NESTING_DEPTH)
+}
+
+pub(crate) fn parse_method_signature_with_depth<'a>(
+    data: &Cow<'a, str>,
+    max_depth: usize,
+) -> Result<MethodSignature<'a>, ParseError> {
+    let mut reader = SignatureReader::new(data, max_depth);
+    let type_parameters = reader.read_type_parameters()?;
+    reader.expect(b'(')?;
+    let mut parameters = Vec::new();
+    while reader.peek() != Some(b')') {
+        parameters.push(reader.read_java_type()?);
+    }
+    reader.index += 1;
+    let result = if reader.peek() == Some(b'V') {
+        reader.index += 1;
+        ResultSignature::Void
+    } else {
+        ResultSignature::Return(reader.read_java_type()?)
+    };
+    let mut throws = Vec::new();
+    while reader.peek().is_some() {
+        reader.expect(b'^')?;
+        let throws_type = match reader.peek() {
+            Some(b'L') | Some(b'T') => reader.read_reference_type()?,
+            _ => fail!("Invalid throws signature at index {}", reader.index),
+        };
+        throws.push(throws_type);
+    }
+    Ok(MethodSignature {
+        type_parameters,
+        parameters,
+        result,
+        throws,
+    })
+}
+
+/// Parses the contents of a Signature attribute attached to a field or record
+/// component, as described in section 4.7.9.1 of the JVM spec.
+pub fn parse_field_signature<'a>(
+    data: &Cow<'a, str>,
+) -> Result<ReferenceTypeSignature<'a>, ParseError> {
+    parse_field_signature_with_depth(data, DEFAULT_MAX_NESTING_DEPTH)
+}
+
+pub(crate) fn parse_field_signature_with_depth<'a>(
+    data: &Cow<'a, str>,
+    max_depth: usize,
+) -> Result<ReferenceTypeSignature<'a>, ParseError> {
+    let mut reader = SignatureReader::new(data, max_depth);
+    let signature = reader.read_reference_type()?;
+    reader.ensure_finished()?;
+    Ok(signature)
+}
+
+// Checks only that a field signature isn't nested more deeply than max_depth, without
+// validating the rest of its grammar. Reading stops at the first malformed part, and
+// nothing parsing the signature later can get any deeper than that either.
+pub(crate) fn check_field_signature_depth(data: &str, max_depth: usize) -> Result<(), ParseError> {
+    let data = Cow::Borrowed(data);
+    let mut reader = SignatureReader::new(&data, max_depth);
+    match reader.read_reference_type() {
+        Err(e) if reader.too_deep => Err(e),
+        _ => Ok(()),
+    }
+}
+
+fn add_class_type_names<'a>(class_type: &ClassTypeSignature<'a>, names: &mut Vec<Cow<'a, str>>) {
+    let mut name = String::new();
+    for segment in &class_type.package {
+        name.push_str(&segment.name);
+        name.push('/');
+    }
+    name.push_str(&class_type.class.name);
+    for suffix in &class_type.suffixes {
+        name.push('$');
+        name.push_str(&suffix.name);
+    }
+    names.push(Cow::Owned(name));
+    add_type_argument_names(&class_type.class.type_arguments, names);
+    for suffix in &class_type.suffixes {
+        add_type_argument_names(&suffix.type_arguments, names);
+    }
+}
+
+fn add_type_argument_names<'a>(arguments: &[TypeArgument<'a>], names: &mut Vec<Cow<'a, str>>) {
+    for argument in arguments {
+        match argument {
+            TypeArgument::Any => (),
+            TypeArgument::Exact(bound)
+            | TypeArgument::Extends(bound)
+            | TypeArgument::Super(bound) => add_reference_type_names(bound, names),
+        }
+    }
+}
+
+fn add_reference_type_names<'a>(
+    reference_type: &ReferenceTypeSignature<'a>,
+    names: &mut Vec<Cow<'a, str>>,
+) {
+    match reference_type {
+        ReferenceTypeSignature::Class(class_type) => add_class_type_names(class_type, names),
+        ReferenceTypeSignature::TypeVariable(_) => (),
+        ReferenceTypeSignature::Array(component) => add_java_type_names(component, names),
+    }
+}
+
+fn add_java_type_names<'a>(java_type: &JavaTypeSignature<'a>, names: &mut Vec<Cow<'a, str>>) {
+    if let JavaTypeSignature::Reference(reference_type) = java_type {
+        add_reference_type_names(reference_type, names);
+    }
+}
+
+fn add_type_parameter_names<'a>(parameters: &[TypeParameter<'a>], names: &mut Vec<Cow<'a, str>>) {
+    for parameter in parameters {
+        for bound in parameter
+            .class_bound
+            .iter()
+            .chain(&parameter.interface_bounds)
+        {
+            add_reference_type_names(bound, names);
+        }
+    }
+}
+
+/// Returns the binary names of all the classes referenced by a field or method descriptor,
+/// or by the contents of a class, method or field Signature attribute, in the order they
+/// appear, with a parameterized class coming before its type arguments. This includes the
+/// classes nested in type arguments and type parameter bounds; type variables and
+/// primitive types are skipped, and a reference to an inner class of a parameterized class
+/// (such as `LOuter<TT;>.Inner;`) yields the name `Outer$Inner`.
+pub fn referenced_class_names<'a>(data: &Cow<'a, str>) -> Result<Vec<Cow<'a, str>>, ParseError> {
+    let mut names = Vec::new();
+    if data.contains('(') {
+        let signature = parse_method_signature(data)?;
+        add_type_parameter_names(&signature.type_parameters, &mut names);
+        for parameter in &signature.parameters {
+            add_java_type_names(parameter, &mut names);
+        }
+        if let ResultSignature::Return(result) = &signature.result {
+            add_java_type_names(result, &mut names);
+        }
+        for throws in &signature.throws {
+            add_reference_type_names(throws, &mut names);
+        }
+    } else if data.starts_with('<') {
+        let signature = parse_class_signature(data)?;
+        add_type_parameter_names(&signature.type_parameters, &mut names);
+        add_class_type_names(&signature.superclass, &mut names);
+        for interface in &signature.superinterfaces {
+            add_class_type_names(interface, &mut names);
+        }
+    } else if data.len() == 1 && "BCDFIJSZ".contains(data.as_ref()) {
+        // A primitive field descriptor references no classes
+    } else if let Ok(signature) = parse_field_signature(data) {
+        add_reference_type_names(&signature, &mut names);
+    } else {
+        // A class signature without type parameters, i.e. a superclass followed by
+        // superinterfaces
+        let signature = parse_class_signature(data)?;
+        add_class_type_names(&signature.superclass, &mut names);
+        for interface in &signature.superinterfaces {
+            add_class_type_names(interface, &mut names);
+        }
+    }
+    Ok(names)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn class_type(package: &[&'static str], name: &'static str) -> ClassTypeSignature<'static> {
+        ClassTypeSignature {
+            package: package
+                .iter()
+                .map(|segment| UnqualifiedSegment {
+                    name: Cow::Borrowed(*segment),
+                })
+                .collect(),
+            class: SimpleClassTypeSignature {
+                name: Cow::Borrowed(name),
+                type_arguments: vec![],
+            },
+            suffixes: vec![],
+        }
+    }
+
+    #[test]
+    fn test_field_signatures() {
+        let chars = Cow::from("Ljava/util/List<+Ljava/lang/Number;>;");
+        let mut expected = class_type(&["java", "util"], "List");
+        expected.class.type_arguments = vec![TypeArgument::Extends(ReferenceTypeSignature::Class(
+            class_type(&["java", "lang"], "Number"),
+        ))];
+        assert_eq!(
+            parse_field_signature(&chars).unwrap(),
+            ReferenceTypeSignature::Class(expected)
+        );
+
+        let chars = Cow::from("[TT;");
+        assert_eq!(
+            parse_field_signature(&chars).unwrap(),
+            ReferenceTypeSignature::Array(Box::new(JavaTypeSignature::Reference(
+                ReferenceTypeSignature::TypeVariable(Cow::Borrowed("T"))
+            )))
+        );
+
+        assert!(parse_field_signature(&Cow::from("Ljava/util/Map<**>.Entry<-TK;>;")).is_ok());
+        assert!(parse_field_signature(&Cow::from("[[I")).is_ok());
+
+        assert!(parse_field_signature(&Cow::from("I")).is_err());
+        assert!(parse_field_signature(&Cow::from("Ljava/util/List<>;")).is_err());
+        assert!(parse_field_signature(&Cow::from("Ljava/util/List;X")).is_err());
+        assert!(parse_field_signature(&Cow::from("Ljava/util/List")).is_err());
+        assert!(parse_field_signature(&Cow::from("TT")).is_err());
+    }
+
+    #[test]
+    fn test_class_signatures() {
+        let chars = Cow::from(
+            "<K:Ljava/lang/Object;V::Ljava/lang/Comparable<TV;>;>Ljava/lang/Object;Ljava/io/Serializable;",
+        );
+        let signature = parse_class_signature(&chars).unwrap();
+        assert_eq!(signature.type_parameters.len(), 2);
+        assert_eq!(signature.type_parameters[0].name, "K");
+        assert!(signature.type_parameters[0].class_bound.is_some());
+        assert_eq!(signature.type_parameters[1].name, "V");
+        assert!(signature.type_parameters[1].class_bound.is_none());
+        assert_eq!(signature.type_parameters[1].interface_bounds.len(), 1);
+        assert_eq!(
+            signature.superclass,
+            class_type(&["java", "lang"], "Object")
+        );
+        assert_eq!(
+            signature.superinterfaces,
+            vec![class_type(&["java", "io"], "Serializable")]
+        );
+
+        assert!(parse_class_signature(&Cow::from("<T>Ljava/lang/Object;")).is_err());
+        assert!(parse_class_signature(&Cow::from("TT;")).is_err());
+    }
+
+    #[test]
+    fn test_method_signatures() {
+        let chars = Cow::from("<T:Ljava/lang/Object;>(ITT;[J)TT;^Ljava/io/IOException;^TE;");
+        let signature = parse_method_signature(&chars).unwrap();
+        assert_eq!(signature.type_parameters.len(), 1);
+        assert_eq!(
+            signature.parameters,
+            vec![
+                JavaTypeSignature::Base(BaseType::Integer),
+                JavaTypeSignature::Reference(ReferenceTypeSignature::TypeVariable(Cow::Borrowed(
+                    "T"
+                ))),
+                JavaTypeSignature::Reference(ReferenceTypeSignature::Array(Box::new(
+                    JavaTypeSignature::Base(BaseType::Long)
+                ))),
+            ]
+        );
+        assert_eq!(
+            signature.result,
+            ResultSignature::Return(JavaTypeSignature::Reference(
+                ReferenceTypeSignature::TypeVariable(Cow::Borrowed("T"))
+            ))
+        );
+        assert_eq!(signature.throws.len(), 2);
+
+        assert!(parse_method_signature(&Cow::from("()V")).is_ok());
+        assert!(parse_method_signature(&Cow::from("()")).is_err());
+        assert!(parse_method_signature(&Cow::from("(V)V")).is_err());
+        assert!(parse_method_signature(&Cow::from("()V^[I")).is_err());
+    }
+
+    #[test]
+    fn test_owned_cow() {
+        let chars = Cow::from("Ljava/util/List<Ljava/lang/String;>;".to_string());
+        let signature = parse_field_signature(&chars).unwrap();
+        match signature {
+            ReferenceTypeSignature::Class(class) => {
+                assert_eq!(class.class.name, "List");
+                assert_eq!(class.class.type_arguments.len(), 1);
+            }
+            _ => panic!("Expected class type signature"),
+        }
+    }
+
+    #[test]
+    fn test_referenced_class_names() {
+        let names = |data: &'static str| -> Vec<String> {
+            referenced_class_names(&Cow::Borrowed(data))
+                .unwrap()
+                .iter()
+                .map(|name| name.to_string())
+                .collect()
+        };
+        assert!(names("I").is_empty());
+        assert!(names("TLoader;").is_empty());
+        assert_eq!(names("[Ljava/lang/String;"), ["java/lang/String"]);
+        assert_eq!(names("(I[La/B;J)Lc/D;"), ["a/B", "c/D"]);
+        assert!(names("([[IZ)V").is_empty());
+        assert_eq!(
+            names("Ljava/util/Map<Ljava/lang/String;Ljava/util/List<+La/B;>;>;"),
+            ["java/util/Map", "java/lang/String", "java/util/List", "a/B"]
+        );
+        assert_eq!(
+            names("<T:La/Base;U::Ljava/lang/Comparable<TU;>;>La/Outer<TT;>.Inner;Ljava/io/Serializable;"),
+            ["a/Base", "java/lang/Comparable", "a/Outer$Inner", "java/io/Serializable"]
+        );
+        assert_eq!(
+            names("<E:Ljava/lang/Exception;>(TE;)V^TE;^La/Failure;"),
+            ["java/lang/Exception", "a/Failure"]
+        );
+        assert_eq!(names("La/B;Lc/D;"), ["a/B", "c/D"]);
+        assert!(referenced_class_names(&Cow::Borrowed("La/B")).is_err());
+    }
+
+    #[test]
+    fn test_nesting_depth() {
+        let nested = |depth: usize| {
+            Cow::from(format!(
+                "{}Ljava/lang/Object;{}",
+                "Ljava/util/List<".repeat(depth - 1),
+                ">;".repeat(depth - 1)
+            ))
+        };
+        assert!(parse_field_signature(&nested(DEFAULT_MAX_NESTING_DEPTH)).is_ok());
+        assert_eq!(
+            parse_field_signature(&nested(DEFAULT_MAX_NESTING_DEPTH + 1))
+                .unwrap_err()
+                .to_string(),
+            format!(
+                "Signature is nested more deeply than the maximum nesting depth {} at index {}",
+                DEFAULT_MAX_NESTING_DEPTH,
+                16 * DEFAULT_MAX_NESTING_DEPTH
+            )
+        );
+        assert!(parse_field_signature_with_depth(&nested(3), 3).is_ok());
+        assert!(parse_field_signature_with_depth(&nested(4), 3).is_err());
+        assert!(
+            parse_method_signature_with_depth(&Cow::from("(Ljava/util/List<[[[I>;)V"), 2).is_ok()
+        );
+        assert!(check_field_signature_depth(&nested(3), 3).is_ok());
+        assert!(check_field_signature_depth(&nested(4), 3).is_err());
+        assert!(check_field_signature_depth(&Cow::from("Ljava/util/List<Lx;"), 3).is_ok());
+        assert!(check_field_signature_depth(&Cow::from("La/B;trailing"), 3).is_ok());
+
+        let array = |dimensions| Cow::from(format!("{}Ljava/lang/Object;", "[".repeat(dimensions)));
+        match parse_field_signature(&array(255)).unwrap() {
+            ReferenceTypeSignature::Array(component) => assert_eq!(
+                *component,
+                JavaTypeSignature::Reference(parse_field_signature(&array(254)).unwrap())
+            ),
+            _ => panic!("Expected array type signature"),
+        }
+        assert_eq!(
+            parse_field_signature(&array(256)).unwrap_err().to_string(),
+            "Array type with 256 dimensions at index 0 of signature; at most 255 are allowed"
+        );
+    }
+}
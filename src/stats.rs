@@ -0,0 +1,52 @@
+//! Statistics about a parsed class file, as returned by
+//! [`parse_class_with_stats`](crate::parse_class_with_stats).
+
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+use crate::attributes::{AttributeData, AttributeInfo};
+use crate::constant_pool::count_constant_pool_entries;
+use crate::ClassFile;
+
+/// Counts and timings gathered while parsing a class file, for profiling the parser and
+/// finding out what a class is made of.
+#[derive(Clone, Debug, Default)]
+pub struct ParseStats {
+    /// The number of constant pool entries of each type, keyed by type name (e.g.
+    /// "Utf8" or "MethodRef"). Index 0 and the unusable index after each Long and Double
+    /// entry are not counted.
+    pub constant_pool_entries: BTreeMap<&'static str, usize>,
+    /// The number of bytes of the input that make up the class file.
+    pub bytes_consumed: usize,
+    /// The number of attributes with each name, counting the attributes of the class,
+    /// its fields and methods, and the attributes nested in Code attributes.
+    pub attributes: BTreeMap<String, usize>,
+    /// The time taken to parse the class header, which includes reading, resolving and
+    /// validating the constant pool.
+    pub header_time: Duration,
+    /// The time taken to parse the whole class file.
+    pub total_time: Duration,
+}
+
+impl ParseStats {
+    pub(crate) fn record_class(&mut self, class: &ClassFile) {
+        self.constant_pool_entries = count_constant_pool_entries(&class.constant_pool);
+        self.bytes_consumed = class.byte_length;
+        self.record_attributes(&class.attributes);
+        for field in &class.fields {
+            self.record_attributes(&field.attributes);
+        }
+        for method in &class.methods {
+            self.record_attributes(&method.attributes);
+        }
+    }
+
+    fn record_attributes(&mut self, attributes: &[AttributeInfo]) {
+        for attr in attributes {
+            *self.attributes.entry(attr.name.to_string()).or_insert(0) += 1;
+            if let AttributeData::Code(code) = &attr.data {
+                self.record_attributes(&code.attributes);
+            }
+        }
+    }
+}
@@ -0,0 +1,1312 @@
+// A lightweight check of a method's StackMapTable against its bytecode, for tools that
+// generate bytecode and want to catch bad frames without loading the class in a JVM. The
+// instructions are interpreted abstractly to follow the types of the locals and the
+// operand stack, which are checked against the frames at branch targets and exception
+// handlers. Reference types are not checked against the class hierarchy, since that
+// would need the other classes to be loaded.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::attributes::{AttributeData, ExceptionTableEntry, StackMapEntry, VerificationType};
+use crate::bytecode::{ByteCode, JumpOffset, Opcode};
+use crate::constant_pool::{LiteralConstant, Loadable};
+use crate::descriptors::{
+    parse_field_descriptor, parse_method_descriptor, FieldDescriptor, FieldType, ReturnDescriptor,
+};
+use crate::{ClassFile, MethodAccessFlags, MethodInfo, ParseError};
+
+// Values of type long and double take up two local variable or operand stack slots.
+fn verification_slots(verification_type: &VerificationType) -> usize {
+    match verification_type {
+        VerificationType::Long | VerificationType::Double => 2,
+        _ => 1,
+    }
+}
+
+fn jump_target(offset: usize, jump: JumpOffset) -> usize {
+    // Jumps are validated when the bytecode is parsed, so this is never negative
+    (offset as i64 + i64::from(jump)) as usize
+}
+
+// The type of a local variable or operand stack slot, as tracked by the interpreter. This
+// is a coarser version of the verification types of section 4.10.1.2, in which all
+// initialized references have the same type.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Type {
+    // An unusable value, which is also the type of the second slot of a long or double
+    Top,
+    Int,
+    Float,
+    Long,
+    Double,
+    Null,
+    Reference,
+    UninitializedThis,
+    Uninitialized(u16),
+}
+
+impl Type {
+    fn from_verification_type(verification_type: &VerificationType) -> Type {
+        match verification_type {
+            VerificationType::Top => Type::Top,
+            VerificationType::Integer => Type::Int,
+            VerificationType::Float => Type::Float,
+            VerificationType::Long => Type::Long,
+            VerificationType::Double => Type::Double,
+            VerificationType::Null => Type::Null,
+            VerificationType::UninitializedThis => Type::UninitializedThis,
+            VerificationType::Uninitialized { code_offset } => Type::Uninitialized(*code_offset),
+            VerificationType::Object { .. } => Type::Reference,
+        }
+    }
+
+    // Boolean, byte, char and short values are all represented as ints by the JVM.
+    fn from_descriptor(descriptor: &FieldDescriptor) -> Type {
+        if descriptor.dimensions > 0 {
+            return Type::Reference;
+        }
+        match descriptor.field_type {
+            FieldType::Boolean
+            | FieldType::Byte
+            | FieldType::Char
+            | FieldType::Short
+            | FieldType::Integer => Type::Int,
+            FieldType::Float => Type::Float,
+            FieldType::Long => Type::Long,
+            FieldType::Double => Type::Double,
+            FieldType::Object(_) => Type::Reference,
+        }
+    }
+
+    fn slots(self) -> usize {
+        match self {
+            Type::Long | Type::Double => 2,
+            _ => 1,
+        }
+    }
+
+    // Returns true for references, including ones to objects that are not yet initialized.
+    fn is_reference(self) -> bool {
+        matches!(
+            self,
+            Type::Null | Type::Reference | Type::UninitializedThis | Type::Uninitialized(_)
+        )
+    }
+
+    // Returns true if a value of this type can be used where the given type is expected.
+    fn is_assignable_to(self, expected: Type) -> bool {
+        match expected {
+            Type::Top => true,
+            Type::Reference => matches!(self, Type::Null | Type::Reference),
+            _ => self == expected,
+        }
+    }
+}
+
+// Appends the slots taken by a value of the given type.
+fn push_slots(slots: &mut Vec<Type>, value: Type) {
+    slots.push(value);
+    if value.slots() == 2 {
+        slots.push(Type::Top);
+    }
+}
+
+// The types of the local variables and operand stack, with one entry per slot.
+#[derive(Clone, Debug)]
+struct TypeState {
+    locals: Vec<Type>,
+    stack: Vec<Type>,
+}
+
+impl TypeState {
+    fn new(locals: &[Type], stack: &[Type], max_locals: usize) -> TypeState {
+        let mut state = TypeState {
+            locals: Vec::with_capacity(max_locals),
+            stack: Vec::new(),
+        };
+        for local in locals {
+            push_slots(&mut state.locals, *local);
+        }
+        state.locals.resize(max_locals, Type::Top);
+        for value in stack {
+            push_slots(&mut state.stack, *value);
+        }
+        state
+    }
+
+    fn check_locals(&self, frame: &TypeState) -> Result<(), ParseError> {
+        for (i, (actual, expected)) in self.locals.iter().zip(&frame.locals).enumerate() {
+            if !actual.is_assignable_to(*expected) {
+                fail!(
+                    "Local variable {} has type {:?} but the frame expects {:?}",
+                    i,
+                    actual,
+                    expected
+                );
+            }
+        }
+        Ok(())
+    }
+
+    // Checks that this state can flow into an instruction with the given frame.
+    fn check_frame(&self, frame: &TypeState) -> Result<(), ParseError> {
+        self.check_locals(frame)?;
+        if self.stack.len() != frame.stack.len() {
+            fail!(
+                "Stack has {} slots but the frame has {}",
+                self.stack.len(),
+                frame.stack.len()
+            );
+        }
+        for (i, (actual, expected)) in self.stack.iter().zip(&frame.stack).enumerate() {
+            if !actual.is_assignable_to(*expected) {
+                fail!(
+                    "Stack slot {} has type {:?} but the frame expects {:?}",
+                    i,
+                    actual,
+                    expected
+                );
+            }
+        }
+        Ok(())
+    }
+}
+
+// Follows the effect of instructions on the type state of a method.
+struct Interpreter {
+    state: TypeState,
+    max_stack: usize,
+}
+
+impl Interpreter {
+    fn push(&mut self, value: Type) -> Result<(), ParseError> {
+        if self.state.stack.len() + value.slots() > self.max_stack {
+            fail!("Stack overflow; max_stack is {}", self.max_stack);
+        }
+        push_slots(&mut self.state.stack, value);
+        Ok(())
+    }
+
+    fn pop_slot(&mut self) -> Result<Type, ParseError> {
+        match self.state.stack.pop() {
+            Some(value) => Ok(value),
+            None => fail!("Stack underflow"),
+        }
+    }
+
+    fn pop(&mut self, expected: Type) -> Result<Type, ParseError> {
+        if expected.slots() == 2 && self.pop_slot()? != Type::Top {
+            fail!("Expected {:?} on the stack", expected);
+        }
+        let value = self.pop_slot()?;
+        if !value.is_assignable_to(expected) {
+            fail!("Expected {:?} on the stack but found {:?}", expected, value);
+        }
+        Ok(value)
+    }
+
+    // Pops a reference which may be to an object that is not yet initialized.
+    fn pop_any_reference(&mut self) -> Result<Type, ParseError> {
+        let value = self.pop_slot()?;
+        if !value.is_reference() {
+            fail!("Expected a reference on the stack but found {:?}", value);
+        }
+        Ok(value)
+    }
+
+    // Pops the given number of slots for a stack manipulation instruction, which must not
+    // take only half of a long or double.
+    fn pop_group(&mut self, slots: usize) -> Result<Vec<Type>, ParseError> {
+        let len = self.state.stack.len();
+        if len < slots {
+            fail!("Stack underflow");
+        }
+        let group = self.state.stack.split_off(len - slots);
+        if group[0] == Type::Top {
+            fail!("Instruction splits a long or double value on the stack");
+        }
+        Ok(group)
+    }
+
+    fn push_group(&mut self, group: &[Type]) -> Result<(), ParseError> {
+        if self.state.stack.len() + group.len() > self.max_stack {
+            fail!("Stack overflow; max_stack is {}", self.max_stack);
+        }
+        self.state.stack.extend_from_slice(group);
+        Ok(())
+    }
+
+    fn local(&self, index: u16, slots: usize) -> Result<usize, ParseError> {
+        let index = usize::from(index);
+        if index + slots > self.state.locals.len() {
+            fail!(
+                "Local variable {} is out of range; max_locals is {}",
+                index,
+                self.state.locals.len()
+            );
+        }
+        Ok(index)
+    }
+
+    fn load(&mut self, index: u16, expected: Type) -> Result<(), ParseError> {
+        let index = self.local(index, expected.slots())?;
+        let value = self.state.locals[index];
+        if !value.is_assignable_to(expected) {
+            fail!(
+                "Expected {:?} in local variable {} but found {:?}",
+                expected,
+                index,
+                value
+            );
+        }
+        self.push(expected)
+    }
+
+    fn store(&mut self, index: u16, value: Type) -> Result<(), ParseError> {
+        let index = self.local(index, value.slots())?;
+        let locals = &mut self.state.locals;
+        // Overwriting the second slot of a long or double makes the value unusable
+        if index > 0 && locals[index - 1].slots() == 2 {
+            locals[index - 1] = Type::Top;
+        }
+        locals[index] = value;
+        if value.slots() == 2 {
+            locals[index + 1] = Type::Top;
+        }
+        Ok(())
+    }
+
+    fn binary(&mut self, operand: Type) -> Result<(), ParseError> {
+        self.pop(operand)?;
+        self.pop(operand)?;
+        self.push(operand)
+    }
+
+    fn convert(&mut self, from: Type, to: Type) -> Result<(), ParseError> {
+        self.pop(from)?;
+        self.push(to)
+    }
+
+    fn pop_arguments(&mut self, parameters: &[FieldDescriptor]) -> Result<(), ParseError> {
+        for parameter in parameters.iter().rev() {
+            self.pop(Type::from_descriptor(parameter))?;
+        }
+        Ok(())
+    }
+
+    fn push_return(&mut self, return_type: &ReturnDescriptor) -> Result<(), ParseError> {
+        match return_type {
+            ReturnDescriptor::Return(descriptor) => self.push(Type::from_descriptor(descriptor)),
+            ReturnDescriptor::Void => Ok(()),
+        }
+    }
+
+    // Calling an instance initializer initializes the object it is called on, replacing
+    // every copy of its uninitialized type (section 4.10.1.9.invokespecial).
+    fn initialize(&mut self, uninitialized: Type) {
+        let state = &mut self.state;
+        for value in state.locals.iter_mut().chain(state.stack.iter_mut()) {
+            if *value == uninitialized {
+                *value = Type::Reference;
+            }
+        }
+    }
+
+    fn ldc(&mut self, loadable: &Loadable, wide: bool) -> Result<(), ParseError> {
+        let value = match loadable {
+            Loadable::LiteralConstant(LiteralConstant::Integer(_)) => Type::Int,
+            Loadable::LiteralConstant(LiteralConstant::Float(_)) => Type::Float,
+            Loadable::LiteralConstant(LiteralConstant::Long(_)) => Type::Long,
+            Loadable::LiteralConstant(LiteralConstant::Double(_)) => Type::Double,
+            Loadable::LiteralConstant(
+                LiteralConstant::String(_) | LiteralConstant::StringBytes(_),
+            )
+            | Loadable::ClassInfo(_)
+            | Loadable::MethodHandle(_)
+            | Loadable::MethodType(_) => Type::Reference,
+            Loadable::Dynamic(dynamic) => {
+                parse_field_descriptor(&dynamic.name_and_type.descriptor, 0)
+                    .map(|descriptor| Type::from_descriptor(&descriptor))?
+            }
+        };
+        if wide != (value.slots() == 2) {
+            fail!(
+                "Constant of type {:?} can't be loaded by {}",
+                value,
+                if wide { "ldc2_w" } else { "ldc or ldc_w" }
+            );
+        }
+        self.push(value)
+    }
+}
+
+// Checks a verification type that refers back into the bytecode: an uninitialized value
+// must have been created by a `new` instruction at its offset, and only an instance
+// initializer has an uninitialized `this` (section 4.10.1.2).
+fn check_uninitialized(
+    verification_type: &VerificationType,
+    bytecode: &ByteCode,
+    is_init: bool,
+) -> Result<(), ParseError> {
+    match verification_type {
+        VerificationType::Uninitialized { code_offset } => {
+            let instruction = bytecode
+                .get_opcode_index(usize::from(*code_offset))
+                .map(|i| &bytecode.opcodes[i].1);
+            if !matches!(instruction, Some(Opcode::New(_))) {
+                fail!(
+                    "Uninitialized offset {} is not a new instruction",
+                    code_offset
+                );
+            }
+        }
+        VerificationType::UninitializedThis if !is_init => {
+            fail!("Uninitialized this outside of an instance initializer")
+        }
+        _ => (),
+    }
+    Ok(())
+}
+
+// Returns the targets of the jumps of an instruction.
+fn jump_targets(offset: usize, opcode: &Opcode) -> Vec<usize> {
+    match opcode {
+        Opcode::Goto(jump)
+        | Opcode::IfAcmpeq(jump)
+        | Opcode::IfAcmpne(jump)
+        | Opcode::IfIcmpeq(jump)
+        | Opcode::IfIcmpge(jump)
+        | Opcode::IfIcmpgt(jump)
+        | Opcode::IfIcmple(jump)
+        | Opcode::IfIcmplt(jump)
+        | Opcode::IfIcmpne(jump)
+        | Opcode::Ifeq(jump)
+        | Opcode::Ifge(jump)
+        | Opcode::Ifgt(jump)
+        | Opcode::Ifle(jump)
+        | Opcode::Iflt(jump)
+        | Opcode::Ifne(jump)
+        | Opcode::Ifnonnull(jump)
+        | Opcode::Ifnull(jump) => vec![jump_target(offset, *jump)],
+        Opcode::Lookupswitch(table) => std::iter::once(table.default)
+            .chain(table.match_offsets.iter().map(|(_, jump)| *jump))
+            .map(|jump| jump_target(offset, jump))
+            .collect(),
+        Opcode::Tableswitch(table) => std::iter::once(table.default)
+            .chain(table.jumps.iter().copied())
+            .map(|jump| jump_target(offset, jump))
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+// Returns true for instructions after which execution does not continue with the next
+// instruction.
+fn is_unconditional(opcode: &Opcode) -> bool {
+    matches!(
+        opcode,
+        Opcode::Goto(_)
+            | Opcode::Lookupswitch(_)
+            | Opcode::Tableswitch(_)
+            | Opcode::Areturn
+            | Opcode::Dreturn
+            | Opcode::Freturn
+            | Opcode::Ireturn
+            | Opcode::Lreturn
+            | Opcode::Return
+            | Opcode::Athrow
+    )
+}
+
+// Returns the offsets at which the type checker requires a stack map frame (section
+// 4.10.1): the targets of jumps, and the instructions following an unconditional jump,
+// return or throw.
+fn required_frames(bytecode: &ByteCode) -> BTreeSet<usize> {
+    let mut offsets = BTreeSet::new();
+    for (i, (offset, opcode)) in bytecode.opcodes.iter().enumerate() {
+        offsets.extend(jump_targets(*offset, opcode));
+        if is_unconditional(opcode) {
+            if let Some((next, _)) = bytecode.opcodes.get(i + 1) {
+                offsets.insert(*next);
+            }
+        }
+    }
+    offsets
+}
+
+// Applies the effect of an instruction to the type state. Jumps are left to the caller.
+fn execute(
+    interpreter: &mut Interpreter,
+    offset: usize,
+    opcode: &Opcode,
+    method: &MethodInfo,
+) -> Result<(), ParseError> {
+    let i = interpreter;
+    match opcode {
+        Opcode::Nop | Opcode::Goto(_) => (),
+        Opcode::AconstNull => i.push(Type::Null)?,
+        Opcode::IconstM1
+        | Opcode::Iconst0
+        | Opcode::Iconst1
+        | Opcode::Iconst2
+        | Opcode::Iconst3
+        | Opcode::Iconst4
+        | Opcode::Iconst5
+        | Opcode::Bipush(_)
+        | Opcode::Sipush(_) => i.push(Type::Int)?,
+        Opcode::Lconst0 | Opcode::Lconst1 => i.push(Type::Long)?,
+        Opcode::Fconst0 | Opcode::Fconst1 | Opcode::Fconst2 => i.push(Type::Float)?,
+        Opcode::Dconst0 | Opcode::Dconst1 => i.push(Type::Double)?,
+        Opcode::Ldc(loadable) | Opcode::LdcW(loadable) => i.ldc(loadable, false)?,
+        Opcode::Ldc2W(loadable) => i.ldc(loadable, true)?,
+        Opcode::Iload(index) => i.load(*index, Type::Int)?,
+        Opcode::Lload(index) => i.load(*index, Type::Long)?,
+        Opcode::Fload(index) => i.load(*index, Type::Float)?,
+        Opcode::Dload(index) => i.load(*index, Type::Double)?,
+        Opcode::Aload(index) => {
+            let index = i.local(*index, 1)?;
+            let value = i.state.locals[index];
+            if !value.is_reference() {
+                fail!(
+                    "Expected a reference in local variable {} but found {:?}",
+                    index,
+                    value
+                );
+            }
+            i.push(value)?;
+        }
+        Opcode::Istore(index) => {
+            let value = i.pop(Type::Int)?;
+            i.store(*index, value)?;
+        }
+        Opcode::Lstore(index) => {
+            let value = i.pop(Type::Long)?;
+            i.store(*index, value)?;
+        }
+        Opcode::Fstore(index) => {
+            let value = i.pop(Type::Float)?;
+            i.store(*index, value)?;
+        }
+        Opcode::Dstore(index) => {
+            let value = i.pop(Type::Double)?;
+            i.store(*index, value)?;
+        }
+        Opcode::Astore(index) => {
+            let value = i.pop_any_reference()?;
+            i.store(*index, value)?;
+        }
+        Opcode::Iinc(index, _) => {
+            let index = i.local(*index, 1)?;
+            if i.state.locals[index] != Type::Int {
+                fail!(
+                    "Expected {:?} in local variable {} but found {:?}",
+                    Type::Int,
+                    index,
+                    i.state.locals[index]
+                );
+            }
+        }
+        Opcode::Iaload | Opcode::Baload | Opcode::Caload | Opcode::Saload => {
+            i.pop(Type::Int)?;
+            i.pop(Type::Reference)?;
+            i.push(Type::Int)?;
+        }
+        Opcode::Laload => {
+            i.pop(Type::Int)?;
+            i.pop(Type::Reference)?;
+            i.push(Type::Long)?;
+        }
+        Opcode::Faload => {
+            i.pop(Type::Int)?;
+            i.pop(Type::Reference)?;
+            i.push(Type::Float)?;
+        }
+        Opcode::Daload => {
+            i.pop(Type::Int)?;
+            i.pop(Type::Reference)?;
+            i.push(Type::Double)?;
+        }
+        Opcode::Aaload => {
+            i.pop(Type::Int)?;
+            i.pop(Type::Reference)?;
+            i.push(Type::Reference)?;
+        }
+        Opcode::Iastore | Opcode::Bastore | Opcode::Castore | Opcode::Sastore => {
+            i.pop(Type::Int)?;
+            i.pop(Type::Int)?;
+            i.pop(Type::Reference)?;
+        }
+        Opcode::Lastore => {
+            i.pop(Type::Long)?;
+            i.pop(Type::Int)?;
+            i.pop(Type::Reference)?;
+        }
+        Opcode::Fastore => {
+            i.pop(Type::Float)?;
+            i.pop(Type::Int)?;
+            i.pop(Type::Reference)?;
+        }
+        Opcode::Dastore => {
+            i.pop(Type::Double)?;
+            i.pop(Type::Int)?;
+            i.pop(Type::Reference)?;
+        }
+        Opcode::Aastore => {
+            i.pop(Type::Reference)?;
+            i.pop(Type::Int)?;
+            i.pop(Type::Reference)?;
+        }
+        Opcode::Pop => {
+            i.pop_group(1)?;
+        }
+        Opcode::Pop2 => {
+            i.pop_group(2)?;
+        }
+        Opcode::Dup => {
+            let value = i.pop_group(1)?;
+            i.push_group(&value)?;
+            i.push_group(&value)?;
+        }
+        Opcode::DupX1 => {
+            let value1 = i.pop_group(1)?;
+            let value2 = i.pop_group(1)?;
+            i.push_group(&value1)?;
+            i.push_group(&value2)?;
+            i.push_group(&value1)?;
+        }
+        Opcode::DupX2 => {
+            let value1 = i.pop_group(1)?;
+            let value2 = i.pop_group(2)?;
+            i.push_group(&value1)?;
+            i.push_group(&value2)?;
+            i.push_group(&value1)?;
+        }
+        Opcode::Dup2 => {
+            let value = i.pop_group(2)?;
+            i.push_group(&value)?;
+            i.push_group(&value)?;
+        }
+        Opcode::Dup2X1 => {
+            let value1 = i.pop_group(2)?;
+            let value2 = i.pop_group(1)?;
+            i.push_group(&value1)?;
+            i.push_group(&value2)?;
+            i.push_group(&value1)?;
+        }
+        Opcode::Dup2X2 => {
+            let value1 = i.pop_group(2)?;
+            let value2 = i.pop_group(2)?;
+            i.push_group(&value1)?;
+            i.push_group(&value2)?;
+            i.push_group(&value1)?;
+        }
+        Opcode::Swap => {
+            let value1 = i.pop_group(1)?;
+            let value2 = i.pop_group(1)?;
+            i.push_group(&value1)?;
+            i.push_group(&value2)?;
+        }
+        Opcode::Iadd
+        | Opcode::Isub
+        | Opcode::Imul
+        | Opcode::Idiv
+        | Opcode::Irem
+        | Opcode::Iand
+        | Opcode::Ior
+        | Opcode::Ixor
+        | Opcode::Ishl
+        | Opcode::Ishr
+        | Opcode::Iushr => i.binary(Type::Int)?,
+        Opcode::Ladd
+        | Opcode::Lsub
+        | Opcode::Lmul
+        | Opcode::Ldiv
+        | Opcode::Lrem
+        | Opcode::Land
+        | Opcode::Lor
+        | Opcode::Lxor => i.binary(Type::Long)?,
+        Opcode::Lshl | Opcode::Lshr | Opcode::Lushr => {
+            i.pop(Type::Int)?;
+            i.pop(Type::Long)?;
+            i.push(Type::Long)?;
+        }
+        Opcode::Fadd | Opcode::Fsub | Opcode::Fmul | Opcode::Fdiv | Opcode::Frem => {
+            i.binary(Type::Float)?
+        }
+        Opcode::Dadd | Opcode::Dsub | Opcode::Dmul | Opcode::Ddiv | Opcode::Drem => {
+            i.binary(Type::Double)?
+        }
+        Opcode::Ineg | Opcode::I2b | Opcode::I2c | Opcode::I2s => {
+            i.convert(Type::Int, Type::Int)?
+        }
+        Opcode::Lneg => i.convert(Type::Long, Type::Long)?,
+        Opcode::Fneg => i.convert(Type::Float, Type::Float)?,
+        Opcode::Dneg => i.convert(Type::Double, Type::Double)?,
+        Opcode::I2l => i.convert(Type::Int, Type::Long)?,
+        Opcode::I2f => i.convert(Type::Int, Type::Float)?,
+        Opcode::I2d => i.convert(Type::Int, Type::Double)?,
+        Opcode::L2i => i.convert(Type::Long, Type::Int)?,
+        Opcode::L2f => i.convert(Type::Long, Type::Float)?,
+        Opcode::L2d => i.convert(Type::Long, Type::Double)?,
+        Opcode::F2i => i.convert(Type::Float, Type::Int)?,
+        Opcode::F2l => i.convert(Type::Float, Type::Long)?,
+        Opcode::F2d => i.convert(Type::Float, Type::Double)?,
+        Opcode::D2i => i.convert(Type::Double, Type::Int)?,
+        Opcode::D2l => i.convert(Type::Double, Type::Long)?,
+        Opcode::D2f => i.convert(Type::Double, Type::Float)?,
+        Opcode::Lcmp => {
+            i.pop(Type::Long)?;
+            i.pop(Type::Long)?;
+            i.push(Type::Int)?;
+        }
+        Opcode::Fcmpl | Opcode::Fcmpg => {
+            i.pop(Type::Float)?;
+            i.pop(Type::Float)?;
+            i.push(Type::Int)?;
+        }
+        Opcode::Dcmpl | Opcode::Dcmpg => {
+            i.pop(Type::Double)?;
+            i.pop(Type::Double)?;
+            i.push(Type::Int)?;
+        }
+        Opcode::Ifeq(_)
+        | Opcode::Ifne(_)
+        | Opcode::Iflt(_)
+        | Opcode::Ifge(_)
+        | Opcode::Ifgt(_)
+        | Opcode::Ifle(_)
+        | Opcode::Tableswitch(_)
+        | Opcode::Lookupswitch(_) => {
+            i.pop(Type::Int)?;
+        }
+        Opcode::IfIcmpeq(_)
+        | Opcode::IfIcmpne(_)
+        | Opcode::IfIcmplt(_)
+        | Opcode::IfIcmpge(_)
+        | Opcode::IfIcmpgt(_)
+        | Opcode::IfIcmple(_) => {
+            i.pop(Type::Int)?;
+            i.pop(Type::Int)?;
+        }
+        Opcode::IfAcmpeq(_) | Opcode::IfAcmpne(_) => {
+            i.pop(Type::Reference)?;
+            i.pop(Type::Reference)?;
+        }
+        Opcode::Ifnull(_)
+        | Opcode::Ifnonnull(_)
+        | Opcode::Athrow
+        | Opcode::Monitorenter
+        | Opcode::Monitorexit => {
+            i.pop(Type::Reference)?;
+        }
+        Opcode::Ireturn | Opcode::Lreturn | Opcode::Freturn | Opcode::Dreturn | Opcode::Areturn => {
+            let expected = match &method.descriptor.return_type {
+                ReturnDescriptor::Return(descriptor) => Type::from_descriptor(descriptor),
+                ReturnDescriptor::Void => fail!("Value returned from void method"),
+            };
+            let instruction = match opcode {
+                Opcode::Ireturn => Type::Int,
+                Opcode::Lreturn => Type::Long,
+                Opcode::Freturn => Type::Float,
+                Opcode::Dreturn => Type::Double,
+                _ => Type::Reference,
+            };
+            if instruction != expected {
+                fail!(
+                    "Return of {:?} from method returning {:?}",
+                    instruction,
+                    expected
+                );
+            }
+            i.pop(expected)?;
+        }
+        Opcode::Return => {
+            if !matches!(method.descriptor.return_type, ReturnDescriptor::Void) {
+                fail!("Return without a value from non-void method");
+            }
+            if i.state.locals.contains(&Type::UninitializedThis) {
+                fail!("Return from instance initializer before this is initialized");
+            }
+        }
+        Opcode::Getstatic(member) => {
+            let descriptor = parse_field_descriptor(&member.name_and_type.descriptor, 0)?;
+            i.push(Type::from_descriptor(&descriptor))?;
+        }
+        Opcode::Putstatic(member) => {
+            let descriptor = parse_field_descriptor(&member.name_and_type.descriptor, 0)?;
+            i.pop(Type::from_descriptor(&descriptor))?;
+        }
+        Opcode::Getfield(member) => {
+            let descriptor = parse_field_descriptor(&member.name_and_type.descriptor, 0)?;
+            i.pop(Type::Reference)?;
+            i.push(Type::from_descriptor(&descriptor))?;
+        }
+        Opcode::Putfield(member) => {
+            let descriptor = parse_field_descriptor(&member.name_and_type.descriptor, 0)?;
+            i.pop(Type::from_descriptor(&descriptor))?;
+            // A constructor may assign fields before calling the superclass constructor
+            let object = i.pop_any_reference()?;
+            if let Type::Uninitialized(_) = object {
+                fail!("Field assigned on uninitialized object");
+            }
+        }
+        Opcode::Invokevirtual(member)
+        | Opcode::Invokespecial(member)
+        | Opcode::Invokestatic(member)
+        | Opcode::Invokeinterface(member, _) => {
+            let descriptor = parse_method_descriptor(&member.name_and_type.descriptor, 0)?;
+            i.pop_arguments(&descriptor.parameters)?;
+            if member.name_and_type.name == "<init>" {
+                match i.pop_any_reference()? {
+                    uninitialized @ (Type::UninitializedThis | Type::Uninitialized(_)) => {
+                        i.initialize(uninitialized)
+                    }
+                    object => fail!("Instance initializer called on {:?}", object),
+                }
+            } else if !matches!(opcode, Opcode::Invokestatic(_)) {
+                i.pop(Type::Reference)?;
+            }
+            i.push_return(&descriptor.return_type)?;
+        }
+        Opcode::Invokedynamic(invoke_dynamic) => {
+            let descriptor = parse_method_descriptor(&invoke_dynamic.name_and_type.descriptor, 0)?;
+            i.pop_arguments(&descriptor.parameters)?;
+            i.push_return(&descriptor.return_type)?;
+        }
+        // The offset fits in a u16 since the code is shorter than 65536 bytes
+        Opcode::New(_) => i.push(Type::Uninitialized(offset as u16))?,
+        Opcode::Newarray(_) | Opcode::Anewarray(_) => i.convert(Type::Int, Type::Reference)?,
+        Opcode::Multianewarray(_, dimensions) => {
+            for _ in 0..*dimensions {
+                i.pop(Type::Int)?;
+            }
+            i.push(Type::Reference)?;
+        }
+        Opcode::Arraylength | Opcode::Instanceof(_) => i.convert(Type::Reference, Type::Int)?,
+        Opcode::Checkcast(_) => i.convert(Type::Reference, Type::Reference)?,
+        Opcode::Jsr(_) | Opcode::Ret(_) => fail!("Subroutines are not supported"),
+        Opcode::Breakpoint | Opcode::Impdep1 | Opcode::Impdep2 => fail!("Reserved opcode"),
+    }
+    Ok(())
+}
+
+// Checks that the locals at an instruction covered by an exception handler can flow into
+// the handler, and that the handler's frame holds the exception on its stack.
+fn check_handler(
+    state: &TypeState,
+    entry: &ExceptionTableEntry,
+    frames: &BTreeMap<usize, (TypeState, usize)>,
+) -> Result<(), ParseError> {
+    // The frames of all handlers were checked to exist before interpreting the code
+    let (frame, _) = &frames[&usize::from(entry.handler_pc)];
+    state.check_locals(frame)?;
+    if frame.stack.len() != 1 || !Type::Reference.is_assignable_to(frame.stack[0]) {
+        fail!(
+            "Stack map frame at handler_pc {} does not hold the exception",
+            entry.handler_pc
+        );
+    }
+    Ok(())
+}
+
+impl<'a> MethodInfo<'a> {
+    /// Checks the StackMapTable of this method's Code attribute against its bytecode, as a
+    /// cheap pre-flight check for tools that generate bytecode. This needs the class that
+    /// declares the method, for its name and major version.
+    ///
+    /// Every frame must be at an instruction and fit within max_locals and max_stack, and
+    /// uninitialized values must refer to the offset of a `new` instruction, with
+    /// uninitialized `this` only appearing in a constructor. From version 50, which
+    /// introduced the StackMapTable, a frame is also required at every jump target,
+    /// exception handler and instruction following an unconditional jump, return or
+    /// throw. The instructions are then interpreted abstractly, following the types of
+    /// the locals and the operand stack from the method's arguments and from each frame,
+    /// and checking that every instruction gets operands of the types it needs and that
+    /// the types flowing into each jump target and exception handler match its frame.
+    /// Older class files are verified by type inference, which ignores the StackMapTable,
+    /// so only the placement of their frames is checked.
+    ///
+    /// This is lighter than the JVM's verifier: all references are treated as having the
+    /// same type, since checking one class against another would need the class
+    /// hierarchy, and methods using the `jsr` and `ret` instructions, which only class
+    /// files before version 51 may use, are not interpreted. A method without code passes
+    /// trivially; otherwise its bytecode must have been parsed (see
+    /// [`ParseOptions::parse_bytecode`]).
+    ///
+    /// [`ParseOptions::parse_bytecode`]: crate::ParseOptions::parse_bytecode
+    pub fn verify_stack_map(&self, class: &ClassFile) -> Result<(), ParseError> {
+        let code = match self.code() {
+            Some(code) => code,
+            None => return Ok(()),
+        };
+        let bytecode = match &code.bytecode {
+            Some(bytecode) => bytecode,
+            None => fail!("Bytecode of method {} was not parsed", self.name),
+        };
+        let entries = code
+            .attributes
+            .iter()
+            .find_map(|attr| match &attr.data {
+                AttributeData::StackMapTable(entries) => Some(entries.as_slice()),
+                _ => None,
+            })
+            .unwrap_or(&[]);
+        let max_locals = usize::from(code.max_locals);
+        let max_stack = usize::from(code.max_stack);
+        let is_init = self.name == "<init>";
+
+        // The types of the locals of the current frame, with one entry per value. The
+        // initial frame holds the arguments, where `this` in a constructor is uninitialized
+        // until the superclass constructor is called (section 4.10.1.6).
+        let mut locals = Vec::new();
+        if !self.access_flags.contains(MethodAccessFlags::STATIC) {
+            locals.push(if is_init && class.this_class != "java/lang/Object" {
+                Type::UninitializedThis
+            } else {
+                Type::Reference
+            });
+        }
+        locals.extend(self.descriptor.parameters.iter().map(Type::from_descriptor));
+        let argument_slots: usize = locals.iter().map(|local| local.slots()).sum();
+        if argument_slots > max_locals {
+            fail!(
+                "Arguments of method {} need {} local variable slots but max_locals is {}",
+                self.name,
+                argument_slots,
+                max_locals
+            );
+        }
+        let initial = TypeState::new(&locals, &[], max_locals);
+
+        // The type state of each frame along with the number of values on its stack
+        let mut frames = BTreeMap::new();
+        let mut previous_offset = None;
+        for (i, entry) in entries.iter().enumerate() {
+            let (offset_delta, stack) = match entry {
+                StackMapEntry::Same { offset_delta } => (offset_delta, &[][..]),
+                StackMapEntry::SameLocals1StackItem {
+                    offset_delta,
+                    stack,
+                } => (offset_delta, std::slice::from_ref(stack)),
+                StackMapEntry::Chop {
+                    offset_delta,
+                    chop_count,
+                } => {
+                    let chop_count = usize::from(*chop_count);
+                    if chop_count > locals.len() {
+                        fail!(
+                            ("Cannot chop {} locals from {}", chop_count, locals.len()),
+                            ("stack map frame {}", i)
+                        );
+                    }
+                    locals.truncate(locals.len() - chop_count);
+                    (offset_delta, &[][..])
+                }
+                StackMapEntry::Append {
+                    offset_delta,
+                    locals: appended,
+                } => {
+                    locals.extend(appended.iter().map(Type::from_verification_type));
+                    (offset_delta, &[][..])
+                }
+                StackMapEntry::FullFrame {
+                    offset_delta,
+                    locals: full,
+                    stack,
+                } => {
+                    locals = full.iter().map(Type::from_verification_type).collect();
+                    (offset_delta, stack.as_slice())
+                }
+            };
+            let offset = match previous_offset {
+                None => usize::from(*offset_delta),
+                Some(previous) => previous + usize::from(*offset_delta) + 1,
+            };
+            previous_offset = Some(offset);
+            if bytecode.get_opcode_index(offset).is_none() {
+                fail!(
+                    ("Offset {} is not the start of an instruction", offset),
+                    ("stack map frame {}", i)
+                );
+            }
+            let listed_locals = match entry {
+                StackMapEntry::Append { locals, .. } | StackMapEntry::FullFrame { locals, .. } => {
+                    locals.as_slice()
+                }
+                _ => &[],
+            };
+            for verification_type in listed_locals.iter().chain(stack) {
+                check_uninitialized(verification_type, bytecode, is_init)
+                    .map_err(|e| err!(e, "stack map frame {}", i))?;
+            }
+            let locals_slots: usize = locals.iter().map(|local| local.slots()).sum();
+            if locals_slots > max_locals {
+                fail!(
+                    (
+                        "Locals need {} slots but max_locals is {}",
+                        locals_slots,
+                        max_locals
+                    ),
+                    ("stack map frame {}", i)
+                );
+            }
+            let stack_slots: usize = stack.iter().map(verification_slots).sum();
+            if stack_slots > max_stack {
+                fail!(
+                    (
+                        "Stack needs {} slots but max_stack is {}",
+                        stack_slots,
+                        max_stack
+                    ),
+                    ("stack map frame {}", i)
+                );
+            }
+            let stack: Vec<Type> = stack.iter().map(Type::from_verification_type).collect();
+            let state = TypeState::new(&locals, &stack, max_locals);
+            frames.insert(offset, (state, stack.len()));
+        }
+
+        if class.major_version < 50 {
+            return Ok(());
+        }
+        for offset in required_frames(bytecode) {
+            if !frames.contains_key(&offset) {
+                fail!("Missing stack map frame at offset {}", offset);
+            }
+        }
+        for (i, entry) in code.exception_table.iter().enumerate() {
+            match frames.get(&usize::from(entry.handler_pc)) {
+                None => fail!(
+                    ("Missing stack map frame at handler_pc {}", entry.handler_pc),
+                    ("exception table entry {}", i)
+                ),
+                Some((_, stack_items)) if *stack_items != 1 => fail!(
+                    (
+                        "Stack map frame at handler_pc {} has {} stack items instead of 1",
+                        entry.handler_pc,
+                        stack_items
+                    ),
+                    ("exception table entry {}", i)
+                ),
+                Some(_) => (),
+            }
+        }
+        if bytecode
+            .opcodes
+            .iter()
+            .any(|(_, opcode)| matches!(opcode, Opcode::Jsr(_) | Opcode::Ret(_)))
+        {
+            return Ok(());
+        }
+
+        let mut interpreter = Interpreter {
+            state: initial,
+            max_stack,
+        };
+        let mut reachable = true;
+        for (offset, opcode) in &bytecode.opcodes {
+            let offset = *offset;
+            if let Some((frame, _)) = frames.get(&offset) {
+                if reachable {
+                    interpreter
+                        .state
+                        .check_frame(frame)
+                        .map_err(|e| err!(e, "stack map frame at offset {}", offset))?;
+                }
+                interpreter.state = frame.clone();
+            }
+            let handlers = code
+                .exception_table
+                .iter()
+                .enumerate()
+                .filter(|(_, entry)| {
+                    usize::from(entry.start_pc) <= offset && offset < usize::from(entry.end_pc)
+                })
+                .collect::<Vec<_>>();
+            for (i, entry) in &handlers {
+                check_handler(&interpreter.state, entry, &frames)
+                    .map_err(|e| err!(e, "exception table entry {}", i))?;
+            }
+            execute(&mut interpreter, offset, opcode, self)
+                .map_err(|e| err!(e, "instruction at offset {}", offset))?;
+            for target in jump_targets(offset, opcode) {
+                // Every jump target was checked to have a frame
+                let (frame, _) = &frames[&target];
+                interpreter
+                    .state
+                    .check_frame(frame)
+                    .map_err(|e| err!(e, "jump from offset {} to {}", offset, target))?;
+            }
+            for (i, entry) in &handlers {
+                check_handler(&interpreter.state, entry, &frames)
+                    .map_err(|e| err!(e, "exception table entry {}", i))?;
+            }
+            reachable = !is_unconditional(opcode);
+        }
+        if reachable {
+            fail!(
+                "Execution falls off the end of the code of method {}",
+                self.name
+            );
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::borrow::Cow;
+
+    use super::*;
+    use crate::attributes::{AttributeInfo, CodeData};
+    use crate::constant_pool::read_constant_pool;
+    use crate::descriptors::parse_method_descriptor;
+    use crate::ClassAccessFlags;
+
+    fn class(major_version: u16) -> ClassFile<'static> {
+        ClassFile {
+            major_version,
+            minor_version: 0,
+            constant_pool: Vec::new(),
+            access_flags: ClassAccessFlags::PUBLIC | ClassAccessFlags::SUPER,
+            this_class: Cow::Borrowed("a/B"),
+            super_class: Some(Cow::Borrowed("java/lang/Object")),
+            interfaces: Vec::new(),
+            fields: Vec::new(),
+            methods: Vec::new(),
+            attributes: Vec::new(),
+            byte_length: 0,
+            undefined_access_flags: 0,
+            max_nesting_depth: crate::DEFAULT_MAX_NESTING_DEPTH,
+            pool_references: Vec::new(),
+        }
+    }
+
+    // Returns the static method `void m(int)` with the given code, whose max_stack is 2 and
+    // max_locals is 1.
+    fn method_with_code(
+        code: &'static [u8],
+        frames: Vec<StackMapEntry<'static>>,
+        exception_table: Vec<ExceptionTableEntry<'static>>,
+    ) -> MethodInfo<'static> {
+        // Utf8 "a/B", Class a/B, Utf8 "<init>", Utf8 "()V", NameAndType <init>:()V and
+        // Methodref a/B.<init>:()V
+        const POOL: &[u8] = &[
+            0, 7, 1, 0, 3, b'a', b'/', b'B', 7, 0, 1, 1, 0, 6, b'<', b'i', b'n', b'i', b't', b'>',
+            1, 0, 3, b'(', b')', b'V', 12, 0, 3, 0, 4, 10, 0, 2, 0, 5,
+        ];
+        let pool = read_constant_pool(POOL, &mut 0, 61).unwrap();
+        let code = CodeData {
+            max_stack: 2,
+            max_locals: 1,
+            code: Cow::Borrowed(code),
+            bytecode: Some(ByteCode::from(code, &pool).unwrap()),
+            exception_table,
+            attributes: vec![AttributeInfo {
+                name: Cow::Borrowed("StackMapTable"),
+                data: AttributeData::StackMapTable(frames),
+            }],
+        };
+        MethodInfo {
+            access_flags: MethodAccessFlags::STATIC,
+            name: Cow::Borrowed("m"),
+            descriptor: parse_method_descriptor(&Cow::Borrowed("(I)V"), 0).unwrap(),
+            attributes: vec![AttributeInfo {
+                name: Cow::Borrowed("Code"),
+                data: AttributeData::Code(code),
+            }],
+            max_nesting_depth: crate::DEFAULT_MAX_NESTING_DEPTH,
+        }
+    }
+
+    fn verify(method: &MethodInfo) -> Result<(), String> {
+        method
+            .verify_stack_map(&class(61))
+            .map_err(|e| e.to_string())
+    }
+
+    #[test]
+    fn test_frame_placement() {
+        // iload_0; ifeq +4; nop; return
+        const CODE: &[u8] = &[0x1a, 0x99, 0, 4, 0x00, 0xb1];
+        let same = |offset_delta| StackMapEntry::Same { offset_delta };
+        assert!(verify(&method_with_code(CODE, vec![same(5)], Vec::new())).is_ok());
+        assert_eq!(
+            verify(&method_with_code(CODE, Vec::new(), Vec::new())),
+            Err("Missing stack map frame at offset 5".to_string())
+        );
+        // Before version 50 the JVM infers types instead of using frames
+        assert!(method_with_code(CODE, Vec::new(), Vec::new())
+            .verify_stack_map(&class(49))
+            .is_ok());
+        assert_eq!(
+            verify(&method_with_code(CODE, vec![same(2)], Vec::new())),
+            Err("Offset 2 is not the start of an instruction for stack map frame 0".to_string())
+        );
+        let append = StackMapEntry::Append {
+            offset_delta: 5,
+            locals: vec![VerificationType::Long],
+        };
+        assert_eq!(
+            verify(&method_with_code(CODE, vec![append], Vec::new())),
+            Err("Locals need 3 slots but max_locals is 1 for stack map frame 0".to_string())
+        );
+        let chop = StackMapEntry::Chop {
+            offset_delta: 5,
+            chop_count: 2,
+        };
+        assert_eq!(
+            verify(&method_with_code(CODE, vec![chop], Vec::new())),
+            Err("Cannot chop 2 locals from 1 for stack map frame 0".to_string())
+        );
+        let handler = ExceptionTableEntry {
+            start_pc: 0,
+            end_pc: 4,
+            handler_pc: 5,
+            catch_type: None,
+        };
+        assert_eq!(
+            verify(&method_with_code(CODE, vec![same(5)], vec![handler])),
+            Err("Stack map frame at handler_pc 5 has 0 stack items instead of 1 for exception table entry 0".to_string())
+        );
+    }
+
+    #[test]
+    fn test_exception_handler() {
+        // nop; return; athrow
+        const CODE: &[u8] = &[0x00, 0xb1, 0xbf];
+        let handler = ExceptionTableEntry {
+            start_pc: 0,
+            end_pc: 1,
+            handler_pc: 2,
+            catch_type: None,
+        };
+        let exception = |stack| StackMapEntry::SameLocals1StackItem {
+            offset_delta: 2,
+            stack,
+        };
+        let throwable = VerificationType::Object {
+            class_name: Cow::Borrowed("java/lang/Throwable"),
+        };
+        assert!(verify(&method_with_code(
+            CODE,
+            vec![exception(throwable)],
+            vec![handler.clone()]
+        ))
+        .is_ok());
+        assert_eq!(
+            verify(&method_with_code(
+                CODE,
+                vec![exception(VerificationType::Integer)],
+                vec![handler]
+            )),
+            Err("Stack map frame at handler_pc 2 does not hold the exception for exception table entry 0".to_string())
+        );
+    }
+
+    #[test]
+    fn test_check_uninitialized() {
+        // new a/B; iload_0; ifeq +4; nop; pop; return
+        const CODE: &[u8] = &[0xbb, 0, 2, 0x1a, 0x99, 0, 4, 0x00, 0x57, 0xb1];
+        let uninitialized = |stack| StackMapEntry::SameLocals1StackItem {
+            offset_delta: 8,
+            stack,
+        };
+        let frame = uninitialized(VerificationType::Uninitialized { code_offset: 0 });
+        assert!(verify(&method_with_code(CODE, vec![frame], Vec::new())).is_ok());
+        let frame = uninitialized(VerificationType::Uninitialized { code_offset: 3 });
+        assert_eq!(
+            verify(&method_with_code(CODE, vec![frame], Vec::new())),
+            Err(
+                "Uninitialized offset 3 is not a new instruction for stack map frame 0".to_string()
+            )
+        );
+        let frame = uninitialized(VerificationType::UninitializedThis);
+        let mut method = method_with_code(CODE, vec![frame], Vec::new());
+        assert_eq!(
+            verify(&method),
+            Err(
+                "Uninitialized this outside of an instance initializer for stack map frame 0"
+                    .to_string()
+            )
+        );
+        method.name = Cow::Borrowed("<init>");
+        assert_eq!(
+            verify(&method),
+            Err("Stack slot 0 has type Uninitialized(0) but the frame expects UninitializedThis for jump from offset 4 to 8".to_string())
+        );
+    }
+
+    #[test]
+    fn test_constructor() {
+        // aload_0; invokespecial a/B.<init>()V; return
+        const CODE: &[u8] = &[0x2a, 0xb7, 0, 6, 0xb1];
+        let mut method = method_with_code(CODE, Vec::new(), Vec::new());
+        method.access_flags = MethodAccessFlags::PUBLIC;
+        method.name = Cow::Borrowed("<init>");
+        method.descriptor = parse_method_descriptor(&Cow::Borrowed("()V"), 0).unwrap();
+        assert!(verify(&method).is_ok());
+        // return
+        let mut method = method_with_code(&[0xb1], Vec::new(), Vec::new());
+        method.access_flags = MethodAccessFlags::PUBLIC;
+        method.name = Cow::Borrowed("<init>");
+        method.descriptor = parse_method_descriptor(&Cow::Borrowed("()V"), 0).unwrap();
+        assert_eq!(
+            verify(&method),
+            Err("Return from instance initializer before this is initialized for instruction at offset 0".to_string())
+        );
+        // Only the constructor of java/lang/Object starts with an initialized this
+        let mut object = class(61);
+        object.this_class = Cow::Borrowed("java/lang/Object");
+        assert!(method.verify_stack_map(&object).is_ok());
+    }
+
+    #[test]
+    fn test_instruction_types() {
+        let check =
+            |code: &'static [u8], frames| verify(&method_with_code(code, frames, Vec::new()));
+        // fload_0; return
+        assert_eq!(
+            check(&[0x22, 0xb1], Vec::new()),
+            Err(
+                "Expected Float in local variable 0 but found Int for instruction at offset 0"
+                    .to_string()
+            )
+        );
+        // fconst_0; fstore_0; goto +3; return
+        assert_eq!(
+            check(
+                &[0x0b, 0x43, 0xa7, 0, 3, 0xb1],
+                vec![StackMapEntry::Same { offset_delta: 5 }]
+            ),
+            Err("Local variable 0 has type Float but the frame expects Int for jump from offset 2 to 5".to_string())
+        );
+        // iconst_0; ireturn
+        assert_eq!(
+            check(&[0x03, 0xac], Vec::new()),
+            Err("Value returned from void method for instruction at offset 1".to_string())
+        );
+        // iconst_0; iconst_0; iconst_0
+        assert_eq!(
+            check(&[0x03, 0x03, 0x03], Vec::new()),
+            Err("Stack overflow; max_stack is 2 for instruction at offset 2".to_string())
+        );
+        // lconst_0; pop; return
+        assert_eq!(
+            check(&[0x09, 0x57, 0xb1], Vec::new()),
+            Err("Instruction splits a long or double value on the stack for instruction at offset 1".to_string())
+        );
+        // lconst_0; pop2; return
+        assert!(check(&[0x09, 0x58, 0xb1], Vec::new()).is_ok());
+        // iload_0; i2f; fadd
+        assert_eq!(
+            check(&[0x1a, 0x86, 0x62], Vec::new()),
+            Err("Stack underflow for instruction at offset 2".to_string())
+        );
+        // nop
+        assert_eq!(
+            check(&[0x00], Vec::new()),
+            Err("Execution falls off the end of the code of method m".to_string())
+        );
+    }
+}
@@ -0,0 +1,49 @@
+use crate::attributes::AttributeInfo;
+use crate::constant_pool::ConstantPoolItem;
+use crate::{ClassFile, FieldInfo, MethodInfo};
+
+/// A push-style alternative to walking the fields of a parsed class directly, similar to
+/// the visitor API of the ASM library. Every method has an empty default implementation,
+/// so implementors only need to override the methods for the parts they care about.
+/// Use `ClassFile::accept` to drive a visitor over a class.
+pub trait ClassVisitor<'a> {
+    /// Called once, before any of the other methods, with the class being visited.
+    fn visit_class(&mut self, _class: &ClassFile<'a>) {}
+
+    /// Called for each item in the constant pool, in constant pool order.
+    fn visit_constant(&mut self, _constant: ConstantPoolItem<'a>) {}
+
+    /// Called for each field of the class, in declaration order.
+    fn visit_field(&mut self, _field: &FieldInfo<'a>) {}
+
+    /// Called for each method of the class, in declaration order.
+    fn visit_method(&mut self, _method: &MethodInfo<'a>) {}
+
+    /// Called for each attribute of the class itself. Attributes of fields and methods
+    /// are not visited, but are available from the FieldInfo and MethodInfo.
+    fn visit_attribute(&mut self, _attribute: &AttributeInfo<'a>) {}
+
+    /// Called once, after all of the other methods.
+    fn visit_end(&mut self) {}
+}
+
+impl<'a> ClassFile<'a> {
+    /// Walks the given visitor over this class. The visitor sees the class itself, then
+    /// the constant pool, fields, methods and class attributes in that order.
+    pub fn accept<V: ClassVisitor<'a>>(&self, visitor: &mut V) {
+        visitor.visit_class(self);
+        for constant in self.constantpool_iter() {
+            visitor.visit_constant(constant);
+        }
+        for field in &self.fields {
+            visitor.visit_field(field);
+        }
+        for method in &self.methods {
+            visitor.visit_method(method);
+        }
+        for attribute in &self.attributes {
+            visitor.visit_attribute(attribute);
+        }
+        visitor.visit_end();
+    }
+}
@@ -0,0 +1,906 @@
+// Serialization of a parsed class file back into the class file format.
+
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::convert::TryFrom;
+
+use crate::attributes::{
+    Annotation, AnnotationElementValue, AttributeData, AttributeInfo, CodeData, ModuleData,
+    StackMapEntry, TypeAnnotation, TypeAnnotationTarget, TypeAnnotationTargetKind,
+    TypeAnnotationTargetPathKind, VerificationType,
+};
+use crate::bytecode::{constant_pool_operands, read_operand};
+use crate::constant_pool::{
+    BootstrapArgument, ConstantPoolBuilder, ConstantPoolEntry, LiteralConstant,
+};
+use crate::{CafeRc, ClassFile, ParseError};
+
+fn write_u1(out: &mut Vec<u8>, value: u8) {
+    out.push(value);
+}
+
+fn write_u2(out: &mut Vec<u8>, value: u16) {
+    out.extend_from_slice(&value.to_be_bytes());
+}
+
+fn write_u4(out: &mut Vec<u8>, value: u32) {
+    out.extend_from_slice(&value.to_be_bytes());
+}
+
+fn write_count_u1(out: &mut Vec<u8>, count: usize, what: &str) -> Result<(), ParseError> {
+    match u8::try_from(count) {
+        Ok(count) => write_u1(out, count),
+        Err(_) => fail!("Too many {} ({}) to write; the maximum is 255", what, count),
+    }
+    Ok(())
+}
+
+fn write_count(out: &mut Vec<u8>, count: usize, what: &str) -> Result<(), ParseError> {
+    match u16::try_from(count) {
+        Ok(count) => write_u2(out, count),
+        Err(_) => fail!(
+            "Too many {} ({}) to write; the maximum is 65535",
+            what,
+            count
+        ),
+    }
+    Ok(())
+}
+
+fn write_length(out: &mut Vec<u8>, length: usize, what: &str) -> Result<(), ParseError> {
+    match u32::try_from(length) {
+        Ok(length) => write_u4(out, length),
+        Err(_) => fail!("Length {} of {} is too large to write", length, what),
+    }
+    Ok(())
+}
+
+// The offset of each constant pool operand in a code array, and whether it is one byte wide.
+type CodeOperands = Vec<(usize, bool)>;
+
+fn type_annotation_target_tag(kind: TypeAnnotationTargetKind) -> u8 {
+    match kind {
+        TypeAnnotationTargetKind::ClassTypeParameter => 0x00,
+        TypeAnnotationTargetKind::MethodTypeParameter => 0x01,
+        TypeAnnotationTargetKind::Supertype => 0x10,
+        TypeAnnotationTargetKind::ClassTypeParameterBound => 0x11,
+        TypeAnnotationTargetKind::MethodTypeParameterBound => 0x12,
+        TypeAnnotationTargetKind::Field => 0x13,
+        TypeAnnotationTargetKind::MethodReturn => 0x14,
+        TypeAnnotationTargetKind::MethodReceiver => 0x15,
+        TypeAnnotationTargetKind::MethodFormalParameter => 0x16,
+        TypeAnnotationTargetKind::Throws => 0x17,
+        TypeAnnotationTargetKind::LocalVariable => 0x40,
+        TypeAnnotationTargetKind::ResourceVariable => 0x41,
+        TypeAnnotationTargetKind::ExceptionParameter => 0x42,
+        TypeAnnotationTargetKind::Instanceof => 0x43,
+        TypeAnnotationTargetKind::New => 0x44,
+        TypeAnnotationTargetKind::ConstructorReference => 0x45,
+        TypeAnnotationTargetKind::MethodReference => 0x46,
+        TypeAnnotationTargetKind::Cast => 0x47,
+        TypeAnnotationTargetKind::ConstructorInvocationTypeArgument => 0x48,
+        TypeAnnotationTargetKind::MethodInvocationTypeArgument => 0x49,
+        TypeAnnotationTargetKind::ConstructorReferenceTypeArgument => 0x4A,
+        TypeAnnotationTargetKind::MethodReferenceTypeArgument => 0x4B,
+    }
+}
+
+// The constant pool indices of the references to constants that the constant pool holds
+// more than once, in the order the class used them when it was parsed, keyed by the index
+// of the first copy of the constant.
+#[derive(Default)]
+struct DuplicateReferences(HashMap<u16, VecDeque<u16>>);
+
+impl DuplicateReferences {
+    fn new(pool: &ConstantPoolBuilder, pool_references: &[u16]) -> Self {
+        let duplicates = pool.duplicates();
+        let first_copies: HashSet<u16> = duplicates.values().copied().collect();
+        let mut references: HashMap<u16, VecDeque<u16>> = HashMap::new();
+        for index in pool_references {
+            let first = duplicates.get(index).copied().unwrap_or(*index);
+            if first_copies.contains(&first) {
+                references.entry(first).or_default().push_back(*index);
+            }
+        }
+        DuplicateReferences(references)
+    }
+
+    // Returns the index to write for a reference to the constant that interning found at
+    // the given index, which is the copy the next such reference originally used.
+    fn resolve(&mut self, index: u16) -> u16 {
+        self.0
+            .get_mut(&index)
+            .and_then(VecDeque::pop_front)
+            .unwrap_or(index)
+    }
+}
+
+struct ClassWriter<'a> {
+    pool: ConstantPoolBuilder<'a>,
+    duplicates: DuplicateReferences,
+}
+
+impl<'a> ClassWriter<'a> {
+    fn write_class(&mut self, out: &mut Vec<u8>, class: &ClassFile<'a>) -> Result<(), ParseError> {
+        write_u2(
+            out,
+            class.access_flags.bits() | class.undefined_access_flags,
+        );
+        write_u2(
+            out,
+            self.duplicates
+                .resolve(self.pool.class(class.this_class.clone())?),
+        );
+        match &class.super_class {
+            Some(super_class) => write_u2(
+                out,
+                self.duplicates
+                    .resolve(self.pool.class(super_class.clone())?),
+            ),
+            None => write_u2(out, 0),
+        }
+        write_count(out, class.interfaces.len(), "interfaces")?;
+        for interface in &class.interfaces {
+            write_u2(
+                out,
+                self.duplicates.resolve(self.pool.class(interface.clone())?),
+            );
+        }
+        write_count(out, class.fields.len(), "fields")?;
+        for (i, field) in class.fields.iter().enumerate() {
+            write_u2(out, field.access_flags.bits());
+            write_u2(
+                out,
+                self.duplicates.resolve(self.pool.utf8(field.name.clone())?),
+            );
+            write_u2(
+                out,
+                self.duplicates
+                    .resolve(self.pool.utf8(field.descriptor.to_string())?),
+            );
+            self.write_attributes(out, &field.attributes)
+                .map_err(|e| err!(e, "class field {}", i))?;
+        }
+        write_count(out, class.methods.len(), "methods")?;
+        for (i, method) in class.methods.iter().enumerate() {
+            write_u2(out, method.access_flags.bits());
+            write_u2(
+                out,
+                self.duplicates
+                    .resolve(self.pool.utf8(method.name.clone())?),
+            );
+            write_u2(
+                out,
+                self.duplicates
+                    .resolve(self.pool.utf8(method.descriptor.to_string())?),
+            );
+            self.write_attributes(out, &method.attributes)
+                .map_err(|e| err!(e, "class method {}", i))?;
+        }
+        self.write_attributes(out, &class.attributes)
+    }
+
+    fn write_attributes(
+        &mut self,
+        out: &mut Vec<u8>,
+        attributes: &[AttributeInfo<'a>],
+    ) -> Result<(), ParseError> {
+        write_count(out, attributes.len(), "attributes")?;
+        for (i, attribute) in attributes.iter().enumerate() {
+            write_u2(
+                out,
+                self.duplicates
+                    .resolve(self.pool.utf8(attribute.name.clone())?),
+            );
+            let mut data = Vec::new();
+            self.write_attribute_data(&mut data, &attribute.data)
+                .and_then(|_| write_length(out, data.len(), "attribute data"))
+                .map_err(|e| err!(e, "{} attribute {}", attribute.name, i))?;
+            out.extend_from_slice(&data);
+        }
+        Ok(())
+    }
+
+    fn write_attribute_data(
+        &mut self,
+        out: &mut Vec<u8>,
+        data: &AttributeData<'a>,
+    ) -> Result<(), ParseError> {
+        match data {
+            AttributeData::ConstantValue(value) => write_u2(out, self.literal(value)?),
+            AttributeData::Code(code) => self.write_code(out, code)?,
+            AttributeData::StackMapTable(entries) => {
+                write_count(out, entries.len(), "stack map entries")?;
+                for entry in entries {
+                    self.write_stack_map_entry(out, entry)?;
+                }
+            }
+            AttributeData::StackMap(frames) => {
+                write_count(out, frames.len(), "stack map frames")?;
+                for frame in frames {
+                    write_u2(out, frame.offset);
+                    write_count(out, frame.locals.len(), "locals")?;
+                    for local in &frame.locals {
+                        self.write_verification_type(out, local)?;
+                    }
+                    write_count(out, frame.stack.len(), "stack entries")?;
+                    for item in &frame.stack {
+                        self.write_verification_type(out, item)?;
+                    }
+                }
+            }
+            AttributeData::Exceptions(names)
+            | AttributeData::NestMembers(names)
+            | AttributeData::PermittedSubclasses(names) => {
+                write_count(out, names.len(), "classes")?;
+                for name in names {
+                    write_u2(out, self.duplicates.resolve(self.pool.class(name.clone())?));
+                }
+            }
+            AttributeData::InnerClasses(entries) => {
+                write_count(out, entries.len(), "inner classes")?;
+                for entry in entries {
+                    write_u2(
+                        out,
+                        self.duplicates
+                            .resolve(self.pool.class(entry.inner_class_info.clone())?),
+                    );
+                    match &entry.outer_class_info {
+                        Some(outer) => write_u2(
+                            out,
+                            self.duplicates.resolve(self.pool.class(outer.clone())?),
+                        ),
+                        None => write_u2(out, 0),
+                    }
+                    match &entry.inner_name {
+                        Some(name) => {
+                            write_u2(out, self.duplicates.resolve(self.pool.utf8(name.clone())?))
+                        }
+                        None => write_u2(out, 0),
+                    }
+                    write_u2(out, entry.access_flags.bits());
+                }
+            }
+            AttributeData::EnclosingMethod { class_name, method } => {
+                write_u2(
+                    out,
+                    self.duplicates
+                        .resolve(self.pool.class(class_name.clone())?),
+                );
+                match method {
+                    Some(method) => {
+                        let index = self
+                            .pool
+                            .name_and_type(method.name.clone(), method.descriptor.clone())?;
+                        write_u2(out, self.duplicates.resolve(index))
+                    }
+                    None => write_u2(out, 0),
+                }
+            }
+            AttributeData::Synthetic | AttributeData::Deprecated => (),
+            AttributeData::Signature(value)
+            | AttributeData::SourceFile(value)
+            | AttributeData::ModuleMainClass(value)
+            | AttributeData::NestHost(value) => {
+                let index = match data {
+                    AttributeData::ModuleMainClass(_) | AttributeData::NestHost(_) => {
+                        self.pool.class(value.clone())?
+                    }
+                    _ => self.pool.utf8(value.clone())?,
+                };
+                write_u2(out, self.duplicates.resolve(index));
+            }
+            AttributeData::SourceDebugExtension(value) => {
+                out.extend_from_slice(&cesu8::to_java_cesu8(value))
+            }
+            AttributeData::SourceDebugExtensionBytes(bytes) => out.extend_from_slice(bytes),
+            AttributeData::Other(bytes) => {
+                // The contents may hold constant pool indices, which can't be found. This
+                // includes attributes the spec defines if their bodies were skipped.
+                if self.pool.records_references() {
+                    fail!("Can't find the constant pool references in an attribute that was not parsed");
+                }
+                out.extend_from_slice(bytes)
+            }
+            AttributeData::LineNumberTable(entries) => {
+                write_count(out, entries.len(), "line numbers")?;
+                for entry in entries {
+                    write_u2(out, entry.start_pc);
+                    write_u2(out, entry.line_number);
+                }
+            }
+            AttributeData::LocalVariableTable(entries) => {
+                write_count(out, entries.len(), "local variables")?;
+                for entry in entries {
+                    write_u2(out, entry.start_pc);
+                    write_u2(out, entry.length);
+                    write_u2(
+                        out,
+                        self.duplicates.resolve(self.pool.utf8(entry.name.clone())?),
+                    );
+                    write_u2(
+                        out,
+                        self.duplicates
+                            .resolve(self.pool.utf8(entry.descriptor.to_string())?),
+                    );
+                    write_u2(out, entry.index);
+                }
+            }
+            AttributeData::LocalVariableTypeTable(entries) => {
+                write_count(out, entries.len(), "local variables")?;
+                for entry in entries {
+                    write_u2(out, entry.start_pc);
+                    write_u2(out, entry.length);
+                    write_u2(
+                        out,
+                        self.duplicates.resolve(self.pool.utf8(entry.name.clone())?),
+                    );
+                    write_u2(
+                        out,
+                        self.duplicates
+                            .resolve(self.pool.utf8(entry.signature.clone())?),
+                    );
+                    write_u2(out, entry.index);
+                }
+            }
+            AttributeData::RuntimeVisibleAnnotations(annotations)
+            | AttributeData::RuntimeInvisibleAnnotations(annotations) => {
+                write_count(out, annotations.len(), "annotations")?;
+                for annotation in annotations {
+                    self.write_annotation(out, annotation)?;
+                }
+            }
+            AttributeData::RuntimeVisibleParameterAnnotations(parameters)
+            | AttributeData::RuntimeInvisibleParameterAnnotations(parameters) => {
+                write_count_u1(out, parameters.len(), "parameters")?;
+                for parameter in parameters {
+                    write_count(out, parameter.annotations.len(), "annotations")?;
+                    for annotation in &parameter.annotations {
+                        self.write_annotation(out, annotation)?;
+                    }
+                }
+            }
+            AttributeData::RuntimeVisibleTypeAnnotations(annotations)
+            | AttributeData::RuntimeInvisibleTypeAnnotations(annotations) => {
+                write_count(out, annotations.len(), "type annotations")?;
+                for annotation in annotations {
+                    self.write_type_annotation(out, annotation)?;
+                }
+            }
+            AttributeData::AnnotationDefault(value) => self.write_element_value(out, value)?,
+            AttributeData::BootstrapMethods(entries) => {
+                write_count(out, entries.len(), "bootstrap methods")?;
+                for entry in entries {
+                    write_u2(
+                        out,
+                        self.duplicates
+                            .resolve(self.pool.method_handle(&entry.method)?),
+                    );
+                    write_count(out, entry.arguments.len(), "bootstrap arguments")?;
+                    for argument in &entry.arguments {
+                        write_u2(out, self.bootstrap_argument(argument)?);
+                    }
+                }
+            }
+            AttributeData::MethodParameters(entries) => {
+                write_count_u1(out, entries.len(), "method parameters")?;
+                for entry in entries {
+                    match &entry.name {
+                        Some(name) => {
+                            write_u2(out, self.duplicates.resolve(self.pool.utf8(name.clone())?))
+                        }
+                        None => write_u2(out, 0),
+                    }
+                    write_u2(out, entry.access_flags.bits());
+                }
+            }
+            AttributeData::Module(module) => self.write_module(out, module)?,
+            AttributeData::ModulePackages(packages) => {
+                write_count(out, packages.len(), "packages")?;
+                for package in packages {
+                    write_u2(
+                        out,
+                        self.duplicates.resolve(self.pool.package(package.clone())?),
+                    );
+                }
+            }
+            AttributeData::Record(components) => {
+                write_count(out, components.len(), "record components")?;
+                for component in components {
+                    write_u2(
+                        out,
+                        self.duplicates
+                            .resolve(self.pool.utf8(component.name.clone())?),
+                    );
+                    write_u2(
+                        out,
+                        self.duplicates
+                            .resolve(self.pool.utf8(component.descriptor.to_string())?),
+                    );
+                    self.write_attributes(out, &component.attributes)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn write_code(&mut self, out: &mut Vec<u8>, code: &CodeData<'a>) -> Result<(), ParseError> {
+        write_u2(out, code.max_stack);
+        write_u2(out, code.max_locals);
+        write_length(out, code.code.len(), "code")?;
+        out.extend_from_slice(&code.code);
+        write_count(out, code.exception_table.len(), "exception table entries")?;
+        for entry in &code.exception_table {
+            write_u2(out, entry.start_pc);
+            write_u2(out, entry.end_pc);
+            write_u2(out, entry.handler_pc);
+            match &entry.catch_type {
+                Some(catch_type) => write_u2(
+                    out,
+                    self.duplicates
+                        .resolve(self.pool.class(catch_type.clone())?),
+                ),
+                None => write_u2(out, 0),
+            }
+        }
+        self.write_attributes(out, &code.attributes)
+    }
+
+    fn write_stack_map_entry(
+        &mut self,
+        out: &mut Vec<u8>,
+        entry: &StackMapEntry<'a>,
+    ) -> Result<(), ParseError> {
+        match entry {
+            StackMapEntry::Same { offset_delta } => {
+                if *offset_delta < 64 {
+                    write_u1(out, *offset_delta as u8);
+                } else {
+                    write_u1(out, 251);
+                    write_u2(out, *offset_delta);
+                }
+            }
+            StackMapEntry::SameLocals1StackItem {
+                offset_delta,
+                stack,
+            } => {
+                if *offset_delta < 64 {
+                    write_u1(out, 64 + *offset_delta as u8);
+                } else {
+                    write_u1(out, 247);
+                    write_u2(out, *offset_delta);
+                }
+                self.write_verification_type(out, stack)?;
+            }
+            StackMapEntry::Chop {
+                offset_delta,
+                chop_count,
+            } => {
+                if !(1..=3).contains(chop_count) {
+                    fail!("Invalid chop count {} in stack map entry", chop_count);
+                }
+                write_u1(out, 251 - *chop_count as u8);
+                write_u2(out, *offset_delta);
+            }
+            StackMapEntry::Append {
+                offset_delta,
+                locals,
+            } => {
+                if !(1..=3).contains(&locals.len()) {
+                    fail!(
+                        "Invalid number of locals {} in append stack map entry",
+                        locals.len()
+                    );
+                }
+                write_u1(out, 251 + locals.len() as u8);
+                write_u2(out, *offset_delta);
+                for local in locals {
+                    self.write_verification_type(out, local)?;
+                }
+            }
+            StackMapEntry::FullFrame {
+                offset_delta,
+                locals,
+                stack,
+            } => {
+                write_u1(out, 255);
+                write_u2(out, *offset_delta);
+                write_count(out, locals.len(), "locals")?;
+                for local in locals {
+                    self.write_verification_type(out, local)?;
+                }
+                write_count(out, stack.len(), "stack entries")?;
+                for item in stack {
+                    self.write_verification_type(out, item)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn write_verification_type(
+        &mut self,
+        out: &mut Vec<u8>,
+        verification_type: &VerificationType<'a>,
+    ) -> Result<(), ParseError> {
+        match verification_type {
+            VerificationType::Top => write_u1(out, 0),
+            VerificationType::Integer => write_u1(out, 1),
+            VerificationType::Float => write_u1(out, 2),
+            VerificationType::Double => write_u1(out, 3),
+            VerificationType::Long => write_u1(out, 4),
+            VerificationType::Null => write_u1(out, 5),
+            VerificationType::UninitializedThis => write_u1(out, 6),
+            VerificationType::Object { class_name } => {
+                write_u1(out, 7);
+                write_u2(
+                    out,
+                    self.duplicates
+                        .resolve(self.pool.class(class_name.clone())?),
+                );
+            }
+            VerificationType::Uninitialized { code_offset } => {
+                write_u1(out, 8);
+                write_u2(out, *code_offset);
+            }
+        }
+        Ok(())
+    }
+
+    fn write_annotation(
+        &mut self,
+        out: &mut Vec<u8>,
+        annotation: &Annotation<'a>,
+    ) -> Result<(), ParseError> {
+        write_u2(
+            out,
+            self.duplicates
+                .resolve(self.pool.utf8(annotation.type_descriptor.to_string())?),
+        );
+        write_count(out, annotation.elements.len(), "annotation elements")?;
+        for element in &annotation.elements {
+            write_u2(
+                out,
+                self.duplicates
+                    .resolve(self.pool.utf8(element.name.clone())?),
+            );
+            self.write_element_value(out, &element.value)?;
+        }
+        Ok(())
+    }
+
+    fn write_element_value(
+        &mut self,
+        out: &mut Vec<u8>,
+        value: &AnnotationElementValue<'a>,
+    ) -> Result<(), ParseError> {
+        let (tag, index) = match value {
+            AnnotationElementValue::ByteConstant(v) => (b'B', self.pool.integer(*v)?),
+            AnnotationElementValue::CharConstant(v) => (b'C', self.pool.integer(*v)?),
+            AnnotationElementValue::DoubleConstant(v) => (b'D', self.pool.double(*v)?),
+            AnnotationElementValue::FloatConstant(v) => (b'F', self.pool.float(*v)?),
+            AnnotationElementValue::IntConstant(v) => (b'I', self.pool.integer(*v)?),
+            AnnotationElementValue::LongConstant(v) => (b'J', self.pool.long(*v)?),
+            AnnotationElementValue::ShortConstant(v) => (b'S', self.pool.integer(*v)?),
+            AnnotationElementValue::BooleanConstant(v) => (b'Z', self.pool.integer(*v)?),
+            AnnotationElementValue::StringConstant(v) => (b's', self.pool.utf8(v.clone())?),
+            AnnotationElementValue::EnumConstant {
+                type_name,
+                const_name,
+            } => {
+                write_u1(out, b'e');
+                write_u2(
+                    out,
+                    self.duplicates
+                        .resolve(self.pool.utf8(type_name.to_string())?),
+                );
+                write_u2(
+                    out,
+                    self.duplicates.resolve(self.pool.utf8(const_name.clone())?),
+                );
+                return Ok(());
+            }
+            AnnotationElementValue::ClassLiteral { class_name } => {
+                (b'c', self.pool.utf8(class_name.clone())?)
+            }
+            AnnotationElementValue::AnnotationValue(annotation) => {
+                write_u1(out, b'@');
+                return self.write_annotation(out, annotation);
+            }
+            AnnotationElementValue::ArrayValue(values) => {
+                write_u1(out, b'[');
+                write_count(out, values.len(), "array values")?;
+                for value in values {
+                    self.write_element_value(out, value)?;
+                }
+                return Ok(());
+            }
+        };
+        write_u1(out, tag);
+        write_u2(out, self.duplicates.resolve(index));
+        Ok(())
+    }
+
+    fn write_type_annotation(
+        &mut self,
+        out: &mut Vec<u8>,
+        annotation: &TypeAnnotation<'a>,
+    ) -> Result<(), ParseError> {
+        write_u1(out, type_annotation_target_tag(annotation.target_kind));
+        match &annotation.target_type {
+            TypeAnnotationTarget::TypeParameter { index }
+            | TypeAnnotationTarget::FormalParameter { index } => write_u1(out, *index),
+            TypeAnnotationTarget::Supertype { index } | TypeAnnotationTarget::Throws { index } => {
+                write_u2(out, *index)
+            }
+            TypeAnnotationTarget::TypeParameterBound {
+                type_parameter_index,
+                bound_index,
+            } => {
+                write_u1(out, *type_parameter_index);
+                write_u1(out, *bound_index);
+            }
+            TypeAnnotationTarget::Empty => (),
+            TypeAnnotationTarget::LocalVar(entries) => {
+                write_count(out, entries.len(), "local variable targets")?;
+                for entry in entries {
+                    write_u2(out, entry.start_pc);
+                    write_u2(out, entry.length);
+                    write_u2(out, entry.index);
+                }
+            }
+            TypeAnnotationTarget::Catch {
+                exception_table_index,
+            } => write_u2(out, *exception_table_index),
+            TypeAnnotationTarget::Offset { offset } => write_u2(out, *offset),
+            TypeAnnotationTarget::TypeArgument {
+                offset,
+                type_argument_index,
+            } => {
+                write_u2(out, *offset);
+                write_u1(out, *type_argument_index);
+            }
+        }
+        write_count_u1(out, annotation.target_path.len(), "type path entries")?;
+        for entry in &annotation.target_path {
+            write_u1(
+                out,
+                match entry.path_kind {
+                    TypeAnnotationTargetPathKind::DeeperArray => 0,
+                    TypeAnnotationTargetPathKind::DeeperNested => 1,
+                    TypeAnnotationTargetPathKind::WildcardTypeArgument => 2,
+                    TypeAnnotationTargetPathKind::TypeArgument => 3,
+                },
+            );
+            write_u1(out, entry.argument_index);
+        }
+        self.write_annotation(out, &annotation.annotation)
+    }
+
+    fn write_module(
+        &mut self,
+        out: &mut Vec<u8>,
+        module: &ModuleData<'a>,
+    ) -> Result<(), ParseError> {
+        write_u2(
+            out,
+            self.duplicates
+                .resolve(self.pool.module(module.name.clone())?),
+        );
+        write_u2(out, module.access_flags.bits());
+        match &module.version {
+            Some(version) => write_u2(
+                out,
+                self.duplicates.resolve(self.pool.utf8(version.clone())?),
+            ),
+            None => write_u2(out, 0),
+        }
+        write_count(out, module.requires.len(), "requires entries")?;
+        for entry in &module.requires {
+            write_u2(
+                out,
+                self.duplicates
+                    .resolve(self.pool.module(entry.name.clone())?),
+            );
+            write_u2(out, entry.flags.bits());
+            match &entry.version {
+                Some(version) => write_u2(
+                    out,
+                    self.duplicates.resolve(self.pool.utf8(version.clone())?),
+                ),
+                None => write_u2(out, 0),
+            }
+        }
+        write_count(out, module.exports.len(), "exports entries")?;
+        for entry in &module.exports {
+            write_u2(
+                out,
+                self.duplicates
+                    .resolve(self.pool.package(entry.package_name.clone())?),
+            );
+            write_u2(out, entry.flags.bits());
+            write_count(out, entry.exports_to.len(), "exports_to entries")?;
+            for name in &entry.exports_to {
+                write_u2(
+                    out,
+                    self.duplicates.resolve(self.pool.module(name.clone())?),
+                );
+            }
+        }
+        write_count(out, module.opens.len(), "opens entries")?;
+        for entry in &module.opens {
+            write_u2(
+                out,
+                self.duplicates
+                    .resolve(self.pool.package(entry.package_name.clone())?),
+            );
+            write_u2(out, entry.flags.bits());
+            write_count(out, entry.opens_to.len(), "opens_to entries")?;
+            for name in &entry.opens_to {
+                write_u2(
+                    out,
+                    self.duplicates.resolve(self.pool.module(name.clone())?),
+                );
+            }
+        }
+        write_count(out, module.uses.len(), "uses entries")?;
+        for name in &module.uses {
+            write_u2(out, self.duplicates.resolve(self.pool.class(name.clone())?));
+        }
+        write_count(out, module.provides.len(), "provides entries")?;
+        for entry in &module.provides {
+            write_u2(
+                out,
+                self.duplicates
+                    .resolve(self.pool.class(entry.service_interface_name.clone())?),
+            );
+            write_count(out, entry.provides_with.len(), "provides_with entries")?;
+            for name in &entry.provides_with {
+                write_u2(out, self.duplicates.resolve(self.pool.class(name.clone())?));
+            }
+        }
+        Ok(())
+    }
+
+    fn literal(&mut self, value: &LiteralConstant<'a>) -> Result<u16, ParseError> {
+        let index = match value {
+            LiteralConstant::Integer(v) => self.pool.integer(*v),
+            LiteralConstant::Float(v) => self.pool.float(*v),
+            LiteralConstant::Long(v) => self.pool.long(*v),
+            LiteralConstant::Double(v) => self.pool.double(*v),
+            LiteralConstant::String(v) => self.pool.string(v.clone()),
+            LiteralConstant::StringBytes(v) => self.pool.string_bytes(v.clone()),
+        }?;
+        Ok(self.duplicates.resolve(index))
+    }
+
+    fn bootstrap_argument(&mut self, argument: &BootstrapArgument<'a>) -> Result<u16, ParseError> {
+        let index = match argument {
+            BootstrapArgument::LiteralConstant(value) => return self.literal(value),
+            BootstrapArgument::ClassInfo(name) => self.pool.class(name.clone()),
+            BootstrapArgument::MethodHandle(handle) => self.pool.method_handle(handle),
+            BootstrapArgument::MethodType(descriptor) => self.pool.method_type(descriptor.clone()),
+            BootstrapArgument::Dynamic(dynamic) => self.pool.dynamic(
+                dynamic.attr_index,
+                dynamic.name_and_type.name.clone(),
+                dynamic.name_and_type.descriptor.clone(),
+            ),
+        }?;
+        Ok(self.duplicates.resolve(index))
+    }
+}
+
+impl<'a> ClassFile<'a> {
+    /// Serializes this class into the class file format. The constant pool keeps the
+    /// layout it was parsed with, and any constants that are needed but not already in it
+    /// (for example after renaming a class) are appended at the end. Since the indices of
+    /// existing constants do not change, the raw `code` of each Code attribute is written
+    /// as-is; note that the parsed `bytecode` is not re-encoded. Attributes are written in
+    /// the order they appear in their `attributes` lists, including any duplicates. Where
+    /// the constant pool holds the same constant more than once, each reference to it is
+    /// written as the copy it used when the class was parsed. Writing a class that was
+    /// parsed and not modified produces the bytes it was parsed from.
+    pub fn to_bytes(&self) -> Result<Vec<u8>, ParseError> {
+        let pool = ConstantPoolBuilder::from_constant_pool(&self.constant_pool)?;
+        let mut writer = ClassWriter {
+            duplicates: DuplicateReferences::new(&pool, &self.pool_references),
+            pool,
+        };
+        let mut body = Vec::new();
+        writer.write_class(&mut body, self)?;
+        let mut out = Vec::new();
+        write_u4(&mut out, 0xCAFE_BABE);
+        write_u2(&mut out, self.minor_version);
+        write_u2(&mut out, self.major_version);
+        writer.pool.write(&mut out);
+        out.extend_from_slice(&body);
+        Ok(out)
+    }
+
+    /// Removes entries that are no longer used from the constant pool, such as those left
+    /// behind by [`ClassFile::retain_methods`] or [`ClassFile::rename_class`], and renumbers
+    /// the remaining entries. The constant pool indices in the `code` of each method are
+    /// rewritten to match. Returns an error, leaving the class unchanged, if any attribute
+    /// was not parsed (i.e. is AttributeData::Other, as every attribute is when parsing
+    /// with [`ParseOptions::skip_attribute_bodies`](crate::ParseOptions::skip_attribute_bodies)),
+    /// since any constant pool indices in its contents can't be rewritten.
+    pub fn compact_constant_pool(&mut self) -> Result<(), ParseError> {
+        let mut writer = ClassWriter {
+            pool: ConstantPoolBuilder::from_constant_pool(&self.constant_pool)?,
+            duplicates: DuplicateReferences::default(),
+        };
+        writer.pool.record_references();
+        writer.write_class(&mut Vec::new(), self)?;
+        let (code_operands, code_references) = self.code_operands()?;
+        let (pool, new_indices) = writer.pool.compact(&code_references)?;
+        let mut constant_pool = vec![CafeRc::new(ConstantPoolEntry::Zero)];
+        pool.extend_constant_pool(&mut constant_pool, self.major_version)?;
+
+        let mut code_operands = code_operands.into_iter();
+        for method in &mut self.methods {
+            for attribute in &mut method.attributes {
+                if let AttributeData::Code(code) = &mut attribute.data {
+                    let code_bytes = code.code.to_mut();
+                    for (offset, narrow) in code_operands.next().unwrap() {
+                        let index = new_indices[&read_operand(code_bytes, offset, narrow)];
+                        if narrow {
+                            // Entries never move to a higher index, so this still fits
+                            code_bytes[offset] = index as u8;
+                        } else {
+                            code_bytes[offset..offset + 2].copy_from_slice(&index.to_be_bytes());
+                        }
+                    }
+                }
+            }
+        }
+        self.constant_pool = constant_pool;
+        // The recorded references are to the old indices
+        self.pool_references.clear();
+        Ok(())
+    }
+
+    /// Returns the number of references to each constant pool entry from other entries
+    /// and from the rest of the class, including the constant pool operands in the `code`
+    /// of each method, keyed by constant pool index. Each reference is counted separately,
+    /// so an entry used as both the name and descriptor of a NameAndType counts twice.
+    /// Entries that are not referenced at all are left out. As with
+    /// [`ClassFile::compact_constant_pool`], this returns an error if any attribute was
+    /// not parsed, since its contents can't be searched for references.
+    pub fn reference_counts(&self) -> Result<HashMap<u16, usize>, ParseError> {
+        let mut writer = ClassWriter {
+            pool: ConstantPoolBuilder::from_constant_pool(&self.constant_pool)?,
+            duplicates: DuplicateReferences::default(),
+        };
+        writer.pool.record_references();
+        writer.write_class(&mut Vec::new(), self)?;
+        let (_, code_references) = self.code_operands()?;
+        Ok(writer.pool.reference_counts(&code_references))
+    }
+
+    /// Returns the number of references to the constant pool entry at the given index, as
+    /// counted by [`ClassFile::reference_counts`], or 0 for an index that is not in use.
+    /// Returns an error if the references can't be counted.
+    pub fn reference_count(&self, index: u16) -> Result<usize, ParseError> {
+        let counts = self.reference_counts()?;
+        Ok(counts.get(&index).copied().unwrap_or(0))
+    }
+
+    // Finds the constant pool operands in the code of each method, returning their
+    // offsets and widths for each Code attribute along with the indices they hold.
+    fn code_operands(&self) -> Result<(Vec<CodeOperands>, Vec<u16>), ParseError> {
+        let mut code_operands = Vec::new();
+        let mut code_references = Vec::new();
+        for (i, method) in self.methods.iter().enumerate() {
+            for attribute in &method.attributes {
+                if let AttributeData::Code(code) = &attribute.data {
+                    let operands = constant_pool_operands(&code.code)
+                        .map_err(|e| err!(e, "code of class method {}", i))?;
+                    for (offset, narrow) in &operands {
+                        code_references.push(read_operand(&code.code, *offset, *narrow));
+                    }
+                    code_operands.push(operands);
+                }
+            }
+        }
+        Ok((code_operands, code_references))
+    }
+}
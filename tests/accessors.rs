@@ -0,0 +1,813 @@
+use std::fs::File;
+use std::io::Read;
+
+#[test]
+fn method_code() {
+    let mut file = File::open("tests/iterator/Object.class").unwrap();
+    let mut bytes = Vec::new();
+    file.read_to_end(&mut bytes).unwrap();
+    let class = cafebabe::parse_class(&bytes).unwrap();
+    let method = class.methods.iter().find(|m| m.name == "hashCode").unwrap();
+    assert_eq!(method.descriptor.to_string(), "()I");
+    // hashCode is native, so has no code
+    assert!(method.code().is_none());
+    let method = class.methods.iter().find(|m| m.name == "toString").unwrap();
+    assert_eq!(method.descriptor.to_string(), "()Ljava/lang/String;");
+    assert!(method.code().unwrap().bytecode.is_some());
+}
+
+#[test]
+fn constant_at_code_offset() {
+    let mut file = File::open("tests/iterator/Object.class").unwrap();
+    let mut bytes = Vec::new();
+    file.read_to_end(&mut bytes).unwrap();
+    let class = cafebabe::parse_class(&bytes).unwrap();
+    let method = class
+        .find_method("toString", "()Ljava/lang/String;")
+        .unwrap();
+    // new java/lang/StringBuilder
+    match class.constant_at_code_offset(method, 1) {
+        Some(cafebabe::constant_pool::ConstantPoolItem::ClassInfo(name)) => {
+            assert_eq!(name, "java/lang/StringBuilder")
+        }
+        other => panic!("unexpected {:?}", other),
+    }
+    // invokevirtual java/lang/Object.getClass
+    match class.constant_at_code_offset(method, 9) {
+        Some(cafebabe::constant_pool::ConstantPoolItem::MethodRef(member)) => {
+            assert_eq!(member.name_and_type.name, "getClass")
+        }
+        other => panic!("unexpected {:?}", other),
+    }
+    // ldc "@"
+    match class.constant_at_code_offset(method, 18) {
+        Some(cafebabe::constant_pool::ConstantPoolItem::LiteralConstant(constant)) => {
+            assert_eq!(format!("{:?}", constant), "String(\"@\")")
+        }
+        other => panic!("unexpected {:?}", other),
+    }
+    // The opcode of invokevirtual rather than its operand
+    assert!(class.constant_at_code_offset(method, 8).is_none());
+    // aload_0, which has no constant pool operand
+    assert!(class.constant_at_code_offset(method, 7).is_none());
+    assert!(class.constant_at_code_offset(method, 1000).is_none());
+    let native = class.find_method("hashCode", "()I").unwrap();
+    assert!(class.constant_at_code_offset(native, 1).is_none());
+}
+
+#[test]
+fn method_code_bytes() {
+    let bytes = std::fs::read("tests/iterator/Object.class").unwrap();
+    let mut opts = cafebabe::ParseOptions::default();
+    opts.parse_bytecode(false);
+    let class = cafebabe::parse_class_with_options(&bytes, &opts).unwrap();
+    let method = class.find_method("hashCode", "()I").unwrap();
+    assert!(method.code_bytes().is_none());
+    let method = class
+        .find_method("toString", "()Ljava/lang/String;")
+        .unwrap();
+    let code = method.code_bytes().unwrap();
+    assert_eq!(code, &method.code().unwrap().code[..]);
+    // The code is borrowed from the input rather than copied
+    let input = bytes.as_ptr_range();
+    assert!(input.contains(&code.as_ptr()));
+    // toString returns a reference, so ends with areturn
+    assert_eq!(code.last(), Some(&0xb0));
+}
+
+#[test]
+fn parse_at_offset() {
+    let class_bytes = std::fs::read("tests/iterator/Object.class").unwrap();
+    let mut buffer = b"container header".to_vec();
+    buffer.extend_from_slice(&class_bytes);
+    buffer.extend_from_slice(b"trailer");
+    let (class, end) = cafebabe::parse_at(&buffer, 16).unwrap();
+    assert_eq!(class.this_class, "java/lang/Object");
+    assert_eq!(end, 16 + class_bytes.len());
+    assert_eq!(&buffer[end..], b"trailer");
+    assert!(cafebabe::parse_at(&buffer, 15).is_err());
+    assert_eq!(
+        cafebabe::parse_at(&buffer, buffer.len() + 1)
+            .unwrap_err()
+            .to_string(),
+        format!(
+            "Start index {} is past the end of the {} byte buffer",
+            buffer.len() + 1,
+            buffer.len()
+        )
+    );
+}
+
+#[test]
+fn parse_associated_fn() {
+    let bytes = std::fs::read("tests/iterator/Object.class").unwrap();
+    let class = cafebabe::ClassFile::parse(&bytes).unwrap();
+    assert_eq!(class.this_class, "java/lang/Object");
+    let mut opts = cafebabe::ParseOptions::default();
+    opts.parse_bytecode(false);
+    let class = cafebabe::ClassFile::parse_with_options(&bytes, &opts).unwrap();
+    let method = class
+        .find_method("toString", "()Ljava/lang/String;")
+        .unwrap();
+    assert!(method.code().unwrap().bytecode.is_none());
+    assert!(cafebabe::ClassFile::parse(&bytes[..10]).is_err());
+}
+
+#[test]
+fn try_from_bytes() {
+    use std::convert::TryFrom;
+
+    fn parse(bytes: &[u8]) -> Result<String, cafebabe::ParseError> {
+        let class = cafebabe::ClassFile::try_from(bytes)?;
+        Ok(class.this_class.to_string())
+    }
+
+    let bytes = std::fs::read("tests/iterator/Object.class").unwrap();
+    assert_eq!(parse(&bytes).unwrap(), "java/lang/Object");
+    assert!(parse(&bytes[..10]).is_err());
+}
+
+#[test]
+fn find_method() {
+    let mut file = File::open("tests/iterator/Object.class").unwrap();
+    let mut bytes = Vec::new();
+    file.read_to_end(&mut bytes).unwrap();
+    let class = cafebabe::parse_class(&bytes).unwrap();
+    let method = class.find_method("wait", "(J)V").unwrap();
+    assert_eq!(method.descriptor.parameters.len(), 1);
+    assert!(class.find_method("wait", "(Ljava/lang/String;)V").is_none());
+    assert!(class.find_method("wait", "not a descriptor").is_none());
+    assert!(class.find_method("wait", "(J)Vextra").is_none());
+    assert_eq!(class.find_methods_named("wait").count(), 3);
+    assert_eq!(class.find_methods_named("missing").count(), 0);
+}
+
+#[test]
+fn method_arity() {
+    let mut file = File::open("tests/iterator/Object.class").unwrap();
+    let mut bytes = Vec::new();
+    file.read_to_end(&mut bytes).unwrap();
+    let class = cafebabe::parse_class(&bytes).unwrap();
+    let method = class.find_method("wait", "(JI)V").unwrap();
+    assert_eq!(method.argument_count(), 2);
+    assert!(method.returns_void());
+    let method = class
+        .find_method("equals", "(Ljava/lang/Object;)Z")
+        .unwrap();
+    assert_eq!(method.argument_count(), 1);
+    assert!(!method.returns_void());
+}
+
+fn parse_owned(path: &str) -> cafebabe::OwnedClassFile {
+    let mut file = File::open(path).unwrap();
+    let mut bytes = Vec::new();
+    file.read_to_end(&mut bytes).unwrap();
+    cafebabe::parse_class(&bytes).unwrap().into_owned().unwrap()
+}
+
+#[test]
+fn owned_class() {
+    let mut cache = std::collections::HashMap::new();
+    let class = parse_owned("tests/iterator/Object.class");
+    cache.insert(class.this_class.to_string(), class.clone());
+    let cached = &cache["java/lang/Object"];
+    assert!(cached.super_class.is_none());
+    assert_eq!(cached.methods.len(), class.methods.len());
+    let method = cached
+        .find_method("toString", "()Ljava/lang/String;")
+        .unwrap();
+    assert!(method.code().unwrap().bytecode.is_some());
+
+    let mut file = File::open("tests/iterator/Object.class").unwrap();
+    let mut bytes = Vec::new();
+    file.read_to_end(&mut bytes).unwrap();
+    let borrowed = cafebabe::parse_class(&bytes).unwrap();
+    assert_eq!(
+        format!("{:?}", cached.constantpool_iter().collect::<Vec<_>>()),
+        format!("{:?}", borrowed.constantpool_iter().collect::<Vec<_>>())
+    );
+    assert_eq!(
+        format!("{:?}", cached.methods),
+        format!("{:?}", borrowed.methods)
+    );
+}
+
+#[test]
+fn rename_class() {
+    let mut file = File::open("tests/iterator/Object.class").unwrap();
+    let mut bytes = Vec::new();
+    file.read_to_end(&mut bytes).unwrap();
+    let mut class = cafebabe::parse_class(&bytes).unwrap();
+    assert_eq!(
+        class
+            .rename_class("com/example/Missing", "x/Missing")
+            .unwrap(),
+        0
+    );
+    assert!(class.rename_class("java/lang/String", "x/Str").unwrap() > 0);
+    assert!(class.find_method("toString", "()Lx/Str;").is_some());
+    assert!(class
+        .find_method("toString", "()Ljava/lang/String;")
+        .is_none());
+    assert!(
+        class
+            .rename_class("java/lang/StringBuilder", "x/Builder")
+            .unwrap()
+            > 0
+    );
+    let classes: Vec<_> = class
+        .constantpool_iter()
+        .filter_map(|c| match c {
+            cafebabe::constant_pool::ConstantPoolItem::ClassInfo(name) => Some(name),
+            _ => None,
+        })
+        .collect();
+    assert!(classes.iter().any(|name| name == "x/Builder"));
+    assert!(!classes.iter().any(|name| name == "java/lang/StringBuilder"));
+}
+
+#[test]
+fn write_class() {
+    let mut file = File::open("tests/iterator/Object.class").unwrap();
+    let mut bytes = Vec::new();
+    file.read_to_end(&mut bytes).unwrap();
+    let mut class = cafebabe::parse_class(&bytes).unwrap();
+    assert_eq!(class.to_bytes().unwrap(), bytes);
+    class.rename_class("java/lang/String", "x/Str").unwrap();
+    let written = class.to_bytes().unwrap();
+    let reparsed = cafebabe::parse_class(&written).unwrap();
+    assert!(reparsed.find_method("toString", "()Lx/Str;").is_some());
+    assert!(reparsed
+        .find_method("toString", "()Ljava/lang/String;")
+        .is_none());
+}
+
+#[test]
+fn write_preserves_attribute_order() {
+    use cafebabe::attributes::{AttributeData, AttributeInfo};
+
+    let bytes = std::fs::read("tests/iterator/Object.class").unwrap();
+    let mut class = cafebabe::parse_class(&bytes).unwrap();
+    let custom = |data: &[u8]| AttributeInfo {
+        name: "Custom".into(),
+        data: AttributeData::Other(data.to_vec().into()),
+    };
+    class.attributes.insert(0, custom(&[1]));
+    class.attributes.push(AttributeInfo {
+        name: "Deprecated".into(),
+        data: AttributeData::Deprecated,
+    });
+    class.attributes.push(custom(&[2]));
+    let names: Vec<String> = class
+        .attributes
+        .iter()
+        .map(|a| a.name.to_string())
+        .collect();
+
+    let written = class.to_bytes().unwrap();
+    let reparsed = cafebabe::parse_class(&written).unwrap();
+    let reparsed_names: Vec<String> = reparsed
+        .attributes
+        .iter()
+        .map(|a| a.name.to_string())
+        .collect();
+    assert_eq!(reparsed_names, names);
+    let custom_data: Vec<&[u8]> = reparsed
+        .attributes
+        .iter()
+        .filter_map(|a| match &a.data {
+            AttributeData::Other(data) => Some(data.as_ref()),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(custom_data, vec![&[1][..], &[2][..]]);
+    assert_eq!(reparsed.to_bytes().unwrap(), written);
+}
+
+#[test]
+fn add_method() {
+    use cafebabe::attributes::CodeData;
+    use cafebabe::bytecode::Opcode;
+    use cafebabe::constant_pool::{LiteralConstant, Loadable};
+    use cafebabe::MethodAccessFlags;
+
+    let mut file = File::open("tests/iterator/Object.class").unwrap();
+    let mut bytes = Vec::new();
+    file.read_to_end(&mut bytes).unwrap();
+    let mut class = cafebabe::parse_class(&bytes).unwrap();
+    let pool_count = class.constantpool_iter().count();
+    let index = class
+        .intern_constants(|pool| pool.string("injected"))
+        .unwrap();
+    assert!(class.constantpool_iter().count() > pool_count);
+    let [hi, lo] = index.to_be_bytes();
+    let code = || CodeData {
+        max_stack: 1,
+        max_locals: 0,
+        code: vec![0x13, hi, lo, 0xb0].into(),
+        bytecode: None,
+        exception_table: Vec::new(),
+        attributes: Vec::new(),
+    };
+    let flags = MethodAccessFlags::PUBLIC | MethodAccessFlags::STATIC;
+    class
+        .add_method(flags, "injected", "()Ljava/lang/String;", code())
+        .unwrap();
+    assert!(class
+        .add_method(flags, "injected", "()Ljava/lang/String;", code())
+        .is_err());
+    assert!(class
+        .add_method(MethodAccessFlags::ABSTRACT, "other", "()V", code())
+        .is_err());
+    assert!(class
+        .add_method(flags, "other", "not a descriptor", code())
+        .is_err());
+
+    let written = class.to_bytes().unwrap();
+    let reparsed = cafebabe::parse_class(&written).unwrap();
+    let method = reparsed
+        .find_method("injected", "()Ljava/lang/String;")
+        .unwrap();
+    let bytecode = method.code().unwrap().bytecode.as_ref().unwrap();
+    assert!(matches!(
+        &bytecode.opcodes[0].1,
+        Opcode::LdcW(Loadable::LiteralConstant(LiteralConstant::String(s))) if s == "injected"
+    ));
+    assert!(matches!(bytecode.opcodes[1].1, Opcode::Areturn));
+}
+
+#[test]
+fn real_methods() {
+    use cafebabe::attributes::CodeData;
+    use cafebabe::MethodAccessFlags;
+
+    let bytes = std::fs::read("tests/iterator/Object.class").unwrap();
+    let mut class = cafebabe::parse_class(&bytes).unwrap();
+    let count = class.methods.len();
+    assert_eq!(class.real_methods().count(), count);
+    let code = || CodeData {
+        max_stack: 1,
+        max_locals: 1,
+        code: vec![0x2a, 0xb0].into(),
+        bytecode: None,
+        exception_table: Vec::new(),
+        attributes: Vec::new(),
+    };
+    let flags =
+        MethodAccessFlags::PUBLIC | MethodAccessFlags::BRIDGE | MethodAccessFlags::SYNTHETIC;
+    class
+        .add_method(flags, "self", "()Ljava/lang/Object;", code())
+        .unwrap();
+    let flags = MethodAccessFlags::PRIVATE | MethodAccessFlags::SYNTHETIC;
+    class
+        .add_method(flags, "lambda$0", "()Ljava/lang/Object;", code())
+        .unwrap();
+    assert!(class
+        .find_method("self", "()Ljava/lang/Object;")
+        .unwrap()
+        .is_bridge());
+    assert!(!class
+        .find_method("lambda$0", "()Ljava/lang/Object;")
+        .unwrap()
+        .is_bridge());
+    assert_eq!(class.methods.len(), count + 2);
+    assert_eq!(class.real_methods().count(), count);
+}
+
+#[test]
+fn diff() {
+    use cafebabe::attributes::CodeData;
+    use cafebabe::diff::MemberKey;
+    use cafebabe::{ClassAccessFlags, MethodAccessFlags};
+
+    let bytes = std::fs::read("tests/iterator/Object.class").unwrap();
+    let old = cafebabe::parse_class(&bytes).unwrap();
+    assert!(old.diff(&old).is_empty());
+
+    let mut new = cafebabe::parse_class(&bytes).unwrap();
+    new.retain_methods(|m| m.name != "finalize");
+    let code = CodeData {
+        max_stack: 0,
+        max_locals: 1,
+        code: vec![0xb1].into(),
+        bytecode: None,
+        exception_table: Vec::new(),
+        attributes: Vec::new(),
+    };
+    new.add_method(MethodAccessFlags::PUBLIC, "close", "()V", code)
+        .unwrap();
+    new.access_flags |= ClassAccessFlags::FINAL;
+    // Round trip through bytes so the constant pool is rebuilt
+    let new_bytes = new.to_bytes().unwrap();
+    let new = cafebabe::parse_class(&new_bytes).unwrap();
+
+    let diff = old.diff(&new);
+    assert!(!diff.is_empty());
+    assert_eq!(
+        diff.access_flags,
+        Some((old.access_flags, old.access_flags | ClassAccessFlags::FINAL))
+    );
+    assert_eq!(diff.super_class, None);
+    assert!(diff.added_interfaces.is_empty() && diff.removed_interfaces.is_empty());
+    assert!(diff.added_fields.is_empty() && diff.removed_fields.is_empty());
+    assert_eq!(
+        diff.added_methods,
+        vec![MemberKey {
+            name: "close".to_string(),
+            descriptor: "()V".to_string(),
+        }]
+    );
+    assert_eq!(
+        diff.removed_methods,
+        vec![MemberKey {
+            name: "finalize".to_string(),
+            descriptor: "()V".to_string(),
+        }]
+    );
+    assert!(diff.changed_methods.is_empty());
+}
+
+#[test]
+fn has_main() {
+    use cafebabe::attributes::CodeData;
+    use cafebabe::MethodAccessFlags;
+
+    let mut file = File::open("tests/iterator/Object.class").unwrap();
+    let mut bytes = Vec::new();
+    file.read_to_end(&mut bytes).unwrap();
+    let mut class = cafebabe::parse_class(&bytes).unwrap();
+    assert!(!class.has_main());
+    let code = || CodeData {
+        max_stack: 0,
+        max_locals: 1,
+        code: vec![0xb1].into(),
+        bytecode: None,
+        exception_table: Vec::new(),
+        attributes: Vec::new(),
+    };
+    class
+        .add_method(
+            MethodAccessFlags::PUBLIC,
+            "main",
+            "([Ljava/lang/String;)V",
+            code(),
+        )
+        .unwrap();
+    assert!(!class.has_main());
+    class.retain_methods(|m| m.name != "main");
+    let flags = MethodAccessFlags::PUBLIC | MethodAccessFlags::STATIC;
+    class
+        .add_method(flags, "main", "([Ljava/lang/String;)V", code())
+        .unwrap();
+    assert!(class.has_main());
+}
+
+#[test]
+fn retain_members() {
+    let mut file = File::open("tests/iterator/Object.class").unwrap();
+    let mut bytes = Vec::new();
+    file.read_to_end(&mut bytes).unwrap();
+    let mut class = cafebabe::parse_class(&bytes).unwrap();
+    let method_count = class.methods.len();
+    class.retain_methods(|m| m.name != "wait");
+    assert_eq!(class.methods.len(), method_count - 3);
+    assert_eq!(class.find_methods_named("wait").count(), 0);
+    class.retain_fields(|_| false);
+    assert!(class.fields.is_empty());
+    let reparsed = class.to_bytes().unwrap();
+    let reparsed = cafebabe::parse_class(&reparsed).unwrap();
+    assert_eq!(reparsed.methods.len(), method_count - 3);
+    assert!(reparsed
+        .find_method("toString", "()Ljava/lang/String;")
+        .is_some());
+}
+
+#[test]
+fn compact_constant_pool() {
+    let mut file = File::open("tests/iterator/Object.class").unwrap();
+    let mut bytes = Vec::new();
+    file.read_to_end(&mut bytes).unwrap();
+    let mut opts = cafebabe::ParseOptions::default();
+    opts.skip_attribute_bodies(true);
+    let mut class = cafebabe::parse_class_with_options(&bytes, &opts).unwrap();
+    assert!(class.compact_constant_pool().is_err());
+
+    let mut class = cafebabe::parse_class(&bytes).unwrap();
+    class.retain_methods(|m| m.name == "toString");
+    let pool_count = class.constantpool_iter().count();
+    class.compact_constant_pool().unwrap();
+    assert!(class.constantpool_iter().count() < pool_count);
+    let written = class.to_bytes().unwrap();
+    assert!(written.len() < bytes.len());
+    let reparsed = cafebabe::parse_class(&written).unwrap();
+    let method = reparsed
+        .find_method("toString", "()Ljava/lang/String;")
+        .unwrap();
+    let original = cafebabe::parse_class(&bytes).unwrap();
+    let original_method = original
+        .find_method("toString", "()Ljava/lang/String;")
+        .unwrap();
+    assert_eq!(
+        format!("{:?}", method.code().unwrap().bytecode),
+        format!("{:?}", original_method.code().unwrap().bytecode)
+    );
+}
+
+#[test]
+fn reference_count() {
+    let mut file = File::open("tests/iterator/Object.class").unwrap();
+    let mut bytes = Vec::new();
+    file.read_to_end(&mut bytes).unwrap();
+    let class = cafebabe::parse_class(&bytes).unwrap();
+    let utf8_index = |value: &str| {
+        class
+            .constantpool_iter()
+            .utf8_entries()
+            .find(|(_, s)| s == value)
+            .unwrap()
+            .0
+    };
+    let code_count = class.methods.iter().filter(|m| m.code().is_some()).count();
+    assert_eq!(
+        class.reference_count(utf8_index("Code")).unwrap(),
+        code_count
+    );
+    let hash_code = class.find_methods_named("hashCode").count();
+    assert_eq!(
+        class.reference_count(utf8_index("hashCode")).unwrap(),
+        hash_code + 1
+    );
+    assert_eq!(class.reference_count(0).unwrap(), 0);
+    let counts = class.reference_counts().unwrap();
+    assert_eq!(counts[&utf8_index("Code")], code_count);
+    assert!(!counts.contains_key(&0));
+
+    let mut opts = cafebabe::ParseOptions::default();
+    opts.skip_attribute_bodies(true);
+    let class = cafebabe::parse_class_with_options(&bytes, &opts).unwrap();
+    assert!(class.reference_counts().is_err());
+    assert!(class.reference_count(1).is_err());
+}
+
+#[test]
+fn verify_stack_map() {
+    let mut file = File::open("tests/iterator/Object.class").unwrap();
+    let mut bytes = Vec::new();
+    file.read_to_end(&mut bytes).unwrap();
+    let class = cafebabe::parse_class(&bytes).unwrap();
+    for method in &class.methods {
+        method.verify_stack_map(&class).unwrap();
+    }
+    let mut opts = cafebabe::ParseOptions::default();
+    opts.parse_bytecode(false);
+    let class = cafebabe::parse_class_with_options(&bytes, &opts).unwrap();
+    let method = class
+        .find_method("toString", "()Ljava/lang/String;")
+        .unwrap();
+    assert!(method.verify_stack_map(&class).is_err());
+}
+
+#[test]
+fn referenced_classes() {
+    let mut file = File::open("tests/iterator/Object.class").unwrap();
+    let mut bytes = Vec::new();
+    file.read_to_end(&mut bytes).unwrap();
+    let class = cafebabe::parse_class(&bytes).unwrap();
+    let classes = class.referenced_classes();
+    assert!(!classes.contains("java/lang/Object"));
+    for name in [
+        "java/lang/StringBuilder",
+        "java/lang/String",
+        "java/lang/Class",
+        "java/lang/Throwable",
+        "java/lang/CloneNotSupportedException",
+        "jdk/internal/vm/annotation/IntrinsicCandidate",
+        "java/lang/Deprecated",
+    ]
+    .iter()
+    {
+        assert!(classes.contains(*name), "missing {}", name);
+    }
+}
+
+#[test]
+fn string_constants() {
+    let mut file = File::open("tests/iterator/Object.class").unwrap();
+    let mut bytes = Vec::new();
+    file.read_to_end(&mut bytes).unwrap();
+    let class = cafebabe::parse_class(&bytes).unwrap();
+    let strings: Vec<_> = class.string_constants().collect();
+    assert!(strings.iter().any(|s| s == "@"));
+    assert!(strings
+        .iter()
+        .any(|s| s == "timeoutMillis value is negative"));
+    assert!(!strings.iter().any(|s| s == "toString"));
+    assert!(!strings.iter().any(|s| s == "java/lang/StringBuilder"));
+}
+
+#[test]
+fn future_major_version() {
+    let mut file = File::open("tests/iterator/Object.class").unwrap();
+    let mut bytes = Vec::new();
+    file.read_to_end(&mut bytes).unwrap();
+    let class = cafebabe::parse_class(&bytes).unwrap();
+    assert!(class.warnings().is_empty());
+    let method_count = class.methods.len();
+
+    let future = cafebabe::MAX_KNOWN_MAJOR_VERSION + 10;
+    bytes[6..8].copy_from_slice(&future.to_be_bytes());
+    let class = cafebabe::parse_class(&bytes).unwrap();
+    assert_eq!(class.major_version, future);
+    assert_eq!(class.methods.len(), method_count);
+    let warnings = class.warnings();
+    assert_eq!(warnings.len(), 1);
+    assert!(warnings[0].starts_with(&format!("Major version {} ", future)));
+}
+
+#[test]
+fn preview_minor_version() {
+    let mut bytes = std::fs::read("tests/iterator/Object.class").unwrap();
+    let mut opts = cafebabe::ParseOptions::default();
+    opts.strict(true);
+    let class = cafebabe::parse_class_with_options(&bytes, &opts).unwrap();
+    assert!(!class.is_preview());
+
+    bytes[4..6].copy_from_slice(&[0xff, 0xff]);
+    let class = cafebabe::parse_class_with_options(&bytes, &opts).unwrap();
+    assert_eq!(class.minor_version, 65535);
+    assert!(class.is_preview());
+
+    bytes[4..6].copy_from_slice(&[0, 3]);
+    assert!(cafebabe::parse_class_with_options(&bytes, &opts)
+        .unwrap_err()
+        .to_string()
+        .starts_with("Invalid minor version 3 for major version"));
+    assert!(!cafebabe::parse_class(&bytes).unwrap().is_preview());
+}
+
+#[test]
+fn error_class_version() {
+    let bytes = std::fs::read("tests/iterator/Object.class").unwrap();
+    let class = cafebabe::parse_class(&bytes).unwrap();
+    let version = Some((class.major_version, class.minor_version));
+    let err = cafebabe::parse_class(&bytes[..bytes.len() - 1]).unwrap_err();
+    assert_eq!(err.class_version(), version);
+    let err = cafebabe::parse_header_only(&bytes[..12]).unwrap_err();
+    assert_eq!(err.class_version(), version);
+    let err = cafebabe::parse_class(&bytes[..7]).unwrap_err();
+    assert_eq!(err.class_version(), None);
+    let err = cafebabe::parse_class(&bytes[1..]).unwrap_err();
+    assert_eq!(err.class_version(), None);
+}
+
+#[test]
+fn descriptors_referencing() {
+    let bytes = std::fs::read("tests/iterator/Object.class").unwrap();
+    let class = cafebabe::parse_class(&bytes).unwrap();
+    let descriptors = class.descriptors_referencing("java/lang/Class");
+    assert!(descriptors.iter().any(|d| d == "()Ljava/lang/Class;"));
+    assert!(descriptors.iter().any(|d| d == "()Ljava/lang/Class<*>;"));
+    let descriptors = class.descriptors_referencing("java/lang/String");
+    assert!(descriptors.iter().any(|d| d == "()Ljava/lang/String;"));
+    let count = descriptors.len();
+    let mut unique = descriptors.clone();
+    unique.sort();
+    unique.dedup();
+    assert_eq!(unique.len(), count);
+    // Only whole class names match
+    assert!(class.descriptors_referencing("java/lang/Str").is_empty());
+    assert!(class.descriptors_referencing("java/lang").is_empty());
+}
+
+#[test]
+fn parse_stats() {
+    let mut file = File::open("tests/iterator/Object.class").unwrap();
+    let mut bytes = Vec::new();
+    file.read_to_end(&mut bytes).unwrap();
+    let (class, stats) =
+        cafebabe::parse_class_with_stats(&bytes, &cafebabe::ParseOptions::default()).unwrap();
+    assert_eq!(stats.bytes_consumed, bytes.len());
+    assert_eq!(
+        stats.constant_pool_entries.values().sum::<usize>(),
+        class.constantpool_iter().count() + stats.constant_pool_entries["Utf8"]
+    );
+    assert_eq!(
+        stats.attributes["Code"],
+        class.methods.iter().filter(|m| m.code().is_some()).count()
+    );
+    assert_eq!(stats.attributes["SourceFile"], 1);
+    assert!(stats.attributes["LineNumberTable"] > 0);
+    assert!(stats.header_time <= stats.total_time);
+}
+
+#[test]
+fn javap_dump() {
+    let mut file = File::open("tests/iterator/Object.class").unwrap();
+    let mut bytes = Vec::new();
+    file.read_to_end(&mut bytes).unwrap();
+    let class = cafebabe::parse_class(&bytes).unwrap();
+    let dump = class.javap_dump();
+    for line in [
+        "public class java.lang.Object",
+        "  flags: (0x0021) ACC_PUBLIC, ACC_SUPER",
+        "   #1 = Class              #2             // java/lang/StringBuilder",
+        "  public final native java.lang.Class getClass();",
+        "         4: invokespecial #3                  // Method java/lang/StringBuilder.\"<init>\":()V",
+        "         2: if_acmpne     9",
+        "        21: ldc           #49                 // int 999999",
+        "SourceFile: \"Object.java\"",
+    ]
+    .iter()
+    {
+        assert!(dump.lines().any(|l| l == *line), "missing {}", line);
+    }
+}
+
+#[test]
+fn defer_resolution() {
+    let bytes = std::fs::read("tests/iterator/Object.class").unwrap();
+    let eager = cafebabe::parse_class(&bytes).unwrap();
+    let mut opts = cafebabe::ParseOptions::default();
+    opts.defer_resolution(true);
+    let deferred = cafebabe::parse_class_with_options(&bytes, &opts).unwrap();
+    assert_eq!(deferred.this_class, eager.this_class);
+    assert_eq!(
+        deferred.constantpool_iter().count(),
+        eager.constantpool_iter().count()
+    );
+    assert_eq!(deferred.javap_dump(), eager.javap_dump());
+    assert_eq!(deferred.to_bytes().unwrap(), bytes);
+}
+
+// A class named A that extends java/lang/Object, with the given entries appended to its
+// constant pool after the four it needs
+fn class_with_extra_constants(count: u16, constants: &[u8]) -> Vec<u8> {
+    let mut bytes = vec![0xCA, 0xFE, 0xBA, 0xBE, 0, 0, 0, 52];
+    bytes.extend_from_slice(&(5 + count).to_be_bytes());
+    bytes.extend_from_slice(&[1, 0, 1, b'A', 7, 0, 1]);
+    bytes.extend_from_slice(&[1, 0, 16]);
+    bytes.extend_from_slice(b"java/lang/Object");
+    bytes.extend_from_slice(&[7, 0, 3]);
+    bytes.extend_from_slice(constants);
+    bytes.extend_from_slice(&[0, 0x21, 0, 2, 0, 4, 0, 0, 0, 0, 0, 0, 0, 0]);
+    bytes
+}
+
+#[test]
+fn defer_resolution_bad_reference() {
+    // An unreferenced ClassInfo at index 5 naming the out-of-bounds index 99
+    let bytes = class_with_extra_constants(1, &[7, 0, 99]);
+    assert!(cafebabe::parse_class(&bytes).is_err());
+    let mut opts = cafebabe::ParseOptions::default();
+    opts.defer_resolution(true);
+    let class = cafebabe::parse_class_with_options(&bytes, &opts).unwrap();
+    assert_eq!(class.this_class, "A");
+    let error = class.constant_pool_snapshot().unwrap_err();
+    assert_eq!(
+        error.to_string(),
+        "Constant pool entry at index 5 references out-of-bounds index 99"
+    );
+    let mut renamed = class.clone();
+    assert!(renamed.rename_class("A", "B").is_err());
+    assert_eq!(renamed.this_class, "A");
+    assert!(class.into_owned().is_err());
+}
+
+#[test]
+fn defer_resolution_cyclic_reference() {
+    // A ClassInfo at index 5 and a String at index 6 that refer to each other
+    let bytes = class_with_extra_constants(2, &[7, 0, 6, 8, 0, 5]);
+    let mut opts = cafebabe::ParseOptions::default();
+    opts.defer_resolution(true);
+    let class = cafebabe::parse_class_with_options(&bytes, &opts).unwrap();
+    let dump = class.javap_dump();
+    assert!(dump.contains("#5 = Class              #6             // <invalid>"));
+    assert!(dump.contains("#6 = String             #5             // <invalid>"));
+}
+
+#[cfg(feature = "kotlin")]
+#[test]
+fn kotlin_metadata() {
+    let mut file = File::open("tests/parse/clazz/Kotlin.clazz").unwrap();
+    let mut bytes = Vec::new();
+    file.read_to_end(&mut bytes).unwrap();
+    let class = cafebabe::parse_class(&bytes).unwrap();
+    let metadata = class.kotlin_metadata().unwrap();
+    assert_eq!(metadata.kind, Some(1));
+    assert_eq!(metadata.metadata_version, [1, 1, 15]);
+    assert_eq!(metadata.bytecode_version, [1, 0, 3]);
+    assert_eq!(metadata.data1.len(), 1);
+    assert_eq!(
+        metadata.data2[0],
+        "Lkotlin/reflect/jvm/internal/WeakClassLoaderBox;"
+    );
+    assert_eq!(metadata.data2.last().unwrap(), "kotlin-reflection");
+    assert!(metadata.extra_string.is_none());
+    assert!(metadata.extra_int.is_none());
+
+    let mut file = File::open("tests/iterator/Object.class").unwrap();
+    let mut bytes = Vec::new();
+    file.read_to_end(&mut bytes).unwrap();
+    let class = cafebabe::parse_class(&bytes).unwrap();
+    assert!(class.kotlin_metadata().is_none());
+}
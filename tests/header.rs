@@ -0,0 +1,84 @@
+use std::fs::File;
+use std::io::Read;
+
+#[test]
+fn header_only() {
+    let mut file = File::open("tests/iterator/Object.class").unwrap();
+    let mut bytes = Vec::new();
+    file.read_to_end(&mut bytes).unwrap();
+    let header = cafebabe::parse_header_only(&bytes).unwrap();
+    assert_eq!(header.this_class, "java/lang/Object");
+    assert_eq!(header.super_class, None);
+    assert!(header.interfaces.is_empty());
+    assert!(header.access_flags.is_public());
+    assert!(header.constantpool_iter().count() > 0);
+
+    // Everything after the header is ignored, so truncating it doesn't matter
+    let class = cafebabe::parse_class(&bytes).unwrap();
+    let truncated = &bytes[..bytes.len() - 1];
+    assert!(cafebabe::parse_class(truncated).is_err());
+    let header = cafebabe::parse_header_only(truncated).unwrap();
+    assert_eq!(header.this_class, class.this_class);
+    assert_eq!(header.major_version, class.major_version);
+}
+
+#[test]
+fn trailing_bytes() {
+    let mut file = File::open("tests/iterator/Object.class").unwrap();
+    let mut bytes = Vec::new();
+    file.read_to_end(&mut bytes).unwrap();
+    let length = bytes.len();
+    assert_eq!(cafebabe::parse_class(&bytes).unwrap().byte_length(), length);
+
+    bytes.extend_from_slice(&[0xca, 0xfe]);
+    assert!(cafebabe::parse_class(&bytes).is_err());
+    let mut opts = cafebabe::ParseOptions::default();
+    opts.allow_trailing_bytes(true);
+    let class = cafebabe::parse_class_with_options(&bytes, &opts).unwrap();
+    assert_eq!(class.byte_length(), length);
+}
+
+#[test]
+fn bad_magic() {
+    // The start of a gzip stream
+    let bytes = [0x1f, 0x8b, 0x08, 0x00, 0x00, 0x00];
+    assert_eq!(
+        cafebabe::parse_class(&bytes).unwrap_err().to_string(),
+        "Not a class file; found magic number 0x1F8B0800 instead of 0xCAFEBABE"
+    );
+    assert_eq!(
+        cafebabe::parse_class(&bytes).unwrap_err().magic(),
+        Some(0x1F8B_0800)
+    );
+    assert!(cafebabe::parse_header_only(&bytes).is_err());
+
+    // Too short to hold a magic number at all
+    let error = cafebabe::parse_class(&[0xCA, 0xFE, 0xBA]).unwrap_err();
+    assert_eq!(
+        error.to_string(),
+        "Unexpected end of stream reading u4 at index 0 for magic number"
+    );
+    assert_eq!(error.magic(), None);
+    assert!(cafebabe::parse_class(&[]).is_err());
+}
+
+#[test]
+fn scan_class_names() {
+    let file = File::open("tests/iterator/Object.class").unwrap();
+    let mut names = Vec::new();
+    cafebabe::scan_class_names(file, &mut |name, super_name| {
+        names.push((name.to_string(), super_name.map(str::to_string)));
+    })
+    .unwrap();
+    assert_eq!(names, vec![("java/lang/Object".to_string(), None)]);
+
+    let bad: &[u8] = &[0xCA, 0xFE, 0xD0, 0x0D, 0, 0];
+    assert!(cafebabe::scan_class_names(bad, &mut |_, _| panic!()).is_err());
+
+    // Errors other than running out of data are reported without reading any further
+    let data = vec![0; 1 << 20];
+    let mut reader = &data[..];
+    let err = cafebabe::scan_class_names(&mut reader, &mut |_, _| panic!()).unwrap_err();
+    assert!(err.to_string().starts_with("Not a class file"));
+    assert!(!reader.is_empty());
+}
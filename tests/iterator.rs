@@ -50,3 +50,176 @@ fn iterator_basic() {
     });
     assert!(iter.next().is_none());
 }
+
+#[test]
+fn iterator_filters() {
+    let mut file = File::open("tests/iterator/Object.class").unwrap();
+    let mut bytes = Vec::new();
+    file.read_to_end(&mut bytes).unwrap();
+    let class = cafebabe::parse_class(&bytes).unwrap();
+
+    let classes: Vec<_> = class.constantpool_iter().class_entries().collect();
+    assert_eq!(
+        classes.first().unwrap(),
+        &(1, "java/lang/StringBuilder".into())
+    );
+    assert_eq!(classes.last().unwrap(), &(84, "java/lang/Throwable".into()));
+
+    let utf8: Vec<_> = class.constantpool_iter().utf8_entries().collect();
+    assert_eq!(utf8[0], (2, "java/lang/StringBuilder".into()));
+    assert_eq!(utf8.last().unwrap(), &(91, "Object.java".into()));
+
+    let (index, method) = class.constantpool_iter().method_refs().next().unwrap();
+    assert_eq!(index, 3);
+    assert_eq!(method.class_name, "java/lang/StringBuilder");
+    assert_eq!(method.name_and_type.name, "<init>");
+
+    let (index, name_and_type) = class
+        .constantpool_iter()
+        .name_and_type_entries()
+        .next()
+        .unwrap();
+    assert_eq!(index, 4);
+    assert_eq!(name_and_type.name, "<init>");
+    assert_eq!(name_and_type.descriptor, "()V");
+
+    assert!(class.constantpool_iter().string_entries().any(
+        |(_, s)| matches!(s, LiteralConstant::String(s) if s == "timeoutMillis value is negative")
+    ));
+    assert_eq!(class.constantpool_iter().field_refs().count(), 0);
+}
+
+#[test]
+fn constant_pool_snapshot() {
+    use cafebabe::constant_pool::ResolvedEntry;
+
+    let mut file = File::open("tests/iterator/Object.class").unwrap();
+    let mut bytes = Vec::new();
+    file.read_to_end(&mut bytes).unwrap();
+    let class = cafebabe::parse_class(&bytes).unwrap();
+    let pool = class.constant_pool_snapshot().unwrap();
+    assert_eq!(pool.get(0), Some(&ResolvedEntry::Unusable));
+    assert_eq!(pool.get(1), Some(&ResolvedEntry::ClassInfo { name: 2 }));
+    assert_eq!(pool.utf8(2), Some("java/lang/StringBuilder"));
+    let (name, descriptor) = match pool.get(3) {
+        Some(ResolvedEntry::MethodRef {
+            class: 1,
+            name_and_type,
+        }) => match pool.get(*name_and_type) {
+            Some(ResolvedEntry::NameAndType { name, descriptor }) => (*name, *descriptor),
+            entry => panic!("Unexpected entry {:?}", entry),
+        },
+        entry => panic!("Unexpected entry {:?}", entry),
+    };
+    assert_eq!(pool.utf8(name), Some("<init>"));
+    assert_eq!(pool.utf8(descriptor), Some("()V"));
+    assert_eq!(pool.entries().len(), 92);
+    assert!(pool.get(92).is_none());
+}
+
+#[test]
+fn unresolved_external_refs() {
+    use cafebabe::constant_pool::MemberKind;
+
+    let mut file = File::open("tests/iterator/Object.class").unwrap();
+    let mut bytes = Vec::new();
+    file.read_to_end(&mut bytes).unwrap();
+    let class = cafebabe::parse_class(&bytes).unwrap();
+    let refs = class.unresolved_external_refs();
+    assert!(matches!(refs[0].kind, MemberKind::Method));
+    assert_eq!(refs[0].class_name, "java/lang/StringBuilder");
+    assert_eq!(refs[0].name, "<init>");
+    assert_eq!(refs[0].descriptor, "()V");
+    assert!(refs.iter().all(|r| r.class_name != "java/lang/Object"));
+    let own_refs = class
+        .constantpool_iter()
+        .method_refs()
+        .filter(|(_, r)| r.class_name == "java/lang/Object")
+        .count();
+    assert!(own_refs > 0);
+    assert_eq!(
+        refs.len() + own_refs,
+        class.constantpool_iter().method_refs().count()
+    );
+}
+
+#[test]
+fn symbolic_ref_keys() {
+    use cafebabe::constant_pool::{MemberKind, SymbolicRef};
+    use std::collections::HashMap;
+
+    let mut file = File::open("tests/iterator/Object.class").unwrap();
+    let mut bytes = Vec::new();
+    file.read_to_end(&mut bytes).unwrap();
+    let class = cafebabe::parse_class(&bytes).unwrap();
+    let mut counts: HashMap<SymbolicRef, usize> = HashMap::new();
+    let refs = class.unresolved_external_refs();
+    for r in refs.iter().chain(refs.iter()) {
+        *counts.entry(r.clone()).or_insert(0) += 1;
+    }
+    assert_eq!(counts.len(), refs.len());
+    let key = SymbolicRef {
+        kind: MemberKind::Method,
+        class_name: "java/lang/StringBuilder".into(),
+        name: "<init>".into(),
+        descriptor: "()V".into(),
+    };
+    assert_eq!(counts.get(&key), Some(&2));
+    let key = SymbolicRef {
+        kind: MemberKind::InterfaceMethod,
+        ..key
+    };
+    assert_eq!(counts.get(&key), None);
+}
+
+#[test]
+fn constant_kinds() {
+    use cafebabe::constant_pool::ConstantKind;
+
+    let mut file = File::open("tests/iterator/Object.class").unwrap();
+    let mut bytes = Vec::new();
+    file.read_to_end(&mut bytes).unwrap();
+    let class = cafebabe::parse_class(&bytes).unwrap();
+    let classes = class
+        .constantpool_iter()
+        .filter(|item| item.kind() == ConstantKind::ClassInfo)
+        .count();
+    assert_eq!(classes, class.constantpool_iter().class_entries().count());
+    assert!(class
+        .constantpool_iter()
+        .all(|item| item.kind() != ConstantKind::Utf8));
+
+    let pool = class.constant_pool_snapshot().unwrap();
+    assert_eq!(pool.get(0).unwrap().kind(), None);
+    assert_eq!(pool.get(1).unwrap().kind(), Some(ConstantKind::ClassInfo));
+    assert_eq!(pool.get(2).unwrap().kind(), Some(ConstantKind::Utf8));
+    assert_eq!(ConstantKind::ClassInfo.tag(), bytes[10]);
+}
+
+#[test]
+fn loadable_constant() {
+    use cafebabe::constant_pool::{LiteralConstant, Loadable};
+
+    let mut file = File::open("tests/iterator/Object.class").unwrap();
+    let mut bytes = Vec::new();
+    file.read_to_end(&mut bytes).unwrap();
+    let mut class = cafebabe::parse_class(&bytes).unwrap();
+    assert!(matches!(
+        class.loadable_constant(1),
+        Some(Loadable::ClassInfo(name)) if name == "java/lang/StringBuilder"
+    ));
+    let (string_index, _) = class.constantpool_iter().string_entries().next().unwrap();
+    assert!(matches!(
+        class.loadable_constant(string_index),
+        Some(Loadable::LiteralConstant(LiteralConstant::String(_)))
+    ));
+    // Utf8, MethodRef and out of range entries can't be loaded
+    assert!(class.loadable_constant(0).is_none());
+    assert!(class.loadable_constant(2).is_none());
+    assert!(class.loadable_constant(3).is_none());
+    assert!(class.loadable_constant(u16::MAX).is_none());
+    // Class constants can only be loaded from version 49
+    class.major_version = 48;
+    assert!(class.loadable_constant(1).is_none());
+    assert!(class.loadable_constant(string_index).is_some());
+}
@@ -9,12 +9,25 @@ use std::process::{Command, Stdio};
 fn parse_success() {
     let classes = generate_classes().unwrap();
     println!("Parsing {} class files...", classes.len());
+    let mut strict = cafebabe::ParseOptions::default();
+    strict.strict(true);
     for classfile in classes {
         let mut file = File::open(&classfile).unwrap();
         let mut bytes = Vec::new();
         file.read_to_end(&mut bytes).unwrap();
         match cafebabe::parse_class(&bytes) {
-            Ok(_) => {
+            Ok(class) => {
+                match class.to_bytes() {
+                    Ok(written) => assert!(
+                        written == bytes,
+                        "[FAIL]: {:?} did not round-trip",
+                        classfile
+                    ),
+                    Err(e) => panic!("[FAIL]: {:?} could not be written\n{}", classfile, e),
+                }
+                if let Err(e) = cafebabe::parse_class_with_options(&bytes, &strict) {
+                    panic!("[FAIL]: {:?} in strict mode\n{}", classfile, e);
+                }
                 println!("[OK] {:?}", classfile);
                 remove_file(&classfile).unwrap();
             }
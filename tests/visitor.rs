@@ -0,0 +1,53 @@
+use std::fs::File;
+use std::io::Read;
+
+use cafebabe::constant_pool::ConstantPoolItem;
+use cafebabe::visitor::ClassVisitor;
+use cafebabe::MethodInfo;
+
+#[derive(Default)]
+struct Counter {
+    classes: usize,
+    methods: Vec<String>,
+    class_infos: usize,
+    ended: bool,
+}
+
+impl<'a> ClassVisitor<'a> for Counter {
+    fn visit_class(&mut self, _class: &cafebabe::ClassFile<'a>) {
+        self.classes += 1;
+    }
+
+    fn visit_constant(&mut self, constant: ConstantPoolItem<'a>) {
+        if let ConstantPoolItem::ClassInfo(_) = constant {
+            self.class_infos += 1;
+        }
+    }
+
+    fn visit_method(&mut self, method: &MethodInfo<'a>) {
+        self.methods.push(method.name.to_string());
+    }
+
+    fn visit_end(&mut self) {
+        self.ended = true;
+    }
+}
+
+#[test]
+fn visit_class() {
+    let mut file = File::open("tests/iterator/Object.class").unwrap();
+    let mut bytes = Vec::new();
+    file.read_to_end(&mut bytes).unwrap();
+    let class = cafebabe::parse_class(&bytes).unwrap();
+    let mut counter = Counter::default();
+    class.accept(&mut counter);
+    assert_eq!(counter.classes, 1);
+    assert!(counter.ended);
+    assert_eq!(counter.methods.len(), class.methods.len());
+    assert_eq!(counter.methods[0], class.methods[0].name);
+    let class_infos = class
+        .constantpool_iter()
+        .filter(|c| matches!(c, ConstantPoolItem::ClassInfo(_)))
+        .count();
+    assert_eq!(counter.class_infos, class_infos);
+}